@@ -0,0 +1,67 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use simples::simplify::rdp::rdp_keep;
+use simples::simplify::vw::vw_keep;
+use simples::{Point, Precision};
+
+/// A deterministic, wiggly 2D line with `n` points.
+fn make_line(n: usize) -> Vec<Point<Precision, 2>> {
+    (0..n)
+        .map(|i| {
+            let x = i as Precision * 0.1;
+            [x, x.sin() + (x * 7.3).sin() * 0.2 + (x * 31.0).cos() * 0.05].into()
+        })
+        .collect()
+}
+
+fn bench_rdp(c: &mut Criterion) {
+    let line = make_line(10_000);
+    let mut group = c.benchmark_group("rdp_keep");
+    for epsilon in [0.01, 0.1, 1.0] {
+        group.bench_with_input(BenchmarkId::from_parameter(epsilon), &epsilon, |b, e| {
+            b.iter(|| rdp_keep(black_box(&line), *e, false))
+        });
+    }
+    group.finish();
+}
+
+/// A deterministic, wiggly 3D line with `n` points.
+fn make_line_3d(n: usize) -> Vec<Point<Precision, 3>> {
+    (0..n)
+        .map(|i| {
+            let x = i as Precision * 0.1;
+            [
+                x,
+                x.sin() + (x * 7.3).sin() * 0.2,
+                (x * 0.3).cos() + (x * 31.0).cos() * 0.05,
+            ]
+            .into()
+        })
+        .collect()
+}
+
+/// Dominated by the max-deviation scan, which is vectorised with the `simd` feature.
+fn bench_rdp_3d(c: &mut Criterion) {
+    let line = make_line_3d(200_000);
+    let mut group = c.benchmark_group("rdp_keep_3d");
+    group.sample_size(10);
+    for epsilon in [0.001, 0.1] {
+        group.bench_with_input(BenchmarkId::from_parameter(epsilon), &epsilon, |b, e| {
+            b.iter(|| rdp_keep(black_box(&line), *e, false))
+        });
+    }
+    group.finish();
+}
+
+fn bench_vw(c: &mut Criterion) {
+    let line = make_line(10_000);
+    let mut group = c.benchmark_group("vw_keep");
+    for n_points in [5_000, 1_000, 100] {
+        group.bench_with_input(BenchmarkId::from_parameter(n_points), &n_points, |b, n| {
+            b.iter(|| vw_keep(black_box(&line), *n, false))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_rdp, bench_rdp_3d, bench_vw);
+criterion_main!(benches);
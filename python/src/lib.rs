@@ -0,0 +1,141 @@
+//! Python bindings for simples.
+//!
+//! Lines are numpy arrays of shape `(n_points, 2)` or `(n_points, 3)`.
+//! C-contiguous `float64` input is read without copying;
+//! output arrays take ownership of the Rust buffers, which are reinterpreted as flat coordinates, also without copying.
+use ::simples::convert::{as_points, into_coords};
+use ::simples::smooth::{Gaussian, Linear};
+use ::simples::{Point, Precision};
+use numpy::ndarray::Ix2;
+use numpy::{PyArray, PyArray1, PyArrayMethods, PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+type Array2<'py> = Bound<'py, PyArray<Precision, Ix2>>;
+
+/// Borrow the array as points, and apply `f` with the matching dimensionality.
+macro_rules! dispatch {
+    ($py:expr, $coords:expr, |$line:ident| $body:expr) => {{
+        let coords = &$coords;
+        let dim = coords.shape()[1];
+        let flat = coords
+            .as_slice()
+            .map_err(|_| PyValueError::new_err("array must be C-contiguous"))?;
+        match dim {
+            2 => {
+                // the length is always a multiple of the dimensionality, as it comes from an (n, D) array
+                let $line: &[Point<Precision, 2>] = as_points(bytemuck::cast_slice(flat));
+                let out = $body;
+                to_array($py, out)
+            }
+            3 => {
+                let $line: &[Point<Precision, 3>] = as_points(bytemuck::cast_slice(flat));
+                let out = $body;
+                to_array($py, out)
+            }
+            _ => Err(PyValueError::new_err("array must have 2 or 3 columns")),
+        }
+    }};
+}
+
+/// Move the points into a new numpy array of shape `(n_points, D)`, re-using the allocation.
+fn to_array<const D: usize>(
+    py: Python<'_>,
+    points: Vec<Point<Precision, D>>,
+) -> PyResult<Array2<'_>> {
+    let n = points.len();
+    let flat: Vec<Precision> = bytemuck::allocation::cast_vec(into_coords(points));
+    PyArray1::from_vec(py, flat).reshape([n, D])
+}
+
+/// Simplify a line using the Ramer-Douglas-Peucker algorithm.
+#[pyfunction]
+#[pyo3(signature = (coords, epsilon, closed=false))]
+fn rdp_reduce<'py>(
+    py: Python<'py>,
+    coords: PyReadonlyArray2<'py, Precision>,
+    epsilon: Precision,
+    closed: bool,
+) -> PyResult<Array2<'py>> {
+    dispatch!(py, coords, |line| ::simples::simplify::rdp::rdp_reduce(
+        line, epsilon, closed
+    ))
+}
+
+/// Decimate a line to `n_points` using the Visvalingam-Whyatt algorithm.
+#[pyfunction]
+#[pyo3(signature = (coords, n_points, closed=false))]
+fn vw_reduce<'py>(
+    py: Python<'py>,
+    coords: PyReadonlyArray2<'py, Precision>,
+    n_points: usize,
+    closed: bool,
+) -> PyResult<Array2<'py>> {
+    dispatch!(py, coords, |line| ::simples::simplify::vw::vw_reduce(
+        line, n_points, closed
+    ))
+}
+
+/// Resample a line with a point every `sample_distance`, starting `offset` along it.
+///
+/// Unlike the Rust function, the remainder is not returned.
+#[pyfunction]
+#[pyo3(signature = (coords, sample_distance, offset=0.0, closed=false))]
+fn sample_every<'py>(
+    py: Python<'py>,
+    coords: PyReadonlyArray2<'py, Precision>,
+    sample_distance: Precision,
+    offset: Precision,
+    closed: bool,
+) -> PyResult<Array2<'py>> {
+    if sample_distance.is_nan() || sample_distance <= 0.0 || offset < 0.0 {
+        return Err(PyValueError::new_err(
+            "sample_distance must be positive and offset non-negative",
+        ));
+    }
+    dispatch!(py, coords, |line| {
+        ::simples::simplify::sample::sample_every(line, sample_distance, offset, closed).0
+    })
+}
+
+/// Smooth a line with a linear kernel, ignoring points further than `max_dist` away.
+#[pyfunction]
+#[pyo3(signature = (coords, max_dist, closed=false))]
+fn smooth_linear<'py>(
+    py: Python<'py>,
+    coords: PyReadonlyArray2<'py, Precision>,
+    max_dist: Precision,
+    closed: bool,
+) -> PyResult<Array2<'py>> {
+    let kernel = Linear::try_new(max_dist).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    dispatch!(py, coords, |line| ::simples::smooth::smooth_convolve(
+        line, kernel, closed
+    ))
+}
+
+/// Smooth a line with a Gaussian kernel, ignoring points more than `width` standard deviations away.
+#[pyfunction]
+#[pyo3(signature = (coords, stdev, width=3.0, closed=false))]
+fn smooth_gaussian<'py>(
+    py: Python<'py>,
+    coords: PyReadonlyArray2<'py, Precision>,
+    stdev: Precision,
+    width: Precision,
+    closed: bool,
+) -> PyResult<Array2<'py>> {
+    let kernel =
+        Gaussian::try_new(stdev, width).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    dispatch!(py, coords, |line| ::simples::smooth::smooth_convolve(
+        line, kernel, closed
+    ))
+}
+
+#[pymodule(name = "simples")]
+fn simples_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(rdp_reduce, m)?)?;
+    m.add_function(wrap_pyfunction!(vw_reduce, m)?)?;
+    m.add_function(wrap_pyfunction!(sample_every, m)?)?;
+    m.add_function(wrap_pyfunction!(smooth_linear, m)?)?;
+    m.add_function(wrap_pyfunction!(smooth_gaussian, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Process many linestrings in parallel.
+//!
+//! Requires the `rayon` feature.
+use crate::simplify::{rdp::rdp_reduce, sample::resample, vw::vw_reduce};
+use crate::smooth::{Kernel, Smoother};
+use crate::{CurveKind, Precision};
+use nalgebra::Point;
+use rayon::prelude::*;
+
+/// Decimate every linestring using RDP; see [rdp_reduce].
+pub fn rdp_reduce_batch<L: AsRef<[Point<Precision, D>]> + Sync, const D: usize>(
+    lines: &[L],
+    epsilon: Precision,
+    kind: impl Into<CurveKind>,
+) -> Vec<Vec<Point<Precision, D>>> {
+    let kind = kind.into();
+    lines
+        .par_iter()
+        .map(|line| rdp_reduce(line.as_ref(), epsilon, kind))
+        .collect()
+}
+
+/// Decimate every linestring using VW; see [vw_reduce].
+pub fn vw_reduce_batch<L: AsRef<[Point<Precision, D>]> + Sync, const D: usize>(
+    lines: &[L],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> Vec<Vec<Point<Precision, D>>> {
+    let kind = kind.into();
+    lines
+        .par_iter()
+        .map(|line| vw_reduce(line.as_ref(), n_points, kind))
+        .collect()
+}
+
+/// Resample every linestring to `n_points`; see [resample].
+pub fn resample_batch<L: AsRef<[Point<Precision, D>]> + Sync, const D: usize>(
+    lines: &[L],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> Vec<Vec<Point<Precision, D>>> {
+    let kind = kind.into();
+    lines
+        .par_iter()
+        .map(|line| resample(line.as_ref(), n_points, kind))
+        .collect()
+}
+
+/// Smooth every linestring with the same kernel; see [crate::smooth::smooth_convolve].
+pub fn smooth_convolve_batch<
+    L: AsRef<[Point<Precision, D>]> + Sync,
+    K: Kernel + Sync,
+    const D: usize,
+>(
+    lines: &[L],
+    kernel: K,
+) -> Vec<Vec<Point<Precision, D>>> {
+    Smoother::new(kernel).par_smooth_many(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn batch_matches_serial() {
+        let lines: Vec<_> = (1..20)
+            .map(|n| {
+                make_line(
+                    (0..n)
+                        .map(|i| [i as Precision, (i % 3) as Precision])
+                        .collect(),
+                )
+            })
+            .collect();
+        let par = rdp_reduce_batch(&lines, 0.5, false);
+        let serial: Vec<_> = lines.iter().map(|l| rdp_reduce(l, 0.5, false)).collect();
+        assert_eq!(par, serial);
+    }
+}
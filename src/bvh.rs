@@ -0,0 +1,287 @@
+//! A bounding volume hierarchy over the edges of a linestring,
+//! for quickly finding edges near a box or along a ray, e.g. for rendering or picking.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::bvh::{build_bvh, Aabb};
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [1.0, 0.0].into(), [1.0, 1.0].into()];
+//! let bvh = build_bvh(&line);
+//! assert_eq!(bvh.query_box(&Aabb::new([0.9, 0.5].into(), [2.0, 2.0].into())), vec![1]);
+//! ```
+use crate::Precision;
+use nalgebra::{Point, SVector};
+
+/// Axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb<const D: usize> {
+    pub min: Point<Precision, D>,
+    pub max: Point<Precision, D>,
+}
+
+impl<const D: usize> Aabb<D> {
+    /// Box with the given corners, which need not be the minimum and maximum.
+    pub fn new(a: Point<Precision, D>, b: Point<Precision, D>) -> Self {
+        Self {
+            min: a.inf(&b),
+            max: a.sup(&b),
+        }
+    }
+
+    /// Box containing all of space, for use as bounds.
+    pub fn unbounded() -> Self {
+        Self {
+            min: Point::from([Precision::NEG_INFINITY; D]),
+            max: Point::from([Precision::INFINITY; D]),
+        }
+    }
+
+    /// Copy of this box with the given limits on one axis.
+    pub fn with_axis(mut self, axis: usize, min: Precision, max: Precision) -> Self {
+        self.min[axis] = min;
+        self.max[axis] = max;
+        self
+    }
+
+    /// Nearest point to `p` inside the box.
+    pub fn clamp(&self, p: &Point<Precision, D>) -> Point<Precision, D> {
+        p.sup(&self.min).inf(&self.max)
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    /// Whether the boxes overlap (including touching).
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..D).all(|d| self.min[d] <= other.max[d] && other.min[d] <= self.max[d])
+    }
+
+    pub fn contains(&self, p: &Point<Precision, D>) -> bool {
+        (0..D).all(|d| self.min[d] <= p[d] && p[d] <= self.max[d])
+    }
+
+    /// Distance along the ray from `origin` in `direction` at which it enters the box,
+    /// in multiples of `direction`'s length.
+    ///
+    /// 0.0 if the ray starts inside the box; None if the ray misses the box.
+    pub fn ray_entry(
+        &self,
+        origin: &Point<Precision, D>,
+        direction: &SVector<Precision, D>,
+    ) -> Option<Precision> {
+        let mut t_min: Precision = 0.0;
+        let mut t_max = Precision::INFINITY;
+        for d in 0..D {
+            if direction[d] == 0.0 {
+                if origin[d] < self.min[d] || origin[d] > self.max[d] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (self.min[d] - origin[d]) / direction[d];
+            let t2 = (self.max[d] - origin[d]) / direction[d];
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+        }
+        (t_min <= t_max).then_some(t_min)
+    }
+}
+
+/// A node in a [Bvh].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BvhNode<const D: usize> {
+    /// The box around a single edge, where edge `i` goes from point `i` to point `i + 1`.
+    Leaf { aabb: Aabb<D>, edge: usize },
+    /// The box around two child nodes, given as indices into [Bvh::nodes].
+    Branch {
+        aabb: Aabb<D>,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl<const D: usize> BvhNode<D> {
+    pub fn aabb(&self) -> &Aabb<D> {
+        match self {
+            BvhNode::Leaf { aabb, .. } => aabb,
+            BvhNode::Branch { aabb, .. } => aabb,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the edges of a linestring; see [build_bvh].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bvh<const D: usize> {
+    nodes: Vec<BvhNode<D>>,
+}
+
+impl<const D: usize> Bvh<D> {
+    /// All nodes; the first is the root.
+    ///
+    /// Empty if the line had no edges.
+    pub fn nodes(&self) -> &[BvhNode<D>] {
+        &self.nodes
+    }
+
+    /// Box around the whole line, if it has any edges.
+    pub fn aabb(&self) -> Option<&Aabb<D>> {
+        self.nodes.first().map(|n| n.aabb())
+    }
+
+    /// Indices of edges whose leaf boxes satisfy `hits`, by descending only into branches which also do.
+    fn query<F: Fn(&Aabb<D>) -> bool>(&self, hits: F) -> Vec<usize> {
+        let mut out = Vec::default();
+        if self.nodes.is_empty() {
+            return out;
+        }
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            if !hits(node.aabb()) {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf { edge, .. } => out.push(*edge),
+                BvhNode::Branch { left, right, .. } => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+        out.sort_unstable();
+        out
+    }
+
+    /// Indices of edges whose bounding boxes intersect the given box, in ascending order.
+    ///
+    /// The edges themselves may not intersect the box.
+    pub fn query_box(&self, aabb: &Aabb<D>) -> Vec<usize> {
+        self.query(|b| b.intersects(aabb))
+    }
+
+    /// Indices of edges whose bounding boxes are hit by the ray from `origin` in `direction`,
+    /// in ascending order.
+    ///
+    /// The edges themselves may not be hit by the ray.
+    pub fn query_ray(
+        &self,
+        origin: &Point<Precision, D>,
+        direction: &SVector<Precision, D>,
+    ) -> Vec<usize> {
+        self.query(|b| b.ray_entry(origin, direction).is_some())
+    }
+}
+
+/// Build nodes for edges `start..end`, returning the index of the subtree's root.
+fn build_nodes<const D: usize>(
+    boxes: &[Aabb<D>],
+    start: usize,
+    end: usize,
+    nodes: &mut Vec<BvhNode<D>>,
+) -> usize {
+    let idx = nodes.len();
+    if end - start == 1 {
+        nodes.push(BvhNode::Leaf {
+            aabb: boxes[start],
+            edge: start,
+        });
+        return idx;
+    }
+    // placeholder, filled in once the children are built
+    nodes.push(BvhNode::Leaf {
+        aabb: boxes[start],
+        edge: start,
+    });
+    let mid = (start + end) / 2;
+    let left = build_nodes(boxes, start, mid, nodes);
+    let right = build_nodes(boxes, mid, end, nodes);
+    nodes[idx] = BvhNode::Branch {
+        aabb: nodes[left].aabb().union(nodes[right].aabb()),
+        left,
+        right,
+    };
+    idx
+}
+
+/// Build a bounding volume hierarchy over the edges of the line,
+/// e.g. after simplification.
+///
+/// Consecutive edges are usually close together,
+/// so the hierarchy splits runs of edges in half rather than partitioning space.
+pub fn build_bvh<const D: usize>(line: &[Point<Precision, D>]) -> Bvh<D> {
+    let boxes: Vec<_> = line
+        .windows(2)
+        .map(|pair| Aabb::new(pair[0], pair[1]))
+        .collect();
+    let mut nodes = Vec::with_capacity(boxes.len() * 2);
+    if !boxes.is_empty() {
+        build_nodes(&boxes, 0, boxes.len(), &mut nodes);
+    }
+    Bvh { nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+    use nalgebra::Vector2;
+
+    fn staircase() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..9)
+                .map(|i| [(i / 2) as Precision, ((i + 1) / 2) as Precision])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn structure() {
+        let bvh = build_bvh(&staircase());
+        assert_eq!(bvh.nodes().len(), 15);
+        assert_eq!(
+            bvh.aabb(),
+            Some(&Aabb::new([0.0, 0.0].into(), [4.0, 4.0].into()))
+        );
+        assert!(build_bvh::<2>(&[]).aabb().is_none());
+    }
+
+    #[test]
+    fn query_box() {
+        let bvh = build_bvh(&staircase());
+        let found = bvh.query_box(&Aabb::new([1.5, 1.5].into(), [2.5, 2.5].into()));
+        assert_eq!(found, vec![3, 4]);
+    }
+
+    #[test]
+    fn query_ray() {
+        let bvh = build_bvh(&staircase());
+        let found = bvh.query_ray(&[-1.0, 3.5].into(), &Vector2::new(1.0, 0.0));
+        assert_eq!(found, vec![6]);
+        let missed = bvh.query_ray(&[-1.0, 3.5].into(), &Vector2::new(-1.0, 0.0));
+        assert!(missed.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let bvh = build_bvh(&staircase());
+        let s = serde_json::to_string(&bvh).unwrap();
+        assert_eq!(bvh, serde_json::from_str(&s).unwrap());
+    }
+
+    #[test]
+    fn clamp() {
+        let bounds = Aabb::unbounded().with_axis(1, 0.0, Precision::INFINITY);
+        assert_eq!(bounds.clamp(&[-5.0, -1.0].into()), [-5.0, 0.0].into());
+        assert_eq!(bounds.clamp(&[-5.0, 1.0].into()), [-5.0, 1.0].into());
+    }
+}
@@ -0,0 +1,233 @@
+//! Clean up degenerate features of linestrings.
+use crate::Precision;
+use nalgebra::{distance, Point};
+
+/// Whether `b` lies on the segment between `a` and `c` (to within floating point error).
+fn is_between<const D: usize>(
+    a: &Point<Precision, D>,
+    b: &Point<Precision, D>,
+    c: &Point<Precision, D>,
+) -> bool {
+    let direct = distance(a, c);
+    let via = distance(a, b) + distance(b, c);
+    via - direct <= direct * Precision::EPSILON * 4.0
+}
+
+/// Remove consecutive duplicate points, and interior points which lie on the segment between their neighbours.
+///
+/// The first and last points are always kept.
+fn drop_degenerate<const D: usize>(line: Vec<Point<Precision, D>>) -> Vec<Point<Precision, D>> {
+    if line.len() <= 2 {
+        return line;
+    }
+    let last = *line.last().unwrap();
+    let mut out: Vec<Point<Precision, D>> = Vec::with_capacity(line.len());
+    for p in line.into_iter() {
+        // drop duplicates
+        if out.last() == Some(&p) {
+            continue;
+        }
+        // drop the previous point if it lies on the way to this one
+        if out.len() >= 2 && is_between(&out[out.len() - 2], &out[out.len() - 1], &p) {
+            out.pop();
+        }
+        out.push(p);
+    }
+    if out.len() == 1 {
+        // every point was a duplicate, but both ends must be kept
+        out.push(last);
+    }
+    out
+}
+
+/// Remove points with any NaN or infinite coordinates.
+pub fn drop_non_finite<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Point<Precision, D>> {
+    line.iter()
+        .filter(|p| p.iter().all(|c| c.is_finite()))
+        .cloned()
+        .collect()
+}
+
+/// Remove points which are exactly the same as the previous point.
+pub fn dedup_consecutive<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Point<Precision, D>> {
+    let mut out = line.to_vec();
+    out.dedup();
+    out
+}
+
+/// Remove points within `tolerance` of the previous kept point, so that no edge is that short or shorter.
+///
+/// Zero-length edges make some algorithms divide by zero,
+/// so a `tolerance` of 0.0 (removing exact repeats) is a useful pre-cleaning step.
+/// The first and last points are always kept;
+/// where the last point is within `tolerance` of kept interior points, they are removed instead.
+/// A line whose points are all within `tolerance` of the first is reduced to the first point,
+/// and the last point if it is further than `tolerance` from the first.
+pub fn dedupe<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+) -> Vec<Point<Precision, D>> {
+    let (Some(first), Some(last)) = (line.first(), line.last()) else {
+        return Vec::default();
+    };
+    let mut out = Vec::with_capacity(line.len());
+    out.push(*first);
+    for p in line.iter().take(line.len() - 1).skip(1) {
+        if distance(out.last().unwrap(), p) > tolerance {
+            out.push(*p);
+        }
+    }
+    while out.len() > 1 && distance(out.last().unwrap(), last) <= tolerance {
+        out.pop();
+    }
+    if out.len() > 1 || (line.len() > 1 && distance(first, last) > tolerance) {
+        out.push(*last);
+    }
+    out
+}
+
+/// Remove interior points where the line turns by no more than `angle_tolerance` (in radians),
+/// i.e. which are nearly on the straight line between their neighbours.
+///
+/// Each point's turn is measured from the previous kept point, rather than the previous original point,
+/// so that gentle curves are not straightened out one small step at a time.
+/// Points adjacent to a zero-length edge have no direction, so are removed.
+/// The first and last points are always kept.
+pub fn remove_collinear<const D: usize>(
+    line: &[Point<Precision, D>],
+    angle_tolerance: Precision,
+) -> Vec<Point<Precision, D>> {
+    if line.len() <= 2 {
+        return line.to_vec();
+    }
+    let mut out = Vec::with_capacity(line.len());
+    out.push(line[0]);
+    for w in line.windows(2).skip(1) {
+        let (p, next) = (&w[0], &w[1]);
+        let incoming = p - out.last().unwrap();
+        let outgoing = next - p;
+        let norms = incoming.norm() * outgoing.norm();
+        if norms == 0.0 {
+            continue;
+        }
+        let angle = (incoming.dot(&outgoing) / norms).clamp(-1.0, 1.0).acos();
+        if angle > angle_tolerance {
+            out.push(*p);
+        }
+    }
+    out.push(line[line.len() - 1]);
+    out
+}
+
+/// Round every coordinate to the given number of decimal places (which may be negative).
+///
+/// Rounding can collapse neighbouring points onto each other, or onto a straight line between their neighbours,
+/// creating zero-length edges which break other algorithms.
+/// So afterwards, consecutive duplicates and interior points lying on the segment between their neighbours are removed.
+/// The first and last points are always kept.
+pub fn round_coords<const D: usize>(
+    line: &[Point<Precision, D>],
+    decimals: i32,
+) -> Vec<Point<Precision, D>> {
+    let factor = (10.0 as Precision).powi(decimals);
+    let rounded = line
+        .iter()
+        .map(|p| p.map(|c| (c * factor).round() / factor))
+        .collect();
+    drop_degenerate(rounded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn round() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.01, 0.01],
+            [1.04, 0.0],
+            [2.0, 0.02],
+            [2.0, 1.0],
+        ]);
+        assert_eq!(
+            round_coords(&line, 1),
+            make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0]])
+        );
+    }
+
+    #[test]
+    fn round_keeps_ends() {
+        let line = make_line(vec![[0.01, 0.0], [0.02, 0.0], [0.03, 0.0]]);
+        assert_eq!(
+            round_coords(&line, 0),
+            make_line(vec![[0.0, 0.0], [0.0, 0.0]])
+        );
+        let line = make_line(vec![[12.0, 0.0], [149.0, 0.0]]);
+        assert_eq!(
+            round_coords(&line, -2),
+            make_line(vec![[0.0, 0.0], [100.0, 0.0]])
+        );
+    }
+
+    #[test]
+    fn dedupe_tolerance() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.0, 0.0],
+            [0.5, 0.0],
+            [1.0, 0.0],
+            [1.0, 0.0],
+            [3.0, 0.0],
+            [3.1, 0.0],
+        ]);
+        assert_eq!(
+            dedupe(&line, 0.0),
+            make_line(vec![
+                [0.0, 0.0],
+                [0.5, 0.0],
+                [1.0, 0.0],
+                [3.0, 0.0],
+                [3.1, 0.0]
+            ])
+        );
+        // the last point replaces the one it is too close to
+        assert_eq!(
+            dedupe(&line, 0.6),
+            make_line(vec![[0.0, 0.0], [1.0, 0.0], [3.1, 0.0]])
+        );
+        assert_eq!(dedupe(&line, 10.0), make_line(vec![[0.0, 0.0]]));
+        assert_eq!(dedupe(&line, 3.0), make_line(vec![[0.0, 0.0], [3.1, 0.0]]));
+        assert_eq!(dedupe(&line[..1], 1.0), line[..1]);
+        assert!(dedupe(&line[..0], 1.0).is_empty());
+    }
+
+    #[test]
+    fn collinear() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.01],
+            [3.0, 0.0],
+            [3.0, 1.0],
+            [3.0, 2.0],
+        ]);
+        assert_eq!(
+            remove_collinear(&line, 0.1),
+            make_line(vec![[0.0, 0.0], [3.0, 0.0], [3.0, 2.0]])
+        );
+        assert_eq!(
+            remove_collinear(&line, 0.0),
+            make_line(vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [2.0, 0.01],
+                [3.0, 0.0],
+                [3.0, 2.0]
+            ])
+        );
+        assert_eq!(remove_collinear(&line[..2], 1.0), line[..2]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Convert between coordinate arrays and [nalgebra::Point]s.
+//!
+//! With the `bytemuck` feature, slices and vectors can be reinterpreted without copying.
+//!
+//! The algorithms in this crate work in [Precision] (`f64`, or `f32` with the `f32` feature),
+//! but lines can be stored in another type (e.g. `half::f16` with the `half` feature)
+//! and converted at the boundaries with [widen] and [narrow], or [process_narrow].
+//!
+//! [AsLine] gives any of these representations as points ready for processing,
+//! only copying when it needs to.
+//! Points and coordinate arrays of any [StorageScalar] also implement [Location](crate::Location),
+//! so can be used directly with the generic functions like [resample_with](crate::simplify::sample::resample_with).
+use crate::Precision;
+use nalgebra::{Point, Scalar};
+use std::borrow::Cow;
+
+/// A point as a plain array of coordinates.
+pub type Coord<T, const D: usize> = [T; D];
+
+/// Copy coordinate arrays into points.
+pub fn to_points<T: Scalar + Copy, const D: usize>(coords: &[Coord<T, D>]) -> Vec<Point<T, D>> {
+    let mut out = Vec::with_capacity(coords.len());
+    out.extend(coords.iter().map(|c| Point::from(*c)));
+    out
+}
+
+/// Copy points into coordinate arrays.
+pub fn from_points<T: Scalar + Copy, const D: usize>(points: &[Point<T, D>]) -> Vec<Coord<T, D>> {
+    let mut out = Vec::with_capacity(points.len());
+    out.extend(points.iter().map(|p| -> Coord<T, D> { p.coords.into() }));
+    out
+}
+
+/// Scalar types which lines can be stored in, and converted to and from [Precision] for processing.
+pub trait StorageScalar: Scalar + Copy {
+    fn to_precision(self) -> Precision;
+
+    /// Rounds to the nearest representable value.
+    fn from_precision(value: Precision) -> Self;
+}
+
+// some of these conversions are the identity, depending on the `f32` feature
+#[allow(clippy::useless_conversion, clippy::unnecessary_cast)]
+impl StorageScalar for f64 {
+    fn to_precision(self) -> Precision {
+        self as Precision
+    }
+
+    fn from_precision(value: Precision) -> Self {
+        value.into()
+    }
+}
+
+#[allow(clippy::useless_conversion, clippy::unnecessary_cast)]
+impl StorageScalar for f32 {
+    fn to_precision(self) -> Precision {
+        self.into()
+    }
+
+    fn from_precision(value: Precision) -> Self {
+        value as f32
+    }
+}
+
+#[cfg(feature = "half")]
+#[allow(clippy::useless_conversion, clippy::unnecessary_cast)]
+impl StorageScalar for half::f16 {
+    fn to_precision(self) -> Precision {
+        self.to_f32().into()
+    }
+
+    fn from_precision(value: Precision) -> Self {
+        half::f16::from_f64(value.into())
+    }
+}
+
+#[cfg(feature = "half")]
+#[allow(clippy::useless_conversion, clippy::unnecessary_cast)]
+impl StorageScalar for half::bf16 {
+    fn to_precision(self) -> Precision {
+        self.to_f32().into()
+    }
+
+    fn from_precision(value: Precision) -> Self {
+        half::bf16::from_f64(value.into())
+    }
+}
+
+/// Convert stored coordinates into points for processing.
+pub fn widen<T: StorageScalar, const D: usize>(coords: &[Coord<T, D>]) -> Vec<Point<Precision, D>> {
+    let mut out = Vec::with_capacity(coords.len());
+    out.extend(coords.iter().map(|c| Point::from(c.map(T::to_precision))));
+    out
+}
+
+/// Convert processed points back into stored coordinates, rounding to the nearest representable values.
+pub fn narrow<T: StorageScalar, const D: usize>(
+    points: &[Point<Precision, D>],
+) -> Vec<Coord<T, D>> {
+    let mut out = Vec::with_capacity(points.len());
+    out.extend(
+        points
+            .iter()
+            .map(|p| -> Coord<T, D> { std::array::from_fn(|d| T::from_precision(p[d])) }),
+    );
+    out
+}
+
+/// Process stored coordinates with any function of this crate, e.g. `|l| rdp_reduce(l, 0.1, false)`,
+/// converting to and from [Precision] around it.
+///
+/// ```
+/// use simples::convert::process_narrow;
+/// use simples::simplify::rdp::rdp_reduce;
+///
+/// let stored: Vec<[f32; 2]> = vec![[0.0, 0.0], [1.0, 0.01], [2.0, 0.0]];
+/// let simplified = process_narrow(&stored, |l| rdp_reduce(l, 0.1, false));
+/// assert_eq!(simplified, vec![[0.0, 0.0], [2.0, 0.0]]);
+/// ```
+pub fn process_narrow<T: StorageScalar, const D: usize, F>(
+    coords: &[Coord<T, D>],
+    f: F,
+) -> Vec<Coord<T, D>>
+where
+    F: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+{
+    narrow(&f(&widen(coords)))
+}
+
+/// Linestrings which can be given as a slice of [Precision] points.
+///
+/// ```
+/// use simples::convert::AsLine;
+/// use simples::simplify::rdp_reduce;
+///
+/// let coords: Vec<[f32; 2]> = vec![[0.0, 0.0], [1.0, 0.01], [2.0, 0.0]];
+/// assert_eq!(rdp_reduce(&coords.as_line(), 0.1, false).len(), 2);
+/// ```
+pub trait AsLine<const D: usize> {
+    /// Borrowed if already points of [Precision], otherwise converted with [widen].
+    fn as_line(&self) -> Cow<'_, [Point<Precision, D>]>;
+}
+
+impl<const D: usize> AsLine<D> for [Point<Precision, D>] {
+    fn as_line(&self) -> Cow<'_, [Point<Precision, D>]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: StorageScalar, const D: usize> AsLine<D> for [Coord<T, D>] {
+    fn as_line(&self) -> Cow<'_, [Point<Precision, D>]> {
+        Cow::Owned(widen(self))
+    }
+}
+
+#[cfg(not(feature = "f32"))]
+impl<const D: usize> AsLine<D> for [Point<f32, D>] {
+    fn as_line(&self) -> Cow<'_, [Point<Precision, D>]> {
+        Cow::Owned(self.iter().map(|p| p.cast()).collect())
+    }
+}
+
+#[cfg(feature = "f32")]
+impl<const D: usize> AsLine<D> for [Point<f64, D>] {
+    fn as_line(&self) -> Cow<'_, [Point<Precision, D>]> {
+        Cow::Owned(self.iter().map(|p| p.cast()).collect())
+    }
+}
+
+/// View coordinate arrays as points, without copying.
+#[cfg(feature = "bytemuck")]
+pub fn as_points<T: Scalar + bytemuck::Pod, const D: usize>(
+    coords: &[Coord<T, D>],
+) -> &[Point<T, D>] {
+    bytemuck::cast_slice(coords)
+}
+
+/// View points as coordinate arrays, without copying.
+#[cfg(feature = "bytemuck")]
+pub fn as_coords<T: Scalar + bytemuck::Pod, const D: usize>(
+    points: &[Point<T, D>],
+) -> &[Coord<T, D>] {
+    bytemuck::cast_slice(points)
+}
+
+/// Convert a vector of coordinate arrays into points, re-using the allocation.
+#[cfg(feature = "bytemuck")]
+pub fn into_points<T: Scalar + bytemuck::Pod, const D: usize>(
+    coords: Vec<Coord<T, D>>,
+) -> Vec<Point<T, D>> {
+    bytemuck::allocation::cast_vec(coords)
+}
+
+/// Convert a vector of points into coordinate arrays, re-using the allocation.
+#[cfg(feature = "bytemuck")]
+pub fn into_coords<T: Scalar + bytemuck::Pod, const D: usize>(
+    points: Vec<Point<T, D>>,
+) -> Vec<Coord<T, D>> {
+    bytemuck::allocation::cast_vec(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let coords = vec![[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]];
+        let points = to_points(&coords);
+        assert_eq!(points[1], Point::from([3.0, 4.0, 5.0]));
+        assert_eq!(from_points(&points), coords);
+    }
+
+    #[test]
+    fn f32_storage() {
+        let stored: Vec<[f32; 3]> = vec![[0.1, 0.2, 0.3], [1.5, 2.5, 3.5]];
+        let points = widen(&stored);
+        assert_eq!(points[0].x, 0.1_f32 as Precision);
+        assert_eq!(narrow::<f32, 3>(&points), stored);
+        let doubled = process_narrow(&stored, |l| l.iter().map(|p| p * 2.0).collect());
+        assert_eq!(doubled[1], [3.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn as_line() {
+        let points = to_points(&[[0.0, 1.0], [2.0, 3.0]]);
+        assert!(matches!(points.as_line(), Cow::Borrowed(_)));
+        let coords: Vec<[Precision; 2]> = from_points(&points);
+        assert_eq!(coords.as_line(), points.as_slice());
+        let narrow_points: Vec<Point<f32, 2>> = to_points(&[[0.0, 1.0], [2.0, 3.0]]);
+        assert_eq!(narrow_points.as_line(), points.as_slice());
+    }
+
+    #[test]
+    fn locations() {
+        use crate::simplify::sample::{resample, resample_with};
+        use crate::Location;
+
+        let points = to_points(&[[0.0, 0.0], [3.0, 4.0]]);
+        let coords = from_points(&points);
+        assert_eq!(coords[0].distance(&coords[1]), 5.0);
+        assert_eq!(
+            from_points(&resample(&points, 3, false)),
+            resample_with(&coords, 3)
+        );
+        let narrow: Vec<Point<f32, 2>> = to_points(&[[0.0, 0.0], [3.0, 4.0]]);
+        assert_eq!(narrow[0].distance(&narrow[1]), 5.0);
+        assert_eq!(resample_with(&narrow, 3)[1], Point::from([1.5_f32, 2.0]));
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn half_storage() {
+        use half::{bf16, f16};
+
+        let stored: Vec<[f16; 2]> = vec![[f16::from_f32(0.5), f16::from_f32(1.25)]];
+        let points = widen(&stored);
+        assert_eq!(points[0], Point::from([0.5, 1.25]));
+        assert_eq!(narrow::<f16, 2>(&points), stored);
+
+        // rounds to nearest
+        let narrowed: Vec<[bf16; 1]> = narrow(&[Point::from([1.0 + 1e-5])]);
+        assert_eq!(narrowed[0][0], bf16::ONE);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn zero_copy() {
+        let coords = vec![[0.0_f64, 1.0], [2.0, 3.0]];
+        let points = as_points(&coords);
+        assert_eq!(points, to_points(&coords).as_slice());
+        assert_eq!(as_coords(points), coords.as_slice());
+
+        let ptr = coords.as_ptr() as usize;
+        let points = into_points(coords);
+        assert_eq!(points.as_ptr() as usize, ptr);
+        assert_eq!(into_coords(points), vec![[0.0, 1.0], [2.0, 3.0]]);
+    }
+}
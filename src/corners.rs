@@ -0,0 +1,145 @@
+//! Find the corners of a linestring, e.g. to keep them sharp while simplifying or smoothing the rest.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::corners::detect_corners;
+//! use simples::simplify::rdp::rdp_keep_preserving;
+//! use simples::smooth::{smooth_preserving, Gaussian};
+//!
+//! // a noisy right angle
+//! let line: Vec<simples::Point<Precision, 2>> = (0..=20)
+//!     .map(|i| {
+//!         let noise = if i % 2 == 0 { 0.05 } else { -0.05 };
+//!         if i <= 10 { [i as Precision, noise] } else { [10.0 + noise, (i - 10) as Precision] }.into()
+//!     })
+//!     .collect();
+//! let corners = detect_corners(&line, 1.0, 2.0);
+//! assert_eq!(corners, vec![10]);
+//!
+//! let smoothed = smooth_preserving(&line, Gaussian::new(1.0, 3.0), &corners);
+//! assert_eq!(smoothed[10], line[10]);
+//! assert!(rdp_keep_preserving(&line, 1.0, &corners).contains(&10));
+//! ```
+use crate::{cumulative_lengths, Precision};
+use nalgebra::Point;
+use std::collections::BTreeSet;
+
+/// Indices of the corners of an open linestring, in order.
+///
+/// A vertex's turning angle (in radians, as in [turning_angles](crate::metrics::turning_angles))
+/// is measured between the chords to the furthest vertices within `min_separation / 2` along the line either side of it
+/// (or its immediate neighbours, if they are further).
+/// This looks past noise and dense sampling, so that a rounded corner made of many small turns is found as a single corner.
+///
+/// Vertices turning by at least `angle_threshold` are corners,
+/// except that corners less than `min_separation` apart along the line are thinned out,
+/// keeping the sharpest (and then the first).
+/// With a `min_separation` of 0.0, every vertex whose own [turning_angles](crate::metrics::turning_angles) reaches the threshold is a corner.
+///
+/// The ends of the line are never corners.
+/// The result can be passed to e.g. [rdp_keep_preserving](crate::simplify::rdp::rdp_keep_preserving)
+/// or [smooth_preserving](crate::smooth::smooth_preserving) to keep the corners in place.
+pub fn detect_corners<const D: usize>(
+    line: &[Point<Precision, D>],
+    angle_threshold: Precision,
+    min_separation: Precision,
+) -> Vec<usize> {
+    if line.len() < 3 {
+        return vec![];
+    }
+    let cumulative = cumulative_lengths(line);
+    let half = (min_separation / 2.0).max(0.0);
+
+    let mut candidates: Vec<(usize, Precision)> = (1..(line.len() - 1))
+        .filter_map(|idx| {
+            let before = cumulative
+                .partition_point(|c| cumulative[idx] - c > half)
+                .min(idx - 1);
+            let after =
+                (cumulative.partition_point(|c| c - cumulative[idx] <= half) - 1).max(idx + 1);
+            let angle = chord_angle(&line[before], &line[idx], &line[after]);
+            (angle >= angle_threshold).then_some((idx, angle))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut corners = BTreeSet::default();
+    for (idx, _) in candidates {
+        let too_close =
+            |other: &usize| (cumulative[idx] - cumulative[*other]).abs() < min_separation;
+        let prev = corners.range(..idx).next_back();
+        let next = corners.range(idx..).next();
+        if !prev.is_some_and(too_close) && !next.is_some_and(too_close) {
+            corners.insert(idx);
+        }
+    }
+    corners.into_iter().collect()
+}
+
+/// Angle between the vectors from `p1` to `p2` and from `p2` to `p3`; 0.0 if either has zero length.
+fn chord_angle<const D: usize>(
+    p1: &Point<Precision, D>,
+    p2: &Point<Precision, D>,
+    p3: &Point<Precision, D>,
+) -> Precision {
+    let v1 = p2 - p1;
+    let v2 = p3 - p2;
+    let norms = v1.norm() * v2.norm();
+    if norms == 0.0 {
+        return 0.0;
+    }
+    (v1.dot(&v2) / norms).clamp(-1.0, 1.0).acos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::FRAC_PI_2;
+    use crate::metrics::turning_angles;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn no_context() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 0.0],
+        ]);
+        let expected: Vec<_> = turning_angles(&line, false)
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, a)| (*a >= 1.0).then_some(idx))
+            .collect();
+        assert_eq!(detect_corners(&line, 1.0, 0.0), expected);
+        assert_eq!(detect_corners(&line, 1.0, 0.0), vec![2, 3]);
+        // the reversal at 3 is sharper, so wins
+        assert_eq!(detect_corners(&line, 1.0, 2.0), vec![3]);
+    }
+
+    #[test]
+    fn rounded_corner() {
+        // straight, then a quarter circle of radius 1 in 20 steps, then straight
+        let mut coords: Vec<[Precision; 2]> = (0..10).map(|i| [i as Precision, 0.0]).collect();
+        coords.extend((0..=20).map(|i| {
+            let theta = i as Precision / 20.0 * FRAC_PI_2;
+            [10.0 + theta.sin(), 1.0 - theta.cos()]
+        }));
+        coords.extend((1..10).map(|i| [11.0, 1.0 + i as Precision]));
+        let line = make_line(coords);
+        // each vertex only turns by 4.5 degrees
+        assert!(detect_corners(&line, 1.0, 0.0).is_empty());
+        let corners = detect_corners(&line, 1.0, 4.0);
+        assert_eq!(corners.len(), 1);
+        assert!((10..=30).contains(&corners[0]));
+    }
+
+    #[test]
+    fn degenerate() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0]]);
+        assert!(detect_corners(&line, 0.0, 1.0).is_empty());
+        let repeated = make_line(vec![[0.0, 0.0]; 4]);
+        assert!(detect_corners(&repeated, 0.1, 1.0).is_empty());
+    }
+}
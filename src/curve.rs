@@ -0,0 +1,259 @@
+//! Whether a linestring is open or closed, and utilities for converting between the two.
+//!
+//! Closed linestrings (rings) do not repeat their first point at the end:
+//! the edge from the last point back to the first is implicit.
+//! Functions throughout the crate which treat the two differently accept a [CurveKind] or a `bool`.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::stats::length;
+//! use simples::{CurveKind, Point};
+//!
+//! let square: Vec<Point<Precision, 2>> =
+//!     vec![[0.0, 0.0].into(), [1.0, 0.0].into(), [1.0, 1.0].into(), [0.0, 1.0].into()];
+//! assert_eq!(length(&square, CurveKind::Open), 3.0);
+//! assert_eq!(length(&square, CurveKind::Closed), 4.0);
+//! ```
+use crate::stats::{winding, Winding};
+use crate::Precision;
+use nalgebra::{distance, Point};
+
+/// Whether a linestring is open, or closed with an implicit edge from its last point to its first.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CurveKind {
+    #[default]
+    Open,
+    Closed,
+}
+
+impl CurveKind {
+    pub fn is_closed(&self) -> bool {
+        matches!(self, CurveKind::Closed)
+    }
+}
+
+impl From<bool> for CurveKind {
+    /// `true` for [CurveKind::Closed].
+    fn from(closed: bool) -> Self {
+        if closed {
+            CurveKind::Closed
+        } else {
+            CurveKind::Open
+        }
+    }
+}
+
+/// Copy of a closed line with its first point repeated at the end.
+pub(crate) fn explicitly_closed<const D: usize>(
+    line: &[Point<Precision, D>],
+) -> Vec<Point<Precision, D>> {
+    let mut out = Vec::with_capacity(line.len() + 1);
+    out.extend_from_slice(line);
+    out.extend(line.first());
+    out
+}
+
+/// Whether the line repeats its first point (to within `tolerance`) at the end, as many file formats require.
+///
+/// Such lines should have the repeat removed (see [remove_closing_point]) before being treated as [CurveKind::Closed],
+/// which would otherwise have a zero-length closing edge.
+/// Lines with fewer than 2 points are never closed.
+pub fn is_closed<const D: usize>(line: &[Point<Precision, D>], tolerance: Precision) -> bool {
+    match (line.first(), line.last()) {
+        (Some(first), Some(last)) if line.len() > 1 => distance(first, last) <= tolerance,
+        _ => false,
+    }
+}
+
+/// Make the line end with exactly its first point, e.g. for writing to a file format which requires it.
+///
+/// If the last point is already within `tolerance` of the first, it is moved onto it;
+/// otherwise the first point is appended.
+/// Empty lines are left empty.
+pub fn ensure_closed<const D: usize>(line: &mut Vec<Point<Precision, D>>, tolerance: Precision) {
+    let Some(first) = line.first().copied() else {
+        return;
+    };
+    if is_closed(line, tolerance) {
+        *line.last_mut().unwrap() = first;
+    } else {
+        line.push(first);
+    }
+}
+
+/// Remove the last point if it repeats the first (to within `tolerance`),
+/// so that the line can be treated as [CurveKind::Closed].
+///
+/// Returns whether a point was removed.
+pub fn remove_closing_point<const D: usize>(
+    line: &mut Vec<Point<Precision, D>>,
+    tolerance: Precision,
+) -> bool {
+    let closed = is_closed(line, tolerance);
+    if closed {
+        line.pop();
+    }
+    closed
+}
+
+/// Direction in which a 2D ring runs, with the y axis pointing up; see [crate::stats::winding].
+///
+/// The ring may or may not repeat its first point at the end.
+pub fn orientation_2d(ring: &[Point<Precision, 2>]) -> Option<Winding> {
+    winding(ring)
+}
+
+/// Reverse the direction of the line.
+///
+/// A closed line keeps its first point first, so that it still starts at the same place.
+pub fn reverse_in_place<const D: usize>(line: &mut [Point<Precision, D>], kind: CurveKind) {
+    match kind {
+        CurveKind::Open => line.reverse(),
+        CurveKind::Closed => {
+            if let Some(rest) = line.get_mut(1..) {
+                rest.reverse();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::PI;
+    use crate::metrics::{curvature, turning_angles};
+    use crate::simplify::rdp::{rdp_keep, rdp_reduce};
+    use crate::simplify::sample::{resample, sample_every};
+    use crate::simplify::vw::vw_keep;
+    use crate::smooth::{smooth_convolve, smooth_moving_average, Linear};
+    use crate::stats::length;
+    use crate::test_utils::tol;
+    use crate::total_length;
+
+    const KINDS: [CurveKind; 2] = [CurveKind::Open, CurveKind::Closed];
+
+    /// A wobbly circle, with its first point not at a corner.
+    fn ring() -> Vec<Point<Precision, 2>> {
+        (0..24)
+            .map(|i| {
+                let theta = i as Precision * PI / 12.0;
+                let r = 10.0 + if i % 2 == 0 { 0.3 } else { -0.3 };
+                [r * theta.cos(), r * theta.sin()].into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn from_bool() {
+        assert_eq!(CurveKind::from(true), CurveKind::Closed);
+        assert_eq!(CurveKind::from(false), CurveKind::Open);
+        assert_eq!(CurveKind::default(), CurveKind::Open);
+    }
+
+    #[test]
+    fn lengths() {
+        let line = ring();
+        let open = length(&line, CurveKind::Open);
+        let closed = length(&line, CurveKind::Closed);
+        assert_eq!(open, total_length(&line));
+        assert!((closed - open - distance(&line[0], &line[23])).abs() < tol(1e-9));
+    }
+
+    #[test]
+    fn matrix() {
+        let line = ring();
+        for kind in KINDS {
+            let resampled = resample(&line, 12, kind);
+            assert_eq!(resampled.len(), 12);
+            assert_eq!(resampled[0], line[0]);
+            let edge = length(&resampled, kind) / if kind.is_closed() { 12.0 } else { 11.0 };
+            let closing = distance(&resampled[11], &resampled[0]);
+            // the closing edge is only part of the sampling for closed lines
+            assert_eq!((closing - edge).abs() < 0.2, kind.is_closed());
+
+            let keep = rdp_keep(&line, 0.5, kind);
+            assert_eq!(keep[0], 0);
+            assert!(keep.windows(2).all(|w| w[0] < w[1]));
+            assert!(keep.len() < line.len());
+            assert_eq!(keep.contains(&23), !kind.is_closed() || keep.len() == 24);
+            assert_eq!(
+                rdp_reduce(&line, 0.5, kind),
+                keep.iter().map(|i| line[*i]).collect::<Vec<_>>()
+            );
+
+            // closed lines are sampled along the closing edge too
+            let (sampled, _) = sample_every(&line, 1.0, 0.0, kind);
+            assert_eq!(sampled.len(), length(&line, kind).floor() as usize + 1);
+
+            let smoothed = smooth_convolve(&line, Linear::new(3.0), kind);
+            assert_eq!(smoothed.len(), line.len());
+            // ends only move for closed lines
+            assert_eq!(smoothed[0] != line[0], kind.is_closed());
+            let radius = |p: &Point<Precision, 2>| p.coords.norm();
+            assert_eq!((radius(&smoothed[0]) - 10.0).abs() < 0.2, kind.is_closed());
+            let averaged = smooth_moving_average(&line, 1, kind);
+            assert_eq!(averaged[0] != line[0], kind.is_closed());
+
+            let angles = turning_angles(&line, kind);
+            let curv = curvature(&line, kind);
+            assert_eq!(angles.len(), line.len());
+            assert_eq!(curv.len(), line.len());
+            assert_eq!(angles[0] > 0.0, kind.is_closed());
+            assert_eq!(curv[23] > 0.0, kind.is_closed());
+
+            // existing closed-aware functions take the same kinds
+            assert_eq!(
+                vw_keep(&line, 10, kind),
+                vw_keep(&line, 10, kind.is_closed())
+            );
+        }
+    }
+
+    #[test]
+    fn closing() {
+        let mut line: Vec<Point<Precision, 2>> =
+            vec![[0.0, 0.0].into(), [1.0, 0.0].into(), [0.0, 1.0].into()];
+        assert!(!is_closed(&line, 0.1));
+        assert!(!is_closed(&line[..1], 0.1));
+        ensure_closed(&mut line, 0.1);
+        assert_eq!(line.len(), 4);
+        assert!(is_closed(&line, 0.0));
+        assert!(remove_closing_point(&mut line, 0.0));
+        assert!(!remove_closing_point(&mut line, 0.0));
+        assert_eq!(line.len(), 3);
+
+        // nearly closed is snapped shut
+        line.push([0.01, 0.0].into());
+        assert!(is_closed(&line, 0.1));
+        ensure_closed(&mut line, 0.1);
+        assert_eq!((line.len(), line[3]), (4, line[0]));
+
+        let mut empty: Vec<Point<Precision, 2>> = vec![];
+        ensure_closed(&mut empty, 0.1);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn reversing() {
+        let mut line = ring();
+        assert_eq!(orientation_2d(&line), Some(Winding::Anticlockwise));
+        assert_eq!(
+            orientation_2d(&explicitly_closed(&line)),
+            Some(Winding::Anticlockwise)
+        );
+        let original = line.clone();
+        reverse_in_place(&mut line, CurveKind::Closed);
+        assert_eq!(orientation_2d(&line), Some(Winding::Clockwise));
+        assert_eq!((line[0], line[1]), (original[0], original[23]));
+        assert!(
+            (length(&line, CurveKind::Closed) - length(&original, CurveKind::Closed)).abs()
+                <= tol(0.0)
+        );
+
+        reverse_in_place(&mut line, CurveKind::Open);
+        assert_eq!((line[0], line[23]), (original[1], original[0]));
+        reverse_in_place(&mut line[..0], CurveKind::Closed);
+    }
+}
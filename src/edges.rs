@@ -0,0 +1,239 @@
+//! Utilities for linestrings representing undirected edges, e.g. in road networks,
+//! where a line and its reverse describe the same thing.
+use crate::Precision;
+use nalgebra::{distance_squared, Point};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// Lexicographic comparison of two points' coordinates.
+fn cmp_points<const D: usize>(a: &Point<Precision, D>, b: &Point<Precision, D>) -> Ordering {
+    for (x, y) in a.iter().zip(b.iter()) {
+        match x.partial_cmp(y).unwrap_or(Ordering::Equal) {
+            Ordering::Equal => continue,
+            o => return o,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Whether the line is already in canonical direction, i.e. no greater than its reverse lexicographically.
+fn is_canonical<const D: usize>(line: &[Point<Precision, D>]) -> bool {
+    for (fwd, bwd) in line.iter().zip(line.iter().rev()) {
+        match cmp_points(fwd, bwd) {
+            Ordering::Equal => continue,
+            o => return o == Ordering::Less,
+        }
+    }
+    true
+}
+
+/// Orient the line in place so that the lexicographically smaller endpoint comes first.
+///
+/// Ties (e.g. for closed loops) are broken by the next point in from each end, and so on.
+/// Returns whether the line was reversed.
+pub fn canonicalize_direction<const D: usize>(line: &mut [Point<Precision, D>]) -> bool {
+    if is_canonical(line) {
+        false
+    } else {
+        line.reverse();
+        true
+    }
+}
+
+/// Drop lines which are exact duplicates of an earlier line, either forwards or reversed.
+///
+/// The first instance of each line is kept in its original direction.
+pub fn merge_bidirectional<L: AsRef<[Point<Precision, D>]>, const D: usize>(
+    lines: &[L],
+) -> Vec<Vec<Point<Precision, D>>> {
+    let mut seen = HashSet::with_capacity(lines.len());
+    let mut out = Vec::default();
+    for line in lines.iter() {
+        let line = line.as_ref();
+        let key: Vec<_> = if is_canonical(line) {
+            line.iter()
+                .flat_map(|p| p.iter().map(|c| c.to_bits()))
+                .collect()
+        } else {
+            line.iter()
+                .rev()
+                .flat_map(|p| p.iter().map(|c| c.to_bits()))
+                .collect()
+        };
+        if seen.insert(key) {
+            out.push(line.to_vec());
+        }
+    }
+    out
+}
+
+/// Among lines not yet `used`, find the one with an endpoint nearest to `p`, within `tolerance`,
+/// for which `allowed(index, reverse)` is true.
+///
+/// Returns its index and whether it needs to be reversed to start at `p`.
+fn nearest_end<L: AsRef<[Point<Precision, D>]>, const D: usize>(
+    lines: &[L],
+    used: &[bool],
+    p: &Point<Precision, D>,
+    tolerance: Precision,
+    allowed: impl Fn(usize, bool) -> bool,
+) -> Option<(usize, bool)> {
+    let tol2 = tolerance * tolerance;
+    let mut best: Option<(usize, bool, Precision)> = None;
+    for (idx, line) in lines.iter().enumerate() {
+        if used[idx] {
+            continue;
+        }
+        let line = line.as_ref();
+        let (Some(first), Some(last)) = (line.first(), line.last()) else {
+            continue;
+        };
+        for (reverse, end) in [(false, first), (true, last)] {
+            let d2 = distance_squared(p, end);
+            if d2 <= tol2 && best.is_none_or(|b| d2 < b.2) && allowed(idx, reverse) {
+                best = Some((idx, reverse, d2));
+            }
+        }
+    }
+    best.map(|(idx, reverse, _)| (idx, reverse))
+}
+
+/// Join lines whose endpoints coincide (within `tolerance`) into maximal paths,
+/// reversing lines as necessary.
+///
+/// Each path is seeded by the first remaining line (in its original direction),
+/// and repeatedly extended at its end and then at its start by whichever remaining line has the nearest endpoint.
+/// Where endpoints are joined, the point already in the path is kept and the other dropped.
+/// Empty lines are ignored.
+///
+/// This is quadratic in the number of lines.
+pub fn concat_lines<L: AsRef<[Point<Precision, D>]>, const D: usize>(
+    lines: &[L],
+    tolerance: Precision,
+) -> Vec<Vec<Point<Precision, D>>> {
+    concat_lines_where(lines, tolerance, |_, _, _| true)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// As [concat_lines], but a line may only join a path if `compatible(seed, index, reversed)` is true,
+/// where `seed` is the index of the line which started the path
+/// and `reversed` is whether the line would be reversed relative to the seed's direction.
+///
+/// Also returns the indices of the lines making up each path, in order.
+pub(crate) fn concat_lines_where<L: AsRef<[Point<Precision, D>]>, const D: usize>(
+    lines: &[L],
+    tolerance: Precision,
+    compatible: impl Fn(usize, usize, bool) -> bool,
+) -> Vec<(Vec<Point<Precision, D>>, Vec<usize>)> {
+    let mut used: Vec<bool> = lines.iter().map(|l| l.as_ref().is_empty()).collect();
+    let mut out = Vec::default();
+    for seed in 0..lines.len() {
+        if used[seed] {
+            continue;
+        }
+        used[seed] = true;
+        let mut path = lines[seed].as_ref().to_vec();
+        let mut members = vec![seed];
+
+        while let Some((idx, reverse)) =
+            nearest_end(lines, &used, &path[path.len() - 1], tolerance, |i, r| {
+                compatible(seed, i, r)
+            })
+        {
+            used[idx] = true;
+            members.push(idx);
+            let line = lines[idx].as_ref();
+            if reverse {
+                path.extend(line.iter().rev().skip(1));
+            } else {
+                path.extend(line.iter().skip(1));
+            }
+        }
+
+        let mut head = Vec::default();
+        let mut head_members = Vec::default();
+        let mut start = path[0];
+        // lines joined at the start are reversed again when the head is flipped
+        while let Some((idx, reverse)) = nearest_end(lines, &used, &start, tolerance, |i, r| {
+            compatible(seed, i, !r)
+        }) {
+            used[idx] = true;
+            head_members.push(idx);
+            let line = lines[idx].as_ref();
+            // built outwards from the path's start, to be reversed afterwards
+            if reverse {
+                head.extend(line.iter().rev().skip(1));
+            } else {
+                head.extend(line.iter().skip(1));
+            }
+            start = head[head.len() - 1];
+        }
+        if !head.is_empty() {
+            head.reverse();
+            head.extend(path);
+            path = head;
+            head_members.reverse();
+            head_members.extend(members);
+            members = head_members;
+        }
+        out.push((path, members));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn canonical() {
+        let mut line = make_line(vec![[1.0, 0.0], [0.5, 0.5], [0.0, 1.0]]);
+        assert!(canonicalize_direction(&mut line));
+        assert_eq!(line, make_line(vec![[0.0, 1.0], [0.5, 0.5], [1.0, 0.0]]));
+        assert!(!canonicalize_direction(&mut line));
+
+        let mut loop_line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+        assert!(canonicalize_direction(&mut loop_line));
+        assert_eq!(loop_line[1], [1.0, 0.0].into());
+    }
+
+    #[test]
+    fn merge() {
+        let a = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        let mut b = a.clone();
+        b.reverse();
+        let c = make_line(vec![[0.0, 0.0], [1.0, 2.0], [2.0, 0.0]]);
+        let merged = merge_bidirectional(&[b.clone(), c.clone(), a.clone(), c.clone()]);
+        assert_eq!(merged, vec![b, c]);
+    }
+
+    #[test]
+    fn concat() {
+        let a = make_line(vec![[1.0, 0.0], [2.0, 0.0]]);
+        let b = make_line(vec![[3.0, 0.0], [2.0, 0.01]]);
+        let c = make_line(vec![[0.0, 0.0], [1.0, 0.0]]);
+        let d = make_line(vec![[10.0, 0.0], [11.0, 0.0]]);
+        let out = concat_lines(&[a, b, c, d.clone()], 0.1);
+        assert_eq!(
+            out,
+            vec![
+                make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]),
+                d
+            ]
+        );
+    }
+
+    #[test]
+    fn concat_head_reversed() {
+        let a = make_line(vec![[1.0, 0.0], [2.0, 0.0]]);
+        let b = make_line(vec![[1.0, 0.0], [0.0, 0.0]]);
+        let out = concat_lines(&[a, b], 0.0);
+        assert_eq!(
+            out,
+            vec![make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]])]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Record simplification and smoothing steps so that they can be undone, redone,
+//! or replayed on an updated version of the input, e.g. for interactive editors.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::edit::EditLog;
+//! use simples::simplify::rdp::rdp_keep;
+//! use simples::smooth::{smooth_convolve, Linear};
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = (0..10).map(|i| [i as Precision, (i % 2) as Precision].into()).collect();
+//! let mut log = EditLog::new(line.clone());
+//! log.displace(|l| smooth_convolve(l, Linear::new(1.5), false)).unwrap();
+//! log.filter(|l| rdp_keep(l, 0.1, false)).unwrap();
+//! log.undo();
+//! log.undo();
+//! assert_eq!(log.line(), line.as_slice());
+//! log.redo();
+//! ```
+use crate::error::{Error, Result};
+use crate::Precision;
+use nalgebra::{Point, SVector};
+
+/// A single recorded change to a line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit<const D: usize> {
+    /// Some points were dropped.
+    Filter {
+        /// Indices of the points which were kept.
+        kept: Vec<usize>,
+        /// Indices and values of the points which were dropped.
+        dropped: Vec<(usize, Point<Precision, D>)>,
+    },
+    /// Every point was moved by the given vector.
+    Displace {
+        displacements: Vec<SVector<Precision, D>>,
+    },
+    /// The line was replaced entirely, e.g. by resampling.
+    Replace {
+        before: Vec<Point<Precision, D>>,
+        after: Vec<Point<Precision, D>>,
+    },
+}
+
+impl<const D: usize> Edit<D> {
+    /// Number of points the edit expects in its input.
+    fn n_before(&self) -> usize {
+        match self {
+            Edit::Filter { kept, dropped } => kept.len() + dropped.len(),
+            Edit::Displace { displacements } => displacements.len(),
+            Edit::Replace { before, .. } => before.len(),
+        }
+    }
+
+    /// Number of points the edit produces.
+    fn n_after(&self) -> usize {
+        match self {
+            Edit::Filter { kept, .. } => kept.len(),
+            Edit::Displace { displacements } => displacements.len(),
+            Edit::Replace { after, .. } => after.len(),
+        }
+    }
+
+    /// Apply the edit to a line with the same number of points as the edit's original input,
+    /// but which may have been changed since.
+    ///
+    /// Filters keep the same indices; displacements move points by the same vectors;
+    /// replacements ignore the input.
+    pub fn apply(&self, line: &[Point<Precision, D>]) -> Result<Vec<Point<Precision, D>>> {
+        if line.len() != self.n_before() {
+            return Err(Error::invalid(
+                "line",
+                "must have as many points as the edit's input",
+            ));
+        }
+        Ok(match self {
+            Edit::Filter { kept, .. } => kept.iter().map(|idx| line[*idx]).collect(),
+            Edit::Displace { displacements } => line
+                .iter()
+                .zip(displacements.iter())
+                .map(|(p, d)| p + d)
+                .collect(),
+            Edit::Replace { after, .. } => after.clone(),
+        })
+    }
+
+    /// Reverse the edit on a line with the same number of points as the edit's output.
+    ///
+    /// Dropped points are restored; displacements are subtracted
+    /// (so points may not return exactly to their original positions, due to rounding);
+    /// replacements return the original line.
+    pub fn undo(&self, line: &[Point<Precision, D>]) -> Result<Vec<Point<Precision, D>>> {
+        if line.len() != self.n_after() {
+            return Err(Error::invalid(
+                "line",
+                "must have as many points as the edit's output",
+            ));
+        }
+        Ok(match self {
+            Edit::Filter { kept, dropped } => {
+                let mut out = Vec::with_capacity(self.n_before());
+                let mut kept_iter = kept.iter().zip(line.iter()).peekable();
+                let mut dropped_iter = dropped.iter().peekable();
+                loop {
+                    let next_kept = kept_iter.peek().map(|(idx, _)| **idx);
+                    let next_dropped = dropped_iter.peek().map(|(idx, _)| *idx);
+                    match (next_kept, next_dropped) {
+                        (Some(k), Some(d)) if k < d => out.push(*kept_iter.next().unwrap().1),
+                        (_, Some(_)) => out.push(dropped_iter.next().unwrap().1),
+                        (Some(_), None) => out.push(*kept_iter.next().unwrap().1),
+                        (None, None) => break,
+                    }
+                }
+                out
+            }
+            Edit::Displace { displacements } => line
+                .iter()
+                .zip(displacements.iter())
+                .map(|(p, d)| p - d)
+                .collect(),
+            Edit::Replace { before, .. } => before.clone(),
+        })
+    }
+}
+
+/// A line with a history of edits which can be undone and redone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditLog<const D: usize> {
+    original: Vec<Point<Precision, D>>,
+    line: Vec<Point<Precision, D>>,
+    done: Vec<Edit<D>>,
+    undone: Vec<Edit<D>>,
+}
+
+impl<const D: usize> EditLog<D> {
+    pub fn new(line: Vec<Point<Precision, D>>) -> Self {
+        Self {
+            original: line.clone(),
+            line,
+            done: Vec::default(),
+            undone: Vec::default(),
+        }
+    }
+
+    /// The line after all edits which have not been undone.
+    pub fn line(&self) -> &[Point<Precision, D>] {
+        &self.line
+    }
+
+    /// Edits which have been made and not undone, in order.
+    pub fn edits(&self) -> &[Edit<D>] {
+        &self.done
+    }
+
+    /// Apply a new edit, clearing the redo history.
+    fn push(&mut self, edit: Edit<D>) -> Result<()> {
+        self.line = edit.apply(&self.line)?;
+        self.done.push(edit);
+        self.undone.clear();
+        Ok(())
+    }
+
+    /// Keep only the points at the indices returned by `keep`, e.g. [rdp_keep](crate::simplify::rdp::rdp_keep).
+    ///
+    /// Fails if the indices are not ascending and in bounds.
+    pub fn filter<F>(&mut self, keep: F) -> Result<()>
+    where
+        F: FnOnce(&[Point<Precision, D>]) -> Vec<usize>,
+    {
+        let kept = keep(&self.line);
+        if kept.windows(2).any(|w| w[0] >= w[1])
+            || kept.last().is_some_and(|idx| *idx >= self.line.len())
+        {
+            return Err(Error::invalid(
+                "keep",
+                "must return ascending, in-bounds indices",
+            ));
+        }
+        let mut kept_iter = kept.iter().peekable();
+        let dropped = self
+            .line
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| {
+                if kept_iter.peek() == Some(&idx) {
+                    kept_iter.next();
+                    false
+                } else {
+                    true
+                }
+            })
+            .map(|(idx, p)| (idx, *p))
+            .collect();
+        self.push(Edit::Filter { kept, dropped })
+    }
+
+    /// Move every point to the corresponding point returned by `smooth`, e.g. [smooth_convolve](crate::smooth::smooth_convolve).
+    ///
+    /// Fails if the number of points changes.
+    pub fn displace<F>(&mut self, smooth: F) -> Result<()>
+    where
+        F: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        let smoothed = smooth(&self.line);
+        if smoothed.len() != self.line.len() {
+            return Err(Error::invalid(
+                "smooth",
+                "must not change the number of points",
+            ));
+        }
+        let displacements = smoothed
+            .iter()
+            .zip(self.line.iter())
+            .map(|(after, before)| after - before)
+            .collect();
+        self.push(Edit::Displace { displacements })
+    }
+
+    /// Replace the line with the output of `f`, e.g. [resample](crate::simplify::sample::resample).
+    pub fn replace<F>(&mut self, f: F)
+    where
+        F: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        let after = f(&self.line);
+        let before = std::mem::replace(&mut self.line, after.clone());
+        self.done.push(Edit::Replace { before, after });
+        self.undone.clear();
+    }
+
+    /// Undo the last edit, returning whether there was one.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.done.pop() else {
+            return false;
+        };
+        self.line = edit
+            .undo(&self.line)
+            .expect("edit was applied to this line");
+        self.undone.push(edit);
+        true
+    }
+
+    /// Redo the last undone edit, returning whether there was one.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.undone.pop() else {
+            return false;
+        };
+        self.line = edit
+            .apply(&self.line)
+            .expect("edit was undone on this line");
+        self.done.push(edit);
+        true
+    }
+
+    /// Apply the same edits to an updated version of the original line,
+    /// which must have the same number of points.
+    pub fn replay(&self, line: &[Point<Precision, D>]) -> Result<Vec<Point<Precision, D>>> {
+        if line.len() != self.original.len() {
+            return Err(Error::invalid(
+                "line",
+                "must have as many points as the original",
+            ));
+        }
+        self.done
+            .iter()
+            .try_fold(line.to_vec(), |current, edit| edit.apply(&current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::rdp::rdp_keep;
+    use crate::simplify::sample::resample;
+    use crate::smooth::{smooth_convolve, Linear};
+    use crate::test_utils::make_line;
+
+    fn zigzag() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..8)
+                .map(|i| [i as Precision, (i % 2) as Precision])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn undo_redo() {
+        let line = zigzag();
+        let mut log = EditLog::new(line.clone());
+        log.displace(|l| smooth_convolve(l, Linear::new(1.5), false))
+            .unwrap();
+        let smoothed = log.line().to_vec();
+        log.filter(|l| rdp_keep(l, 1.0, false)).unwrap();
+        let simplified = log.line().to_vec();
+        assert!(simplified.len() < line.len());
+        log.replace(|l| resample(l, 3, false));
+        assert_eq!(log.edits().len(), 3);
+
+        assert!(log.undo());
+        assert_eq!(log.line(), simplified.as_slice());
+        assert!(log.undo());
+        assert_eq!(log.line(), smoothed.as_slice());
+        assert!(log.redo());
+        assert_eq!(log.line(), simplified.as_slice());
+        assert!(log.undo());
+        assert!(log.undo());
+        assert_eq!(log.line(), line.as_slice());
+        assert!(!log.undo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut log = EditLog::new(zigzag());
+        log.filter(|_| vec![0, 7]).unwrap();
+        log.undo();
+        log.filter(|_| vec![0, 1, 7]).unwrap();
+        assert!(!log.redo());
+        assert_eq!(log.line().len(), 3);
+    }
+
+    #[test]
+    fn replay_on_updated() {
+        let mut log = EditLog::new(zigzag());
+        log.filter(|_| vec![0, 3, 7]).unwrap();
+        let mut updated = zigzag();
+        updated[3].y = 10.0;
+        assert_eq!(
+            log.replay(&updated).unwrap(),
+            make_line(vec![[0.0, 0.0], [3.0, 10.0], [7.0, 1.0]])
+        );
+        assert!(log.replay(&updated[1..]).is_err());
+    }
+
+    #[test]
+    fn invalid_filter() {
+        let mut log = EditLog::new(zigzag());
+        assert!(log.filter(|_| vec![3, 1]).is_err());
+        assert!(log.filter(|_| vec![0, 8]).is_err());
+        assert!(log.edits().is_empty());
+    }
+}
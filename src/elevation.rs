@@ -0,0 +1,92 @@
+//! Utilities for 3D lines where the third dimension is elevation,
+//! such as roads and hiking tracks.
+//!
+//! Such lines are often measured and generalised in "plan view",
+//! i.e. ignoring elevation, while keeping it in the output.
+use crate::simplify::rdp::rdp_keep;
+use crate::{total_length, Precision};
+use nalgebra::{Point, Point2};
+
+/// Project the line onto the horizontal (x-y) plane.
+pub fn plan_view(line: &[Point<Precision, 3>]) -> Vec<Point<Precision, 2>> {
+    line.iter().map(|p| Point2::new(p.x, p.y)).collect()
+}
+
+/// Length of the line in plan view, i.e. ignoring elevation.
+///
+/// For the full 3D length, see [total_length].
+pub fn length_2d(line: &[Point<Precision, 3>]) -> Precision {
+    total_length(&plan_view(line))
+}
+
+/// Slope of each edge: change in elevation divided by horizontal distance.
+///
+/// Vertical edges have infinite slope (with the sign of the elevation change);
+/// zero-length edges have zero slope.
+pub fn gradient_profile(line: &[Point<Precision, 3>]) -> Vec<Precision> {
+    line.windows(2)
+        .map(|pair| {
+            let dz = pair[1].z - pair[0].z;
+            let dh = (pair[1].xy() - pair[0].xy()).norm();
+            if dh == 0.0 {
+                if dz == 0.0 {
+                    0.0
+                } else {
+                    Precision::INFINITY.copysign(dz)
+                }
+            } else {
+                dz / dh
+            }
+        })
+        .collect()
+}
+
+/// Simplify a 3D line using any algorithm which returns kept indices (e.g. [rdp_keep]),
+/// with the algorithm applied in plan view but the 3D points retained.
+pub fn reduce_in_plan_view<F>(line: &[Point<Precision, 3>], keep: F) -> Vec<Point<Precision, 3>>
+where
+    F: FnOnce(&[Point<Precision, 2>]) -> Vec<usize>,
+{
+    keep(&plan_view(line))
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+/// Decimate the 3D line using RDP with `epsilon` measured in plan view.
+pub fn rdp_reduce_2d(line: &[Point<Precision, 3>], epsilon: Precision) -> Vec<Point<Precision, 3>> {
+    reduce_in_plan_view(line, |l| rdp_keep(l, epsilon, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    fn line() -> Vec<Point3<Precision>> {
+        vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(3.0, 4.0, 5.0),
+            Point3::new(6.0, 8.0, 5.0),
+            Point3::new(6.0, 8.0, 3.0),
+        ]
+    }
+
+    #[test]
+    fn lengths() {
+        let line = line();
+        assert_eq!(length_2d(&line), 10.0);
+        assert!(total_length(&line) > 10.0);
+        assert_eq!(
+            gradient_profile(&line),
+            vec![1.0, 0.0, Precision::NEG_INFINITY]
+        );
+    }
+
+    #[test]
+    fn plan_view_rdp() {
+        let line = line();
+        // in 3D, the middle points deviate a lot; in plan view they're all collinear
+        assert_eq!(rdp_reduce_2d(&line, 0.1), vec![line[0], line[3]]);
+    }
+}
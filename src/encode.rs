@@ -0,0 +1,186 @@
+//! Convert 2D linestrings to and from the
+//! [Encoded Polyline Algorithm Format](https://developers.google.com/maps/documentation/utilities/polylinealgorithm).
+//!
+//! Points are taken to be `(x, y) = (longitude, latitude)`;
+//! the encoded string stores latitude first, as the format specifies.
+//! Coordinates are rounded to `precision` decimal places:
+//! 5 is used by Google Maps, and 6 by e.g. OSRM and Valhalla.
+use crate::error::{Error, Result};
+use crate::simplify::rdp::rdp_reduce;
+use crate::{check_finite, Point, Precision};
+
+/// Decimal places used by Google Maps.
+pub const PRECISION_5: u32 = 5;
+/// Decimal places used by OSRM, Valhalla and others.
+pub const PRECISION_6: u32 = 6;
+
+/// Largest `precision` for which any latitude/longitude fits comfortably in the encoding.
+const MAX_PRECISION: u32 = 10;
+
+fn factor(precision: u32) -> Result<Precision> {
+    if precision > MAX_PRECISION {
+        return Err(Error::invalid("precision", "must be at most 10"));
+    }
+    Ok((10.0 as Precision).powi(precision as i32))
+}
+
+fn encode_value(value: i64, out: &mut String) {
+    let mut v = if value < 0 { !(value << 1) } else { value << 1 } as u64;
+    while v >= 0x20 {
+        out.push(char::from((0x20 | (v & 0x1f)) as u8 + 63));
+        v >>= 5;
+    }
+    out.push(char::from(v as u8 + 63));
+}
+
+fn decode_value(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i64>> {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let Some(b) = bytes.next() else {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(Error::invalid("encoded", "ends partway through a value"))
+            };
+        };
+        if !(63..=126).contains(&b) || shift > 60 {
+            return Err(Error::invalid("encoded", "is not a valid encoded polyline"));
+        }
+        let chunk = (b - 63) as u64;
+        result |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    let value = (result >> 1) as i64;
+    Ok(Some(if result & 1 == 1 { !value } else { value }))
+}
+
+/// Encode a linestring as a polyline string, with coordinates rounded to `precision` decimal places.
+///
+/// Longitudes must be within ±180 and latitudes within ±90,
+/// so that every encoded value fits in the format.
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::encode::{encode, PRECISION_5};
+/// use simples::Point;
+///
+/// let line: Vec<Point<Precision, 2>> = vec![
+///     [-120.2, 38.5].into(),
+///     [-120.95, 40.7].into(),
+///     [-126.453, 43.252].into(),
+/// ];
+/// assert_eq!(encode(&line, PRECISION_5).unwrap(), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+/// ```
+pub fn encode(line: &[Point<Precision, 2>], precision: u32) -> Result<String> {
+    let factor = factor(precision)?;
+    check_finite(line)?;
+    let mut out = String::with_capacity(line.len() * 8);
+    let mut prev = [0_i64; 2];
+    for p in line {
+        if p.x.abs() > 180.0 || p.y.abs() > 90.0 {
+            return Err(Error::invalid(
+                "line",
+                "longitudes must be within ±180 and latitudes within ±90",
+            ));
+        }
+        let current = [(p.y * factor).round() as i64, (p.x * factor).round() as i64];
+        for (c, pr) in current.iter().zip(prev.iter()) {
+            encode_value(c - pr, &mut out);
+        }
+        prev = current;
+    }
+    Ok(out)
+}
+
+/// Decode a polyline string, encoded with `precision` decimal places, into a linestring.
+pub fn decode(encoded: &str, precision: u32) -> Result<Vec<Point<Precision, 2>>> {
+    let factor = factor(precision)?;
+    let mut bytes = encoded.bytes();
+    let mut out = Vec::with_capacity(encoded.len() / 8);
+    let mut lat = 0_i64;
+    let mut lon = 0_i64;
+    while let Some(dlat) = decode_value(&mut bytes)? {
+        let Some(dlon) = decode_value(&mut bytes)? else {
+            return Err(Error::invalid("encoded", "has an odd number of values"));
+        };
+        let (Some(next_lat), Some(next_lon)) = (lat.checked_add(dlat), lon.checked_add(dlon))
+        else {
+            return Err(Error::invalid("encoded", "is not a valid encoded polyline"));
+        };
+        (lat, lon) = (next_lat, next_lon);
+        out.push(Point::from([
+            lon as Precision / factor,
+            lat as Precision / factor,
+        ]));
+    }
+    Ok(out)
+}
+
+/// Simplify a linestring with [rdp_reduce] and [encode] the result.
+///
+/// `epsilon` is in the units of the coordinates, i.e. degrees.
+pub fn simplify_and_encode(
+    line: &[Point<Precision, 2>],
+    epsilon: Precision,
+    precision: u32,
+) -> Result<String> {
+    encode(&rdp_reduce(line, epsilon, false), precision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+
+    #[test]
+    fn roundtrip() {
+        let line = make_line(vec![[-120.2, 38.5], [-120.95, 40.7], [-126.453, 43.252]]);
+        for precision in [PRECISION_5, PRECISION_6] {
+            let decoded = decode(&encode(&line, precision).unwrap(), precision).unwrap();
+            assert_eq!(decoded.len(), line.len());
+            for (a, b) in decoded.iter().zip(line.iter()) {
+                assert!((a - b).norm() < tol(1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_reference() {
+        let line = decode("_p~iF~ps|U_ulLnnqC_mqNvxq`@", PRECISION_5).unwrap();
+        assert_eq!(line[2], Point::from([-126.453, 43.252]));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(decode("_p~iF~ps|U_", PRECISION_5).is_err());
+        assert!(decode("_p~iF", PRECISION_5).is_err());
+        assert!(decode("ab\ncd", PRECISION_5).is_err());
+        assert_eq!(decode("", PRECISION_5).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let line = make_line(vec![[0.0, 0.0], [1e30, 0.0]]);
+        assert!(encode(&line, PRECISION_5).is_err());
+        let line = make_line(vec![[0.0, -90.5]]);
+        assert!(encode(&line, PRECISION_5).is_err());
+        let line = make_line(vec![[-180.0, 90.0], [180.0, -90.0]]);
+        let decoded = decode(&encode(&line, MAX_PRECISION).unwrap(), MAX_PRECISION).unwrap();
+        for (a, b) in decoded.iter().zip(line.iter()) {
+            assert!((a - b).norm() < tol(1e-9));
+        }
+        // two latitude deltas of 2^62, whose sum overflows
+        assert!(decode("____________G?____________G?", PRECISION_5).is_err());
+    }
+
+    #[test]
+    fn simplified() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.00001], [2.0, 0.0]]);
+        let decoded = decode(&simplify_and_encode(&line, 0.001, 6).unwrap(), 6).unwrap();
+        assert_eq!(decoded, vec![line[0], line[2]]);
+    }
+}
@@ -0,0 +1,59 @@
+//! Errors for fallible versions of the crate's functions.
+use std::fmt;
+
+/// Things which can go wrong when processing a linestring.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The line has zero length, so cannot be divided up.
+    ZeroLength,
+    /// A parameter was outside its valid range.
+    InvalidParameter {
+        name: &'static str,
+        reason: &'static str,
+    },
+    /// The point at this index has a NaN or infinite coordinate.
+    NonFiniteCoordinate { index: usize },
+    /// More points were requested than the line has.
+    TooFewPoints { n_points: usize, requested: usize },
+    /// A serialised pipeline contains a stage (at this index) which this version of the crate does not recognise.
+    UnknownStage { index: usize, name: String },
+    /// Serialised input (e.g. WKT or WKB) could not be parsed; the offset is in bytes.
+    Parse { offset: usize, reason: &'static str },
+}
+
+impl Error {
+    pub(crate) fn invalid(name: &'static str, reason: &'static str) -> Self {
+        Self::InvalidParameter { name, reason }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ZeroLength => write!(f, "line has zero length"),
+            Error::InvalidParameter { name, reason } => {
+                write!(f, "invalid parameter `{name}`: {reason}")
+            }
+            Error::NonFiniteCoordinate { index } => {
+                write!(f, "point {index} has a non-finite coordinate")
+            }
+            Error::TooFewPoints {
+                n_points,
+                requested,
+            } => write!(
+                f,
+                "requested {requested} points from a line with {n_points}"
+            ),
+            Error::UnknownStage { index, name } => {
+                write!(f, "unknown pipeline stage `{name}` at index {index}")
+            }
+            Error::Parse { offset, reason } => {
+                write!(f, "could not parse input at byte {offset}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,302 @@
+//! Approximate linestrings with smooth curves.
+//!
+//! Fitting a curve with few parameters to many noisy points both simplifies and smooths them,
+//! which often suits sensor trajectories better than either alone.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::fit::bspline;
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = (0..=100)
+//!     .map(|i| {
+//!         let x = i as Precision / 10.0;
+//!         let noise = if i % 2 == 0 { 0.01 } else { -0.01 };
+//!         [x, x.sin() + noise].into()
+//!     })
+//!     .collect();
+//! let spline = bspline(&line, 12, 3).unwrap();
+//! assert_eq!(spline.control_points().len(), 12);
+//! let smooth = spline.sample(50);
+//! assert_eq!(smooth.first(), line.first());
+//! assert_eq!(smooth.last(), line.last());
+//! assert!(smooth.iter().all(|p| (p.y - p.x.sin()).abs() < 0.05));
+//! ```
+use crate::error::{Error, Result};
+use crate::{check_finite, cumulative_lengths, Precision};
+use nalgebra::{DMatrix, Point};
+
+/// A clamped B-spline curve, parameterised from 0.0 (the first control point) to 1.0 (the last).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BSpline<const D: usize> {
+    degree: usize,
+    /// Non-decreasing, with `degree + 1` repeats at either end.
+    knots: Vec<Precision>,
+    control_points: Vec<Point<Precision, D>>,
+}
+
+impl<const D: usize> BSpline<D> {
+    /// Spline with uniformly spaced knots.
+    ///
+    /// Fails if there are fewer than `degree + 1` control points, `degree` is 0,
+    /// or a control point has a NaN or infinite coordinate.
+    pub fn new(control_points: Vec<Point<Precision, D>>, degree: usize) -> Result<Self> {
+        if degree == 0 {
+            return Err(Error::invalid("degree", "must be at least 1"));
+        }
+        if control_points.len() <= degree {
+            return Err(Error::invalid(
+                "control_points",
+                "must be more than the degree",
+            ));
+        }
+        check_finite(&control_points)?;
+        Ok(Self {
+            knots: clamped_knots(control_points.len(), degree),
+            degree,
+            control_points,
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn knots(&self) -> &[Precision] {
+        &self.knots
+    }
+
+    pub fn control_points(&self) -> &[Point<Precision, D>] {
+        &self.control_points
+    }
+
+    /// The point on the curve at parameter `u`, which is clamped between 0.0 and 1.0.
+    ///
+    /// Every coordinate is NaN if `u` is NaN.
+    pub fn at(&self, u: Precision) -> Point<Precision, D> {
+        if u.is_nan() {
+            return Point::from([Precision::NAN; D]);
+        }
+        let (span, basis) = basis_functions(&self.knots, self.degree, u.clamp(0.0, 1.0));
+        let mut out = Point::origin();
+        for (i, b) in basis.iter().enumerate() {
+            out += self.control_points[span - self.degree + i].coords * *b;
+        }
+        out
+    }
+
+    /// `n_points` points on the curve, evenly spaced in parameter (not necessarily in distance),
+    /// including both ends if `n_points` is at least 2.
+    pub fn sample(&self, n_points: usize) -> Vec<Point<Precision, D>> {
+        match n_points {
+            0 => vec![],
+            1 => vec![self.at(0.0)],
+            _ => (0..n_points)
+                .map(|i| self.at(i as Precision / (n_points - 1) as Precision))
+                .collect(),
+        }
+    }
+}
+
+/// Knot vector on [0, 1] with `degree + 1` repeats at either end and uniformly spaced interior knots.
+fn clamped_knots(n_control_points: usize, degree: usize) -> Vec<Precision> {
+    let n_spans = n_control_points - degree;
+    let mut knots = vec![0.0; degree];
+    knots.extend((0..=n_spans).map(|i| i as Precision / n_spans as Precision));
+    knots.extend(std::iter::repeat_n(1.0, degree));
+    knots
+}
+
+/// Index of the knot span containing `u`, and the values of the `degree + 1` basis functions which are non-zero there
+/// (those of control points `span - degree..=span`).
+///
+/// Cox-de Boor recursion, as in Piegl & Tiller's "The NURBS Book" (algorithms A2.1 and A2.2).
+fn basis_functions(knots: &[Precision], degree: usize, u: Precision) -> (usize, Vec<Precision>) {
+    let n = knots.len() - degree - 1;
+    let span = if u >= knots[n] {
+        n - 1
+    } else {
+        knots[degree..=n].partition_point(|k| *k <= u) + degree - 1
+    };
+    let mut basis = vec![0.0; degree + 1];
+    let mut left = vec![0.0; degree + 1];
+    let mut right = vec![0.0; degree + 1];
+    basis[0] = 1.0;
+    for j in 1..=degree {
+        left[j] = u - knots[span + 1 - j];
+        right[j] = knots[span + j] - u;
+        let mut saved = 0.0;
+        for r in 0..j {
+            let temp = basis[r] / (right[r + 1] + left[j - r]);
+            basis[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        basis[j] = saved;
+    }
+    (span, basis)
+}
+
+/// Fit a clamped B-spline with `n_control_points` control points of the given `degree` to the line,
+/// minimising the sum of squared distances between each point and the curve at the same parameter.
+///
+/// Points are parameterised by their fraction of the way along the line.
+/// The first and last control points are the ends of the line, so the curve starts and ends there;
+/// the rest are fitted by least squares.
+///
+/// Fails if `degree` is 0, there are not more control points than `degree`,
+/// the line has fewer points than control points, has a NaN or infinite coordinate, or has zero length,
+/// or the points are too unevenly spread for every control point to be constrained.
+pub fn bspline<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_control_points: usize,
+    degree: usize,
+) -> Result<BSpline<D>> {
+    if degree == 0 {
+        return Err(Error::invalid("degree", "must be at least 1"));
+    }
+    if n_control_points <= degree {
+        return Err(Error::invalid(
+            "n_control_points",
+            "must be more than the degree",
+        ));
+    }
+    if line.len() < n_control_points {
+        return Err(Error::TooFewPoints {
+            n_points: line.len(),
+            requested: n_control_points,
+        });
+    }
+    check_finite(line)?;
+    let cumulative = cumulative_lengths(line);
+    let total = cumulative[cumulative.len() - 1];
+    if total.is_nan() || total <= 0.0 {
+        return Err(Error::ZeroLength);
+    }
+    let first = line[0];
+    let last = line[line.len() - 1];
+    let knots = clamped_knots(n_control_points, degree);
+    let n_free = n_control_points - 2;
+
+    let mut control_points = vec![first];
+    if n_free > 0 {
+        // least squares for the interior control points, with the fixed ends moved to the right hand side
+        let mut basis = DMatrix::zeros(line.len(), n_free);
+        let mut rhs = DMatrix::zeros(line.len(), D);
+        for (row, (p, length)) in line.iter().zip(cumulative.iter()).enumerate() {
+            let (span, values) = basis_functions(&knots, degree, length / total);
+            let mut residual = p.coords;
+            for (i, b) in values.iter().enumerate() {
+                match span - degree + i {
+                    0 => residual -= first.coords * *b,
+                    c if c == n_control_points - 1 => residual -= last.coords * *b,
+                    c => basis[(row, c - 1)] = *b,
+                }
+            }
+            for d in 0..D {
+                rhs[(row, d)] = residual[d];
+            }
+        }
+        let bt = basis.transpose();
+        let solved = (&bt * &basis)
+            .cholesky()
+            .ok_or(Error::invalid(
+                "n_control_points",
+                "too many to be constrained by the points",
+            ))?
+            .solve(&(bt * rhs));
+        control_points
+            .extend((0..n_free).map(|row| Point::from(std::array::from_fn(|d| solved[(row, d)]))));
+    }
+    control_points.push(last);
+
+    Ok(BSpline {
+        degree,
+        knots,
+        control_points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+    use nalgebra::distance;
+
+    #[test]
+    fn basis_partition_of_unity() {
+        let knots = clamped_knots(7, 3);
+        assert_eq!(knots.len(), 11);
+        for i in 0..=20 {
+            let (span, basis) = basis_functions(&knots, 3, i as Precision / 20.0);
+            assert!((3..7).contains(&span));
+            assert!((basis.iter().sum::<Precision>() - 1.0).abs() < tol(1e-12));
+            assert!(basis.iter().all(|b| *b >= 0.0));
+        }
+    }
+
+    #[test]
+    fn straight_line() {
+        let line = make_line(
+            (0..=10)
+                .map(|i| [i as Precision, 2.0 * i as Precision])
+                .collect(),
+        );
+        let spline = bspline(&line, 4, 3).unwrap();
+        assert_eq!(spline.at(0.0), line[0]);
+        assert_eq!(spline.at(1.0), line[10]);
+        for p in spline.sample(11) {
+            assert!((p.y - 2.0 * p.x).abs() < tol(1e-9));
+        }
+        // evenly spaced points on a line are fitted with an evenly parameterised curve
+        let mid = spline.at(0.5);
+        assert!(distance(&mid, &line[5]) < tol(1e-9));
+    }
+
+    #[test]
+    fn linear_interpolates() {
+        // with as many control points as points, a degree-1 spline through evenly spaced points is the line itself
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+        let spline = bspline(&line, 4, 1).unwrap();
+        for (c, p) in spline.control_points().iter().zip(line.iter()) {
+            assert!(distance(c, p) < tol(1e-12));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        assert!(bspline(&line, 3, 0).is_err());
+        assert!(bspline(&line, 3, 3).is_err());
+        assert!(matches!(
+            bspline(&line, 4, 2),
+            Err(Error::TooFewPoints { .. })
+        ));
+        let zero = make_line(vec![[1.0, 1.0]; 5]);
+        assert!(matches!(bspline(&zero, 3, 2), Err(Error::ZeroLength)));
+        assert!(BSpline::new(line, 3).is_err());
+    }
+
+    #[test]
+    fn non_finite() {
+        let mut line = make_line((0..10).map(|i| [i as Precision, 0.0]).collect());
+        let spline = bspline(&line, 4, 3).unwrap();
+        assert!(spline.at(Precision::NAN).iter().all(|c| c.is_nan()));
+        assert_eq!(spline.at(Precision::INFINITY), line[9]);
+        assert_eq!(spline.at(Precision::NEG_INFINITY), line[0]);
+
+        line[3].y = Precision::INFINITY;
+        assert_eq!(
+            bspline(&line, 4, 3),
+            Err(Error::NonFiniteCoordinate { index: 3 })
+        );
+        line[3].y = Precision::NAN;
+        assert_eq!(
+            bspline(&line, 4, 3),
+            Err(Error::NonFiniteCoordinate { index: 3 })
+        );
+        assert_eq!(
+            BSpline::new(line, 3),
+            Err(Error::NonFiniteCoordinate { index: 3 })
+        );
+    }
+}
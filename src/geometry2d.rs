@@ -0,0 +1,173 @@
+//! Find and remove places where a 2D linestring crosses itself.
+//!
+//! Simplification can introduce crossings which were not in the original line,
+//! which many consumers (e.g. polygon triangulators) cannot handle.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::geometry2d::{find_self_intersections, remove_self_intersections};
+//! use simples::Point;
+//!
+//! // a bow tie
+//! let ring: Vec<Point<Precision, 2>> = vec![
+//!     [0.0, 0.0].into(),
+//!     [2.0, 2.0].into(),
+//!     [2.0, 0.0].into(),
+//!     [0.0, 2.0].into(),
+//! ];
+//! let found = find_self_intersections(&ring, true);
+//! assert_eq!(found.len(), 1);
+//! assert_eq!(found[0].edges, (0, 2));
+//! assert_eq!(found[0].point, [1.0, 1.0].into());
+//!
+//! let fixed = remove_self_intersections(&ring, true);
+//! assert!(find_self_intersections(&fixed, true).is_empty());
+//! ```
+use crate::bvh::{build_bvh, Aabb};
+use crate::metrics::{segment_intersection, shoelace2};
+use crate::{CurveKind, Lerp, Precision};
+use nalgebra::Point;
+
+/// A place where two non-adjacent edges of a line meet.
+///
+/// Edge `i` goes from point `i` to point `i + 1`;
+/// for closed lines, the last edge goes from the last point back to the first.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SelfIntersection {
+    /// Indices of the two edges, lowest first.
+    pub edges: (usize, usize),
+    pub point: Point<Precision, 2>,
+}
+
+/// Whether two edges share an endpoint, so are expected to touch.
+fn adjacent(a: usize, b: usize, n_edges: usize, closed: bool) -> bool {
+    b == a + 1 || (closed && a == 0 && b + 1 == n_edges)
+}
+
+/// Find every pair of non-adjacent edges which cross or touch, ordered by their edge indices.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// Candidate pairs are found with a [crate::bvh::Bvh], so this is fast where few edges overlap.
+/// Collinear overlapping edges are not reported.
+pub fn find_self_intersections(
+    line: &[Point<Precision, 2>],
+    kind: impl Into<CurveKind>,
+) -> Vec<SelfIntersection> {
+    let closed = kind.into().is_closed() && line.len() > 2;
+    let mut edges = line.to_vec();
+    if closed {
+        edges.push(line[0]);
+    }
+    let n_edges = edges.len().saturating_sub(1);
+    let bvh = build_bvh(&edges);
+    let mut out = Vec::default();
+    for (a, pa) in edges.windows(2).enumerate() {
+        for b in bvh.query_box(&Aabb::new(pa[0], pa[1])) {
+            if b <= a || adjacent(a, b, n_edges, closed) {
+                continue;
+            }
+            if let Some((t, _)) = segment_intersection(&pa[0], &pa[1], &edges[b], &edges[b + 1]) {
+                out.push(SelfIntersection {
+                    edges: (a, b),
+                    point: pa[0].lerp(&pa[1], t),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Remove loops until the line no longer crosses itself.
+///
+/// For open lines, the points between each crossing are replaced by the crossing point,
+/// so the ends of the line are kept.
+/// Closed lines are split at each crossing into two rings, and the ring with the larger area is kept.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+pub fn remove_self_intersections(
+    line: &[Point<Precision, 2>],
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, 2>> {
+    let closed = kind.into().is_closed();
+    let mut out = line.to_vec();
+    // each iteration removes at least one point, so this terminates
+    while let Some(x) = find_self_intersections(&out, closed).first().copied() {
+        let (a, b) = x.edges;
+        let mut inner = vec![x.point];
+        inner.extend_from_slice(&out[(a + 1)..=b]);
+        let mut outer = out[..=a].to_vec();
+        outer.push(x.point);
+        outer.extend_from_slice(&out[(b + 1)..]);
+        out = if closed && shoelace2(&inner).abs() > shoelace2(&outer).abs() {
+            inner
+        } else {
+            outer
+        };
+        out.dedup();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn simple_lines() {
+        let square = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+        assert!(find_self_intersections(&square, true).is_empty());
+        assert!(find_self_intersections(&square, false).is_empty());
+        assert!(find_self_intersections(&square[..2], true).is_empty());
+    }
+
+    #[test]
+    fn open_loop() {
+        // goes right, loops back up and across itself, then continues right
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [3.0, 1.0],
+            [2.0, -1.0],
+            [6.0, -1.0],
+        ]);
+        let found = find_self_intersections(&line, false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].edges, (0, 2));
+        assert_eq!(found[0].point, [2.5, 0.0].into());
+
+        let fixed = remove_self_intersections(&line, false);
+        assert_eq!(
+            fixed,
+            make_line(vec![[0.0, 0.0], [2.5, 0.0], [2.0, -1.0], [6.0, -1.0]])
+        );
+    }
+
+    #[test]
+    fn touching_vertex() {
+        // the last point lands on the first edge
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 0.0]]);
+        let found = find_self_intersections(&line, false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].point, [1.0, 0.0].into());
+    }
+
+    #[test]
+    fn ring_keeps_larger_part() {
+        // a big square with a small twisted lobe on one corner
+        let ring = make_line(vec![
+            [0.0, 0.0],
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [11.0, 11.0],
+            [11.0, 10.0],
+            [10.0, 11.0],
+            [0.0, 10.0],
+        ]);
+        assert_eq!(find_self_intersections(&ring, true).len(), 1);
+        let fixed = remove_self_intersections(&ring, true);
+        assert!(find_self_intersections(&fixed, true).is_empty());
+        assert!(shoelace2(&fixed).abs() / 2.0 > 99.0);
+        assert!(fixed.iter().all(|p| p.x <= 10.5));
+    }
+}
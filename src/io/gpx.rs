@@ -0,0 +1,433 @@
+//! [GPX](https://www.topografix.com/gpx.asp) tracks.
+//!
+//! Track points are read as `(x, y) = (longitude, latitude)` for `D = 2`,
+//! or `(longitude, latitude, elevation)` for `D = 3`, in which case every point must have an elevation.
+//! Times are read as seconds since the Unix epoch, and written back in UTC.
+//!
+//! Only tracks (`<trk>`) are read; waypoints, routes and extensions are ignored,
+//! and are not written back.
+//!
+//! ```
+//! use simples::io::gpx::{read_gpx, write_gpx};
+//! use simples::simplify::rdp::rdp_keep;
+//!
+//! let xml = r#"<gpx version="1.1" creator="me" xmlns="http://www.topografix.com/GPX/1/1">
+//!   <trk><name>Walk</name><trkseg>
+//!     <trkpt lat="51.0" lon="0.0"><time>2024-05-01T09:00:00Z</time></trkpt>
+//!     <trkpt lat="51.00001" lon="0.001"><time>2024-05-01T09:01:00Z</time></trkpt>
+//!     <trkpt lat="51.0" lon="0.002"><time>2024-05-01T09:02:00Z</time></trkpt>
+//!   </trkseg></trk>
+//! </gpx>"#;
+//! let mut tracks = read_gpx::<2>(xml).unwrap();
+//! let segment = &mut tracks[0].segments[0];
+//! *segment = segment.keep(&rdp_keep(&segment.points, 0.0001, false));
+//! assert_eq!(segment.points.len(), 2);
+//!
+//! let written = write_gpx(&tracks, "simples").unwrap();
+//! assert!(written.contains("<time>2024-05-01T09:02:00Z</time>"));
+//! assert_eq!(read_gpx::<2>(&written).unwrap(), tracks);
+//! ```
+use std::fmt::Write;
+
+use crate::error::{Error, Result};
+use crate::simplify::apply_keep;
+use crate::{check_finite, Point, Precision, Time, TimedPoint};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A continuous run of track points.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpxSegment<const D: usize> {
+    pub points: Vec<Point<Precision, D>>,
+    /// Seconds since the Unix epoch of each point, if every point has a time.
+    pub times: Option<Vec<Time>>,
+}
+
+impl<const D: usize> GpxSegment<D> {
+    /// Keep only the points (and times) at these indices, e.g. from [crate::simplify::rdp::rdp_keep].
+    pub fn keep(&self, keep: &[usize]) -> Self {
+        Self {
+            points: apply_keep(keep, &self.points),
+            times: self.times.as_ref().map(|t| apply_keep(keep, t)),
+        }
+    }
+
+    /// Replace the points with the output of `f`, e.g. a smoother.
+    ///
+    /// Times are kept if `f` returns the same number of points, and dropped otherwise;
+    /// to resample a timed segment, use [GpxSegment::timed] instead.
+    pub fn map_points<F>(&self, f: F) -> Self
+    where
+        F: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        let points = f(&self.points);
+        let times = self
+            .times
+            .as_ref()
+            .filter(|t| t.len() == points.len())
+            .cloned();
+        Self { points, times }
+    }
+
+    /// The points with their times, if they have them.
+    pub fn timed(&self) -> Option<Vec<TimedPoint<D>>> {
+        let times = self.times.as_ref()?;
+        Some(
+            self.points
+                .iter()
+                .zip(times.iter())
+                .map(|(p, t)| TimedPoint::new(*p, *t))
+                .collect(),
+        )
+    }
+
+    pub fn from_timed(points: &[TimedPoint<D>]) -> Self {
+        Self {
+            points: points.iter().map(|p| p.point).collect(),
+            times: Some(points.iter().map(|p| p.time).collect()),
+        }
+    }
+}
+
+/// A track, made up of segments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpxTrack<const D: usize> {
+    pub name: Option<String>,
+    pub segments: Vec<GpxSegment<D>>,
+}
+
+fn parse_err(node: roxmltree::Node, reason: &'static str) -> Error {
+    Error::Parse {
+        offset: node.range().start,
+        reason,
+    }
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|c| c.tag_name().name() == name)
+}
+
+fn attr(node: roxmltree::Node, name: &str) -> Result<Precision> {
+    node.attribute(name)
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or_else(|| parse_err(node, "missing or invalid lat/lon"))
+}
+
+fn read_segment<const D: usize>(seg: roxmltree::Node) -> Result<GpxSegment<D>> {
+    let mut out = GpxSegment::default();
+    let mut times = Vec::new();
+    for pt in seg.children().filter(|c| c.tag_name().name() == "trkpt") {
+        let mut coords = [0.0; D];
+        coords[0] = attr(pt, "lon")?;
+        coords[1] = attr(pt, "lat")?;
+        if D == 3 {
+            coords[2] = child(pt, "ele")
+                .and_then(|e| e.text())
+                .and_then(|t| t.trim().parse().ok())
+                .ok_or_else(|| parse_err(pt, "missing or invalid elevation"))?;
+        }
+        out.points.push(coords.into());
+        if let Some(time) = child(pt, "time") {
+            let text = time.text().unwrap_or_default();
+            times.push(parse_time(text).ok_or_else(|| parse_err(time, "invalid time"))?);
+        }
+    }
+    if !out.points.is_empty() && times.len() == out.points.len() {
+        out.times = Some(times);
+    }
+    Ok(out)
+}
+
+/// Read every track from a GPX document.
+///
+/// `D` must be 2 (longitude, latitude) or 3 (with elevation).
+pub fn read_gpx<const D: usize>(xml: &str) -> Result<Vec<GpxTrack<D>>> {
+    if !(D == 2 || D == 3) {
+        return Err(Error::invalid("D", "must be 2 or 3"));
+    }
+    let doc = roxmltree::Document::parse(xml).map_err(|_| Error::Parse {
+        offset: 0,
+        reason: "invalid XML",
+    })?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "gpx" {
+        return Err(parse_err(root, "root element is not <gpx>"));
+    }
+    root.children()
+        .filter(|c| c.tag_name().name() == "trk")
+        .map(|trk| {
+            Ok(GpxTrack {
+                name: child(trk, "name").and_then(|n| n.text()).map(String::from),
+                segments: trk
+                    .children()
+                    .filter(|c| c.tag_name().name() == "trkseg")
+                    .map(read_segment)
+                    .collect::<Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Write tracks as a GPX 1.1 document.
+///
+/// `D` must be 2 (longitude, latitude) or 3 (with elevation).
+pub fn write_gpx<const D: usize>(tracks: &[GpxTrack<D>], creator: &str) -> Result<String> {
+    if !(D == 2 || D == 3) {
+        return Err(Error::invalid("D", "must be 2 or 3"));
+    }
+    let mut out = String::new();
+    // writing to a String cannot fail
+    let _ = write!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"{}\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        escape(creator)
+    );
+    for track in tracks {
+        out.push_str("  <trk>\n");
+        if let Some(name) = &track.name {
+            let _ = writeln!(out, "    <name>{}</name>", escape(name));
+        }
+        for seg in track.segments.iter() {
+            check_finite(&seg.points)?;
+            if seg
+                .times
+                .as_ref()
+                .is_some_and(|t| t.len() != seg.points.len())
+            {
+                return Err(Error::invalid("times", "must be the same length as points"));
+            }
+            out.push_str("    <trkseg>\n");
+            for (idx, p) in seg.points.iter().enumerate() {
+                let _ = write!(out, "      <trkpt lat=\"{}\" lon=\"{}\">", p[1], p[0]);
+                if D == 3 {
+                    let _ = write!(out, "<ele>{}</ele>", p[2]);
+                }
+                if let Some(times) = &seg.times {
+                    let _ = write!(out, "<time>{}</time>", format_time(times[idx])?);
+                }
+                out.push_str("</trkpt>\n");
+            }
+            out.push_str("    </trkseg>\n");
+        }
+        out.push_str("  </trk>\n");
+    }
+    out.push_str("</gpx>\n");
+    Ok(out)
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [days_from_civil].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parse an RFC 3339 timestamp (`YYYY-MM-DDTHH:MM:SS[.fff](Z|+HH:MM|-HH:MM)`) into seconds since the Unix epoch.
+fn parse_time(s: &str) -> Option<Time> {
+    let s = s.trim();
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    if b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let int = |range: std::ops::Range<usize>| -> Option<i64> {
+        let part = s.get(range)?;
+        part.bytes()
+            .all(|c| c.is_ascii_digit())
+            .then(|| part.parse().ok())?
+    };
+    let (year, month, day) = (int(0..4)?, int(5..7)?, int(8..10)?);
+    let (hour, minute, second) = (int(11..13)?, int(14..16)?, int(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let mut rest = &s[19..];
+    let mut fraction = 0.0;
+    if let Some(frac) = rest.strip_prefix('.') {
+        let n_digits = frac.bytes().take_while(|c| c.is_ascii_digit()).count();
+        if n_digits == 0 {
+            return None;
+        }
+        fraction = format!("0.{}", &frac[..n_digits]).parse().ok()?;
+        rest = &frac[n_digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let tz = rest.get(1..)?;
+            if tz.len() != 5 || tz.as_bytes()[2] != b':' {
+                return None;
+            }
+            let (h, m): (i64, i64) = (tz[..2].parse().ok()?, tz[3..].parse().ok()?);
+            sign * (h * 3600 + m * 60)
+        }
+    };
+    let seconds =
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second
+            - offset;
+    Some(seconds as Time + fraction)
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp in UTC, to millisecond precision.
+fn format_time(time: Time) -> Result<String> {
+    if !time.is_finite() {
+        return Err(Error::invalid("times", "must be finite"));
+    }
+    let millis = (time * 1000.0).round() as i64;
+    let seconds = millis.div_euclid(1000);
+    let millis = millis.rem_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    let secs_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let mut out = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    );
+    if millis != 0 {
+        let _ = write!(out, ".{millis:03}");
+    }
+    out.push('Z');
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::sample::resample_with;
+
+    const TRACK: &str = r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="1" lon="1"/>
+  <trk>
+    <name>Ups &amp; downs</name>
+    <trkseg>
+      <trkpt lat="10" lon="20"><ele>100</ele><time>2000-01-01T00:00:00Z</time></trkpt>
+      <trkpt lat="10.5" lon="20"><ele>200</ele><time>2000-01-01T01:00:00.5+01:00</time></trkpt>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="0" lon="0"><ele>0</ele></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    #[test]
+    fn reads() {
+        let tracks = read_gpx::<3>(TRACK).unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].name.as_deref(), Some("Ups & downs"));
+        let seg = &tracks[0].segments[0];
+        assert_eq!(seg.points[1], [20.0, 10.5, 200.0].into());
+        assert_eq!(seg.times, Some(vec![946_684_800.0, 946_684_800.5]));
+        assert_eq!(tracks[0].segments[1].times, None);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let tracks = read_gpx::<2>(TRACK).unwrap();
+        let written = write_gpx(&tracks, "a \"creator\"").unwrap();
+        assert!(written.contains("creator=\"a &quot;creator&quot;\""));
+        assert!(written.contains("<time>2000-01-01T00:00:00.500Z</time>"));
+        assert_eq!(read_gpx::<2>(&written).unwrap(), tracks);
+        let tracks = read_gpx::<3>(TRACK).unwrap();
+        assert_eq!(
+            read_gpx::<3>(&write_gpx(&tracks, "test").unwrap()).unwrap(),
+            tracks
+        );
+    }
+
+    #[test]
+    fn resample_timed() {
+        let seg = &read_gpx::<2>(TRACK).unwrap()[0].segments[0];
+        let resampled = GpxSegment::from_timed(&resample_with(&seg.timed().unwrap(), 3));
+        assert_eq!(resampled.points[1], [20.0, 10.25].into());
+        assert_eq!(resampled.times.unwrap()[1], 946_684_800.25);
+    }
+
+    #[test]
+    fn times() {
+        for (s, t) in [
+            ("1970-01-01T00:00:00Z", 0.0),
+            ("1969-12-31T23:59:59Z", -1.0),
+            ("2024-02-29T12:00:00Z", 1_709_208_000.0),
+            ("2024-02-29T13:30:00+01:30", 1_709_208_000.0),
+        ] {
+            assert_eq!(parse_time(s), Some(t), "{s}");
+        }
+        assert_eq!(
+            format_time(1_709_208_000.0).unwrap(),
+            "2024-02-29T12:00:00Z"
+        );
+        assert_eq!(format_time(-0.25).unwrap(), "1969-12-31T23:59:59.750Z");
+        for s in [
+            "2024-02-29",
+            "2024-13-01T00:00:00Z",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00.Z",
+        ] {
+            assert_eq!(parse_time(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(read_gpx::<2>(
+            "<gpx><trk><trkseg><trkpt lat=\"x\" lon=\"0\"/></trkseg></trk></gpx>"
+        )
+        .is_err());
+        assert!(read_gpx::<3>(
+            "<gpx><trk><trkseg><trkpt lat=\"0\" lon=\"0\"/></trkseg></trk></gpx>"
+        )
+        .is_err());
+        assert!(read_gpx::<2>("<kml/>").is_err());
+        assert!(read_gpx::<2>("<gpx>").is_err());
+        assert!(read_gpx::<4>(TRACK).is_err());
+    }
+}
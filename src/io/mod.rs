@@ -0,0 +1,4 @@
+//! Reading and writing domain-specific file formats.
+#[cfg(feature = "gpx")]
+pub mod gpx;
+pub mod swc;
@@ -0,0 +1,327 @@
+//! [SWC](http://www.neuronland.org/NLMorphologyConverter/MorphologyFormats/SWC/Spec.html) neuron skeletons.
+//!
+//! Each line of an SWC file is a node: `id type x y z radius parent`, with a parent of `-1` for roots.
+//! Lines starting with `#` are comments, which are kept as the header.
+//!
+//! Skeletons are processed per branch (see [crate::tree]);
+//! new nodes take their radius and type from the nearest part of the original branch,
+//! and nodes are renumbered from 1 with parents before their children.
+//!
+//! ```
+//! use simples::io::swc::Swc;
+//! use simples::simplify::rdp::rdp_reduce;
+//!
+//! let swc: Swc = "\
+//! ## a single straight branch
+//! 1 1 0 0 0 2.0 -1
+//! 2 3 1 0.01 0 1.0 1
+//! 3 3 2 0 0 0.5 2
+//! ".parse().unwrap();
+//! let simplified = swc.map_branches(|b| rdp_reduce(b, 0.1, false)).unwrap();
+//! assert_eq!(simplified.nodes.len(), 2);
+//! assert_eq!(
+//!     simplified.to_string(),
+//!     "# a single straight branch\n1 1 0 0 0 2 -1\n2 3 2 0 0 0.5 1\n",
+//! );
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::split::project_onto_edge;
+use crate::tree::{Origin, PolylineTree};
+use crate::{Lerp, Point, Precision};
+use nalgebra::distance_squared;
+
+/// One line of an SWC file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwcNode {
+    pub id: usize,
+    /// Structure identifier, e.g. 1 for soma, 2 for axon, 3 for (basal) dendrite.
+    pub structure: i32,
+    pub point: Point<Precision, 3>,
+    pub radius: Precision,
+    /// ID (not index) of the parent node, or None for a root.
+    pub parent: Option<usize>,
+}
+
+/// The points and radii of an unbranched path between key nodes; see [PolylineTree::branches].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwcBranch {
+    /// Indices into [Swc::nodes].
+    pub nodes: Vec<usize>,
+    pub points: Vec<Point<Precision, 3>>,
+    pub radii: Vec<Precision>,
+}
+
+/// A neuron skeleton read from an SWC file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Swc {
+    /// Comment lines, without their leading `#`.
+    pub header: Vec<String>,
+    pub nodes: Vec<SwcNode>,
+}
+
+impl Swc {
+    /// The skeleton as a [PolylineTree], with nodes in the same order as [Swc::nodes].
+    ///
+    /// Fails if IDs are repeated, a parent ID does not exist, or the parents form a cycle.
+    pub fn tree(&self) -> Result<PolylineTree<3>> {
+        let mut indices = HashMap::with_capacity(self.nodes.len());
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if indices.insert(node.id, idx).is_some() {
+                return Err(Error::invalid("nodes", "IDs must be unique"));
+            }
+        }
+        let parents = self
+            .nodes
+            .iter()
+            .map(|n| match n.parent {
+                Some(p) => indices
+                    .get(&p)
+                    .copied()
+                    .map(Some)
+                    .ok_or(Error::invalid("parent", "must be the ID of a node")),
+                None => Ok(None),
+            })
+            .collect::<Result<_>>()?;
+        PolylineTree::new(self.nodes.iter().map(|n| n.point).collect(), parents)
+    }
+
+    /// Every unbranched path between key nodes, with its points and radii.
+    pub fn branches(&self) -> Result<Vec<SwcBranch>> {
+        Ok(self
+            .tree()?
+            .branches()
+            .into_iter()
+            .map(|nodes| self.branch(nodes))
+            .collect())
+    }
+
+    fn branch(&self, nodes: Vec<usize>) -> SwcBranch {
+        SwcBranch {
+            points: nodes.iter().map(|idx| self.nodes[*idx].point).collect(),
+            radii: nodes.iter().map(|idx| self.nodes[*idx].radius).collect(),
+            nodes,
+        }
+    }
+
+    /// Replace the interior of every branch with the output of `f`; see [PolylineTree::map_branches].
+    ///
+    /// Key nodes keep their radius and type.
+    /// Each new interior node's radius is interpolated from the nearest edge of the original branch
+    /// (searching forwards from the previous node's edge, so that branches which double back are followed in order),
+    /// and its type is that of the nearer end of that edge.
+    pub fn map_branches<F>(&self, f: F) -> Result<Self>
+    where
+        F: FnMut(&[Point<Precision, 3>]) -> Vec<Point<Precision, 3>>,
+    {
+        let tree = self.tree()?;
+        let branches: Vec<_> = tree
+            .branches()
+            .into_iter()
+            .map(|b| self.branch(b))
+            .collect();
+        let (new_tree, origins) = tree.map_branches_with_origin(f);
+
+        let mut nodes = Vec::with_capacity(new_tree.len());
+        // edge of the current branch to start searching from
+        let mut cursor = (usize::MAX, 0);
+        for (idx, ((point, parent), origin)) in new_tree
+            .points()
+            .iter()
+            .zip(new_tree.parents())
+            .zip(origins)
+            .enumerate()
+        {
+            let (structure, radius) = match origin {
+                Origin::Node(orig) => (self.nodes[orig].structure, self.nodes[orig].radius),
+                Origin::Branch(b) => {
+                    if cursor.0 != b {
+                        cursor = (b, 0);
+                    }
+                    let branch = &branches[b];
+                    let (edge, t) = nearest_edge(&branch.points, point, cursor.1);
+                    cursor.1 = edge;
+                    let nearer = if t < 0.5 { edge } else { edge + 1 };
+                    (
+                        self.nodes[branch.nodes[nearer]].structure,
+                        branch.radii[edge].lerp(&branch.radii[edge + 1], t),
+                    )
+                }
+            };
+            nodes.push(SwcNode {
+                id: idx + 1,
+                structure,
+                point: *point,
+                radius,
+                parent: parent.map(|p| p + 1),
+            });
+        }
+        Ok(Self {
+            header: self.header.clone(),
+            nodes,
+        })
+    }
+}
+
+/// Index of the edge at or after `start` nearest to `p`, and the fraction of the way along it of the nearest point.
+fn nearest_edge(
+    line: &[Point<Precision, 3>],
+    p: &Point<Precision, 3>,
+    start: usize,
+) -> (usize, Precision) {
+    let mut best = (start, 0.0, Precision::INFINITY);
+    for (edge, w) in line.windows(2).enumerate().skip(start) {
+        let t = project_onto_edge(&w[0], &w[1], p);
+        let d2 = distance_squared(&w[0].lerp(&w[1], t), p);
+        if d2 < best.2 {
+            best = (edge, t, d2);
+        }
+    }
+    (best.0, best.1)
+}
+
+fn parse_field<T: FromStr>(field: Option<&str>, offset: usize, reason: &'static str) -> Result<T> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or(Error::Parse { offset, reason })
+}
+
+impl FromStr for Swc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut out = Self::default();
+        let mut offset = 0;
+        for line in s.split_inclusive('\n') {
+            let line_offset = offset;
+            offset += line.len();
+            let trimmed = line.trim();
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                out.header.push(comment.to_string());
+                continue;
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+            let mut fields = trimmed.split_whitespace();
+            let id = parse_field(fields.next(), line_offset, "invalid node ID")?;
+            let structure = parse_field(fields.next(), line_offset, "invalid type")?;
+            let mut coords = [0.0; 3];
+            for c in coords.iter_mut() {
+                *c = parse_field(fields.next(), line_offset, "invalid coordinate")?;
+            }
+            let radius = parse_field(fields.next(), line_offset, "invalid radius")?;
+            let parent: i64 = parse_field(fields.next(), line_offset, "invalid parent ID")?;
+            if fields.next().is_some() {
+                return Err(Error::Parse {
+                    offset: line_offset,
+                    reason: "too many fields",
+                });
+            }
+            out.nodes.push(SwcNode {
+                id,
+                structure,
+                point: coords.into(),
+                radius,
+                parent: usize::try_from(parent).ok(),
+            });
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for Swc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.header.iter() {
+            writeln!(f, "#{line}")?;
+        }
+        for n in self.nodes.iter() {
+            write!(
+                f,
+                "{} {} {} {} {} {} ",
+                n.id, n.structure, n.point.x, n.point.y, n.point.z, n.radius
+            )?;
+            match n.parent {
+                Some(p) => writeln!(f, "{p}")?,
+                None => writeln!(f, "-1")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::sample::sample_every;
+
+    /// A "Y" with the stem along y and arms at 45 degrees, with radius decreasing towards the tips.
+    const Y: &str = "\
+# created by hand
+#
+10 1 0 0 0 4 -1
+11 3 0 1 0 3 10
+12 3 0 2 0 2 11
+13 2 -1 3 0 1 12
+14 2 1 3 0 1 12
+";
+
+    #[test]
+    fn roundtrip() {
+        let swc: Swc = Y.parse().unwrap();
+        assert_eq!(
+            swc.header,
+            vec![" created by hand".to_string(), String::new()]
+        );
+        assert_eq!(swc.nodes.len(), 5);
+        assert_eq!(swc.nodes[2].parent, Some(11));
+        assert_eq!(swc.to_string().parse::<Swc>().unwrap(), swc);
+    }
+
+    #[test]
+    fn branches() {
+        let swc: Swc = Y.parse().unwrap();
+        let branches = swc.branches().unwrap();
+        assert_eq!(branches.len(), 3);
+        assert_eq!(branches[0].nodes, vec![0, 1, 2]);
+        assert_eq!(branches[0].radii, vec![4.0, 3.0, 2.0]);
+        assert_eq!(branches[1].nodes, vec![2, 3]);
+    }
+
+    #[test]
+    fn resampled_attributes() {
+        let swc: Swc = Y.parse().unwrap();
+        let resampled = swc
+            .map_branches(|b| sample_every(b, 0.5, 0.0, false).0)
+            .unwrap();
+        let tree = resampled.tree().unwrap();
+        assert_eq!(tree.roots().count(), 1);
+        for (idx, n) in resampled.nodes.iter().enumerate() {
+            assert_eq!(n.id, idx + 1);
+            assert!(n.parent.is_none_or(|p| p <= idx));
+        }
+        // halfway between the root and the next node
+        let mid = &resampled.nodes[1];
+        assert_eq!(mid.point, [0.0, 0.5, 0.0].into());
+        assert_eq!(mid.radius, 3.5);
+        assert_eq!(mid.structure, 3);
+        let tip = resampled.nodes.iter().find(|n| n.point.x < -0.9).unwrap();
+        assert_eq!((tip.radius, tip.structure), (1.0, 2));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            "1 1 0 0 0 1 -1\n2 1 0 x 0 1 1\n".parse::<Swc>(),
+            Err(Error::Parse { offset: 15, .. })
+        ));
+        assert!("1 1 0 0 0 1\n".parse::<Swc>().is_err());
+        let missing: Swc = "1 1 0 0 0 1 -1\n2 1 0 0 0 1 3\n".parse().unwrap();
+        assert!(missing.tree().is_err());
+        let duplicate: Swc = "1 1 0 0 0 1 -1\n1 1 0 0 0 1 1\n".parse().unwrap();
+        assert!(duplicate.tree().is_err());
+    }
+}
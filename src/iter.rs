@@ -0,0 +1,244 @@
+//! Iterator adapters for the algorithms which can process a line one point at a time,
+//! so that they can be chained in pipelines without collecting the points in between.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::iter::LineIterExt;
+//! use simples::simplify::{radial_reduce, sample_every};
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = (0..100).map(|i| [i as Precision * 0.1, 0.0].into()).collect();
+//! let streamed: Vec<_> = line.iter().simplify_radial(0.25).sample_every(1.0).collect();
+//! assert_eq!(streamed, sample_every(&radial_reduce(&line, 0.25), 1.0, 0.0, false).0);
+//! ```
+use crate::simplify::incremental::{IncrementalSimplify, Radial, WindowedVw};
+use crate::simplify::sleeve::Sleeve;
+use crate::Precision;
+use nalgebra::Point;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+/// Adapters for iterators of points (or references to points); see the [module docs](self).
+pub trait LineIterExt<const D: usize>: Iterator + Sized {
+    /// Simplify the points with any [IncrementalSimplify].
+    fn simplify_with<S: IncrementalSimplify<D>>(self, simplifier: S) -> Simplified<Self, S, D>;
+
+    /// Simplify the points with the radial distance filter;
+    /// see [radial_keep](crate::simplify::radial::radial_keep).
+    fn simplify_radial(self, tolerance: Precision) -> Simplified<Self, Radial<D>, D> {
+        self.simplify_with(Radial::new(tolerance))
+    }
+
+    /// Simplify the points with the sleeve-fitting algorithm;
+    /// see [sleeve_keep](crate::simplify::sleeve::sleeve_keep).
+    fn simplify_sleeve(self, epsilon: Precision) -> Simplified<Self, Sleeve, D>
+    where
+        Sleeve: IncrementalSimplify<D>,
+    {
+        self.simplify_with(Sleeve::new(epsilon))
+    }
+
+    /// Simplify the points with Visvalingam-Whyatt over a sliding window; see [WindowedVw].
+    fn simplify_windowed_vw(
+        self,
+        window: usize,
+        min_area: Precision,
+    ) -> Simplified<Self, WindowedVw<D>, D> {
+        self.simplify_with(WindowedVw::new(window, min_area))
+    }
+
+    /// Resample the points every `sample_distance` along the line, starting with the first point;
+    /// as [sample_every](crate::simplify::sample::sample_every) with an offset of 0.0.
+    ///
+    /// Panics if `sample_distance` is not positive.
+    fn sample_every(self, sample_distance: Precision) -> SampleEvery<Self, D>;
+}
+
+impl<I, const D: usize> LineIterExt<D> for I
+where
+    I: Iterator,
+    I::Item: Borrow<Point<Precision, D>>,
+{
+    fn simplify_with<S: IncrementalSimplify<D>>(self, simplifier: S) -> Simplified<Self, S, D> {
+        Simplified {
+            points: self,
+            simplifier,
+            tail: None,
+        }
+    }
+
+    fn sample_every(self, sample_distance: Precision) -> SampleEvery<Self, D> {
+        if sample_distance <= 0.0 {
+            panic!("`sample_distance` must be positive");
+        }
+        SampleEvery {
+            points: self,
+            sample_distance,
+            remaining_dist: sample_distance,
+            prev: None,
+            next: None,
+        }
+    }
+}
+
+/// Iterator over the points of a line simplified by an [IncrementalSimplify];
+/// see [LineIterExt::simplify_with].
+#[derive(Clone, Debug)]
+pub struct Simplified<I, S, const D: usize> {
+    points: I,
+    simplifier: S,
+    /// Points returned by [IncrementalSimplify::finish], once the input has run out.
+    tail: Option<std::vec::IntoIter<Point<Precision, D>>>,
+}
+
+impl<I, S, const D: usize> Iterator for Simplified<I, S, D>
+where
+    I: Iterator,
+    I::Item: Borrow<Point<Precision, D>>,
+    S: IncrementalSimplify<D>,
+{
+    type Item = Point<Precision, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(tail) = self.tail.as_mut() {
+            return tail.next();
+        }
+        for p in self.points.by_ref() {
+            if let Some(kept) = self.simplifier.push(*p.borrow()) {
+                return Some(kept);
+            }
+        }
+        let mut tail = self.simplifier.finish().into_iter();
+        let out = tail.next();
+        self.tail = Some(tail);
+        out
+    }
+}
+
+/// Iterator over evenly-spaced points along a line; see [LineIterExt::sample_every].
+#[derive(Clone, Debug)]
+pub struct SampleEvery<I, const D: usize> {
+    points: I,
+    sample_distance: Precision,
+    /// Distance along the line from `prev` to the next sample.
+    remaining_dist: Precision,
+    /// The latest sample or original point passed.
+    prev: Option<Point<Precision, D>>,
+    /// The end of the edge `prev` is on.
+    next: Option<Point<Precision, D>>,
+}
+
+impl<I, const D: usize> Iterator for SampleEvery<I, D>
+where
+    I: Iterator,
+    I::Item: Borrow<Point<Precision, D>>,
+{
+    type Item = Point<Precision, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(mut prev) = self.prev else {
+            let first = *self.points.next()?.borrow();
+            self.prev = Some(first);
+            return Some(first);
+        };
+        loop {
+            let next = match self.next {
+                Some(n) => n,
+                None => *self.next.insert(*self.points.next()?.borrow()),
+            };
+            let vec = next - prev;
+            let edge_length = vec.magnitude();
+
+            match self.remaining_dist.partial_cmp(&edge_length).unwrap() {
+                Ordering::Less => {
+                    prev += (vec / edge_length) * self.remaining_dist;
+                    self.remaining_dist = self.sample_distance;
+                    self.prev = Some(prev);
+                    return Some(prev);
+                }
+                Ordering::Equal => {
+                    self.remaining_dist = self.sample_distance;
+                    self.prev = Some(next);
+                    self.next = None;
+                    return Some(next);
+                }
+                Ordering::Greater => {
+                    self.remaining_dist -= edge_length;
+                    prev = next;
+                    self.prev = Some(next);
+                    self.next = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::incremental::IncrementalSimplifier;
+    use crate::simplify::radial::radial_reduce;
+    use crate::simplify::sample::sample_every;
+    use crate::test_utils::make_line;
+
+    fn wiggly() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..100)
+                .map(|i| {
+                    let x = i as Precision * 0.1;
+                    [x, x.sin() + (x * 7.0).sin() * 0.05]
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn simplified_matches_incremental() {
+        let line = wiggly();
+        let radial: Vec<_> = line.iter().simplify_radial(0.35).collect();
+        assert_eq!(radial, radial_reduce(&line, 0.35));
+
+        let mut simplifier = IncrementalSimplifier::new(WindowedVw::new(5, 0.01));
+        for p in line.iter() {
+            simplifier.push(*p);
+        }
+        let vw: Vec<_> = line.into_iter().simplify_windowed_vw(5, 0.01).collect();
+        assert_eq!(vw, simplifier.finish());
+    }
+
+    #[test]
+    fn sample_matches_batch() {
+        let lines = [
+            wiggly(),
+            make_line(vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [1.0, 0.0],
+                [1.0, 2.0],
+                [1.5, 2.0],
+            ]),
+            make_line(vec![[1.0, 1.0]]),
+            vec![],
+        ];
+        for line in lines.iter() {
+            for d in [0.5, 0.3, 10.0] {
+                let sampled: Vec<_> = line.iter().sample_every(d).collect();
+                assert_eq!(sampled, sample_every(line, d, 0.0, false).0);
+            }
+        }
+    }
+
+    #[test]
+    fn chained() {
+        let line = wiggly();
+        let chained: Vec<_> = line
+            .iter()
+            .sample_every(0.05)
+            .simplify_radial(0.2)
+            .simplify_sleeve(0.05)
+            .collect();
+        let sampled = sample_every(&line, 0.05, 0.0, false).0;
+        let expected = crate::simplify::sleeve::sleeve_reduce(&radial_reduce(&sampled, 0.2), 0.05);
+        assert_eq!(chained, expected);
+    }
+}
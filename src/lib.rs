@@ -1,13 +1,178 @@
 //! Simplify and smooth linestrings in N dimensions.
-pub use nalgebra;
+use convert::StorageScalar;
 use nalgebra::distance;
-pub use nalgebra::Point;
 
+#[cfg(feature = "rayon")]
+pub mod batch;
+pub mod bvh;
+pub mod clean;
+pub mod convert;
+pub mod corners;
+pub mod curve;
+pub mod edges;
+pub mod edit;
+pub mod elevation;
+pub mod encode;
+pub mod error;
+pub mod fit;
+pub mod geometry2d;
+pub mod io;
+pub mod iter;
+pub mod metric;
+pub mod metrics;
+pub mod multi;
+pub mod nearest;
+pub mod offset;
+pub mod online;
+pub mod pipeline;
+pub mod planar;
+pub mod prelude;
+#[cfg(feature = "simd")]
+mod simd;
 pub mod simplify;
 pub mod smooth;
+pub mod split;
+pub mod stats;
+pub mod timed;
+pub mod topology;
+pub mod tree;
+pub mod util;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wkt")]
+pub mod wkt;
+
+pub use convert::Coord;
+pub use curve::CurveKind;
+pub use error::Error;
+pub use nalgebra;
+pub use nalgebra::Point;
+pub use timed::{Time, TimedPoint};
 
+/// The floating-point type which all algorithms compute in: `f64`, or `f32` with the `f32` feature.
+///
+/// Single precision halves the memory and bandwidth taken by lines, at the cost of accuracy.
+/// As it changes the type throughout the crate's API,
+/// the `f32` feature should only be enabled by the final application, not by libraries.
+#[cfg(not(feature = "f32"))]
 pub type Precision = f64;
 
+/// The floating-point type which all algorithms compute in: `f64`, or `f32` with the `f32` feature.
+///
+/// Single precision halves the memory and bandwidth taken by lines, at the cost of accuracy.
+/// As it changes the type throughout the crate's API,
+/// the `f32` feature should only be enabled by the final application, not by libraries.
+#[cfg(feature = "f32")]
+pub type Precision = f32;
+
+/// Mathematical constants in [Precision].
+pub(crate) mod consts {
+    #[cfg(feature = "f32")]
+    pub use std::f32::consts::*;
+    #[cfg(not(feature = "f32"))]
+    pub use std::f64::consts::*;
+}
+
+/// Types which can be linearly interpolated.
+pub trait Lerp: Sized {
+    /// Interpolate a fraction `t` of the way from `self` to `other`.
+    ///
+    /// `t` is generally between 0.0 (`self`) and 1.0 (`other`), but may extrapolate beyond them.
+    fn lerp(&self, other: &Self, t: Precision) -> Self;
+}
+
+impl Lerp for Precision {
+    fn lerp(&self, other: &Self, t: Precision) -> Self {
+        self + (other - self) * t
+    }
+}
+
+/// Interpolated in [Precision], then rounded back to `T`.
+impl<T: StorageScalar, const D: usize> Lerp for Point<T, D> {
+    fn lerp(&self, other: &Self, t: Precision) -> Self {
+        Point::from(self.coords.zip_map(&other.coords, |a, b| {
+            T::from_precision(a.to_precision().lerp(&b.to_precision(), t))
+        }))
+    }
+}
+
+/// Interpolated in [Precision], then rounded back to `T`.
+impl<T: StorageScalar, const D: usize> Lerp for Coord<T, D> {
+    fn lerp(&self, other: &Self, t: Precision) -> Self {
+        std::array::from_fn(|d| {
+            T::from_precision(self[d].to_precision().lerp(&other[d].to_precision(), t))
+        })
+    }
+}
+
+/// Something with a location in space.
+///
+/// Implement this (and [Lerp]) for your own point types
+/// to carry other attributes through algorithms which create new points, like resampling.
+pub trait Location: Lerp {
+    /// Distance between the locations of `self` and `other`.
+    fn distance(&self, other: &Self) -> Precision;
+
+    /// Move `distance` along the straight line from `self` towards `other`.
+    ///
+    /// Overshoots `other` if `distance` is larger than the distance between them.
+    /// If `self` and `other` are at the same location, returns `self` unchanged (interpolated with `t = 0`).
+    fn project_towards(&self, other: &Self, distance: Precision) -> Self {
+        let total = self.distance(other);
+        if total == 0.0 {
+            return self.lerp(other, 0.0);
+        }
+        self.lerp(other, distance / total)
+    }
+
+    /// Interpolate a fraction `t` of the way from `self` to `other`.
+    ///
+    /// As with [Lerp::lerp], `t` outside of 0.0..=1.0 extrapolates beyond `self` or `other`.
+    fn lerp_towards(&self, other: &Self, t: Precision) -> Self {
+        self.lerp(other, t)
+    }
+
+    /// The location halfway between `self` and `other`.
+    fn midpoint(&self, other: &Self) -> Self {
+        self.lerp(other, 0.5)
+    }
+
+    /// As [Location::project_towards], but never overshooting `other` or moving away from it.
+    ///
+    /// `distance` is clamped between 0.0 (returning `self`) and the distance between the two (returning `other`).
+    fn clamped_project_towards(&self, other: &Self, distance: Precision) -> Self {
+        let total = self.distance(other);
+        if total == 0.0 || distance <= 0.0 {
+            return self.lerp(other, 0.0);
+        }
+        self.lerp(other, (distance / total).min(1.0))
+    }
+}
+
+/// Euclidean distance, calculated in [Precision].
+impl<T: StorageScalar, const D: usize> Location for Point<T, D> {
+    fn distance(&self, other: &Self) -> Precision {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (a.to_precision() - b.to_precision()).powi(2))
+            .sum::<Precision>()
+            .sqrt()
+    }
+}
+
+/// Euclidean distance, calculated in [Precision].
+impl<T: StorageScalar, const D: usize> Location for Coord<T, D> {
+    fn distance(&self, other: &Self) -> Precision {
+        self.iter()
+            .zip(other.iter())
+            .map(|(a, b)| (a.to_precision() - b.to_precision()).powi(2))
+            .sum::<Precision>()
+            .sqrt()
+    }
+}
+
 /// Find the total length of a linestring.
 pub fn total_length<const D: usize>(line: &[Point<Precision, D>]) -> Precision {
     if line.len() < 2 {
@@ -18,38 +183,132 @@ pub fn total_length<const D: usize>(line: &[Point<Precision, D>]) -> Precision {
         .sum::<Precision>()
 }
 
+/// Return an error for the first point with a NaN or infinite coordinate, if any.
+pub fn check_finite<const D: usize>(line: &[Point<Precision, D>]) -> Result<(), Error> {
+    match line.iter().position(|p| p.iter().any(|c| !c.is_finite())) {
+        Some(index) => Err(Error::NonFiniteCoordinate { index }),
+        None => Ok(()),
+    }
+}
+
+/// Find the distance along the linestring of every point, starting at 0.0.
+///
+/// The last element is the total length of the linestring.
+pub fn cumulative_lengths<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Precision> {
+    let mut out = Vec::with_capacity(line.len());
+    let mut total = 0.0;
+    let mut iter = line.iter();
+    let Some(mut prev) = iter.next() else {
+        return out;
+    };
+    out.push(total);
+    for p in iter {
+        total += distance(prev, p);
+        out.push(total);
+        prev = p;
+    }
+    out
+}
+
 #[cfg(test)]
 mod test_utils {
+    use crate::Precision;
     use nalgebra::Point2;
-    pub type Pt = Point2<f64>;
+    pub type Pt = Point2<Precision>;
 
-    pub fn make_line(arrs: Vec<[f64; 2]>) -> Vec<Pt> {
+    pub fn make_line(arrs: Vec<[Precision; 2]>) -> Vec<Pt> {
         arrs.into_iter().map(|p| p.into()).collect()
     }
+
+    /// `tolerance` for results computed in double precision,
+    /// loosened to what single precision can meet with the `f32` feature.
+    pub fn tol(tolerance: Precision) -> Precision {
+        if cfg!(feature = "f32") {
+            tolerance.max(1e-4)
+        } else {
+            tolerance
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::tol;
     use nalgebra::{Point1, Point2, Point3};
+    use proptest::prelude::*;
+
+    #[cfg(feature = "f32")]
+    #[test]
+    fn single_precision() {
+        use crate::simplify::rdp::rdp_reduce;
+
+        assert_eq!(std::mem::size_of::<Point<Precision, 3>>(), 12);
+        let line: Vec<Point<f32, 3>> = vec![
+            [0.0, 0.0, 0.0].into(),
+            [1.0, 0.01, 0.0].into(),
+            [2.0, 0.0, 0.0].into(),
+        ];
+        assert_eq!(rdp_reduce(&line, 0.1, false), vec![line[0], line[2]]);
+    }
 
     #[test]
     fn length1() {
-        let line: Vec<_> = (0..5).map(|n| Point1::new(n as f64)).collect();
+        let line: Vec<_> = (0..5).map(|n| Point1::new(n as Precision)).collect();
         assert_eq!(total_length(&line), 4.0);
     }
 
     #[test]
     fn length2() {
-        let line: Vec<_> = (0..2).map(|n| Point2::new(n as f64, n as f64)).collect();
-        assert_eq!(total_length(&line), (2.0 as f64).sqrt());
+        let line: Vec<_> = (0..2)
+            .map(|n| Point2::new(n as Precision, n as Precision))
+            .collect();
+        assert_eq!(total_length(&line), (2.0 as Precision).sqrt());
     }
 
     #[test]
     fn length3() {
         let line: Vec<_> = (0..2)
-            .map(|n| Point3::new(n as f64, n as f64, n as f64))
+            .map(|n| Point3::new(n as Precision, n as Precision, n as Precision))
             .collect();
-        assert_eq!(total_length(&line), (3.0 as f64).sqrt());
+        assert_eq!(total_length(&line), (3.0 as Precision).sqrt());
+    }
+
+    #[test]
+    fn cumulative() {
+        let line: Vec<_> = [0.0, 1.0, 3.0, 6.0].into_iter().map(Point1::new).collect();
+        assert_eq!(cumulative_lengths(&line), vec![0.0, 1.0, 3.0, 6.0]);
+        assert_eq!(
+            *cumulative_lengths(&line).last().unwrap(),
+            total_length(&line)
+        );
+    }
+
+    fn point3() -> impl proptest::strategy::Strategy<Value = Point<Precision, 3>> {
+        proptest::array::uniform3((-100.0 as Precision)..100.0).prop_map(Point::from)
+    }
+
+    proptest! {
+        #[test]
+        fn midpoint_equidistant(a in point3(), b in point3()) {
+            let m = a.midpoint(&b);
+            prop_assert!((m.distance(&a) - m.distance(&b)).abs() < tol(1e-9));
+        }
+
+        #[test]
+        fn lerp_towards_fraction(a in point3(), b in point3(), t in (0.0 as Precision)..1.0) {
+            let p = a.lerp_towards(&b, t);
+            prop_assert!((p.distance(&a) - t * a.distance(&b)).abs() < tol(1e-9));
+        }
+
+        #[test]
+        fn clamped_never_overshoots(a in point3(), b in point3(), d in (-500.0 as Precision)..500.0) {
+            let p = a.clamped_project_towards(&b, d);
+            let total = a.distance(&b);
+            prop_assert!(p.distance(&a) <= total + tol(1e-9));
+            prop_assert!(p.distance(&b) <= total + tol(1e-9));
+            let expected = d.clamp(0.0, total);
+            prop_assert!((p.distance(&a) - expected).abs() < tol(1e-9));
+        }
     }
 }
@@ -0,0 +1,438 @@
+//! Ways of measuring distance between points, for coordinates which are not Cartesian.
+//!
+//! The `*_metric` variants of RDP, VW, resampling and convolution smoothing take a [DistanceMetric];
+//! the plain functions are equivalent to using [Euclidean].
+//! [ScaledEuclidean] handles data whose axes have different units or resolutions
+//! (e.g. anisotropic voxels) without rescaling the coordinates first.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::metric::Haversine;
+//! use simples::simplify::rdp::rdp_reduce_metric;
+//! use simples::Point;
+//!
+//! // (longitude, latitude) in degrees: a track which deviates about 11m from a straight line
+//! let track: Vec<Point<Precision, 2>> = vec![[0.0, 51.0].into(), [0.001, 51.0001].into(), [0.002, 51.0].into()];
+//! assert_eq!(rdp_reduce_metric(&track, 20.0, &Haversine::default()).len(), 2);
+//! assert_eq!(rdp_reduce_metric(&track, 5.0, &Haversine::default()).len(), 3);
+//! ```
+use crate::Precision;
+use nalgebra::{distance, distance_squared, Point};
+
+/// Mean radius of the Earth in metres, as used by WGS84.
+pub const EARTH_RADIUS: Precision = 6_371_008.8;
+
+/// A way of measuring the distance between two points.
+pub trait DistanceMetric<const D: usize> {
+    fn distance(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision;
+
+    /// Square of [DistanceMetric::distance], which some metrics can calculate more cheaply.
+    fn distance_squared(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        self.distance(a, b).powi(2)
+    }
+
+    /// Shortest distance from `p` to the segment between `start` and `end`.
+    ///
+    /// By default, this finds the nearest point on the segment in coordinate space,
+    /// and measures the distance to that; implementors should override this if that is inaccurate.
+    fn segment_distance(
+        &self,
+        start: &Point<Precision, D>,
+        end: &Point<Precision, D>,
+        p: &Point<Precision, D>,
+    ) -> Precision {
+        let t = crate::split::project_onto_edge(start, end, p);
+        self.distance(p, &(start + (end - start) * t))
+    }
+
+    /// Area of the triangle with these corners, used by VW.
+    ///
+    /// By default, this uses Heron's formula with the lengths of the sides.
+    /// It is NaN if any corner has non-finite coordinates.
+    fn triangle_area(
+        &self,
+        a: &Point<Precision, D>,
+        b: &Point<Precision, D>,
+        c: &Point<Precision, D>,
+    ) -> Precision {
+        let s1 = self.distance(a, b);
+        let s2 = self.distance(b, c);
+        let s3 = self.distance(c, a);
+        let s = (s1 + s2 + s3) / 2.0;
+        let product = s * (s - s1) * (s - s2) * (s - s3);
+        // rounding can make a degenerate triangle's product slightly negative;
+        // not `max`, which would also turn NaN into 0
+        if product < 0.0 {
+            0.0
+        } else {
+            product.sqrt()
+        }
+    }
+
+    /// [DistanceMetric::triangle_area] of the triangle made by each point other than the ends of the line,
+    /// and its neighbours.
+    ///
+    /// Implementors can override this where it is faster to calculate many areas at once.
+    fn triangle_areas(&self, line: &[Point<Precision, D>]) -> Vec<Precision> {
+        line.windows(3)
+            .map(|w| self.triangle_area(&w[0], &w[1], &w[2]))
+            .collect()
+    }
+
+    /// Total length of the linestring.
+    fn length(&self, line: &[Point<Precision, D>]) -> Precision {
+        line.windows(2).map(|w| self.distance(&w[0], &w[1])).sum()
+    }
+}
+
+/// Straight-line distance in Cartesian coordinates.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Euclidean;
+
+impl<const D: usize> DistanceMetric<D> for Euclidean {
+    fn distance(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        distance(a, b)
+    }
+
+    fn distance_squared(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        distance_squared(a, b)
+    }
+
+    #[cfg(feature = "simd")]
+    fn triangle_areas(&self, line: &[Point<Precision, D>]) -> Vec<Precision> {
+        crate::simd::triangle_areas(line)
+    }
+}
+
+/// Euclidean distance after multiplying each axis by a scale factor,
+/// e.g. the voxel size of each axis to measure distances in physical units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "[Precision; D]: serde::Serialize",
+        deserialize = "[Precision; D]: serde::Deserialize<'de>"
+    ))
+)]
+pub struct ScaledEuclidean<const D: usize> {
+    pub scale: [Precision; D],
+}
+
+impl<const D: usize> ScaledEuclidean<D> {
+    pub fn new(scale: [Precision; D]) -> Self {
+        Self { scale }
+    }
+
+    fn scaled(&self, p: &Point<Precision, D>) -> Point<Precision, D> {
+        let mut out = *p;
+        for (c, s) in out.iter_mut().zip(self.scale.iter()) {
+            *c *= s;
+        }
+        out
+    }
+}
+
+impl<const D: usize> DistanceMetric<D> for ScaledEuclidean<D> {
+    fn distance(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        self.distance_squared(a, b).sqrt()
+    }
+
+    fn distance_squared(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        a.iter()
+            .zip(b.iter())
+            .zip(self.scale.iter())
+            .map(|((a, b), s)| ((a - b) * s).powi(2))
+            .sum()
+    }
+
+    /// Exact, as the nearest point is found in scaled space.
+    fn segment_distance(
+        &self,
+        start: &Point<Precision, D>,
+        end: &Point<Precision, D>,
+        p: &Point<Precision, D>,
+    ) -> Precision {
+        Euclidean.segment_distance(&self.scaled(start), &self.scaled(end), &self.scaled(p))
+    }
+}
+
+/// Minimise a convex function of `t` over `0.0..=1.0` by ternary search.
+fn minimise_convex(f: impl Fn(Precision) -> Precision) -> Precision {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    // each iteration shrinks the interval by a third; 2/3^80 is well below f64 resolution
+    for _ in 0..80 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if f(m1) <= f(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+    f(lo).min(f(0.0)).min(f(1.0))
+}
+
+/// Taxicab distance: the sum of the absolute differences along each axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manhattan;
+
+impl<const D: usize> DistanceMetric<D> for Manhattan {
+    fn distance(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        (a - b).lp_norm(1)
+    }
+
+    /// Exact, minimising the distance along the segment.
+    fn segment_distance(
+        &self,
+        start: &Point<Precision, D>,
+        end: &Point<Precision, D>,
+        p: &Point<Precision, D>,
+    ) -> Precision {
+        minimise_convex(|t| self.distance(p, &(start + (end - start) * t)))
+    }
+}
+
+/// Chessboard distance: the largest absolute difference along any axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chebyshev;
+
+impl<const D: usize> DistanceMetric<D> for Chebyshev {
+    fn distance(&self, a: &Point<Precision, D>, b: &Point<Precision, D>) -> Precision {
+        (a - b).amax()
+    }
+
+    /// Exact, minimising the distance along the segment.
+    fn segment_distance(
+        &self,
+        start: &Point<Precision, D>,
+        end: &Point<Precision, D>,
+        p: &Point<Precision, D>,
+    ) -> Precision {
+        minimise_convex(|t| self.distance(p, &(start + (end - start) * t)))
+    }
+}
+
+/// Great-circle distance between `(x, y) = (longitude, latitude)` points in degrees, on a sphere.
+///
+/// Distances are in the units of `radius`, which by default is the Earth's in metres.
+/// Interpolation (e.g. when resampling) is still linear in longitude and latitude,
+/// which is accurate enough for the short edges of a track,
+/// but not for edges which span a large fraction of the globe or cross the antimeridian.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Haversine {
+    pub radius: Precision,
+}
+
+impl Default for Haversine {
+    fn default() -> Self {
+        Self {
+            radius: EARTH_RADIUS,
+        }
+    }
+}
+
+impl Haversine {
+    pub fn new(radius: Precision) -> Self {
+        Self { radius }
+    }
+
+    /// Central angle between the points, in radians.
+    fn angle(a: &Point<Precision, 2>, b: &Point<Precision, 2>) -> Precision {
+        let (lat1, lat2) = (a.y.to_radians(), b.y.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (b.x - a.x).to_radians();
+        let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * h.sqrt().min(1.0).asin()
+    }
+
+    /// Initial bearing from `a` to `b`, in radians.
+    fn bearing(a: &Point<Precision, 2>, b: &Point<Precision, 2>) -> Precision {
+        let (lat1, lat2) = (a.y.to_radians(), b.y.to_radians());
+        let dlon = (b.x - a.x).to_radians();
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        y.atan2(x)
+    }
+}
+
+impl DistanceMetric<2> for Haversine {
+    fn distance(&self, a: &Point<Precision, 2>, b: &Point<Precision, 2>) -> Precision {
+        Self::angle(a, b) * self.radius
+    }
+
+    /// Cross-track distance to the great circle through `start` and `end`,
+    /// or the distance to the nearer end if `p` is beyond either of them.
+    fn segment_distance(
+        &self,
+        start: &Point<Precision, 2>,
+        end: &Point<Precision, 2>,
+        p: &Point<Precision, 2>,
+    ) -> Precision {
+        let d13 = Self::angle(start, p);
+        let d12 = Self::angle(start, end);
+        if d12 == 0.0 || d13 == 0.0 {
+            return d13 * self.radius;
+        }
+        let dtheta = Self::bearing(start, p) - Self::bearing(start, end);
+        if dtheta.cos() < 0.0 {
+            return d13 * self.radius;
+        }
+        let xt = (d13.sin() * dtheta.sin()).clamp(-1.0, 1.0).asin();
+        let at = (d13.cos() / xt.cos()).clamp(-1.0, 1.0).acos();
+        if at > d12 {
+            return Self::angle(end, p) * self.radius;
+        }
+        xt.abs() * self.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::tol;
+
+    fn p(x: Precision, y: Precision) -> Point<Precision, 2> {
+        [x, y].into()
+    }
+
+    #[test]
+    fn haversine_distance() {
+        let h = Haversine::default();
+        // one degree of latitude
+        let d = h.distance(&p(0.0, 0.0), &p(0.0, 1.0));
+        assert!((d - 111_195.0).abs() < 1.0, "{d}");
+        // a degree of longitude shrinks with latitude
+        let d60 = h.distance(&p(0.0, 60.0), &p(1.0, 60.0));
+        assert!((d60 - d / 2.0).abs() < 300.0, "{d60}");
+        // London to Paris, about 344km
+        let lp = h.distance(&p(-0.1278, 51.5074), &p(2.3522, 48.8566));
+        assert!((lp - 343_500.0).abs() < 1000.0, "{lp}");
+    }
+
+    #[test]
+    fn haversine_segment_distance() {
+        let h = Haversine::default();
+        let (a, b) = (p(0.0, 0.0), p(2.0, 0.0));
+        let above = h.segment_distance(&a, &b, &p(1.0, 1.0));
+        assert!((above - h.distance(&p(1.0, 0.0), &p(1.0, 1.0))).abs() < 1.0);
+        let before = h.segment_distance(&a, &b, &p(-1.0, 0.0));
+        assert!((before - h.distance(&a, &p(-1.0, 0.0))).abs() < tol(1e-6));
+        let after = h.segment_distance(&a, &b, &p(3.0, 1.0));
+        assert!((after - h.distance(&b, &p(3.0, 1.0))).abs() < tol(1e-6));
+    }
+
+    #[test]
+    fn euclidean_matches_plain() {
+        use crate::simplify::rdp::{rdp_keep, rdp_keep_metric};
+        use crate::simplify::vw::{vw_keep, vw_keep_metric};
+
+        let line: Vec<_> = (0..50)
+            .map(|i| {
+                p(
+                    i as Precision,
+                    (i as Precision * 0.7).sin() * (i % 7) as Precision,
+                )
+            })
+            .collect();
+        for eps in [0.1, 1.0, 3.0] {
+            assert_eq!(
+                rdp_keep_metric(&line, eps, &Euclidean),
+                rdp_keep(&line, eps, false)
+            );
+        }
+        for n in [3, 10, 40] {
+            assert_eq!(
+                vw_keep_metric(&line, n, false, &Euclidean),
+                vw_keep(&line, n, false)
+            );
+        }
+    }
+
+    #[test]
+    fn haversine_resample() {
+        use crate::simplify::sample::resample_metric;
+
+        let h = Haversine::default();
+        // a line heading north then east, at high latitude where longitude degrees are short
+        let line = vec![p(0.0, 60.0), p(0.0, 61.0), p(2.0, 61.0)];
+        let out = resample_metric(&line, 5, &h);
+        assert_eq!(out.len(), 5);
+        assert_eq!(out[4], line[2]);
+        let steps: Vec<_> = out.windows(2).map(|w| h.distance(&w[0], &w[1])).collect();
+        // the corner is cut, but otherwise the spacing along the line is even
+        assert!(steps.iter().all(|s| *s <= h.length(&line) / 4.0 * 1.001));
+        assert!((steps[0] - h.length(&line) / 4.0).abs() < tol(1e-6) * h.length(&line));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn scaled_serde_roundtrip() {
+        let metric = ScaledEuclidean::new([0.5, 0.5, 2.0]);
+        let s = serde_json::to_string(&metric).unwrap();
+        assert_eq!(metric, serde_json::from_str(&s).unwrap());
+    }
+
+    #[test]
+    fn scaled_matches_prescaled() {
+        use crate::simplify::rdp::rdp_keep_metric;
+        use crate::smooth::{smooth_convolve, smooth_convolve_metric, Gaussian};
+
+        let metric = ScaledEuclidean::new([0.5, 0.5, 2.0]);
+        let line: Vec<Point<Precision, 3>> = (0..30)
+            .map(|i| {
+                let i = i as Precision;
+                [i, (i * 0.5).sin() * 3.0, (i * 0.3).cos()].into()
+            })
+            .collect();
+        let prescaled: Vec<_> = line.iter().map(|p| metric.scaled(p)).collect();
+        for eps in [0.1, 0.5, 1.0] {
+            assert_eq!(
+                rdp_keep_metric(&line, eps, &metric),
+                rdp_keep_metric(&prescaled, eps, &Euclidean)
+            );
+        }
+        let kernel = Gaussian::new(1.0, 3.0);
+        let smoothed = smooth_convolve_metric(&line, kernel, &metric);
+        let expected = smooth_convolve(&prescaled, kernel, false);
+        for (a, b) in smoothed.iter().zip(expected.iter()) {
+            assert!((metric.scaled(a) - b).norm() < tol(1e-9));
+        }
+    }
+
+    #[test]
+    fn manhattan_chebyshev() {
+        let (a, b) = (p(0.0, 0.0), p(3.0, -4.0));
+        assert_eq!(Manhattan.distance(&a, &b), 7.0);
+        assert_eq!(Chebyshev.distance(&a, &b), 4.0);
+        // segment along x; p is above its middle
+        let (s, e, q) = (p(0.0, 0.0), p(4.0, 0.0), p(1.0, 2.0));
+        assert!((Manhattan.segment_distance(&s, &e, &q) - 2.0).abs() < tol(1e-9));
+        assert!((Chebyshev.segment_distance(&s, &e, &q) - 2.0).abs() < tol(1e-9));
+        // diagonal segment: the nearest point differs from the Euclidean projection
+        let (s, e, q) = (p(0.0, 0.0), p(4.0, 4.0), p(0.0, 2.0));
+        assert!((Chebyshev.segment_distance(&s, &e, &q) - 1.0).abs() < tol(1e-9));
+        assert!((Manhattan.segment_distance(&s, &e, &q) - 2.0).abs() < tol(1e-9));
+        assert!((Manhattan.segment_distance(&s, &e, &p(-1.0, 0.0)) - 1.0).abs() < tol(1e-9));
+    }
+
+    #[test]
+    fn euclidean_defaults() {
+        let e = Euclidean;
+        assert_eq!(
+            e.segment_distance(&p(0.0, 0.0), &p(2.0, 0.0), &p(1.0, 3.0)),
+            3.0
+        );
+        assert_eq!(
+            e.segment_distance(&p(0.0, 0.0), &p(0.0, 0.0), &p(3.0, 4.0)),
+            5.0
+        );
+        let area = e.triangle_area(&p(0.0, 0.0), &p(2.0, 0.0), &p(0.0, 3.0));
+        assert!((area - 3.0).abs() < tol(1e-12));
+        assert_eq!(e.length(&[p(0.0, 0.0), p(3.0, 4.0), p(3.0, 5.0)]), 6.0);
+    }
+}
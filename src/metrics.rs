@@ -0,0 +1,578 @@
+//! Measure how different two linestrings are, e.g. to evaluate simplification quality.
+use crate::simplify::rdp::proj_dist2;
+use crate::{total_length, CurveKind, Precision};
+use nalgebra::{distance, distance_squared, Point};
+
+/// Discrete Fréchet distance between two linestrings.
+///
+/// Informally, the shortest leash needed for a person walking along one line's vertices
+/// and a dog walking along the other's, where both may only go forwards.
+/// Infinite if either line is empty.
+pub fn discrete_frechet_distance<const D: usize>(
+    a: &[Point<Precision, D>],
+    b: &[Point<Precision, D>],
+) -> Precision {
+    if a.is_empty() || b.is_empty() {
+        return Precision::INFINITY;
+    }
+    // only the previous row of the dynamic programming table is needed
+    let mut prev: Vec<Precision> = vec![0.0; b.len()];
+    let mut curr = vec![0.0; b.len()];
+    for (i, pa) in a.iter().enumerate() {
+        for (j, pb) in b.iter().enumerate() {
+            let d = distance(pa, pb);
+            curr[j] = match (i, j) {
+                (0, 0) => d,
+                (0, _) => curr[j - 1].max(d),
+                (_, 0) => prev[0].max(d),
+                _ => prev[j].min(prev[j - 1]).min(curr[j - 1]).max(d),
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len() - 1]
+}
+
+/// Shortest distance from a point to any edge (or the only point) of the line.
+pub(crate) fn distance_to_line<const D: usize>(
+    p: &Point<Precision, D>,
+    line: &[Point<Precision, D>],
+) -> Precision {
+    if line.len() == 1 {
+        return distance(p, &line[0]);
+    }
+    line.windows(2)
+        .map(|pair| proj_dist2(&pair[0], &pair[1], p, distance_squared(&pair[0], &pair[1])))
+        .fold(Precision::INFINITY, Precision::min)
+        .sqrt()
+}
+
+/// Greatest distance from any vertex of `a` to the line `b`.
+pub fn directed_hausdorff_distance<const D: usize>(
+    a: &[Point<Precision, D>],
+    b: &[Point<Precision, D>],
+) -> Precision {
+    if b.is_empty() {
+        return Precision::INFINITY;
+    }
+    a.iter()
+        .map(|p| distance_to_line(p, b))
+        .fold(0.0, Precision::max)
+}
+
+/// Hausdorff distance between two linestrings:
+/// the greatest distance from a vertex of either line to the nearest point on the other line.
+///
+/// Infinite if either line is empty.
+pub fn hausdorff_distance<const D: usize>(
+    a: &[Point<Precision, D>],
+    b: &[Point<Precision, D>],
+) -> Precision {
+    if a.is_empty() || b.is_empty() {
+        return Precision::INFINITY;
+    }
+    directed_hausdorff_distance(a, b).max(directed_hausdorff_distance(b, a))
+}
+
+/// Indices of vertices of `chunked_result` further than `tolerance` from the line `full_result`.
+///
+/// For checking the output of an algorithm run on overlapping chunks of a long line
+/// against the output of the same algorithm run on the whole line,
+/// to find artifacts where chunks meet and so tune the overlap.
+/// All vertices are returned if `full_result` is empty.
+pub fn boundary_artifacts<const D: usize>(
+    full_result: &[Point<Precision, D>],
+    chunked_result: &[Point<Precision, D>],
+    tolerance: Precision,
+) -> Vec<usize> {
+    if full_result.is_empty() {
+        return (0..chunked_result.len()).collect();
+    }
+    chunked_result
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, p)| (distance_to_line(p, full_result) > tolerance).then_some(idx))
+        .collect()
+}
+
+/// Apply `f` to every vertex and its neighbours, treating a closed line's ends as interior vertices.
+fn per_vertex<const D: usize, F>(
+    line: &[Point<Precision, D>],
+    kind: CurveKind,
+    f: F,
+) -> Vec<Precision>
+where
+    F: Fn(&Point<Precision, D>, &Point<Precision, D>, &Point<Precision, D>) -> Precision,
+{
+    if kind == CurveKind::Open || line.len() < 3 {
+        return per_interior_vertex(line, f);
+    }
+    let mut padded = Vec::with_capacity(line.len() + 2);
+    padded.push(line[line.len() - 1]);
+    padded.extend_from_slice(line);
+    padded.push(line[0]);
+    let mut out = per_interior_vertex(&padded, f);
+    out.pop();
+    out.remove(0);
+    out
+}
+
+/// Angle in radians between the incoming and outgoing edges at every vertex:
+/// 0.0 for going straight on, up to pi for a complete reversal.
+///
+/// The endpoints, and vertices adjacent to a zero-length edge, have an angle of 0.0.
+/// Every vertex of a [CurveKind::Closed] line (or `true`) has a turning angle, including the first and last.
+pub fn turning_angles<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Vec<Precision> {
+    per_vertex(line, kind.into(), |p1, p2, p3| {
+        let v1 = p2 - p1;
+        let v2 = p3 - p2;
+        let norms = v1.norm() * v2.norm();
+        if norms == 0.0 {
+            return 0.0;
+        }
+        (v1.dot(&v2) / norms).clamp(-1.0, 1.0).acos()
+    })
+}
+
+/// Discrete (Menger) curvature at every vertex:
+/// the reciprocal of the radius of the circle through the vertex and its neighbours.
+///
+/// The endpoints, and vertices where the circle is undefined (e.g. repeated points), have a curvature of 0.0.
+/// Every vertex of a [CurveKind::Closed] line (or `true`) has a curvature, including the first and last.
+pub fn curvature<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Vec<Precision> {
+    per_vertex(line, kind.into(), |p1, p2, p3| {
+        let (a, b, c) = (distance(p1, p2), distance(p2, p3), distance(p1, p3));
+        let sides = a * b * c;
+        if sides == 0.0 {
+            return 0.0;
+        }
+        // Heron's formula; rounding can push flat triangles slightly negative
+        let s = (a + b + c) / 2.0;
+        let area = (s * (s - a) * (s - b) * (s - c)).max(0.0).sqrt();
+        4.0 * area / sides
+    })
+}
+
+/// As [curvature], but positive where the line turns left (anticlockwise) and negative where it turns right.
+pub fn signed_curvature(line: &[Point<Precision, 2>]) -> Vec<Precision> {
+    per_interior_vertex(line, |p1, p2, p3| {
+        let (a, b, c) = (distance(p1, p2), distance(p2, p3), distance(p1, p3));
+        let sides = a * b * c;
+        if sides == 0.0 {
+            return 0.0;
+        }
+        // twice the signed area
+        2.0 * (p2 - p1).perp(&(p3 - p2)) / sides
+    })
+}
+
+/// Apply `f` to every vertex and its neighbours, with 0.0 at the endpoints.
+fn per_interior_vertex<const D: usize, F>(line: &[Point<Precision, D>], f: F) -> Vec<Precision>
+where
+    F: Fn(&Point<Precision, D>, &Point<Precision, D>, &Point<Precision, D>) -> Precision,
+{
+    let mut out = Vec::with_capacity(line.len());
+    if line.is_empty() {
+        return out;
+    }
+    out.push(0.0);
+    out.extend(line.windows(3).map(|w| f(&w[0], &w[1], &w[2])));
+    if line.len() > 1 {
+        out.push(0.0);
+    }
+    out
+}
+
+/// Summary statistics of a line's edge lengths; see [segment_length_stats].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SegmentLengthStats {
+    pub n_segments: usize,
+    pub min: Precision,
+    pub max: Precision,
+    pub mean: Precision,
+    /// Population standard deviation.
+    pub stddev: Precision,
+    /// 1.0 for perfectly even spacing, approaching 0.0 as spacing becomes more uneven:
+    /// `1 / (1 + stddev / mean)`.
+    pub uniformity: Precision,
+}
+
+impl SegmentLengthStats {
+    /// Whether the spacing is even enough for distance-based smoothing kernels,
+    /// i.e. `uniformity` is at least `min_uniformity`.
+    /// If not, consider resampling first.
+    pub fn is_uniform(&self, min_uniformity: Precision) -> bool {
+        self.uniformity >= min_uniformity
+    }
+}
+
+/// Summary statistics of the lengths of the line's edges.
+///
+/// None if the line has no edges.
+/// A line whose edges all have zero length has a uniformity of 1.0.
+pub fn segment_length_stats<const D: usize>(
+    line: &[Point<Precision, D>],
+) -> Option<SegmentLengthStats> {
+    let lengths: Vec<_> = line.windows(2).map(|w| distance(&w[0], &w[1])).collect();
+    if lengths.is_empty() {
+        return None;
+    }
+    let n = lengths.len() as Precision;
+    let mean = lengths.iter().sum::<Precision>() / n;
+    let variance = lengths
+        .iter()
+        .map(|l| (l - mean).powi(2))
+        .sum::<Precision>()
+        / n;
+    let stddev = variance.sqrt();
+    let uniformity = if mean == 0.0 {
+        1.0
+    } else {
+        1.0 / (1.0 + stddev / mean)
+    };
+    Some(SegmentLengthStats {
+        n_segments: lengths.len(),
+        min: lengths
+            .iter()
+            .copied()
+            .fold(Precision::INFINITY, Precision::min),
+        max: lengths.iter().copied().fold(0.0, Precision::max),
+        mean,
+        stddev,
+        uniformity,
+    })
+}
+
+/// Count the line's edges by length, in `n_bins` equal-width bins from 0.0 to the longest edge.
+///
+/// Returns the counts and the width of each bin.
+/// The longest edges are counted in the last bin.
+pub fn segment_length_histogram<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_bins: usize,
+) -> (Vec<usize>, Precision) {
+    let mut counts = vec![0; n_bins];
+    let lengths: Vec<_> = line.windows(2).map(|w| distance(&w[0], &w[1])).collect();
+    let max = lengths.iter().copied().fold(0.0, Precision::max);
+    if n_bins == 0 || max == 0.0 {
+        if let Some(c) = counts.first_mut() {
+            *c = lengths.len();
+        }
+        return (counts, max / n_bins.max(1) as Precision);
+    }
+    let width = max / n_bins as Precision;
+    for l in lengths {
+        let bin = ((l / width) as usize).min(n_bins - 1);
+        counts[bin] += 1;
+    }
+    (counts, width)
+}
+
+/// Summary of how a simplified linestring differs from the original; see [compare].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComparisonReport {
+    pub n_points_original: usize,
+    pub n_points_simplified: usize,
+    /// Fraction of points retained: `n_points_simplified / n_points_original`.
+    pub point_ratio: Precision,
+    /// Simplified length minus original length; usually negative, as simplification cuts corners.
+    pub length_change: Precision,
+    /// Simplified length divided by original length.
+    pub length_ratio: Precision,
+    /// See [hausdorff_distance].
+    pub max_deviation: Precision,
+    /// Mean distance from each original vertex to the simplified line.
+    pub mean_deviation: Precision,
+    /// Simplified total absolute turning (the sum of [turning_angles]) minus the original's, in radians;
+    /// negative where wiggles have been removed.
+    pub turning_change: Precision,
+}
+
+/// Measure how a simplified linestring differs from the original, e.g. for logging the quality of many simplifications.
+///
+/// Ratios are NaN where the original has no points or zero length;
+/// deviations are infinite where either line is empty.
+pub fn compare<const D: usize>(
+    original: &[Point<Precision, D>],
+    simplified: &[Point<Precision, D>],
+) -> ComparisonReport {
+    let length_original = total_length(original);
+    let length_simplified = total_length(simplified);
+    let mean_deviation = if simplified.is_empty() {
+        Precision::INFINITY
+    } else {
+        original
+            .iter()
+            .map(|p| distance_to_line(p, simplified))
+            .sum::<Precision>()
+            / original.len() as Precision
+    };
+    let turning = |line| turning_angles(line, false).iter().sum::<Precision>();
+    ComparisonReport {
+        n_points_original: original.len(),
+        n_points_simplified: simplified.len(),
+        point_ratio: simplified.len() as Precision / original.len() as Precision,
+        length_change: length_simplified - length_original,
+        length_ratio: length_simplified / length_original,
+        max_deviation: hausdorff_distance(original, simplified),
+        mean_deviation,
+        turning_change: turning(simplified) - turning(original),
+    }
+}
+
+/// Twice the signed area of a ring, by the shoelace formula.
+pub(crate) fn shoelace2(ring: &[Point<Precision, 2>]) -> Precision {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let p = &ring[i];
+            let q = &ring[(i + 1) % n];
+            p.x * q.y - q.x * p.y
+        })
+        .sum()
+}
+
+/// Where two 2D segments cross, as the fraction of the way along each segment.
+pub(crate) fn segment_intersection(
+    a1: &Point<Precision, 2>,
+    a2: &Point<Precision, 2>,
+    b1: &Point<Precision, 2>,
+    b2: &Point<Precision, 2>,
+) -> Option<(Precision, Precision)> {
+    let r = a2 - a1;
+    let s = b2 - b1;
+    let denom = r.perp(&s);
+    if denom == 0.0 {
+        return None;
+    }
+    let qp = b1 - a1;
+    let t = qp.perp(&s) / denom;
+    let u = qp.perp(&r) / denom;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some((t, u))
+    } else {
+        None
+    }
+}
+
+/// Points of `line` from position `from` to position `to`,
+/// where a position is an edge index plus the fraction along that edge.
+fn sub_line(
+    line: &[Point<Precision, 2>],
+    from: (usize, Precision),
+    to: (usize, Precision),
+) -> Vec<Point<Precision, 2>> {
+    let at = |(edge, t): (usize, Precision)| {
+        if edge + 1 >= line.len() {
+            line[line.len() - 1]
+        } else {
+            line[edge] + (line[edge + 1] - line[edge]) * t
+        }
+    };
+    let mut out = vec![at(from)];
+    out.extend(line[(from.0 + 1)..=(to.0.min(line.len() - 1))].iter());
+    out.push(at(to));
+    out
+}
+
+/// Area enclosed between two 2D linestrings which share a general direction,
+/// i.e. of the polygon formed by `a` followed by `b` reversed.
+///
+/// Where the lines cross each other (in the same order along both lines),
+/// the regions between the crossings are measured separately, so that they don't cancel out.
+/// Zero if either line has fewer than 2 points.
+pub fn area_between(a: &[Point<Precision, 2>], b: &[Point<Precision, 2>]) -> Precision {
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let mut crossings = Vec::default();
+    for (i, pa) in a.windows(2).enumerate() {
+        for (j, pb) in b.windows(2).enumerate() {
+            if let Some((t, u)) = segment_intersection(&pa[0], &pa[1], &pb[0], &pb[1]) {
+                crossings.push(((i, t), (j, u)));
+            }
+        }
+    }
+    crossings
+        .sort_by(|x, y| (x.0 .0 as Precision + x.0 .1).total_cmp(&(y.0 .0 as Precision + y.0 .1)));
+
+    let whole = || {
+        let mut ring = a.to_vec();
+        ring.extend(b.iter().rev());
+        shoelace2(&ring).abs() / 2.0
+    };
+
+    let monotonic = crossings
+        .windows(2)
+        .all(|w| w[0].1 .0 as Precision + w[0].1 .1 <= w[1].1 .0 as Precision + w[1].1 .1);
+    if !monotonic {
+        return whole();
+    }
+
+    let start = ((0, 0.0), (0, 0.0));
+    let end = ((a.len() - 1, 0.0), (b.len() - 1, 0.0));
+    let mut stops = vec![start];
+    stops.extend(crossings);
+    stops.push(end);
+
+    stops
+        .windows(2)
+        .map(|w| {
+            let mut ring = sub_line(a, w[0].0, w[1].0);
+            let mut back = sub_line(b, w[0].1, w[1].1);
+            back.reverse();
+            ring.extend(back);
+            shoelace2(&ring).abs() / 2.0
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{FRAC_PI_2, FRAC_PI_4};
+    use crate::test_utils::{make_line, tol};
+
+    #[test]
+    fn frechet() {
+        let a = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+        let b = make_line(vec![[0.0, 1.0], [2.0, 1.0]]);
+        assert_eq!(discrete_frechet_distance(&a, &b), (2.0 as Precision).sqrt());
+        assert_eq!(discrete_frechet_distance(&a, &a), 0.0);
+    }
+
+    #[test]
+    fn hausdorff() {
+        let a = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+        let b = make_line(vec![[0.0, 0.0], [1.0, 0.5], [2.0, 0.0]]);
+        assert_eq!(
+            directed_hausdorff_distance(&a, &b),
+            1.0 / (5.0 as Precision).sqrt()
+        );
+        assert_eq!(directed_hausdorff_distance(&b, &a), 0.5);
+        assert_eq!(hausdorff_distance(&a, &b), 0.5);
+    }
+
+    #[test]
+    fn area() {
+        let a = make_line(vec![[0.0, 0.0], [2.0, 0.0]]);
+        let b = make_line(vec![[0.0, 1.0], [2.0, 1.0]]);
+        assert_eq!(area_between(&a, &b), 2.0);
+
+        // crossing in the middle: two triangles of area 0.5 each
+        let c = make_line(vec![[0.0, 1.0], [2.0, -1.0]]);
+        assert!((area_between(&a, &c) - 1.0).abs() < tol(1e-12));
+    }
+
+    #[test]
+    fn artifacts() {
+        let full = make_line(vec![[0.0, 0.0], [4.0, 0.0]]);
+        let chunked = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.5],
+            [3.0, 0.01],
+            [4.0, 0.0],
+        ]);
+        assert_eq!(boundary_artifacts(&full, &chunked, 0.1), vec![2]);
+        assert!(boundary_artifacts(&full, &full, 0.0).is_empty());
+    }
+
+    #[test]
+    fn angles() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 0.0],
+        ]);
+        let angles = turning_angles(&line, false);
+        let expected = [0.0, 0.0, FRAC_PI_2, 3.0 * FRAC_PI_4, 0.0];
+        for (a, e) in angles.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < tol(1e-12));
+        }
+    }
+
+    #[test]
+    fn curvatures() {
+        // on a circle of radius 2
+        let line = make_line(vec![[2.0, 0.0], [0.0, 2.0], [-2.0, 0.0], [0.0, -2.0]]);
+        let c = curvature(&line, false);
+        assert_eq!(c[0], 0.0);
+        assert!((c[1] - 0.5).abs() < tol(1e-12));
+        assert!((c[2] - 0.5).abs() < tol(1e-12));
+
+        let signed = signed_curvature(&line);
+        assert!((signed[1] - 0.5).abs() < tol(1e-12));
+        let mut reversed = line.clone();
+        reversed.reverse();
+        assert!((signed_curvature(&reversed)[1] + 0.5).abs() < tol(1e-12));
+
+        assert_eq!(curvature::<2>(&[], false), Vec::<Precision>::new());
+        assert_eq!(curvature(&line[..1], false), vec![0.0]);
+    }
+
+    #[test]
+    fn segment_lengths() {
+        let even = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+        let stats = segment_length_stats(&even).unwrap();
+        assert_eq!(stats.uniformity, 1.0);
+        assert!(stats.is_uniform(0.9));
+
+        let uneven = make_line(vec![[0.0, 0.0], [1.0, 0.0], [4.0, 0.0]]);
+        let stats = segment_length_stats(&uneven).unwrap();
+        assert_eq!(
+            (stats.min, stats.max, stats.mean, stats.stddev),
+            (1.0, 3.0, 2.0, 1.0)
+        );
+        assert_eq!(stats.uniformity, 2.0 / 3.0);
+        assert!(!stats.is_uniform(0.9));
+
+        assert!(segment_length_stats(&even[..1]).is_none());
+    }
+
+    #[test]
+    fn histogram() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [4.0, 0.0], [4.5, 0.0]]);
+        assert_eq!(segment_length_histogram(&line, 3), (vec![1, 1, 1], 1.0));
+        assert_eq!(segment_length_histogram(&line, 2), (vec![2, 1], 1.5));
+    }
+
+    #[test]
+    fn comparison() {
+        let original = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0], [3.0, 0.0]]);
+        let simplified = make_line(vec![[0.0, 0.0], [3.0, 0.0]]);
+        let report = compare(&original, &simplified);
+        assert_eq!(report.n_points_original, 4);
+        assert_eq!(report.n_points_simplified, 2);
+        assert_eq!(report.point_ratio, 0.5);
+        let length = 2.0 * (2.0 as Precision).sqrt() + 1.0;
+        assert!((report.length_change - (3.0 - length)).abs() < tol(1e-12));
+        assert!((report.length_ratio - 3.0 / length).abs() < tol(1e-12));
+        assert_eq!(report.max_deviation, 1.0);
+        assert_eq!(report.mean_deviation, 0.25);
+        // a right-angle turn and a 45 degree turn removed
+        assert!((report.turning_change + FRAC_PI_2 + FRAC_PI_4).abs() < tol(1e-12));
+
+        let same = compare(&original, &original);
+        assert_eq!(
+            (
+                same.point_ratio,
+                same.length_change,
+                same.max_deviation,
+                same.mean_deviation
+            ),
+            (1.0, 0.0, 0.0, 0.0)
+        );
+        assert_eq!(compare(&original, &[]).mean_deviation, Precision::INFINITY);
+    }
+}
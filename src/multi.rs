@@ -0,0 +1,138 @@
+//! Linestrings with breaks in them, like multi-part features in shapefiles or GeoJSON.
+//!
+//! Algorithms apply to each part independently,
+//! but the line as a whole can be measured and serialised.
+//!
+//! ```
+//! use simples::multi::MultiLine;
+//! use simples::pipeline::Pipeline;
+//! use simples::Point;
+//!
+//! let multi: MultiLine<2> = MultiLine::new(vec![
+//!     vec![[0.0, 0.0].into(), [1.0, 0.01].into(), [2.0, 0.0].into()],
+//!     vec![[5.0, 0.0].into(), [5.0, 3.0].into()],
+//! ]);
+//! assert!((multi.length() - 5.0).abs() < 0.001);
+//! let simplified = multi.apply(&Pipeline::new().rdp(0.1));
+//! assert_eq!(simplified.n_points(), 4);
+//! ```
+use crate::pipeline::Pipeline;
+use crate::{total_length, Precision};
+use nalgebra::Point;
+
+/// A linestring made up of several disconnected parts.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiLine<const D: usize> {
+    parts: Vec<Vec<Point<Precision, D>>>,
+}
+
+impl<const D: usize> MultiLine<D> {
+    pub fn new(parts: Vec<Vec<Point<Precision, D>>>) -> Self {
+        Self { parts }
+    }
+
+    pub fn parts(&self) -> &[Vec<Point<Precision, D>>] {
+        &self.parts
+    }
+
+    pub fn into_parts(self) -> Vec<Vec<Point<Precision, D>>> {
+        self.parts
+    }
+
+    pub fn push(&mut self, part: Vec<Point<Precision, D>>) {
+        self.parts.push(part);
+    }
+
+    pub fn n_parts(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Total number of points in all parts.
+    pub fn n_points(&self) -> usize {
+        self.parts.iter().map(|p| p.len()).sum()
+    }
+
+    /// Total length of all parts, not including the gaps between them.
+    pub fn length(&self) -> Precision {
+        self.parts.iter().map(|p| total_length(p)).sum()
+    }
+
+    /// Apply any simplification or smoothing function to each part, e.g.
+    /// `multi.map(|part| rdp_reduce(part, 1.0, false))`.
+    pub fn map<F>(&self, f: F) -> Self
+    where
+        F: FnMut(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        Self::new(self.parts.iter().map(|p| p.as_slice()).map(f).collect())
+    }
+
+    /// Run the pipeline on each part.
+    pub fn apply(&self, pipeline: &Pipeline) -> Self {
+        self.map(|part| pipeline.apply(part))
+    }
+}
+
+impl<const D: usize> From<Vec<Vec<Point<Precision, D>>>> for MultiLine<D> {
+    fn from(parts: Vec<Vec<Point<Precision, D>>>) -> Self {
+        Self::new(parts)
+    }
+}
+
+impl<const D: usize> FromIterator<Vec<Point<Precision, D>>> for MultiLine<D> {
+    fn from_iter<T: IntoIterator<Item = Vec<Point<Precision, D>>>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a, const D: usize> IntoIterator for &'a MultiLine<D> {
+    type Item = &'a Vec<Point<Precision, D>>;
+    type IntoIter = std::slice::Iter<'a, Vec<Point<Precision, D>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.parts.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::sample::resample;
+    use crate::test_utils::make_line;
+
+    fn multi() -> MultiLine<2> {
+        vec![
+            make_line(vec![[0.0, 0.0], [2.0, 0.0]]),
+            make_line(vec![[3.0, 0.0], [3.0, 1.0], [3.0, 2.0]]),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn measure() {
+        let m = multi();
+        assert_eq!(m.n_parts(), 2);
+        assert_eq!(m.n_points(), 5);
+        assert_eq!(m.length(), 4.0);
+    }
+
+    #[test]
+    fn per_part() {
+        let m = multi().map(|part| resample(part, 3, false));
+        assert_eq!(
+            m.parts(),
+            &[
+                make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]),
+                make_line(vec![[3.0, 0.0], [3.0, 1.0], [3.0, 2.0]]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let m = multi();
+        let s = serde_json::to_string(&m).unwrap();
+        assert_eq!(m, serde_json::from_str(&s).unwrap());
+    }
+}
@@ -0,0 +1,242 @@
+//! Find the point on a linestring nearest to a query point, e.g. to snap observations onto a centreline.
+//!
+//! For many queries against the same long line, build a [LineIndex] once.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::nearest::{nearest_point_on_line, LineIndex};
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [2.0, 0.0].into(), [2.0, 2.0].into()];
+//! let query = [2.5, 1.5].into();
+//! let nearest = nearest_point_on_line(&line, &query).unwrap();
+//! assert_eq!(nearest.point, [2.0, 1.5].into());
+//! assert_eq!((nearest.edge, nearest.t, nearest.distance), (1, 0.75, 0.5));
+//!
+//! let index = LineIndex::new(&line);
+//! assert_eq!(index.nearest(&query), Some(nearest));
+//! ```
+use crate::bvh::{build_bvh, Bvh, BvhNode};
+use crate::split::project_onto_edge;
+use crate::{Lerp, Precision};
+use nalgebra::{distance_squared, Point};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// The point on a linestring nearest to some query point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NearestPoint<const D: usize> {
+    pub point: Point<Precision, D>,
+    /// Distance from the query point.
+    pub distance: Precision,
+    /// Index of the edge the point is on, where edge `i` goes from point `i` to point `i + 1`.
+    pub edge: usize,
+    /// Fraction of the way along the edge.
+    pub t: Precision,
+}
+
+/// The nearest point on one edge, and its squared distance from `query`.
+fn nearest_on_edge<const D: usize>(
+    line: &[Point<Precision, D>],
+    edge: usize,
+    query: &Point<Precision, D>,
+) -> (NearestPoint<D>, Precision) {
+    let (a, b) = (&line[edge], &line[edge + 1]);
+    let t = project_onto_edge(a, b, query);
+    let point = a.lerp(b, t);
+    let dist2 = distance_squared(&point, query);
+    let nearest = NearestPoint {
+        point,
+        distance: dist2.sqrt(),
+        edge,
+        t,
+    };
+    (nearest, dist2)
+}
+
+/// The single point of a line with no edges, if any.
+fn nearest_degenerate<const D: usize>(
+    line: &[Point<Precision, D>],
+    query: &Point<Precision, D>,
+) -> Option<NearestPoint<D>> {
+    let point = *line.first()?;
+    Some(NearestPoint {
+        point,
+        distance: distance_squared(&point, query).sqrt(),
+        edge: 0,
+        t: 0.0,
+    })
+}
+
+/// Find the point on the line nearest to `query`, checking every edge.
+///
+/// Where several points are equally near, the one on the lowest-indexed edge is returned.
+/// A line with a single point has that point on "edge" 0; None for an empty line.
+pub fn nearest_point_on_line<const D: usize>(
+    line: &[Point<Precision, D>],
+    query: &Point<Precision, D>,
+) -> Option<NearestPoint<D>> {
+    if line.len() < 2 {
+        return nearest_degenerate(line, query);
+    }
+    let mut best = nearest_on_edge(line, 0, query);
+    for edge in 1..(line.len() - 1) {
+        let candidate = nearest_on_edge(line, edge, query);
+        if candidate.1 < best.1 {
+            best = candidate;
+        }
+    }
+    Some(best.0)
+}
+
+/// A node of the [Bvh] to search, ordered so that the nearest box is popped first.
+struct Candidate {
+    dist2: Precision,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for min-heap
+        other
+            .dist2
+            .total_cmp(&self.dist2)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+/// A linestring with a [Bvh] over its edges, for repeated nearest-point queries.
+///
+/// Each query only examines edges whose bounding boxes could contain a nearer point than the best so far,
+/// which for long lines is far fewer than [nearest_point_on_line].
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a, const D: usize> {
+    line: &'a [Point<Precision, D>],
+    bvh: Bvh<D>,
+}
+
+impl<'a, const D: usize> LineIndex<'a, D> {
+    pub fn new(line: &'a [Point<Precision, D>]) -> Self {
+        Self {
+            line,
+            bvh: build_bvh(line),
+        }
+    }
+
+    pub fn line(&self) -> &'a [Point<Precision, D>] {
+        self.line
+    }
+
+    /// As [nearest_point_on_line], returning the same result.
+    pub fn nearest(&self, query: &Point<Precision, D>) -> Option<NearestPoint<D>> {
+        let nodes = self.bvh.nodes();
+        if nodes.is_empty() {
+            return nearest_degenerate(self.line, query);
+        }
+        let box_dist2 = |node: usize| {
+            let aabb = nodes[node].aabb();
+            distance_squared(&aabb.clamp(query), query)
+        };
+        let mut best: Option<(NearestPoint<D>, Precision)> = None;
+        let mut queue = BinaryHeap::default();
+        queue.push(Candidate {
+            dist2: box_dist2(0),
+            node: 0,
+        });
+        while let Some(c) = queue.pop() {
+            if let Some((_, best_dist2)) = best {
+                if c.dist2 > best_dist2 {
+                    break;
+                }
+            }
+            match nodes[c.node] {
+                BvhNode::Leaf { edge, .. } => {
+                    let candidate = nearest_on_edge(self.line, edge, query);
+                    let better = best.is_none_or(|(b, d2)| {
+                        candidate.1 < d2 || (candidate.1 == d2 && edge < b.edge)
+                    });
+                    if better {
+                        best = Some(candidate);
+                    }
+                }
+                BvhNode::Branch { left, right, .. } => {
+                    for node in [left, right] {
+                        queue.push(Candidate {
+                            dist2: box_dist2(node),
+                            node,
+                        });
+                    }
+                }
+            }
+        }
+        best.map(|b| b.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    fn zigzag() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..200)
+                .map(|i| [i as Precision * 0.5, if i % 2 == 0 { 0.0 } else { 1.0 }])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn nearest_edges() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]]);
+        let n = nearest_point_on_line(&line, &[1.0, -1.0].into()).unwrap();
+        assert_eq!(
+            (n.point, n.edge, n.t, n.distance),
+            ([1.0, 0.0].into(), 0, 0.5, 1.0)
+        );
+        // beyond the end of the line
+        let n = nearest_point_on_line(&line, &[2.0, 3.0].into()).unwrap();
+        assert_eq!((n.edge, n.t), (1, 1.0));
+        // equidistant from both edges' shared vertex
+        let n = nearest_point_on_line(&line, &[3.0, -1.0].into()).unwrap();
+        assert_eq!((n.edge, n.t), (0, 1.0));
+    }
+
+    #[test]
+    fn degenerate() {
+        let empty: Vec<Point<Precision, 2>> = vec![];
+        assert!(nearest_point_on_line(&empty, &[0.0, 0.0].into()).is_none());
+        assert!(LineIndex::new(&empty).nearest(&[0.0, 0.0].into()).is_none());
+        let single = make_line(vec![[3.0, 4.0]]);
+        let n = LineIndex::new(&single).nearest(&[0.0, 0.0].into()).unwrap();
+        assert_eq!((n.point, n.distance), ([3.0, 4.0].into(), 5.0));
+    }
+
+    #[test]
+    fn index_matches_brute_force() {
+        let line = zigzag();
+        let index = LineIndex::new(&line);
+        for i in 0..100 {
+            let query = [i as Precision * 1.01 - 1.0, (i % 7) as Precision - 3.0].into();
+            assert_eq!(
+                index.nearest(&query),
+                nearest_point_on_line(&line, &query),
+                "query {query:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Offset 2D linestrings, i.e. find parallel curves at a given distance.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::offset::{offset, JoinStyle};
+//! use simples::Point;
+//!
+//! let centreline: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [10.0, 0.0].into(), [10.0, 10.0].into()];
+//! let left = offset(&centreline, 1.0, JoinStyle::Round { max_angle: 0.1 });
+//! let right = offset(&centreline, -1.0, JoinStyle::Miter { limit: 4.0 });
+//! assert_eq!(right.last(), Some(&[11.0, 10.0].into()));
+//! ```
+use crate::metrics::segment_intersection;
+use crate::Precision;
+use nalgebra::{Point, Rotation2, Vector2};
+
+/// How to fill the gap on the outside of a corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JoinStyle {
+    /// Extend both edges until they meet,
+    /// unless that point is more than `limit` times the offset distance from the corner,
+    /// in which case fall back to [JoinStyle::Bevel].
+    Miter { limit: Precision },
+    /// Go around the corner in a circular arc,
+    /// with no more than `max_angle` radians between arc points.
+    Round { max_angle: Precision },
+    /// Cut the corner straight across.
+    Bevel,
+}
+
+/// Left-hand normal of the edge's direction, scaled to the given length.
+fn normal(
+    p1: &Point<Precision, 2>,
+    p2: &Point<Precision, 2>,
+    length: Precision,
+) -> Vector2<Precision> {
+    let dir = (p2 - p1).normalize();
+    Vector2::new(-dir.y, dir.x) * length
+}
+
+/// Where the infinite lines through `a` along `r` and through `b` along `s` cross.
+fn line_intersection(
+    a: Point<Precision, 2>,
+    r: Vector2<Precision>,
+    b: Point<Precision, 2>,
+    s: Vector2<Precision>,
+) -> Option<Point<Precision, 2>> {
+    let denom = r.perp(&s);
+    if denom == 0.0 {
+        return None;
+    }
+    Some(a + r * ((b - a).perp(&s) / denom))
+}
+
+/// Add the points joining two offset edges around `corner`, on the outside of the turn.
+fn outer_join(
+    out: &mut Vec<Point<Precision, 2>>,
+    corner: &Point<Precision, 2>,
+    (n0, n1): (Vector2<Precision>, Vector2<Precision>),
+    (dir0, dir1): (Vector2<Precision>, Vector2<Precision>),
+    join: JoinStyle,
+) {
+    let bevel = |out: &mut Vec<_>| {
+        out.push(corner + n0);
+        out.push(corner + n1);
+    };
+    match join {
+        JoinStyle::Miter { limit } => {
+            match line_intersection(corner + n0, dir0, corner + n1, dir1) {
+                Some(p) if (p - corner).norm() <= limit * n0.norm() => out.push(p),
+                _ => bevel(out),
+            }
+        }
+        JoinStyle::Round { max_angle } => {
+            let sweep = n0.perp(&n1).atan2(n0.dot(&n1));
+            let steps = (sweep.abs() / max_angle).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let rot = Rotation2::new(sweep * step as Precision / steps as Precision);
+                out.push(corner + rot * n0);
+            }
+        }
+        JoinStyle::Bevel => bevel(out),
+    }
+}
+
+/// Cut out any loops where the line crosses itself, keeping the crossing point.
+fn remove_loops(line: Vec<Point<Precision, 2>>) -> Vec<Point<Precision, 2>> {
+    let mut out: Vec<Point<Precision, 2>> = Vec::with_capacity(line.len());
+    for p in line {
+        let n = out.len();
+        if n >= 3 {
+            let a = out[n - 1];
+            // earliest crossing, so that the whole loop is removed
+            let crossing = (0..(n - 2)).find_map(|k| {
+                segment_intersection(&out[k], &out[k + 1], &a, &p)
+                    .map(|(t, _)| (k, out[k] + (out[k + 1] - out[k]) * t))
+            });
+            if let Some((k, x)) = crossing {
+                out.truncate(k + 1);
+                out.push(x);
+            }
+        }
+        if out.last() != Some(&p) {
+            out.push(p);
+        }
+    }
+    out
+}
+
+/// Find the curve parallel to the line, at `distance` to its left (or right, if negative).
+///
+/// Corners on the outside of a turn are filled according to `join`.
+/// Where the offset curve crosses itself
+/// (e.g. on the inside of corners, or where the original line comes within `distance` of itself),
+/// the loop is removed.
+///
+/// Repeated consecutive points are ignored.
+/// Lines with fewer than 2 distinct points, or a `distance` of 0, are returned without repeated points.
+pub fn offset(
+    line: &[Point<Precision, 2>],
+    distance: Precision,
+    join: JoinStyle,
+) -> Vec<Point<Precision, 2>> {
+    let mut line = line.to_vec();
+    line.dedup();
+    if line.len() < 2 || distance == 0.0 {
+        return line;
+    }
+
+    let normals: Vec<_> = line
+        .windows(2)
+        .map(|pair| normal(&pair[0], &pair[1], distance))
+        .collect();
+    let dirs: Vec<_> = line
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).normalize())
+        .collect();
+
+    let mut out = Vec::with_capacity(line.len() * 2);
+    out.push(line[0] + normals[0]);
+    for (idx, corner) in line.iter().enumerate().skip(1).take(line.len() - 2) {
+        let (n0, n1) = (normals[idx - 1], normals[idx]);
+        let (dir0, dir1) = (dirs[idx - 1], dirs[idx]);
+        let cross = dir0.perp(&dir1);
+        if cross == 0.0 && dir0.dot(&dir1) > 0.0 {
+            // straight on
+            out.push(corner + n0);
+        } else if cross * distance > 0.0 {
+            // inside of the turn: the resulting loop is removed later
+            out.push(corner + n0);
+            out.push(corner + n1);
+        } else {
+            outer_join(&mut out, corner, (n0, n1), (dir0, dir1), join);
+        }
+    }
+    out.push(line[line.len() - 1] + normals[normals.len() - 1]);
+
+    remove_loops(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+    use nalgebra::distance;
+
+    fn corner() -> Vec<Point<Precision, 2>> {
+        make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]])
+    }
+
+    #[test]
+    fn straight() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 0.0], [3.0, 0.0]]);
+        assert_eq!(
+            offset(&line, 1.0, JoinStyle::Bevel),
+            make_line(vec![[0.0, 1.0], [1.0, 1.0], [3.0, 1.0]])
+        );
+    }
+
+    #[test]
+    fn miter() {
+        assert_eq!(
+            offset(&corner(), -1.0, JoinStyle::Miter { limit: 2.0 }),
+            make_line(vec![[0.0, -1.0], [3.0, -1.0], [3.0, 2.0]])
+        );
+    }
+
+    #[test]
+    fn miter_limit() {
+        assert_eq!(
+            offset(&corner(), -1.0, JoinStyle::Miter { limit: 1.1 }),
+            offset(&corner(), -1.0, JoinStyle::Bevel),
+        );
+    }
+
+    #[test]
+    fn bevel() {
+        assert_eq!(
+            offset(&corner(), -1.0, JoinStyle::Bevel),
+            make_line(vec![[0.0, -1.0], [2.0, -1.0], [3.0, 0.0], [3.0, 2.0]])
+        );
+    }
+
+    #[test]
+    fn round() {
+        let out = offset(&corner(), -1.0, JoinStyle::Round { max_angle: 0.1 });
+        assert!(out.len() > 16);
+        for p in &out[1..(out.len() - 1)] {
+            assert!((distance(p, &[2.0, 0.0].into()) - 1.0).abs() < tol(1e-9));
+        }
+    }
+
+    #[test]
+    fn inside_loop_removed() {
+        assert_eq!(
+            offset(&corner(), 1.0, JoinStyle::Bevel),
+            make_line(vec![[0.0, 1.0], [1.0, 1.0], [1.0, 2.0]])
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Accumulators which summarise a linestring one point at a time,
+//! so that streamed lines need not be buffered.
+//!
+//! ```
+//! use simples::online::OnlineLength;
+//! use simples::Point;
+//!
+//! let mut length = OnlineLength::new();
+//! for p in [[0.0, 0.0], [3.0, 4.0], [3.0, 5.0]] {
+//!     length.push(Point::from(p));
+//! }
+//! assert_eq!(length.total(), 6.0);
+//! ```
+use crate::bvh::Aabb;
+use crate::Precision;
+use nalgebra::{distance, Point, SVector};
+
+/// Running total length of a linestring; see [crate::total_length].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OnlineLength<const D: usize> {
+    last: Option<Point<Precision, D>>,
+    total: Precision,
+}
+
+impl<const D: usize> Default for OnlineLength<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const D: usize> OnlineLength<D> {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            total: 0.0,
+        }
+    }
+
+    /// Add the next point of the line.
+    pub fn push(&mut self, point: Point<Precision, D>) {
+        if let Some(last) = self.last {
+            self.total += distance(&last, &point);
+        }
+        self.last = Some(point);
+    }
+
+    /// Length of the line so far.
+    pub fn total(&self) -> Precision {
+        self.total
+    }
+
+    /// The most recently pushed point, if any.
+    pub fn last(&self) -> Option<&Point<Precision, D>> {
+        self.last.as_ref()
+    }
+
+    /// Start again with an empty line.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Running axis-aligned bounding box of a linestring.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OnlineAabb<const D: usize> {
+    aabb: Option<Aabb<D>>,
+}
+
+impl<const D: usize> Default for OnlineAabb<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const D: usize> OnlineAabb<D> {
+    pub fn new() -> Self {
+        Self { aabb: None }
+    }
+
+    /// Add the next point of the line.
+    pub fn push(&mut self, point: Point<Precision, D>) {
+        let point_box = Aabb::new(point, point);
+        self.aabb = Some(match self.aabb {
+            Some(aabb) => aabb.union(&point_box),
+            None => point_box,
+        });
+    }
+
+    /// Bounding box of the points so far; None if no points have been pushed.
+    pub fn aabb(&self) -> Option<Aabb<D>> {
+        self.aabb
+    }
+
+    /// Start again with an empty line.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Running mean position of a linestring's points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OnlineCentroid<const D: usize> {
+    sum: SVector<Precision, D>,
+    count: usize,
+}
+
+impl<const D: usize> Default for OnlineCentroid<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const D: usize> OnlineCentroid<D> {
+    pub fn new() -> Self {
+        Self {
+            sum: SVector::zeros(),
+            count: 0,
+        }
+    }
+
+    /// Add the next point of the line.
+    pub fn push(&mut self, point: Point<Precision, D>) {
+        self.sum += point.coords;
+        self.count += 1;
+    }
+
+    /// Number of points pushed so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Mean of the points so far; None if no points have been pushed.
+    pub fn centroid(&self) -> Option<Point<Precision, D>> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(Point::from(self.sum / self.count as Precision))
+    }
+
+    /// Start again with an empty line.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+macro_rules! impl_extend {
+    ($t:ident) => {
+        impl<const D: usize> Extend<Point<Precision, D>> for $t<D> {
+            fn extend<I: IntoIterator<Item = Point<Precision, D>>>(&mut self, iter: I) {
+                for p in iter {
+                    self.push(p);
+                }
+            }
+        }
+    };
+}
+
+impl_extend!(OnlineLength);
+impl_extend!(OnlineAabb);
+impl_extend!(OnlineCentroid);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+    use crate::total_length;
+
+    #[test]
+    fn matches_batch() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 2.0], [-1.0, 3.0], [4.0, 1.0]]);
+
+        let mut length = OnlineLength::new();
+        let mut aabb = OnlineAabb::new();
+        let mut centroid = OnlineCentroid::new();
+        length.extend(line.iter().cloned());
+        aabb.extend(line.iter().cloned());
+        centroid.extend(line.iter().cloned());
+
+        assert!((length.total() - total_length(&line)).abs() < tol(1e-12));
+        assert_eq!(length.last(), line.last());
+        assert_eq!(
+            aabb.aabb(),
+            Some(Aabb::new([-1.0, 0.0].into(), [4.0, 3.0].into()))
+        );
+        assert_eq!(centroid.centroid(), Some([1.0, 1.5].into()));
+
+        length.reset();
+        aabb.reset();
+        centroid.reset();
+        assert_eq!(length.total(), 0.0);
+        assert_eq!(aabb.aabb(), None);
+        assert_eq!(centroid.centroid(), None);
+    }
+}
@@ -0,0 +1,593 @@
+//! Chain simplification and smoothing steps together.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::pipeline::Pipeline;
+//! use simples::Point;
+//!
+//! let line: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [5.0, 0.1].into(), [10.0, 0.0].into()];
+//! let pipeline = Pipeline::new().resample(1.0).linear_smooth(2.0).rdp(0.5);
+//! let out = pipeline.apply(&line);
+//! assert!((out[0] - line[0]).norm() < 1e-6);
+//! println!("{pipeline}");
+//! ```
+//!
+//! # Wire format
+//!
+//! With the `serde` feature, a [Pipeline] is serialised as a [PipelineConfig]:
+//! the [SCHEMA_VERSION], the [CurveKind] of the lines it applies to, and a list of stages,
+//! each of which is an object whose `stage` field is the snake_case name of the [Stage] variant,
+//! and whose other fields are that variant's parameters.
+//! In JSON:
+//!
+//! ```json
+//! {"version": 1, "kind": "closed", "stages": [{"stage": "resample", "spacing": 1.0}, {"stage": "rdp", "epsilon": 0.5}]}
+//! ```
+//!
+//! Stage names and parameters are stable: new stages may be added,
+//! but existing ones will not be renamed or change meaning without a new schema version.
+//! Configs without a version are treated as version 1, and those without a kind are for open lines.
+//!
+//! A config written by a newer version of this crate may contain stages which this version does not know about.
+//! Deserialising a [Pipeline] directly fails on these;
+//! to skip them instead, deserialise a [PipelineConfig] and use [Pipeline::from_config].
+use crate::clean::round_coords;
+#[cfg(feature = "serde")]
+use crate::error::{Error, Result};
+use crate::simplify::{rdp::rdp_keep, sample::resample, sample::sample_every, vw::vw_keep};
+use crate::smooth::{smooth_convolve, smooth_moving_average, Gaussian, Linear};
+use crate::{CurveKind, Precision};
+use nalgebra::Point;
+use std::fmt;
+
+/// Width (in standard deviations) used by [Pipeline::gaussian_smooth].
+pub const DEFAULT_GAUSSIAN_WIDTH: Precision = 3.0;
+
+/// A single step of a [Pipeline].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "stage", rename_all = "snake_case"))]
+pub enum Stage {
+    /// Place a point every `spacing` along the line, starting at the first point;
+    /// see [sample_every].
+    Resample { spacing: Precision },
+    /// Divide the line evenly into `n_points` points; see [resample].
+    ResampleN { n_points: usize },
+    /// See [smooth_moving_average].
+    MovingAverage { width: usize },
+    /// Convolve with a [Gaussian] kernel.
+    Gaussian { stdev: Precision, width: Precision },
+    /// Convolve with a [Linear] kernel.
+    Linear { max_dist: Precision },
+    /// Simplify with RDP; see [rdp_keep].
+    Rdp { epsilon: Precision },
+    /// Simplify with VW; see [vw_keep].
+    Vw { n_points: usize },
+    /// Round coordinates to `decimals` decimal places, then clean up degenerate points;
+    /// see [round_coords].
+    Round { decimals: i32 },
+}
+
+impl Stage {
+    /// The `stage` field of every variant in the wire format.
+    #[cfg(feature = "serde")]
+    const NAMES: &'static [&'static str] = &[
+        "resample",
+        "resample_n",
+        "moving_average",
+        "gaussian",
+        "linear",
+        "rdp",
+        "vw",
+        "round",
+    ];
+
+    /// Apply this stage to the points in `buf`, replacing them with the output.
+    fn apply_to<const D: usize>(&self, buf: &mut Vec<Point<Precision, D>>, kind: CurveKind) {
+        match *self {
+            Stage::Resample { spacing } => *buf = sample_every(buf, spacing, 0.0, kind).0,
+            Stage::ResampleN { n_points } => *buf = resample(buf, n_points, kind),
+            Stage::MovingAverage { width } => *buf = smooth_moving_average(buf, width, kind),
+            Stage::Gaussian { stdev, width } => {
+                *buf = smooth_convolve(buf, Gaussian::new(stdev, width), kind)
+            }
+            Stage::Linear { max_dist } => *buf = smooth_convolve(buf, Linear::new(max_dist), kind),
+            Stage::Rdp { epsilon } => {
+                let keep = rdp_keep(buf, epsilon, kind);
+                retain_indices(buf, &keep);
+            }
+            Stage::Vw { n_points } => {
+                let keep = vw_keep(buf, n_points, kind);
+                retain_indices(buf, &keep);
+            }
+            Stage::Round { decimals } => *buf = round_coords(buf, decimals),
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Resample { spacing } => write!(f, "resample(spacing={spacing})"),
+            Stage::ResampleN { n_points } => write!(f, "resample(n_points={n_points})"),
+            Stage::MovingAverage { width } => write!(f, "moving_average(width={width})"),
+            Stage::Gaussian { stdev, width } => {
+                write!(f, "gaussian(stdev={stdev}, width={width})")
+            }
+            Stage::Linear { max_dist } => write!(f, "linear(max_dist={max_dist})"),
+            Stage::Rdp { epsilon } => write!(f, "rdp(epsilon={epsilon})"),
+            Stage::Vw { n_points } => write!(f, "vw(n_points={n_points})"),
+            Stage::Round { decimals } => write!(f, "round(decimals={decimals})"),
+        }
+    }
+}
+
+/// Keep only the points at the given ascending indices, without reallocating.
+fn retain_indices<T: Copy>(buf: &mut Vec<T>, indices: &[usize]) {
+    for (new_idx, old_idx) in indices.iter().enumerate() {
+        buf[new_idx] = buf[*old_idx];
+    }
+    buf.truncate(indices.len());
+}
+
+/// Version of the [Pipeline] wire format written by this version of the crate.
+#[cfg(feature = "serde")]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What to do with stages in a config which this version of the crate does not recognise.
+#[cfg(feature = "serde")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnknownStages {
+    /// Return [Error::UnknownStage].
+    #[default]
+    Error,
+    /// Leave the stage out of the pipeline.
+    Skip,
+}
+
+/// A single stage in a [PipelineConfig], which may not be recognised.
+///
+/// Deserialising fails if a stage with a recognised name has invalid parameters.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum StageConfig {
+    Known(Stage),
+    /// A stage with an unrecognised name; its parameters are ignored.
+    Unknown {
+        stage: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StageConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        use serde::de::{value::MapDeserializer, Error};
+
+        let params = std::collections::BTreeMap::<String, param::Param>::deserialize(deserializer)?;
+        let name = match params.get("stage") {
+            Some(param::Param::Str(name)) => name.clone(),
+            _ => return Err(D::Error::missing_field("stage")),
+        };
+        if !Stage::NAMES.contains(&name.as_str()) {
+            return Ok(StageConfig::Unknown { stage: name });
+        }
+        Stage::deserialize(MapDeserializer::<_, D::Error>::new(params.into_iter()))
+            .map(StageConfig::Known)
+    }
+}
+
+/// A stage's parameters are buffered until its name is known,
+/// so that only unrecognised stages are skipped, and known ones report invalid parameters.
+#[cfg(feature = "serde")]
+mod param {
+    use serde::de::{
+        self, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+    };
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    /// A parameter value; no known stage has nested parameters, so their contents are discarded.
+    pub(super) enum Param {
+        Bool(bool),
+        Int(i64),
+        UInt(u64),
+        Float(f64),
+        Str(String),
+        Other,
+    }
+
+    impl<'de> de::Deserialize<'de> for Param {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(ParamVisitor)
+        }
+    }
+
+    struct ParamVisitor;
+
+    impl<'de> Visitor<'de> for ParamVisitor {
+        type Value = Param;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "a stage parameter")
+        }
+
+        fn visit_bool<E>(self, v: bool) -> Result<Param, E> {
+            Ok(Param::Bool(v))
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Param, E> {
+            Ok(Param::Int(v))
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Param, E> {
+            Ok(Param::UInt(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<Param, E> {
+            Ok(Param::Float(v))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Param, E> {
+            Ok(Param::Str(v.to_owned()))
+        }
+
+        fn visit_unit<E>(self) -> Result<Param, E> {
+            Ok(Param::Other)
+        }
+
+        fn visit_none<E>(self) -> Result<Param, E> {
+            Ok(Param::Other)
+        }
+
+        fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Param, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Param, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(Param::Other)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Param, A::Error> {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            Ok(Param::Other)
+        }
+    }
+
+    pub(super) struct ParamDeserializer<E>(Param, PhantomData<E>);
+
+    impl<'de, E: de::Error> IntoDeserializer<'de, E> for Param {
+        type Deserializer = ParamDeserializer<E>;
+
+        fn into_deserializer(self) -> Self::Deserializer {
+            ParamDeserializer(self, PhantomData)
+        }
+    }
+
+    impl<'de, E: de::Error> de::Deserializer<'de> for ParamDeserializer<E> {
+        type Error = E;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+            match self.0 {
+                Param::Bool(v) => visitor.visit_bool(v),
+                Param::Int(v) => visitor.visit_i64(v),
+                Param::UInt(v) => visitor.visit_u64(v),
+                Param::Float(v) => visitor.visit_f64(v),
+                Param::Str(v) => visitor.visit_string(v),
+                Param::Other => Err(E::invalid_type(
+                    Unexpected::Other("null or nested value"),
+                    &visitor,
+                )),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+}
+
+/// The serialised form of a [Pipeline]; see the [module documentation](self) for the wire format.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PipelineConfig {
+    /// 0 if absent, which is treated as version 1.
+    #[serde(default)]
+    pub version: u32,
+    /// [CurveKind::Open] if absent.
+    #[serde(default)]
+    pub kind: CurveKind,
+    pub stages: Vec<StageConfig>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Pipeline> for PipelineConfig {
+    fn from(pipeline: Pipeline) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            kind: pipeline.kind,
+            stages: pipeline
+                .stages
+                .into_iter()
+                .map(StageConfig::Known)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PipelineConfig> for Pipeline {
+    type Error = Error;
+
+    /// Fails on unknown stages; see [Pipeline::from_config].
+    fn try_from(config: PipelineConfig) -> Result<Self> {
+        Self::from_config(config, UnknownStages::Error)
+    }
+}
+
+/// An ordered sequence of simplification and smoothing stages,
+/// for lines of a single [CurveKind] (open, unless set with [Pipeline::kind]).
+///
+/// Displays as a human-readable description of its stages;
+/// with the `serde` feature, it can be serialised for reproducibility
+/// (see the [module documentation](self) for the wire format).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "PipelineConfig", into = "PipelineConfig")
+)]
+pub struct Pipeline {
+    kind: CurveKind,
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether the lines this pipeline applies to are open or closed.
+    pub fn kind(mut self, kind: impl Into<CurveKind>) -> Self {
+        self.kind = kind.into();
+        self
+    }
+
+    /// Add an arbitrary stage.
+    pub fn stage(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn resample(self, spacing: Precision) -> Self {
+        self.stage(Stage::Resample { spacing })
+    }
+
+    pub fn resample_n(self, n_points: usize) -> Self {
+        self.stage(Stage::ResampleN { n_points })
+    }
+
+    pub fn moving_average(self, width: usize) -> Self {
+        self.stage(Stage::MovingAverage { width })
+    }
+
+    /// Gaussian smoothing, ignoring points beyond [DEFAULT_GAUSSIAN_WIDTH] standard deviations.
+    pub fn gaussian_smooth(self, stdev: Precision) -> Self {
+        self.stage(Stage::Gaussian {
+            stdev,
+            width: DEFAULT_GAUSSIAN_WIDTH,
+        })
+    }
+
+    pub fn linear_smooth(self, max_dist: Precision) -> Self {
+        self.stage(Stage::Linear { max_dist })
+    }
+
+    pub fn rdp(self, epsilon: Precision) -> Self {
+        self.stage(Stage::Rdp { epsilon })
+    }
+
+    pub fn vw(self, n_points: usize) -> Self {
+        self.stage(Stage::Vw { n_points })
+    }
+
+    /// Usually the final stage, to limit output precision.
+    pub fn round(self, decimals: i32) -> Self {
+        self.stage(Stage::Round { decimals })
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    pub fn curve_kind(&self) -> CurveKind {
+        self.kind
+    }
+
+    /// Build a pipeline from a deserialised config,
+    /// which may have been written by another version of this crate.
+    #[cfg(feature = "serde")]
+    pub fn from_config(config: PipelineConfig, unknown: UnknownStages) -> Result<Self> {
+        let mut stages = Vec::with_capacity(config.stages.len());
+        for (index, stage) in config.stages.into_iter().enumerate() {
+            match (stage, unknown) {
+                (StageConfig::Known(stage), _) => stages.push(stage),
+                (StageConfig::Unknown { .. }, UnknownStages::Skip) => (),
+                (StageConfig::Unknown { stage }, UnknownStages::Error) => {
+                    return Err(Error::UnknownStage { index, name: stage })
+                }
+            }
+        }
+        Ok(Self {
+            kind: config.kind,
+            stages,
+        })
+    }
+
+    /// Run every stage in order.
+    ///
+    /// Simplification stages filter the working buffer in place rather than allocating a new one.
+    /// A zero-point line remains zero-point.
+    pub fn apply<const D: usize>(&self, line: &[Point<Precision, D>]) -> Vec<Point<Precision, D>> {
+        let mut buf = line.to_vec();
+        if buf.is_empty() {
+            return buf;
+        }
+        for stage in self.stages.iter() {
+            stage.apply_to(&mut buf, self.kind);
+        }
+        buf
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for stage in self.stages.iter() {
+            if !first {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{stage}")?;
+            first = false;
+        }
+        if self.kind.is_closed() {
+            write!(f, " (closed)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn resample_then_rdp() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [4.0, 0.0]]);
+        let out = Pipeline::new().resample(0.5).rdp(0.1).apply(&line);
+        assert_eq!(out, make_line(vec![[0.0, 0.0], [4.0, 0.0]]));
+    }
+
+    #[test]
+    fn final_rounding() {
+        let line = make_line(vec![[0.0, 0.0], [1.001, 0.01], [2.0, 0.0], [2.0, 1.0]]);
+        let out = Pipeline::new().rdp(0.001).round(1).apply(&line);
+        assert_eq!(out, make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0]]));
+    }
+
+    #[test]
+    fn closed() {
+        let square = make_line(vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]]);
+        let pipeline = Pipeline::new().resample(1.0);
+        assert_eq!(pipeline.apply(&square).len(), 13);
+        let closed = pipeline.kind(true);
+        assert_eq!(closed.curve_kind(), CurveKind::Closed);
+        // the closing edge is resampled too, without repeating the first point
+        let out = closed.apply(&square);
+        assert_eq!(out.len(), 16);
+        assert_eq!(out[15], [0.0, 1.0].into());
+        assert_eq!(closed.to_string(), "resample(spacing=1) (closed)");
+    }
+
+    #[test]
+    fn describe() {
+        let p = Pipeline::new().resample(1.0).linear_smooth(2.0).rdp(0.5);
+        assert_eq!(
+            p.to_string(),
+            "resample(spacing=1) -> linear(max_dist=2) -> rdp(epsilon=0.5)"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let p = Pipeline::new()
+            .resample(1.0)
+            .gaussian_smooth(2.0)
+            .vw(10)
+            .kind(true);
+        let s = serde_json::to_string(&p).unwrap();
+        assert!(s.starts_with(r#"{"version":1,"kind":"closed","#));
+        assert_eq!(p, serde_json::from_str(&s).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unversioned_config() {
+        let s = r#"{"stages": [{"stage": "rdp", "epsilon": 0.5}]}"#;
+        let p: Pipeline = serde_json::from_str(s).unwrap();
+        assert_eq!(p, Pipeline::new().rdp(0.5));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unknown_stages() {
+        let s = r#"{"version": 2, "stages": [
+            {"stage": "rdp", "epsilon": 0.5},
+            {"stage": "teleport", "to": [1, 2]},
+            {"stage": "round", "decimals": 2}
+        ]}"#;
+        assert!(serde_json::from_str::<Pipeline>(s).is_err());
+
+        let config: PipelineConfig = serde_json::from_str(s).unwrap();
+        assert_eq!(
+            Pipeline::from_config(config.clone(), UnknownStages::Error),
+            Err(Error::UnknownStage {
+                index: 1,
+                name: "teleport".to_string()
+            })
+        );
+        assert_eq!(
+            Pipeline::from_config(config, UnknownStages::Skip).unwrap(),
+            Pipeline::new().rdp(0.5).round(2)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn invalid_parameters() {
+        for stage in [
+            r#"{"stage": "rdp", "epsilon": "big"}"#,
+            r#"{"stage": "rdp"}"#,
+            r#"{"stage": "vw", "n_points": -1}"#,
+            r#"{"stage": "round", "decimals": [2]}"#,
+        ] {
+            let s = format!(r#"{{"stages": [{stage}]}}"#);
+            assert!(
+                serde_json::from_str::<PipelineConfig>(&s).is_err(),
+                "{stage}"
+            );
+        }
+        assert!(
+            serde_json::from_str::<PipelineConfig>(r#"{"stages": [{"epsilon": 0.5}]}"#).is_err()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stage_names() {
+        let all = Pipeline::new()
+            .resample(1.0)
+            .resample_n(3)
+            .moving_average(1)
+            .gaussian_smooth(1.0)
+            .linear_smooth(1.0)
+            .rdp(1.0)
+            .vw(3)
+            .round(1);
+        let value = serde_json::to_value(&all).unwrap();
+        let names: Vec<_> = value["stages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["stage"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, Stage::NAMES);
+    }
+}
@@ -0,0 +1,155 @@
+//! Process nearly-planar 3D linestrings in 2D, so that the results stay within the plane.
+//!
+//! Points are projected onto a plane (either given, or fitted to the line),
+//! processed as 2D points in that plane's coordinate system, and then lifted back into 3D.
+use crate::Precision;
+use nalgebra::{Matrix3, Point, Point2, Point3, Unit, Vector3};
+
+/// A plane in 3D space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    origin: Point3<Precision>,
+    normal: Unit<Vector3<Precision>>,
+    // orthonormal axes within the plane
+    u: Vector3<Precision>,
+    v: Vector3<Precision>,
+}
+
+impl Plane {
+    /// Plane through `origin` perpendicular to `normal`.
+    ///
+    /// Panics if `normal` is zero.
+    pub fn new(origin: Point3<Precision>, normal: Vector3<Precision>) -> Self {
+        let normal = Unit::try_new(normal, Precision::EPSILON).expect("Normal must be non-zero");
+        // any vector not parallel to the normal will do as a starting point
+        let not_parallel = if normal.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let u = normal.cross(&not_parallel).normalize();
+        let v = normal.cross(&u);
+        Self {
+            origin,
+            normal,
+            u,
+            v,
+        }
+    }
+
+    /// Least-squares best-fit plane through the given points.
+    ///
+    /// None if there are no points.
+    /// If the points are collinear, one of the planes containing them is chosen arbitrarily.
+    pub fn fit(points: &[Point3<Precision>]) -> Option<Self> {
+        if points.is_empty() {
+            return None;
+        }
+        let centroid = Point3::from(
+            points
+                .iter()
+                .fold(Vector3::zeros(), |acc, p| acc + p.coords)
+                / points.len() as Precision,
+        );
+        let covariance = points.iter().fold(Matrix3::zeros(), |acc, p| {
+            let d = p - centroid;
+            acc + d * d.transpose()
+        });
+        let eigen = covariance.symmetric_eigen();
+        let normal = eigen
+            .eigenvectors
+            .column(eigen.eigenvalues.imin())
+            .into_owned();
+        Some(Self::new(centroid, normal))
+    }
+
+    pub fn origin(&self) -> &Point3<Precision> {
+        &self.origin
+    }
+
+    pub fn normal(&self) -> &Unit<Vector3<Precision>> {
+        &self.normal
+    }
+
+    /// Signed distance from the plane, positive on the side the normal points to.
+    pub fn signed_distance(&self, p: &Point3<Precision>) -> Precision {
+        (p - self.origin).dot(&self.normal)
+    }
+
+    /// Closest point on the plane.
+    pub fn project(&self, p: &Point3<Precision>) -> Point3<Precision> {
+        p - self.normal.into_inner() * self.signed_distance(p)
+    }
+
+    /// Coordinates of the projected point within the plane.
+    pub fn to_2d(&self, p: &Point3<Precision>) -> Point2<Precision> {
+        let d = p - self.origin;
+        Point2::new(d.dot(&self.u), d.dot(&self.v))
+    }
+
+    /// Location in 3D space of a point within the plane.
+    pub fn to_3d(&self, p: &Point2<Precision>) -> Point3<Precision> {
+        self.origin + self.u * p.x + self.v * p.y
+    }
+}
+
+/// Apply a 2D operation (e.g. smoothing or simplification) to a 3D line within the given plane.
+///
+/// The line is projected onto the plane, so the output lies exactly within it.
+pub fn in_plane<F>(line: &[Point<Precision, 3>], plane: &Plane, f: F) -> Vec<Point<Precision, 3>>
+where
+    F: FnOnce(&[Point<Precision, 2>]) -> Vec<Point<Precision, 2>>,
+{
+    let flat: Vec<_> = line.iter().map(|p| plane.to_2d(p)).collect();
+    f(&flat).iter().map(|p| plane.to_3d(p)).collect()
+}
+
+/// Apply a 2D operation (e.g. smoothing or simplification) to a 3D line within its best-fit plane.
+///
+/// See [in_plane] and [Plane::fit].
+pub fn in_best_fit_plane<F>(line: &[Point<Precision, 3>], f: F) -> Vec<Point<Precision, 3>>
+where
+    F: FnOnce(&[Point<Precision, 2>]) -> Vec<Point<Precision, 2>>,
+{
+    match Plane::fit(line) {
+        Some(plane) => in_plane(line, &plane, f),
+        None => Vec::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smooth::{smooth_convolve, Linear};
+    use crate::test_utils::tol;
+
+    #[test]
+    fn roundtrip() {
+        let plane = Plane::new(Point3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 1.0, 0.0));
+        let p = Point3::new(3.0, 0.0, 5.0);
+        assert!((plane.to_3d(&plane.to_2d(&p)) - p).norm() < tol(1e-12));
+        let off = p + plane.normal().into_inner() * 2.0;
+        assert!((plane.signed_distance(&off) - 2.0).abs() < tol(1e-12));
+        assert!((plane.project(&off) - p).norm() < tol(1e-12));
+    }
+
+    #[test]
+    fn smoothing_stays_in_plane() {
+        let normal = Vector3::new(0.0, 1.0, 1.0);
+        let plane = Plane::new(Point3::origin(), normal);
+        let line: Vec<_> = (0..20)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 0.3 } else { -0.3 };
+                plane.to_3d(&Point2::new(i as Precision, noise))
+            })
+            .collect();
+        let fitted = Plane::fit(&line).unwrap();
+        assert!(fitted.normal().dot(&normal.normalize()).abs() > 1.0 - tol(1e-9));
+
+        let smoothed = in_best_fit_plane(&line, |l| smooth_convolve(l, Linear::new(2.5), false));
+        assert_eq!(smoothed.len(), line.len());
+        for p in smoothed.iter() {
+            assert!(plane.signed_distance(p).abs() < tol(1e-9));
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Commonly-used traits, types and functions.
+//!
+//! ```
+//! use simples::prelude::*;
+//! ```
+pub use crate::iter::LineIterExt;
+pub use crate::pipeline::{Pipeline, Stage};
+pub use crate::simplify::pd::pd_reduce;
+pub use crate::simplify::radial::radial_reduce;
+pub use crate::simplify::rdp::{rdp_keep, rdp_reduce};
+pub use crate::simplify::sample::{
+    resample, resample_exact, resample_with, sample_every, sample_every_snapped, sample_every_with,
+};
+pub use crate::simplify::vw::{vw_keep, vw_reduce};
+pub use crate::smooth::{
+    smooth_convolve, smooth_moving_average, Cosine, Epanechnikov, Gaussian, Kernel, Linear,
+    Smoother,
+};
+pub use crate::{cumulative_lengths, total_length, Lerp, Location, Point, Precision};
@@ -0,0 +1,206 @@
+//! Distance calculations vectorised over batches of 4 points, with the `simd` feature.
+//!
+//! Each lane does the same operations in the same order as the scalar code it replaces,
+//! so results are identical to it (for up to 7 dimensions, beyond which nalgebra sums coordinates in a different order).
+use crate::metric::{DistanceMetric, Euclidean};
+use crate::Precision;
+use nalgebra::{distance_squared, Point};
+#[cfg(feature = "f32")]
+use wide::f32x4 as Lanes;
+#[cfg(not(feature = "f32"))]
+use wide::f64x4 as Lanes;
+use wide::{CmpGe, CmpLe, CmpLt};
+
+const LANES: usize = 4;
+
+/// Coordinate `dim` of each of the first 4 points.
+fn gather<const D: usize>(points: &[Point<Precision, D>], dim: usize) -> Lanes {
+    Lanes::from([
+        points[0][dim],
+        points[1][dim],
+        points[2][dim],
+        points[3][dim],
+    ])
+}
+
+/// Squared distance from `from` to each of the first 4 points.
+fn distance_squared4<const D: usize>(
+    from: &Point<Precision, D>,
+    points: &[Point<Precision, D>],
+) -> Lanes {
+    let mut sum = Lanes::ZERO;
+    for dim in 0..D {
+        let diff = gather(points, dim) - Lanes::splat(from[dim]);
+        sum += diff * diff;
+    }
+    sum
+}
+
+/// Index of the first point at least `threshold_sq` squared distance from `from`.
+pub(crate) fn first_at_least<const D: usize>(
+    from: &Point<Precision, D>,
+    points: &[Point<Precision, D>],
+    threshold_sq: Precision,
+) -> Option<usize> {
+    let threshold = Lanes::splat(threshold_sq);
+    let mut chunks = points.chunks_exact(LANES);
+    for (chunk_idx, chunk) in chunks.by_ref().enumerate() {
+        let found = distance_squared4(from, chunk).cmp_ge(threshold);
+        if found.any() {
+            let lane = found.move_mask().trailing_zeros() as usize;
+            return Some(chunk_idx * LANES + lane);
+        }
+    }
+    let done = points.len() - chunks.remainder().len();
+    chunks
+        .remainder()
+        .iter()
+        .position(|p| distance_squared(from, p) >= threshold_sq)
+        .map(|idx| done + idx)
+}
+
+/// Call `f` with the index of every point and its squared distance (as `proj_dist2`)
+/// from the segment from `start` to `end`, whose squared length is `length_sq`.
+pub(crate) fn each_proj_dist2<const D: usize, F: FnMut(usize, Precision)>(
+    start: &Point<Precision, D>,
+    end: &Point<Precision, D>,
+    points: &[Point<Precision, D>],
+    length_sq: Precision,
+    mut f: F,
+) {
+    let mut chunks = points.chunks_exact(LANES);
+    for (chunk_idx, chunk) in chunks.by_ref().enumerate() {
+        let d2 = if length_sq == 0.0 {
+            distance_squared4(start, chunk)
+        } else {
+            let mut along = Lanes::ZERO;
+            for dim in 0..D {
+                let u = Lanes::splat(end[dim] - start[dim]);
+                along += (gather(chunk, dim) - Lanes::splat(start[dim])) * u;
+            }
+            let t = along / Lanes::splat(length_sq);
+            let mut middle = Lanes::ZERO;
+            for dim in 0..D {
+                let u = Lanes::splat(end[dim] - start[dim]);
+                let diff = gather(chunk, dim) - (Lanes::splat(start[dim]) + t * u);
+                middle += diff * diff;
+            }
+            // most points project onto the segment, so only measure to the ends if needed
+            let after = along.cmp_ge(Lanes::splat(length_sq));
+            if after.any() {
+                middle = after.blend(distance_squared4(end, chunk), middle);
+            }
+            let before = along.cmp_le(Lanes::ZERO);
+            if before.any() {
+                middle = before.blend(distance_squared4(start, chunk), middle);
+            }
+            middle
+        };
+        for (lane, d2) in d2.to_array().into_iter().enumerate() {
+            f(chunk_idx * LANES + lane, d2);
+        }
+    }
+    let done = points.len() - chunks.remainder().len();
+    for (idx, p) in chunks.remainder().iter().enumerate() {
+        f(
+            done + idx,
+            crate::simplify::rdp::proj_dist2(start, end, p, length_sq),
+        );
+    }
+}
+
+/// Area (by Heron's formula, as [Euclidean](crate::metric::Euclidean)) of the triangle made by each point other than the ends,
+/// and its neighbours.
+pub(crate) fn triangle_areas<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Precision> {
+    let mut out = Vec::with_capacity(line.len().saturating_sub(2));
+    if line.len() < 3 {
+        return out;
+    }
+    let mut start = 0;
+    while start + LANES + 2 <= line.len() {
+        let (a, b, c) = (&line[start..], &line[(start + 1)..], &line[(start + 2)..]);
+        let mut ab = Lanes::ZERO;
+        let mut bc = Lanes::ZERO;
+        let mut ca = Lanes::ZERO;
+        for dim in 0..D {
+            let (pa, pb, pc) = (gather(a, dim), gather(b, dim), gather(c, dim));
+            ab += (pb - pa) * (pb - pa);
+            bc += (pc - pb) * (pc - pb);
+            ca += (pa - pc) * (pa - pc);
+        }
+        let (s1, s2, s3) = (ab.sqrt(), bc.sqrt(), ca.sqrt());
+        let s = (s1 + s2 + s3) / Lanes::splat(2.0);
+        let product = s * (s - s1) * (s - s2) * (s - s3);
+        // as the scalar version, clamp negative products but keep NaN
+        let area = product
+            .cmp_lt(Lanes::ZERO)
+            .blend(Lanes::ZERO, product)
+            .sqrt();
+        out.extend(area.to_array());
+        start += LANES;
+    }
+    out.extend(
+        line[start..]
+            .windows(3)
+            .map(|w| Euclidean.triangle_area(&w[0], &w[1], &w[2])),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::rdp::proj_dist2;
+
+    /// An irregular 3D line, of a length which is not a multiple of [LANES].
+    fn wiggle(n: usize) -> Vec<Point<Precision, 3>> {
+        (0..n)
+            .map(|i| {
+                let x = i as Precision * 0.37;
+                [x, x.sin() * 3.1, (x * 2.3).cos() - x * 0.1].into()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn proj_matches_scalar() {
+        let line = wiggle(23);
+        let segments = [(line[0], line[22]), (line[5], line[9]), (line[3], line[3])];
+        for (start, end) in segments {
+            let length_sq = distance_squared(&start, &end);
+            let mut out = vec![];
+            each_proj_dist2(&start, &end, &line, length_sq, |idx, d2| {
+                out.push((idx, d2))
+            });
+            let expected: Vec<_> = line
+                .iter()
+                .map(|p| proj_dist2(&start, &end, p, length_sq))
+                .enumerate()
+                .collect();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn first_at_least_matches_scalar() {
+        let line = wiggle(23);
+        for threshold in [0.0, 1.0, 30.0, 100.0, 1e6] {
+            let expected = line
+                .iter()
+                .position(|p| distance_squared(&line[0], p) >= threshold);
+            assert_eq!(first_at_least(&line[0], &line, threshold), expected);
+        }
+    }
+
+    #[test]
+    fn areas_match_scalar() {
+        for n in [0, 2, 3, 6, 7, 23] {
+            let line = wiggle(n);
+            let expected: Vec<_> = line
+                .windows(3)
+                .map(|w| Euclidean.triangle_area(&w[0], &w[1], &w[2]))
+                .collect();
+            assert_eq!(triangle_areas(&line), expected);
+        }
+    }
+}
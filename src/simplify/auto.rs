@@ -0,0 +1,195 @@
+//! Simplify a batch of linestrings of varying sizes to fit an overall point budget,
+//! choosing an algorithm for each line.
+use super::radial::radial_keep;
+use super::vw::vw_reduce;
+use crate::{total_length, Precision};
+use nalgebra::Point;
+
+/// Lines with no more than this many points are simplified with the (cheap) radial distance filter;
+/// larger lines are simplified with VW.
+pub const RADIAL_MAX_POINTS: usize = 64;
+
+/// Bisection steps used when searching for a radial distance tolerance.
+const TOLERANCE_SEARCH_STEPS: usize = 64;
+
+/// How [auto_simplify_batch] treated a single line.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutoChoice {
+    /// The line was already within its share of the budget.
+    Unchanged,
+    /// Simplified with the radial distance filter at this tolerance.
+    Radial { tolerance: Precision },
+    /// Simplified with VW to this number of points.
+    Vw { n_points: usize },
+}
+
+/// What was done to a single line by [auto_simplify_batch].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoReport {
+    pub choice: AutoChoice,
+    /// Share of the point budget allocated to the line.
+    pub budget: usize,
+    pub n_points_in: usize,
+    pub n_points_out: usize,
+}
+
+/// Share out the budget in proportion to each line's size, by the largest remainder method.
+///
+/// Every line gets at least its first and last point, even if that exceeds the budget.
+fn allocate(sizes: &[usize], target_total_points: usize) -> Vec<usize> {
+    let minimum = |n: usize| n.min(2);
+    // lines whose proportional share would be below their minimum get the minimum,
+    // and the rest share what's left
+    let mut fixed = vec![false; sizes.len()];
+    let ratio = loop {
+        let (fixed_total, free_total) =
+            sizes
+                .iter()
+                .zip(fixed.iter())
+                .fold((0, 0), |(fixed_total, free_total), (n, f)| {
+                    if *f {
+                        (fixed_total + minimum(*n), free_total)
+                    } else {
+                        (fixed_total, free_total + n)
+                    }
+                });
+        let ratio = target_total_points.saturating_sub(fixed_total) as Precision
+            / free_total.max(1) as Precision;
+        let mut changed = false;
+        for (n, f) in sizes.iter().zip(fixed.iter_mut()) {
+            if !*f && (*n as Precision * ratio) < minimum(*n) as Precision {
+                *f = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break ratio;
+        }
+    };
+
+    let exact = |idx: usize| sizes[idx] as Precision * ratio;
+    let mut shares: Vec<usize> = (0..sizes.len())
+        .map(|idx| {
+            if fixed[idx] {
+                minimum(sizes[idx])
+            } else {
+                (exact(idx).floor() as usize).min(sizes[idx])
+            }
+        })
+        .collect();
+    let mut remaining = target_total_points.saturating_sub(shares.iter().sum());
+
+    let mut by_remainder: Vec<usize> = (0..sizes.len()).filter(|idx| !fixed[*idx]).collect();
+    by_remainder.sort_by(|a, b| exact(*b).fract().total_cmp(&exact(*a).fract()));
+    for idx in by_remainder {
+        if remaining == 0 {
+            break;
+        }
+        if shares[idx] < sizes[idx] {
+            shares[idx] += 1;
+            remaining -= 1;
+        }
+    }
+    shares
+}
+
+/// Smallest radial distance tolerance (approximately) which reduces the line to no more than `n_points`.
+fn radial_tolerance<const D: usize>(line: &[Point<Precision, D>], n_points: usize) -> Precision {
+    let mut lo = 0.0;
+    let mut hi = total_length(line);
+    for _ in 0..TOLERANCE_SEARCH_STEPS {
+        let mid = (lo + hi) / 2.0;
+        if radial_keep(line, mid).len() <= n_points {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
+/// Simplify every line so that, together, they have about `target_total_points` points.
+///
+/// Each line's share of the budget is proportional to its number of points,
+/// although every line keeps at least its endpoints.
+/// Lines with up to [RADIAL_MAX_POINTS] points are simplified with the radial distance filter,
+/// which may undershoot the line's share;
+/// larger lines are simplified with VW, which hits it exactly.
+///
+/// Returns the simplified lines and a report of what was done to each.
+pub fn auto_simplify_batch<L: AsRef<[Point<Precision, D>]>, const D: usize>(
+    lines: &[L],
+    target_total_points: usize,
+) -> (Vec<Vec<Point<Precision, D>>>, Vec<AutoReport>) {
+    let sizes: Vec<_> = lines.iter().map(|l| l.as_ref().len()).collect();
+    let budgets = allocate(&sizes, target_total_points);
+
+    lines
+        .iter()
+        .zip(budgets)
+        .map(|(line, budget)| {
+            let line = line.as_ref();
+            let (out, choice) = if line.len() <= budget {
+                (line.to_vec(), AutoChoice::Unchanged)
+            } else if line.len() <= RADIAL_MAX_POINTS {
+                let tolerance = radial_tolerance(line, budget);
+                let out = radial_keep(line, tolerance)
+                    .into_iter()
+                    .map(|idx| line[idx])
+                    .collect();
+                (out, AutoChoice::Radial { tolerance })
+            } else {
+                (
+                    vw_reduce(line, budget, false),
+                    AutoChoice::Vw { n_points: budget },
+                )
+            };
+            let report = AutoReport {
+                choice,
+                budget,
+                n_points_in: line.len(),
+                n_points_out: out.len(),
+            };
+            (out, report)
+        })
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    fn wiggle(n: usize) -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..n)
+                .map(|i| [i as Precision, (i as Precision * 0.7).sin()])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn allocation() {
+        assert_eq!(allocate(&[10, 30, 2], 21), vec![5, 14, 2]);
+        assert_eq!(allocate(&[10, 30], 100), vec![10, 30]);
+        assert_eq!(allocate(&[10, 10, 10], 3), vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn hits_budget() {
+        let lines = vec![wiggle(2), wiggle(20), wiggle(1000)];
+        let (out, reports) = auto_simplify_batch(&lines, 200);
+        assert_eq!(reports[0].choice, AutoChoice::Unchanged);
+        assert!(matches!(reports[1].choice, AutoChoice::Radial { .. }));
+        assert!(matches!(reports[2].choice, AutoChoice::Vw { .. }));
+
+        let total: usize = out.iter().map(|l| l.len()).sum();
+        assert!(total <= 200 && total > 190);
+        for (line, report) in out.iter().zip(reports.iter()) {
+            assert_eq!(line.len(), report.n_points_out);
+            assert!(report.n_points_out <= report.budget);
+        }
+    }
+}
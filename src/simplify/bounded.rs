@@ -0,0 +1,173 @@
+//! Simplify as far as possible while staying within a given deviation from the original line.
+use super::rdp::rdp_keep_constrained;
+use super::vw::vw_keep;
+use crate::metrics::{discrete_frechet_distance, hausdorff_distance};
+use crate::{cumulative_lengths, Lerp, Precision};
+use nalgebra::Point;
+
+/// Simplification algorithm to be tuned by [simplify_to_error].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    /// RDP, keeping the most deviant points first (see [rdp_keep_constrained]).
+    Rdp,
+    /// VW; see [vw_keep].
+    Vw { closed: bool },
+}
+
+impl Algorithm {
+    fn keep<const D: usize>(&self, line: &[Point<Precision, D>], n_points: usize) -> Vec<usize> {
+        match *self {
+            Algorithm::Rdp => rdp_keep_constrained(line, 0.0, n_points),
+            Algorithm::Vw { closed } => vw_keep(line, n_points, closed),
+        }
+    }
+}
+
+/// How to measure the deviation of a simplified line from the original.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Deviation {
+    /// See [discrete_frechet_distance].
+    ///
+    /// Every dropped point is matched to the position on its simplified edge
+    /// at the same fraction of the distance along the edge as it was along the original sub-line,
+    /// so that long simplified edges are not penalised for lacking vertices.
+    #[default]
+    Frechet,
+    /// See [hausdorff_distance].
+    Hausdorff,
+}
+
+impl Deviation {
+    fn measure<const D: usize>(&self, line: &[Point<Precision, D>], kept: &[usize]) -> Precision {
+        match self {
+            Deviation::Frechet => discrete_frechet_distance(line, &along_kept(line, kept)),
+            Deviation::Hausdorff => {
+                let simplified: Vec<_> = kept.iter().map(|idx| line[*idx]).collect();
+                hausdorff_distance(line, &simplified)
+            }
+        }
+    }
+}
+
+/// For every point in the line, the corresponding point on the simplified line.
+fn along_kept<const D: usize>(
+    line: &[Point<Precision, D>],
+    kept: &[usize],
+) -> Vec<Point<Precision, D>> {
+    let cumulative = cumulative_lengths(line);
+    let mut out = Vec::with_capacity(line.len());
+    out.push(line[kept[0]]);
+    for pair in kept.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let sub_length = cumulative[end] - cumulative[start];
+        for idx in (start + 1)..=end {
+            let t = if sub_length > 0.0 {
+                (cumulative[idx] - cumulative[start]) / sub_length
+            } else {
+                1.0
+            };
+            out.push(line[start].lerp(&line[end], t));
+        }
+    }
+    out
+}
+
+/// Find the smallest simplification of the line by the given algorithm
+/// whose (discrete) Fréchet distance from the original is no more than `max_error`.
+///
+/// See [simplify_to_error_with].
+pub fn simplify_to_error<const D: usize>(
+    line: &[Point<Precision, D>],
+    max_error: Precision,
+    algorithm: Algorithm,
+) -> Vec<Point<Precision, D>> {
+    simplify_to_error_with(line, max_error, algorithm, Deviation::Frechet)
+}
+
+/// Find the smallest simplification of the line by the given algorithm
+/// whose deviation from the original is no more than `max_error`.
+///
+/// Binary-searches the number of points in the output,
+/// so assumes the deviation shrinks as more points are kept
+/// (which is not strictly true, but close enough for a good approximation).
+/// Lines with 2 or fewer points are returned unchanged.
+pub fn simplify_to_error_with<const D: usize>(
+    line: &[Point<Precision, D>],
+    max_error: Precision,
+    algorithm: Algorithm,
+    deviation: Deviation,
+) -> Vec<Point<Precision, D>> {
+    if line.len() <= 2 {
+        return line.to_vec();
+    }
+    // the full line has zero deviation, so always satisfies the bound
+    let mut best: Vec<_> = (0..line.len()).collect();
+    let mut lo = 2;
+    let mut hi = line.len();
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let candidate = algorithm.keep(line, mid);
+        if deviation.measure(line, &candidate) <= max_error {
+            hi = mid;
+            best = candidate;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    best.into_iter().map(|idx| line[idx]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    fn zigzag() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..20)
+                .map(|i| {
+                    [
+                        i as Precision,
+                        if i % 5 == 0 {
+                            3.0
+                        } else {
+                            0.1 * (i % 2) as Precision
+                        },
+                    ]
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn within_bound() {
+        let line = zigzag();
+        for algorithm in [Algorithm::Rdp, Algorithm::Vw { closed: false }] {
+            for deviation in [Deviation::Frechet, Deviation::Hausdorff] {
+                let out = simplify_to_error_with(&line, 0.5, algorithm, deviation);
+                assert!(out.len() < line.len());
+                let kept: Vec<_> = out
+                    .iter()
+                    .map(|p| line.iter().position(|q| q == p).unwrap())
+                    .collect();
+                assert!(deviation.measure(&line, &kept) <= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_error_keeps_everything() {
+        let line = zigzag();
+        let out = simplify_to_error(&line, 0.0, Algorithm::Rdp);
+        assert_eq!(out, line);
+    }
+
+    #[test]
+    fn straight_line() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+        let out = simplify_to_error(&line, 1e-9, Algorithm::Rdp);
+        assert_eq!(out, make_line(vec![[0.0, 0.0], [3.0, 0.0]]));
+    }
+}
@@ -0,0 +1,271 @@
+//! Simplify a linestring by keeping its dominant points: those of locally greatest curvature.
+//!
+//! Unlike RDP or VW, there is no tolerance to tune.
+//! Each point's curvature is measured over its own region of support,
+//! which grows until the line around the point stops looking like a single bend,
+//! so that both sharp and gradual corners are found.
+//! This suits digitised outlines and handwriting, whose corners matter more than small deviations.
+//!
+//! Noise is taken as genuine detail, so noisy lines should be smoothed first.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::simplify::dominant::teh_chin_keep;
+//! use simples::{Lerp, Point};
+//!
+//! // a square with 4 points per side
+//! let corners: Vec<Point<Precision, 2>> =
+//!     vec![[0.0, 0.0].into(), [4.0, 0.0].into(), [4.0, 4.0].into(), [0.0, 4.0].into()];
+//! let ring: Vec<_> = (0..4)
+//!     .flat_map(|side| {
+//!         let (a, b) = (corners[side], corners[(side + 1) % 4]);
+//!         (0..4).map(move |i| a.lerp(&b, i as Precision / 4.0))
+//!     })
+//!     .collect();
+//! assert_eq!(teh_chin_keep(&ring, true), vec![0, 4, 8, 12]);
+//! ```
+use crate::{CurveKind, Precision};
+use nalgebra::Point;
+
+/// Points whose k-cosine is within this of -1.0 are on a straight line, so are never dominant.
+const FLAT: Precision = if cfg!(feature = "f32") { 1e-5 } else { 1e-9 };
+
+/// Indices into a line, wrapping around if it is closed.
+struct Neighbours {
+    len: usize,
+    closed: bool,
+}
+
+impl Neighbours {
+    /// Largest region of support possible for point `idx`.
+    fn max_support(&self, idx: usize) -> usize {
+        if self.closed {
+            (self.len - 1) / 2
+        } else {
+            idx.min(self.len - 1 - idx)
+        }
+    }
+
+    /// The points `k` before and after `idx`, which must be within [Neighbours::max_support].
+    fn around(&self, idx: usize, k: usize) -> (usize, usize) {
+        if self.closed {
+            ((idx + self.len - k) % self.len, (idx + k) % self.len)
+        } else {
+            (idx - k, idx + k)
+        }
+    }
+}
+
+/// Length of the chord from `a` to `b`, and the ratio of `p`'s distance from that (infinite) line to it.
+///
+/// The ratio is infinite for a zero-length chord.
+fn chord<const D: usize>(
+    a: &Point<Precision, D>,
+    b: &Point<Precision, D>,
+    p: &Point<Precision, D>,
+) -> (Precision, Precision) {
+    let u = b - a;
+    let v = p - a;
+    let length_sq = u.norm_squared();
+    if length_sq == 0.0 {
+        return (0.0, Precision::INFINITY);
+    }
+    let along = v.dot(&u);
+    let dist_sq = (v.norm_squared() - along * along / length_sq).max(0.0);
+    (length_sq.sqrt(), (dist_sq / length_sq).sqrt())
+}
+
+/// Region of support of every point: the number of points either side which make up its bend.
+///
+/// It grows while the chord between the points either side gets longer
+/// and the point's distance from that chord, relative to the chord's length, increases (or is still zero).
+/// Ends of open lines have a region of 0.
+fn regions_of_support<const D: usize>(
+    line: &[Point<Precision, D>],
+    nbrs: &Neighbours,
+) -> Vec<usize> {
+    (0..line.len())
+        .map(|idx| {
+            let max_k = nbrs.max_support(idx);
+            if max_k == 0 {
+                return 0;
+            }
+            let at = |k| {
+                let (before, after) = nbrs.around(idx, k);
+                chord(&line[before], &line[after], &line[idx])
+            };
+            let mut k = 1;
+            let (mut length, mut ratio) = at(k);
+            while k < max_k {
+                let (next_length, next_ratio) = at(k + 1);
+                if length >= next_length || (ratio > 0.0 && ratio >= next_ratio) {
+                    break;
+                }
+                (length, ratio) = (next_length, next_ratio);
+                k += 1;
+            }
+            k
+        })
+        .collect()
+}
+
+/// Cosine of the angle at `idx` between the points `k` either side:
+/// -1.0 for a straight line, up to 1.0 for a complete reversal.
+fn k_cosine<const D: usize>(
+    line: &[Point<Precision, D>],
+    nbrs: &Neighbours,
+    idx: usize,
+    k: usize,
+) -> Precision {
+    let (before, after) = nbrs.around(idx, k);
+    let a = line[before] - line[idx];
+    let b = line[after] - line[idx];
+    let norms = a.norm() * b.norm();
+    if norms == 0.0 {
+        return -1.0;
+    }
+    (a.dot(&b) / norms).clamp(-1.0, 1.0)
+}
+
+/// Return the indices of the line's dominant points,
+/// found with the algorithm of Teh & Chin (1989, "On the detection of dominant points on digital curves")
+/// using the k-cosine measure of curvature.
+///
+/// 1. Each point's region of support `k` is found (see module docs).
+/// 2. Its curvature is the cosine of the angle to the points `k` before and after it.
+/// 3. Points are dropped if any other point within `k / 2` either side of them is more curved.
+/// 4. Points on a straight line are dropped.
+///
+/// The ends of open lines are always kept.
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first;
+/// a closed line which is nearly straight may be reduced to fewer than 3 points.
+pub fn teh_chin_keep<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Vec<usize> {
+    let closed = kind.into().is_closed();
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let nbrs = Neighbours {
+        len: line.len(),
+        closed,
+    };
+    let support = regions_of_support(line, &nbrs);
+    let significance: Vec<_> = support
+        .iter()
+        .enumerate()
+        .map(|(idx, k)| {
+            if *k == 0 {
+                Precision::NEG_INFINITY
+            } else {
+                k_cosine(line, &nbrs, idx, *k)
+            }
+        })
+        .collect();
+
+    (0..line.len())
+        .filter(|idx| {
+            let k = support[*idx];
+            if k == 0 {
+                // end of an open line
+                return true;
+            }
+            let sig = significance[*idx];
+            if sig <= -1.0 + FLAT {
+                return false;
+            }
+            (1..=(k / 2).min(nbrs.max_support(*idx))).all(|offset| {
+                let (before, after) = nbrs.around(*idx, offset);
+                significance[before] <= sig && significance[after] <= sig
+            })
+        })
+        .collect()
+}
+
+/// Decimate the linestring to its dominant points; see [teh_chin_keep].
+pub fn teh_chin_reduce<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, D>> {
+    teh_chin_keep(line, kind)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    /// An L shape with `per_side` points along each arm, and the corner at `per_side`.
+    fn ell(per_side: usize) -> Vec<Point<Precision, 2>> {
+        let mut coords: Vec<_> = (0..per_side).map(|i| [i as Precision, 0.0]).collect();
+        coords.extend((0..=per_side).map(|i| [per_side as Precision, i as Precision]));
+        make_line(coords)
+    }
+
+    #[test]
+    fn straight() {
+        let line = make_line(
+            (0..10)
+                .map(|i| [i as Precision, 2.0 * i as Precision])
+                .collect(),
+        );
+        assert_eq!(teh_chin_keep(&line, false), vec![0, 9]);
+        // closing the line doubles back on itself at the ends
+        let closed = teh_chin_keep(&line, true);
+        if cfg!(feature = "f32") {
+            // the points next to the ends reverse too, and tie with them in single precision
+            assert!(closed.iter().all(|idx| [0, 1, 8, 9].contains(idx)));
+        } else {
+            assert_eq!(closed, vec![0, 9]);
+        }
+    }
+
+    #[test]
+    fn corner() {
+        let line = ell(5);
+        assert_eq!(teh_chin_keep(&line, false), vec![0, 5, 10]);
+        assert_eq!(
+            teh_chin_reduce(&line, false),
+            vec![line[0], line[5], line[10]]
+        );
+        // closing the L makes a right-angled triangle
+        assert_eq!(teh_chin_keep(&line, true), vec![0, 5, 10]);
+    }
+
+    #[test]
+    fn three_d() {
+        // up the z axis, then along x, then along y
+        let mut line: Vec<Point<Precision, 3>> =
+            (0..4).map(|i| [0.0, 0.0, i as Precision].into()).collect();
+        line.extend((0..4).map(|i| Point::from([i as Precision, 0.0, 4.0])));
+        line.extend((0..=4).map(|i| Point::from([4.0, i as Precision, 4.0])));
+        assert_eq!(teh_chin_keep(&line, false), vec![0, 4, 8, 12]);
+    }
+
+    #[test]
+    fn support() {
+        let line = ell(5);
+        let nbrs = Neighbours {
+            len: line.len(),
+            closed: false,
+        };
+        let support = regions_of_support(&line, &nbrs);
+        assert_eq!(support[0], 0);
+        assert_eq!(support[10], 0);
+        // a point on an arm sees as far as it can, until the chord reaches past the corner
+        assert!(support[3] > 1);
+    }
+
+    #[test]
+    fn short() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0]]);
+        assert_eq!(teh_chin_keep(&line, false), vec![0, 1]);
+        assert_eq!(teh_chin_keep(&line[..1], true), vec![0]);
+        let repeated = make_line(vec![[0.0, 0.0]; 4]);
+        assert_eq!(teh_chin_keep(&repeated, false), vec![0, 3]);
+    }
+}
@@ -0,0 +1,328 @@
+//! Simplify a growing linestring one point at a time,
+//! emitting kept points as soon as they are certain.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::simplify::incremental::{IncrementalSimplifier, IncrementalSimplify, Radial};
+//! use simples::simplify::sleeve::Sleeve;
+//! use simples::Point;
+//!
+//! // cheaply thin out dense points, then fit sleeves to what's left
+//! let mut simplifier = IncrementalSimplifier::new(Radial::new(0.5).then(Sleeve::new(0.1)));
+//! for i in 0..100 {
+//!     simplifier.push(Point::from([i as Precision * 0.1, 0.0]));
+//!     // a view of the track so far, ending at the latest point
+//!     assert_eq!(simplifier.view().last().unwrap().x, i as Precision * 0.1);
+//! }
+//! assert_eq!(simplifier.finish().len(), 2);
+//! ```
+use super::sleeve::Sleeve;
+use super::vw::VwSteps;
+use crate::Precision;
+use nalgebra::{distance_squared, Point};
+use std::collections::{HashSet, VecDeque};
+
+/// A simplification algorithm which can be run on a stream of points.
+pub trait IncrementalSimplify<const D: usize> {
+    /// Add the next point of the line,
+    /// returning a point of the simplified line if one has become certain.
+    fn push(&mut self, point: Point<Precision, D>) -> Option<Point<Precision, D>>;
+
+    /// Finish the line, returning the remaining points of the simplified line.
+    ///
+    /// The simplifier can then be reused for another line.
+    fn finish(&mut self) -> Vec<Point<Precision, D>>;
+
+    /// Feed the output of this simplifier into another.
+    fn then<S: IncrementalSimplify<D>>(self, other: S) -> Then<Self, S>
+    where
+        Self: Sized,
+    {
+        Then {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+/// Two incremental simplifiers, one feeding the other.
+#[derive(Clone, Debug)]
+pub struct Then<S1, S2> {
+    first: S1,
+    second: S2,
+}
+
+impl<const D: usize, S1, S2> IncrementalSimplify<D> for Then<S1, S2>
+where
+    S1: IncrementalSimplify<D>,
+    S2: IncrementalSimplify<D>,
+{
+    fn push(&mut self, point: Point<Precision, D>) -> Option<Point<Precision, D>> {
+        self.first.push(point).and_then(|p| self.second.push(p))
+    }
+
+    fn finish(&mut self) -> Vec<Point<Precision, D>> {
+        let mut out: Vec<_> = self
+            .first
+            .finish()
+            .into_iter()
+            .filter_map(|p| self.second.push(p))
+            .collect();
+        out.extend(self.second.finish());
+        out
+    }
+}
+
+impl IncrementalSimplify<2> for Sleeve {
+    fn push(&mut self, point: Point<Precision, 2>) -> Option<Point<Precision, 2>> {
+        Sleeve::push(self, point)
+    }
+
+    fn finish(&mut self) -> Vec<Point<Precision, 2>> {
+        Sleeve::finish(self).into_iter().collect()
+    }
+}
+
+/// Streaming version of the radial distance filter; see [crate::simplify::radial::radial_keep].
+#[derive(Clone, Debug)]
+pub struct Radial<const D: usize> {
+    tolerance: Precision,
+    anchor: Option<Point<Precision, D>>,
+    /// The latest point, if it has not been emitted.
+    pending: Option<Point<Precision, D>>,
+}
+
+impl<const D: usize> Radial<D> {
+    pub fn new(tolerance: Precision) -> Self {
+        Self {
+            tolerance,
+            anchor: None,
+            pending: None,
+        }
+    }
+}
+
+impl<const D: usize> IncrementalSimplify<D> for Radial<D> {
+    fn push(&mut self, point: Point<Precision, D>) -> Option<Point<Precision, D>> {
+        let keep = self
+            .anchor
+            .is_none_or(|a| distance_squared(&a, &point) >= self.tolerance * self.tolerance);
+        if keep {
+            self.anchor = Some(point);
+            self.pending = None;
+            Some(point)
+        } else {
+            self.pending = Some(point);
+            None
+        }
+    }
+
+    fn finish(&mut self) -> Vec<Point<Precision, D>> {
+        let out = self.pending.into_iter().collect();
+        *self = Self::new(self.tolerance);
+        out
+    }
+}
+
+/// Visvalingam-Whyatt over a sliding window of points.
+///
+/// Points are removed while they have an effective area less than `min_area`,
+/// considering only the last kept point and the next `window` points.
+/// Once a point leaves the window, it is emitted if it survived.
+/// Larger windows give results closer to VW on the whole line, at a higher cost per point.
+#[derive(Clone, Debug)]
+pub struct WindowedVw<const D: usize> {
+    window: usize,
+    min_area: Precision,
+    anchor: Option<Point<Precision, D>>,
+    buffer: VecDeque<Point<Precision, D>>,
+}
+
+impl<const D: usize> WindowedVw<D> {
+    /// `window` must be at least 2.
+    pub fn new(window: usize, min_area: Precision) -> Self {
+        Self {
+            window: window.max(2),
+            min_area,
+            anchor: None,
+            buffer: VecDeque::with_capacity(window + 1),
+        }
+    }
+
+    /// Anchor plus buffer, and the indices VW would remove from it.
+    fn dropped(&self, anchor: Point<Precision, D>) -> (Vec<Point<Precision, D>>, HashSet<usize>) {
+        let mut line = Vec::with_capacity(self.buffer.len() + 1);
+        line.push(anchor);
+        line.extend(self.buffer.iter());
+        let dropped = VwSteps::new(&line, false)
+            .take_while(|step| step.area < self.min_area)
+            .map(|step| step.removed)
+            .collect();
+        (line, dropped)
+    }
+}
+
+impl<const D: usize> IncrementalSimplify<D> for WindowedVw<D> {
+    fn push(&mut self, point: Point<Precision, D>) -> Option<Point<Precision, D>> {
+        let Some(anchor) = self.anchor else {
+            self.anchor = Some(point);
+            return Some(point);
+        };
+        self.buffer.push_back(point);
+        if self.buffer.len() <= self.window {
+            return None;
+        }
+        let (_, dropped) = self.dropped(anchor);
+        let oldest = self.buffer.pop_front().unwrap();
+        if dropped.contains(&1) {
+            None
+        } else {
+            self.anchor = Some(oldest);
+            Some(oldest)
+        }
+    }
+
+    fn finish(&mut self) -> Vec<Point<Precision, D>> {
+        let out = match self.anchor {
+            Some(anchor) if !self.buffer.is_empty() => {
+                let (line, dropped) = self.dropped(anchor);
+                line.into_iter()
+                    .enumerate()
+                    .skip(1)
+                    .filter_map(|(idx, p)| (!dropped.contains(&idx)).then_some(p))
+                    .collect()
+            }
+            _ => Vec::default(),
+        };
+        *self = Self::new(self.window, self.min_area);
+        out
+    }
+}
+
+/// Maintains a simplified view of a growing linestring.
+///
+/// Wraps any [IncrementalSimplify], keeping the points it has confirmed
+/// so that the simplified line so far is always available.
+#[derive(Clone, Debug)]
+pub struct IncrementalSimplifier<S, const D: usize> {
+    simplifier: S,
+    confirmed: Vec<Point<Precision, D>>,
+    latest: Option<Point<Precision, D>>,
+}
+
+impl<S: IncrementalSimplify<D>, const D: usize> IncrementalSimplifier<S, D> {
+    pub fn new(simplifier: S) -> Self {
+        Self {
+            simplifier,
+            confirmed: Vec::default(),
+            latest: None,
+        }
+    }
+
+    /// Add the next point of the line, returning a newly-confirmed point of the simplified line, if any.
+    pub fn push(&mut self, point: Point<Precision, D>) -> Option<Point<Precision, D>> {
+        self.latest = Some(point);
+        let kept = self.simplifier.push(point);
+        self.confirmed.extend(kept);
+        kept
+    }
+
+    /// Points of the simplified line which are certain.
+    pub fn confirmed(&self) -> &[Point<Precision, D>] {
+        &self.confirmed
+    }
+
+    /// The simplified line so far: the confirmed points, followed by the latest point if it is not among them.
+    ///
+    /// Later points may cause some of the latest points to be confirmed,
+    /// so this is not necessarily a prefix of the final line.
+    pub fn view(&self) -> Vec<Point<Precision, D>> {
+        let mut out = self.confirmed.clone();
+        if let Some(latest) = self.latest {
+            if out.last() != Some(&latest) {
+                out.push(latest);
+            }
+        }
+        out
+    }
+
+    /// Finish the line, returning the whole simplified line.
+    pub fn finish(mut self) -> Vec<Point<Precision, D>> {
+        self.confirmed.extend(self.simplifier.finish());
+        self.confirmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::radial::radial_reduce;
+    use crate::simplify::vw::vw_reduce;
+    use crate::test_utils::make_line;
+
+    fn wiggly() -> Vec<Point<Precision, 2>> {
+        make_line(
+            (0..100)
+                .map(|i| {
+                    let x = i as Precision * 0.1;
+                    [x, x.sin() + (x * 7.0).sin() * 0.05]
+                })
+                .collect(),
+        )
+    }
+
+    fn run<S: IncrementalSimplify<2>>(
+        s: S,
+        line: &[Point<Precision, 2>],
+    ) -> Vec<Point<Precision, 2>> {
+        let mut simplifier = IncrementalSimplifier::new(s);
+        for p in line {
+            simplifier.push(*p);
+        }
+        simplifier.finish()
+    }
+
+    #[test]
+    fn radial_matches_batch() {
+        let line = wiggly();
+        assert_eq!(run(Radial::new(0.35), &line), radial_reduce(&line, 0.35));
+    }
+
+    #[test]
+    fn windowed_vw() {
+        let line = wiggly();
+        // a window covering the whole line behaves like VW with an area threshold
+        let whole = run(WindowedVw::new(line.len(), 0.01), &line);
+        let n = whole.len();
+        assert!(n < line.len());
+        assert_eq!(whole, vw_reduce(&line, n, false));
+
+        let small = run(WindowedVw::new(5, 0.01), &line);
+        assert_eq!(small[0], line[0]);
+        assert_eq!(small[small.len() - 1], line[line.len() - 1]);
+        assert!(small.len() < line.len());
+    }
+
+    #[test]
+    fn chained() {
+        let line = wiggly();
+        let chained = run(Radial::new(0.2).then(Sleeve::new(0.05)), &line);
+        let radial = radial_reduce(&line, 0.2);
+        assert_eq!(
+            chained,
+            crate::simplify::sleeve::sleeve_reduce(&radial, 0.05)
+        );
+    }
+
+    #[test]
+    fn view_tracks_latest() {
+        let line = wiggly();
+        let mut simplifier = IncrementalSimplifier::new(Sleeve::new(0.05));
+        for p in line.iter() {
+            simplifier.push(*p);
+            let view = simplifier.view();
+            assert_eq!(view.last(), Some(p));
+            assert!(view.starts_with(simplifier.confirmed()));
+        }
+    }
+}
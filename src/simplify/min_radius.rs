@@ -0,0 +1,132 @@
+//! Repair simplifications which introduce turns too sharp to follow,
+//! e.g. for road centrelines which must remain drivable.
+use super::rdp::{proj_dist2, rdp_keep};
+use crate::Precision;
+use nalgebra::{distance, distance_squared, Point};
+
+/// Radius of the largest circular arc which is tangent to both edges of the turn at `p2`,
+/// and touches each edge within half of the shorter edge's length.
+///
+/// Infinite for a straight line; zero for a complete reversal or a zero-length edge.
+pub fn turn_radius<const D: usize>(
+    p1: &Point<Precision, D>,
+    p2: &Point<Precision, D>,
+    p3: &Point<Precision, D>,
+) -> Precision {
+    let v1 = p2 - p1;
+    let v2 = p3 - p2;
+    let l1 = v1.norm();
+    let l2 = v2.norm();
+    if l1 == 0.0 || l2 == 0.0 {
+        return 0.0;
+    }
+    let cos_turn = (v1.dot(&v2) / (l1 * l2)).clamp(-1.0, 1.0);
+    let half_turn = cos_turn.acos() / 2.0;
+    if half_turn == 0.0 {
+        return Precision::INFINITY;
+    }
+    (l1.min(l2) / 2.0) / half_turn.tan()
+}
+
+/// Find the dropped point between kept indices `start` and `end` furthest from the segment between them.
+fn most_deviant<const D: usize>(
+    line: &[Point<Precision, D>],
+    start: usize,
+    end: usize,
+) -> Option<usize> {
+    let length_sq = distance_squared(&line[start], &line[end]);
+    ((start + 1)..end).max_by(|a, b| {
+        let da = proj_dist2(&line[start], &line[end], &line[*a], length_sq);
+        let db = proj_dist2(&line[start], &line[end], &line[*b], length_sq);
+        da.total_cmp(&db)
+    })
+}
+
+/// Given the ascending indices of points kept by some simplification of `line`,
+/// re-insert dropped points until no kept point's [turn_radius] is below `min_radius`,
+/// or there are no dropped points left near the offending turns.
+///
+/// Turns which were already too sharp in the original line cannot be repaired.
+pub fn enforce_min_radius<const D: usize>(
+    line: &[Point<Precision, D>],
+    kept: &[usize],
+    min_radius: Precision,
+) -> Vec<usize> {
+    let mut kept = kept.to_vec();
+    let mut idx = 1;
+    while idx + 1 < kept.len() {
+        let (prev, this, next) = (kept[idx - 1], kept[idx], kept[idx + 1]);
+        if turn_radius(&line[prev], &line[this], &line[next]) >= min_radius {
+            idx += 1;
+            continue;
+        }
+        // re-insert a point on the longer side of the turn, if possible
+        let before = most_deviant(line, prev, this);
+        let after = most_deviant(line, this, next);
+        let insert_before = match (before, after) {
+            (None, None) => {
+                idx += 1;
+                continue;
+            }
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (Some(_), Some(_)) => {
+                distance(&line[prev], &line[this]) >= distance(&line[this], &line[next])
+            }
+        };
+        if insert_before {
+            kept.insert(idx, before.unwrap());
+            // the previous turn has changed too, so re-check it
+            idx = idx.saturating_sub(1).max(1);
+        } else {
+            kept.insert(idx + 1, after.unwrap());
+        }
+    }
+    kept
+}
+
+/// Decimate the linestring using RDP, then re-insert points so that no turn is sharper than `min_radius`;
+/// see [enforce_min_radius].
+pub fn rdp_reduce_min_radius<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    min_radius: Precision,
+) -> Vec<Point<Precision, D>> {
+    let kept = rdp_keep(line, epsilon, false);
+    enforce_min_radius(line, &kept, min_radius)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+
+    #[test]
+    fn radius() {
+        let a: Point<Precision, 2> = [0.0, 0.0].into();
+        let b: Point<Precision, 2> = [2.0, 0.0].into();
+        assert_eq!(turn_radius(&a, &b, &[4.0, 0.0].into()), Precision::INFINITY);
+        assert!((turn_radius(&a, &b, &[2.0, 2.0].into()) - 1.0).abs() < tol(1e-12));
+        assert!(turn_radius(&a, &b, &[0.0, 0.0].into()) < tol(1e-12));
+    }
+
+    #[test]
+    fn reinserts() {
+        // a rounded corner, which RDP turns into a sharp corner
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.7, 0.3],
+            [5.0, 1.0],
+            [5.0, 5.0],
+        ]);
+        let kept = rdp_keep(&line, 0.5, false);
+        assert_eq!(kept, vec![0, 2, 4]);
+        let fixed = enforce_min_radius(&line, &kept, 3.0);
+        assert_eq!(fixed, vec![0, 1, 2, 3, 4]);
+        assert_eq!(enforce_min_radius(&line, &kept, 0.01), kept);
+    }
+}
@@ -1,6 +1,336 @@
 //! Simplify linestrings.
 //!
 //! Linestrings are generally simplified by changing the number of points.
+//!
+//! Each algorithm lives in its own submodule, which has the full range of variants;
+//! the most common entry points are re-exported here.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::simplify::{rdp_reduce, vw_reduce};
+//!
+//! let line: Vec<simples::Point<Precision, 2>> = (0..10).map(|i| [i as Precision, (i % 2) as Precision * 0.01].into()).collect();
+//! assert_eq!(rdp_reduce(&line, 0.1, false).len(), 2);
+//! assert_eq!(vw_reduce(&line, 2, false).len(), 2);
+//! ```
+use crate::error::{Error, Result};
+use crate::{check_finite, CurveKind, Lerp, Precision};
+use nalgebra::{distance, Point};
+use std::collections::BinaryHeap;
+
+pub mod auto;
+pub mod bounded;
+pub mod dominant;
+pub mod incremental;
+pub mod min_radius;
+pub mod multilevel;
+pub mod pd;
+pub mod radial;
 pub mod rdp;
 pub mod sample;
+pub mod sleeve;
 pub mod vw;
+
+pub use dominant::{teh_chin_keep, teh_chin_reduce};
+pub use pd::{pd_keep, pd_reduce};
+pub use radial::{radial_keep, radial_reduce, radial_reduce_into};
+pub use rdp::{rdp_keep, rdp_reduce, rdp_reduce_into};
+pub use sample::{resample, resample_exact, sample_every, sample_every_into};
+pub use sleeve::{sleeve_keep, sleeve_reduce};
+pub use vw::{vw_keep, vw_reduce};
+
+/// What to do when asked for more points than a linestring has.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TooFewPoints {
+    /// Return the line unchanged.
+    #[default]
+    AsIs,
+    /// Add points along the existing edges (see [densify]).
+    Densify,
+    /// Return [Error::TooFewPoints].
+    Error,
+}
+
+impl TooFewPoints {
+    /// Apply the policy if the line has fewer than `n_points` points.
+    ///
+    /// None if the line has enough points, i.e. the caller should proceed as normal.
+    pub(crate) fn handle<const D: usize>(
+        &self,
+        line: &[Point<Precision, D>],
+        n_points: usize,
+    ) -> Option<Result<Vec<Point<Precision, D>>>> {
+        if line.len() >= n_points {
+            return None;
+        }
+        Some(match self {
+            TooFewPoints::AsIs => Ok(line.to_vec()),
+            TooFewPoints::Densify => Ok(densify(line, n_points)),
+            TooFewPoints::Error => Err(Error::TooFewPoints {
+                n_points: line.len(),
+                requested: n_points,
+            }),
+        })
+    }
+}
+
+/// Counters describing how much work a simplification did.
+///
+/// Useful for comparing parameters, and for spotting performance regressions.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// Number of points examined, e.g. distance calculations in RDP or triangles popped from the queue in VW.
+    pub points_visited: usize,
+    /// Number of VW triangles which were recalculated because a neighbouring point was removed.
+    pub triangles_recomputed: usize,
+    /// Number of VW triangles which were still valid when popped from the queue, so needed no recalculation.
+    pub cache_hits: usize,
+}
+
+/// An edge of a line which is being divided into `parts` parts, ordered by the length of those parts.
+struct Subdivided {
+    part_length: Precision,
+    edge: usize,
+    parts: usize,
+}
+
+impl PartialEq for Subdivided {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Subdivided {}
+
+impl PartialOrd for Subdivided {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Subdivided {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // prefer earlier edges on ties, for determinism
+        self.part_length
+            .total_cmp(&other.part_length)
+            .then_with(|| other.edge.cmp(&self.edge))
+    }
+}
+
+/// Add points along the edges of the line until it has `n_points` points, without moving any existing points.
+///
+/// New points are added to whichever edge has the longest sub-edges at the time,
+/// and spread evenly along it.
+/// Lines with fewer than 2 points, or at least `n_points` points already, are returned unchanged.
+pub fn densify<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+) -> Vec<Point<Precision, D>> {
+    if line.len() < 2 || line.len() >= n_points {
+        return line.to_vec();
+    }
+    let mut queue: BinaryHeap<_> = line
+        .windows(2)
+        .enumerate()
+        .map(|(edge, pair)| Subdivided {
+            part_length: distance(&pair[0], &pair[1]),
+            edge,
+            parts: 1,
+        })
+        .collect();
+    for _ in line.len()..n_points {
+        let mut sub = queue.pop().unwrap();
+        sub.part_length *= sub.parts as Precision / (sub.parts + 1) as Precision;
+        sub.parts += 1;
+        queue.push(sub);
+    }
+    let mut parts = vec![1; line.len() - 1];
+    for sub in queue.into_iter() {
+        parts[sub.edge] = sub.parts;
+    }
+
+    let mut out = Vec::with_capacity(n_points);
+    for (pair, n_parts) in line.windows(2).zip(parts) {
+        out.push(pair[0]);
+        for idx in 1..n_parts {
+            out.push(pair[0].lerp(&pair[1], idx as Precision / n_parts as Precision));
+        }
+    }
+    out.push(*line.last().unwrap());
+    out
+}
+
+/// Split any edges longer than `max_len` into equal parts no longer than `max_len`, without moving any existing points.
+///
+/// Useful after simplification (e.g. [rdp::rdp_reduce] or [vw::vw_reduce]) where consumers need bounded edge lengths,
+/// as resampling the output would move the kept points.
+/// Edge lengths may exceed `max_len` by rounding error.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first;
+/// points splitting that edge are added to the end of the line.
+///
+/// Fails if `max_len` is not positive and finite, if any coordinate is not finite,
+/// or if any edge is too long to measure.
+pub fn enforce_max_edge<const D: usize>(
+    line: &[Point<Precision, D>],
+    max_len: Precision,
+    kind: impl Into<CurveKind>,
+) -> Result<Vec<Point<Precision, D>>> {
+    if !max_len.is_finite() || max_len <= 0.0 {
+        return Err(Error::invalid("max_len", "must be positive and finite"));
+    }
+    check_finite(line)?;
+    let closing = match (line.first(), line.last()) {
+        (Some(first), Some(last)) if kind.into().is_closed() && line.len() > 1 => {
+            Some([*last, *first])
+        }
+        _ => None,
+    };
+    let mut out = Vec::with_capacity(line.len());
+    for pair in line.windows(2).chain(closing.as_ref().map(|c| &c[..])) {
+        out.push(pair[0]);
+        let length = distance(&pair[0], &pair[1]);
+        if !length.is_finite() {
+            return Err(Error::invalid("line", "edge lengths must be finite"));
+        }
+        let n_parts = (length / max_len).ceil() as usize;
+        for idx in 1..n_parts {
+            out.push(pair[0].lerp(&pair[1], idx as Precision / n_parts as Precision));
+        }
+    }
+    if closing.is_none() {
+        out.extend(line.last());
+    }
+    Ok(out)
+}
+
+/// Select the elements of a per-point array (e.g. radii, labels or timestamps)
+/// at the indices kept by a simplification (e.g. [rdp::rdp_keep] or [vw::vw_keep]),
+/// so that it stays in sync with the simplified line.
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::simplify::{apply_keep, rdp::rdp_keep};
+/// use simples::Point;
+///
+/// let line: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [1.0, 0.01].into(), [2.0, 0.0].into()];
+/// let radii = vec![1.0, 2.0, 3.0];
+/// assert_eq!(apply_keep(&rdp_keep(&line, 0.1, false), &radii), vec![1.0, 3.0]);
+/// ```
+///
+/// Panics if any index is out of bounds.
+pub fn apply_keep<T: Clone>(keep: &[usize], data: &[T]) -> Vec<T> {
+    keep.iter().map(|idx| data[*idx].clone()).collect()
+}
+
+/// As [apply_keep], for the output of the `*_keep_mask` functions.
+///
+/// Panics if the mask and data are different lengths.
+pub fn apply_keep_mask<T: Clone>(mask: &[bool], data: &[T]) -> Vec<T> {
+    assert_eq!(mask.len(), data.len(), "Mask and data of different length");
+    data.iter()
+        .zip(mask.iter())
+        .filter_map(|(d, keep)| if *keep { Some(d.clone()) } else { None })
+        .collect()
+}
+
+/// As [apply_keep_mask], but filtering the data in place.
+///
+/// Panics if the mask and data are different lengths.
+pub fn retain_keep_mask<T>(mask: &[bool], data: &mut Vec<T>) {
+    assert_eq!(mask.len(), data.len(), "Mask and data of different length");
+    let mut keep = mask.iter();
+    data.retain(|_| *keep.next().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn densify_keeps_points() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [4.0, 0.0]]);
+        assert_eq!(
+            densify(&line, 6),
+            make_line(vec![
+                [0.0, 0.0],
+                [0.5, 0.0],
+                [1.0, 0.0],
+                [2.0, 0.0],
+                [3.0, 0.0],
+                [4.0, 0.0]
+            ])
+        );
+        assert_eq!(densify(&line, 2), line);
+    }
+
+    #[test]
+    fn max_edge() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [4.0, 0.0], [4.0, 2.0]]);
+        let out = enforce_max_edge(&line, 1.0, false).unwrap();
+        assert_eq!(
+            out,
+            make_line(vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [2.0, 0.0],
+                [3.0, 0.0],
+                [4.0, 0.0],
+                [4.0, 1.0],
+                [4.0, 2.0]
+            ])
+        );
+
+        let closed = enforce_max_edge(&line, 1.0, true).unwrap();
+        assert_eq!(closed[..out.len()], out[..]);
+        // the closing edge is sqrt(20) = 4.47 long, so split in 5
+        assert_eq!(closed.len(), out.len() + 4);
+        let mut ring = closed.clone();
+        ring.push(ring[0]);
+        assert!(ring
+            .windows(2)
+            .all(|w| distance(&w[0], &w[1]) <= 1.0 + 1e-12));
+
+        assert_eq!(enforce_max_edge(&line, 10.0, true).unwrap(), line);
+        assert_eq!(enforce_max_edge(&line[..1], 1.0, true).unwrap(), line[..1]);
+        assert!(enforce_max_edge(&line, 0.0, false).is_err());
+        assert!(enforce_max_edge(&line, Precision::NAN, false).is_err());
+        assert!(enforce_max_edge(&line, -1.0, false).is_err());
+        let infinite = make_line(vec![[0.0, 0.0], [Precision::INFINITY, 0.0]]);
+        assert_eq!(
+            enforce_max_edge(&infinite, 1.0, false),
+            Err(Error::NonFiniteCoordinate { index: 1 })
+        );
+        let huge = make_line(vec![[-Precision::MAX, 0.0], [Precision::MAX, 0.0]]);
+        assert!(enforce_max_edge(&huge, 1.0, false).is_err());
+    }
+
+    #[test]
+    fn policies() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0]]);
+        assert_eq!(TooFewPoints::AsIs.handle(&line, 2), None);
+        assert_eq!(TooFewPoints::AsIs.handle(&line, 3), Some(Ok(line.clone())));
+        assert_eq!(
+            TooFewPoints::Densify.handle(&line, 3),
+            Some(Ok(make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]])))
+        );
+        assert!(matches!(
+            TooFewPoints::Error.handle(&line, 3),
+            Some(Err(Error::TooFewPoints { .. }))
+        ));
+    }
+
+    #[test]
+    fn parallel_arrays() {
+        let labels = vec!["a", "b", "c", "d"];
+        assert_eq!(apply_keep(&[0, 2, 3], &labels), vec!["a", "c", "d"]);
+        let mask = [true, false, true, true];
+        assert_eq!(apply_keep_mask(&mask, &labels), vec!["a", "c", "d"]);
+        let mut owned = labels.clone();
+        retain_keep_mask(&mask, &mut owned);
+        assert_eq!(owned, vec!["a", "c", "d"]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Simplify a linestring once, then extract it at many levels of detail.
+//!
+//! A [MultiLevelLine] orders the points of a line by importance,
+//! so that the simplified line at any tolerance or point count is a prefix of that order.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::simplify::multilevel::MultiLevelLine;
+//! use simples::simplify::rdp::rdp_reduce;
+//!
+//! let line: Vec<simples::Point<Precision, 2>> = (0..100)
+//!     .map(|i| [i as Precision, (i as Precision / 10.0).sin()].into())
+//!     .collect();
+//! let levels = MultiLevelLine::rdp(line.clone());
+//! for epsilon in [0.01, 0.1, 1.0] {
+//!     assert_eq!(levels.at_tolerance(epsilon), rdp_reduce(&line, epsilon, false));
+//! }
+//! assert_eq!(levels.at_count(5).len(), 5);
+//! ```
+use super::rdp::rdp_rank;
+use super::vw::VwSteps;
+use crate::{CurveKind, Precision};
+use nalgebra::Point;
+
+/// A linestring whose points are ordered by importance.
+///
+/// Importance is in the units of the ranking algorithm:
+/// distance for [MultiLevelLine::rdp], area for [MultiLevelLine::vw].
+/// Points which are never removed (e.g. the ends of an open line) have infinite importance.
+///
+/// Extracting `k` points takes `O(k log k)` time, regardless of the length of the line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiLevelLine<const D: usize> {
+    points: Vec<Point<Precision, D>>,
+    /// Indices into `points`, most important first.
+    order: Vec<usize>,
+    /// Importance of each point in `order`, never increasing.
+    importance: Vec<Precision>,
+}
+
+impl<const D: usize> MultiLevelLine<D> {
+    /// Rank points by [rdp_rank], so that [MultiLevelLine::at_tolerance] matches [crate::simplify::rdp::rdp_reduce].
+    ///
+    /// Ties are broken towards the start of the line.
+    pub fn rdp(points: Vec<Point<Precision, D>>) -> Self {
+        let ranks = rdp_rank(&points);
+        let mut order: Vec<_> = (0..points.len()).collect();
+        order.sort_by(|a, b| ranks[*b].total_cmp(&ranks[*a]));
+        let importance = order.iter().map(|idx| ranks[*idx]).collect();
+        Self {
+            points,
+            order,
+            importance,
+        }
+    }
+
+    /// Rank points by VW effective area (see [crate::simplify::vw::vw_rank]),
+    /// so that [MultiLevelLine::at_count] matches [crate::simplify::vw::vw_reduce].
+    ///
+    /// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+    pub fn vw(points: Vec<Point<Precision, D>>, kind: impl Into<CurveKind>) -> Self {
+        let mut removed = vec![false; points.len()];
+        let mut steps = Vec::with_capacity(points.len());
+        let mut max_area = Precision::NEG_INFINITY;
+        for step in VwSteps::new(&points, kind) {
+            removed[step.removed] = true;
+            max_area = max_area.max(step.area);
+            steps.push((step.removed, max_area));
+        }
+        let mut order: Vec<_> = (0..points.len()).filter(|idx| !removed[*idx]).collect();
+        let mut importance = vec![Precision::INFINITY; order.len()];
+        for (idx, area) in steps.into_iter().rev() {
+            order.push(idx);
+            importance.push(area);
+        }
+        Self {
+            points,
+            order,
+            importance,
+        }
+    }
+
+    /// The original points.
+    pub fn points(&self) -> &[Point<Precision, D>] {
+        &self.points
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Importance of each point in the original order.
+    pub fn importance(&self) -> Vec<Precision> {
+        let mut out = vec![Precision::NAN; self.len()];
+        for (idx, imp) in self.order.iter().zip(self.importance.iter()) {
+            out[*idx] = *imp;
+        }
+        out
+    }
+
+    /// Number of points whose importance is greater than `tolerance`.
+    pub fn count_at_tolerance(&self, tolerance: Precision) -> usize {
+        self.importance.partition_point(|imp| *imp > tolerance)
+    }
+
+    /// Indices of the `n_points` most important points, in their original order.
+    pub fn keep_at_count(&self, n_points: usize) -> Vec<usize> {
+        let mut out = self.order[..n_points.min(self.len())].to_vec();
+        out.sort_unstable();
+        out
+    }
+
+    /// Indices of the points whose importance is greater than `tolerance`, in their original order.
+    pub fn keep_at_tolerance(&self, tolerance: Precision) -> Vec<usize> {
+        self.keep_at_count(self.count_at_tolerance(tolerance))
+    }
+
+    /// The line simplified to its `n_points` most important points.
+    pub fn at_count(&self, n_points: usize) -> Vec<Point<Precision, D>> {
+        self.keep_at_count(n_points)
+            .into_iter()
+            .map(|idx| self.points[idx])
+            .collect()
+    }
+
+    /// The line simplified to the points whose importance is greater than `tolerance`.
+    pub fn at_tolerance(&self, tolerance: Precision) -> Vec<Point<Precision, D>> {
+        self.at_count(self.count_at_tolerance(tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::{rdp::rdp_keep, vw::vw_keep};
+    use crate::test_utils::make_line;
+
+    fn wiggly() -> Vec<Point<Precision, 2>> {
+        make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.3],
+            [2.0, -0.2],
+            [3.0, 2.0],
+            [4.0, 2.1],
+            [5.0, 1.0],
+            [6.0, 4.0],
+            [7.0, 3.5],
+            [8.0, 0.0],
+        ])
+    }
+
+    #[test]
+    fn rdp_levels() {
+        let line = wiggly();
+        let levels = MultiLevelLine::rdp(line.clone());
+        for epsilon in [0.0, 0.1, 0.3, 1.0, 2.0, 10.0] {
+            assert_eq!(
+                levels.keep_at_tolerance(epsilon),
+                rdp_keep(&line, epsilon, false)
+            );
+        }
+        assert_eq!(levels.keep_at_count(2), vec![0, 8]);
+        assert_eq!(levels.importance(), rdp_rank(&line));
+    }
+
+    #[test]
+    fn vw_levels() {
+        for closed in [false, true] {
+            let line = wiggly();
+            let levels = MultiLevelLine::vw(line.clone(), closed);
+            for n in 0..=line.len() {
+                assert_eq!(levels.keep_at_count(n).len(), n);
+                if n >= 3 {
+                    assert_eq!(levels.keep_at_count(n), vw_keep(&line, n, closed));
+                }
+            }
+            let imp = levels.importance();
+            assert_eq!(
+                imp.iter().filter(|i| i.is_infinite()).count(),
+                2 + closed as usize
+            );
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let levels = MultiLevelLine::<2>::rdp(vec![]);
+        assert!(levels.is_empty());
+        assert!(levels.at_count(3).is_empty());
+        assert!(levels.at_tolerance(1.0).is_empty());
+    }
+}
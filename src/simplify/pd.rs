@@ -0,0 +1,94 @@
+//! Simplify a linestring by removing points which are close to the segment joining their neighbours.
+//!
+//! This is cheap, and so useful as a pre-filter before more expensive algorithms on very large lines.
+use super::rdp::proj_dist2;
+use crate::Precision;
+use nalgebra::{distance_squared, Point};
+
+/// One pass over the kept points, returning whether any were dropped.
+///
+/// If a point is dropped, the next point is always kept,
+/// so that the line cannot drift by more than `tolerance` from the original in a single pass.
+fn pd_pass<const D: usize>(
+    line: &[Point<Precision, D>],
+    kept: &mut Vec<usize>,
+    tolerance_sq: Precision,
+) -> bool {
+    if kept.len() <= 2 {
+        return false;
+    }
+    let mut out = Vec::with_capacity(kept.len());
+    out.push(kept[0]);
+    let mut idx = 1;
+    while idx < kept.len() - 1 {
+        let start = &line[*out.last().unwrap()];
+        let end = &line[kept[idx + 1]];
+        let d2 = proj_dist2(start, end, &line[kept[idx]], distance_squared(start, end));
+        if d2 < tolerance_sq {
+            // drop this point, keep the next
+            out.push(kept[idx + 1]);
+            idx += 2;
+        } else {
+            out.push(kept[idx]);
+            idx += 1;
+        }
+    }
+    if *out.last().unwrap() != *kept.last().unwrap() {
+        out.push(*kept.last().unwrap());
+    }
+    let changed = out.len() != kept.len();
+    *kept = out;
+    changed
+}
+
+/// Return the indices of the points in the line which would be kept by the perpendicular distance filter.
+///
+/// A point is dropped if it is less than `tolerance` from the segment joining its neighbours.
+/// The filter is applied up to `repeat` times (at least once), stopping early if no points are dropped.
+pub fn pd_keep<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+    repeat: usize,
+) -> Vec<usize> {
+    let tolerance_sq = tolerance * tolerance;
+    let mut kept: Vec<_> = (0..line.len()).collect();
+    for _ in 0..repeat.max(1) {
+        if !pd_pass(line, &mut kept, tolerance_sq) {
+            break;
+        }
+    }
+    kept
+}
+
+/// Decimate the linestring using the perpendicular distance filter; see [pd_keep].
+pub fn pd_reduce<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+    repeat: usize,
+) -> Vec<Point<Precision, D>> {
+    pd_keep(line, tolerance, repeat)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn reduce() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.05],
+            [2.0, 0.0],
+            [3.0, 0.05],
+            [4.0, 0.0],
+            [5.0, 1.0],
+            [6.0, 0.0],
+        ]);
+        assert_eq!(pd_keep(&line, 0.1, 1), vec![0, 2, 4, 5, 6]);
+        assert_eq!(pd_keep(&line, 0.1, 5), vec![0, 4, 5, 6]);
+    }
+}
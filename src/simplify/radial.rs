@@ -0,0 +1,119 @@
+//! Simplify a linestring by dropping points which are too close to the last kept point.
+//!
+//! This is very cheap, and so useful as a pre-filter before more expensive algorithms on dense lines.
+use crate::Precision;
+#[cfg(not(feature = "simd"))]
+use nalgebra::distance_squared;
+use nalgebra::Point;
+
+/// Call `keep` with the index of every point kept by the radial distance filter, in order.
+fn radial_each<const D: usize, F: FnMut(usize)>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+    mut keep: F,
+) {
+    if line.len() <= 2 {
+        (0..line.len()).for_each(keep);
+        return;
+    }
+    let tolerance_sq = tolerance * tolerance;
+    let last_idx = line.len() - 1;
+    keep(0);
+    let mut anchor = 0;
+    #[cfg(feature = "simd")]
+    while let Some(found) =
+        crate::simd::first_at_least(&line[anchor], &line[(anchor + 1)..last_idx], tolerance_sq)
+    {
+        anchor += found + 1;
+        keep(anchor);
+    }
+    #[cfg(not(feature = "simd"))]
+    for (idx, p) in line.iter().enumerate().take(last_idx).skip(1) {
+        if distance_squared(&line[anchor], p) >= tolerance_sq {
+            keep(idx);
+            anchor = idx;
+        }
+    }
+    keep(last_idx);
+}
+
+/// Return the indices of the points in the line which would be kept by the radial distance filter.
+///
+/// A point is dropped if it is less than `tolerance` from the last kept point.
+/// The first and last points are always kept.
+pub fn radial_keep<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+) -> Vec<usize> {
+    let mut out = Vec::default();
+    radial_each(line, tolerance, |idx| out.push(idx));
+    out
+}
+
+/// Decimate the linestring using the radial distance filter; see [radial_keep].
+///
+/// Allocates only the returned Vec; see [radial_reduce_into] to reuse a buffer instead.
+pub fn radial_reduce<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+) -> Vec<Point<Precision, D>> {
+    let mut out = Vec::default();
+    radial_reduce_into(line, tolerance, &mut out);
+    out
+}
+
+/// As [radial_reduce], but clearing `out` and writing the simplified line into it.
+///
+/// Nothing is allocated other than to grow `out`.
+pub fn radial_reduce_into<const D: usize>(
+    line: &[Point<Precision, D>],
+    tolerance: Precision,
+    out: &mut Vec<Point<Precision, D>>,
+) {
+    out.clear();
+    radial_each(line, tolerance, |idx| out.push(line[idx]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn reduce() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.5, 0.0],
+            [1.0, 0.0],
+            [1.2, 0.5],
+            [2.0, 0.0],
+            [2.1, 0.0],
+        ]);
+        assert_eq!(
+            radial_reduce(&line, 1.0),
+            make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.1, 0.0]])
+        );
+    }
+
+    #[test]
+    fn reduce_into_reuses() {
+        let line = make_line((0..20).map(|i| [i as Precision * 0.3, 0.0]).collect());
+        let mut out = make_line(vec![[9.0, 9.0]; 30]);
+        radial_reduce_into(&line, 1.0, &mut out);
+        let expected: Vec<_> = radial_keep(&line, 1.0)
+            .into_iter()
+            .map(|i| line[i])
+            .collect();
+        assert_eq!(out, expected);
+        radial_reduce_into(&line[..2], 1.0, &mut out);
+        assert_eq!(out, line[..2]);
+    }
+
+    #[test]
+    fn non_finite_endpoint() {
+        let line = make_line(vec![[0.0, 0.0], [0.1, 0.0], [Precision::NAN, 0.0]]);
+        let mut out = vec![];
+        radial_reduce_into(&line, 1.0, &mut out);
+        assert_eq!(out.len(), radial_keep(&line, 1.0).len());
+    }
+}
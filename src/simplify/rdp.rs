@@ -1,13 +1,26 @@
 //! Simplify a linestring using the [Ramer-Douglas-Peucker](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm) algorithm.
-use crate::Precision;
+use super::{Stats, TooFewPoints};
+use crate::curve::explicitly_closed;
+use crate::error::{Error, Result};
+use crate::metric::DistanceMetric;
+use crate::util::normalise_anchors;
+use crate::{CurveKind, Precision};
 use nalgebra::{distance_squared, Point};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 
-fn proj_dist2<const D: usize>(
+/// Squared distance from `p` to the segment from `start` to `end`, whose squared length is `length_sq`.
+///
+/// If the segment has zero length (e.g. the ends of a loop), this is the squared distance to `start`.
+pub(crate) fn proj_dist2<const D: usize>(
     start: &Point<Precision, D>,
     end: &Point<Precision, D>,
     p: &Point<Precision, D>,
     length_sq: Precision,
 ) -> Precision {
+    if length_sq == 0.0 {
+        return distance_squared(p, start);
+    }
     let lensq_along = (p - start).dot(&(end - start));
     let proj;
     if lensq_along <= 0.0 {
@@ -20,13 +33,11 @@ fn proj_dist2<const D: usize>(
     distance_squared(p, proj)
 }
 
-fn rdp_keep_inner<const D: usize>(
-    line: &[Point<Precision, D>],
-    epsilon_sq: Precision,
-    offset: usize,
-) -> Vec<usize> {
+/// Find the index of the interior point furthest from the line between the first and last points,
+/// and its squared distance.
+fn furthest<const D: usize>(line: &[Point<Precision, D>]) -> Option<(usize, Precision)> {
     if line.len() <= 2 {
-        return vec![];
+        return None;
     }
 
     let first = line.first().unwrap();
@@ -34,52 +45,634 @@ fn rdp_keep_inner<const D: usize>(
     let length_sq = distance_squared(first, last);
 
     let mut greatest_dist2 = (0, Precision::NEG_INFINITY);
-    for (idx, point) in line.iter().enumerate().skip(1).take(line.len() - 2) {
-        let d2 = proj_dist2(first, last, point, length_sq);
+    let mut consider = |idx, d2| {
         if d2 > greatest_dist2.1 {
-            greatest_dist2 = (idx + offset, d2)
+            greatest_dist2 = (idx, d2)
         }
+    };
+    #[cfg(feature = "simd")]
+    crate::simd::each_proj_dist2(
+        first,
+        last,
+        &line[1..(line.len() - 1)],
+        length_sq,
+        |idx, d2| consider(idx + 1, d2),
+    );
+    #[cfg(not(feature = "simd"))]
+    for (idx, point) in line.iter().enumerate().skip(1).take(line.len() - 2) {
+        consider(idx, proj_dist2(first, last, point, length_sq));
     }
+    Some(greatest_dist2)
+}
 
-    let mut to_keep = vec![];
-    if greatest_dist2.1 > epsilon_sq {
-        if greatest_dist2.0 > 1 {
-            to_keep.append(&mut rdp_keep_inner(
-                &line[0..=greatest_dist2.0],
-                epsilon_sq,
-                offset,
-            ));
-        }
-        to_keep.push(greatest_dist2.0);
-        if greatest_dist2.0 < line.len() - 2 {
-            to_keep.append(&mut rdp_keep_inner(
-                &line[greatest_dist2.0..line.len()],
-                epsilon_sq,
-                offset + greatest_dist2.0,
-            ));
-        }
+/// Find the index of the point furthest from the line between the first and last points,
+/// if it is further than the squared tolerance.
+fn split_index<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon_sq: Precision,
+) -> Option<usize> {
+    furthest(line).and_then(|(idx, d2)| if d2 > epsilon_sq { Some(idx) } else { None })
+}
+
+/// Call `keep` with the index of every point to keep strictly between `start` and `end`, in order,
+/// where `split` gives the point (if any) at which to split the sub-line between two indices.
+///
+/// Every variant of RDP is this recursion with a different `split` and `keep`.
+fn rdp_recurse<S, K>(start: usize, end: usize, split: &mut S, keep: &mut K)
+where
+    S: FnMut(usize, usize) -> Option<usize>,
+    K: FnMut(usize),
+{
+    if end - start < 2 {
+        return;
     }
+    let Some(idx) = split(start, end) else {
+        return;
+    };
+    rdp_recurse(start, idx, split, keep);
+    keep(idx);
+    rdp_recurse(idx, end, split, keep);
+}
 
+/// Indices of the points to keep, given the `split` for [rdp_recurse].
+fn rdp_keep_by<const D: usize, F: FnMut(usize, usize) -> Option<usize>>(
+    line: &[Point<Precision, D>],
+    mut split: F,
+) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let mut out = Vec::with_capacity(line.len());
+    out.push(0);
+    rdp_recurse(0, line.len() - 1, &mut split, &mut |idx| out.push(idx));
+    out.push(line.len() - 1);
+    out
+}
+
+/// The `split` for [rdp_recurse] with a fixed squared tolerance.
+fn split_within<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon_sq: Precision,
+) -> impl Fn(usize, usize) -> Option<usize> + '_ {
+    move |start, end| split_index(&line[start..=end], epsilon_sq).map(|idx| idx + start)
+}
+
+/// Sub-lines shorter than this are not worth splitting across threads.
+#[cfg(feature = "rayon")]
+const PAR_MIN_LEN: usize = 4096;
+
+/// As [rdp_recurse] with [split_within], but returning the indices, and splitting long sub-lines across threads.
+#[cfg(feature = "rayon")]
+fn rdp_keep_inner_par<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon_sq: Precision,
+    start: usize,
+    end: usize,
+) -> Vec<usize> {
+    let mut split = split_within(line, epsilon_sq);
+    let mut to_keep = Vec::default();
+    if end - start < PAR_MIN_LEN {
+        rdp_recurse(start, end, &mut split, &mut |idx| to_keep.push(idx));
+        return to_keep;
+    }
+    let Some(idx) = split(start, end) else {
+        return to_keep;
+    };
+    let (mut left, mut right) = rayon::join(
+        || rdp_keep_inner_par(line, epsilon_sq, start, idx),
+        || rdp_keep_inner_par(line, epsilon_sq, idx, end),
+    );
+    to_keep.append(&mut left);
+    to_keep.push(idx);
+    to_keep.append(&mut right);
     to_keep
 }
 
 /// Return the indices of the points in the line which would be kept if simplified using RDP.
-pub fn rdp_keep<const D: usize>(line: &[Point<Precision, D>], epsilon: Precision) -> Vec<usize> {
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// A closed line is split into two at the first point and the point furthest from it,
+/// both of which are kept, and each half is simplified separately.
+pub fn rdp_keep<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    kind: impl Into<CurveKind>,
+) -> Vec<usize> {
+    if !kind.into().is_closed() || line.len() <= 3 {
+        return rdp_keep_with_stats(line, epsilon).0;
+    }
+    let (far, _) = line
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(idx, p)| (idx, distance_squared(p, &line[0])))
+        .fold((0, Precision::NEG_INFINITY), |best, next| {
+            if next.1 > best.1 {
+                next
+            } else {
+                best
+            }
+        });
+    let mut keep = rdp_keep_with_stats(&line[..=far], epsilon).0;
+    let rest = explicitly_closed(line);
+    keep.extend(
+        rdp_keep_with_stats(&rest[far..], epsilon)
+            .0
+            .into_iter()
+            .skip(1)
+            .map(|idx| idx + far)
+            .filter(|idx| *idx < line.len()),
+    );
+    keep
+}
+
+/// As [rdp_keep], but also returning [Stats] about the work done.
+///
+/// RDP has no cache, so only [Stats::points_visited] is counted.
+pub fn rdp_keep_with_stats<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+) -> (Vec<usize>, Stats) {
+    let mut stats = Stats::default();
+    let split = split_within(line, epsilon * epsilon);
+    let out = rdp_keep_by(line, |start, end| {
+        stats.points_visited += end - start - 1;
+        split(start, end)
+    });
+    (out, stats)
+}
+
+/// As [rdp_keep], but returning whether each point is kept, rather than the indices of kept points.
+///
+/// Useful for filtering other arrays of per-point data alongside the line,
+/// and cheaper than [rdp_keep] for very long lines.
+pub fn rdp_keep_mask<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+) -> Vec<bool> {
+    let mut mask = vec![false; line.len()];
+    if let Some(last) = line.len().checked_sub(1) {
+        mask[0] = true;
+        mask[last] = true;
+        let mut split = split_within(line, epsilon * epsilon);
+        rdp_recurse(0, last, &mut split, &mut |idx| mask[idx] = true);
+    }
+    mask
+}
+
+/// A single decision made by RDP.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RdpStep {
+    /// The sub-line from `start` to `end` was split at `split`, which is `distance` from the chord between them.
+    Split {
+        start: usize,
+        end: usize,
+        split: usize,
+        distance: Precision,
+    },
+    /// The sub-line from `start` to `end` was simplified to a single edge,
+    /// because its furthest interior point (if any) was only `distance` from the chord.
+    Accept {
+        start: usize,
+        end: usize,
+        distance: Option<Precision>,
+    },
+}
+
+/// Iterator over the decisions made by RDP, depth-first (left sub-line before right).
+///
+/// Useful for visualising or debugging the algorithm.
+/// The kept points are the ends of the line plus every [RdpStep::Split]'s `split`.
+pub struct RdpSteps<'a, const D: usize> {
+    line: &'a [Point<Precision, D>],
+    epsilon_sq: Precision,
+    // sub-lines yet to be examined; last is next
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'a, const D: usize> RdpSteps<'a, D> {
+    pub fn new(line: &'a [Point<Precision, D>], epsilon: Precision) -> Self {
+        let stack = if line.len() >= 2 {
+            vec![(0, line.len() - 1)]
+        } else {
+            vec![]
+        };
+        Self {
+            line,
+            epsilon_sq: epsilon * epsilon,
+            stack,
+        }
+    }
+}
+
+impl<'a, const D: usize> Iterator for RdpSteps<'a, D> {
+    type Item = RdpStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, end) = self.stack.pop()?;
+        match furthest(&self.line[start..=end]) {
+            Some((idx, d2)) if d2 > self.epsilon_sq => {
+                let split = idx + start;
+                self.stack.push((split, end));
+                self.stack.push((start, split));
+                Some(RdpStep::Split {
+                    start,
+                    end,
+                    split,
+                    distance: d2.sqrt(),
+                })
+            }
+            o => Some(RdpStep::Accept {
+                start,
+                end,
+                distance: o.map(|(_, d2)| d2.sqrt()),
+            }),
+        }
+    }
+}
+
+/// A sub-line which could be split, ordered by how far its split point deviates from it.
+struct Candidate {
+    dist2: Precision,
+    start: usize,
+    split: usize,
+    end: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // break ties towards the start of the line, for determinism
+        self.dist2
+            .total_cmp(&other.dist2)
+            .then_with(|| other.split.cmp(&self.split))
+    }
+}
+
+/// As [rdp_keep], but keeping no more than `max_points` points (although the first and last are always kept).
+///
+/// Rather than recursing depth-first, this always splits whichever sub-line has the most deviant point,
+/// so if the point budget runs out before `epsilon` is satisfied,
+/// the points kept are the most important ones by the RDP ranking.
+pub fn rdp_keep_constrained<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    max_points: usize,
+) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
     let epsilon_sq = epsilon * epsilon;
+    let last_idx = line.len() - 1;
+    let mut out = vec![0, last_idx];
+    let mut queue = BinaryHeap::default();
+
+    let push_candidate = |queue: &mut BinaryHeap<Candidate>, start, end| {
+        if let Some((split, dist2)) = furthest(&line[start..=end]) {
+            if dist2 > epsilon_sq {
+                queue.push(Candidate {
+                    dist2,
+                    start,
+                    split: split + start,
+                    end,
+                })
+            }
+        }
+    };
+    push_candidate(&mut queue, 0, last_idx);
+
+    while out.len() < max_points {
+        let Some(c) = queue.pop() else {
+            break;
+        };
+        out.push(c.split);
+        push_candidate(&mut queue, c.start, c.split);
+        push_candidate(&mut queue, c.split, c.end);
+    }
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// The largest `epsilon` at which each point would still be kept by [rdp_keep].
+///
+/// This is the point's distance from the chord it splits,
+/// or that of the split which created its sub-line if that was smaller.
+/// The first and last points have infinite rank.
+/// Points which are never split, because their sub-line's distances are all NaN, have rank 0.
+/// Keeping the points whose rank is greater than `epsilon` gives the same result as [rdp_keep],
+/// so the ranking can be computed once and the line sliced at many tolerances.
+pub fn rdp_rank<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Precision> {
+    let mut ranks = vec![0.0; line.len()];
+    if let Some(last) = ranks.len().checked_sub(1) {
+        ranks[0] = Precision::INFINITY;
+        ranks[last] = Precision::INFINITY;
+    }
+    if line.len() <= 2 {
+        return ranks;
+    }
+    // sub-lines yet to be split, with the rank of the split which created them
+    let mut stack = vec![(0, line.len() - 1, Precision::INFINITY)];
+    while let Some((start, end, parent)) = stack.pop() {
+        let Some((idx, d2)) = furthest(&line[start..=end]) else {
+            continue;
+        };
+        // no distance was comparable (i.e. all NaN), so splitting would not shrink the sub-line
+        if idx == 0 {
+            continue;
+        }
+        let split = idx + start;
+        let rank = d2.sqrt().min(parent);
+        ranks[split] = rank;
+        stack.push((start, split, rank));
+        stack.push((split, end, rank));
+    }
+    ranks
+}
+
+/// An `epsilon` for which [rdp_keep] keeps about `target_fraction` of the line's points
+/// (but never fewer than the 2 ends).
+///
+/// Found exactly from [rdp_rank], in `O(n log n)` time, so there is no need to search by trial and error.
+/// Where several points share a rank, fewer points than targeted may be kept.
+///
+/// Fails if `target_fraction` is not between 0.0 and 1.0.
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::simplify::rdp::{rdp_keep, suggest_rdp_epsilon};
+///
+/// let line: Vec<simples::Point<Precision, 2>> = (0..100)
+///     .map(|i| [i as Precision, (i as Precision / 5.0).sin()].into())
+///     .collect();
+/// let epsilon = suggest_rdp_epsilon(&line, 0.2).unwrap();
+/// assert_eq!(rdp_keep(&line, epsilon, false).len(), 20);
+/// ```
+pub fn suggest_rdp_epsilon<const D: usize>(
+    line: &[Point<Precision, D>],
+    target_fraction: Precision,
+) -> Result<Precision> {
+    if !(0.0..=1.0).contains(&target_fraction) {
+        return Err(Error::invalid(
+            "target_fraction",
+            "must be between 0.0 and 1.0",
+        ));
+    }
+    let mut ranks = rdp_rank(line);
+    let n_points = ((target_fraction * line.len() as Precision).round() as usize).max(2);
+    if n_points >= line.len() {
+        return Ok(0.0);
+    }
+    ranks.sort_unstable_by(|a, b| b.total_cmp(a));
+    // the first rank not to be kept, and the smallest rank greater than it
+    let dropped = ranks[n_points];
+    let kept = ranks
+        .iter()
+        .rev()
+        .copied()
+        .find(|r| *r > dropped)
+        .unwrap_or(Precision::INFINITY);
+    // halfway between, so that rounding error doesn't tip any point either way
+    if kept.is_finite() {
+        Ok((dropped + kept) / 2.0)
+    } else {
+        Ok(dropped * 2.0)
+    }
+}
+
+/// As [rdp_keep], but recursing into each half of long lines in parallel.
+#[cfg(feature = "rayon")]
+pub fn rdp_keep_par<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
     let mut out = Vec::with_capacity(line.len());
     out.push(0);
-    out.append(&mut rdp_keep_inner(line, epsilon_sq, 0));
+    out.append(&mut rdp_keep_inner_par(
+        line,
+        epsilon * epsilon,
+        0,
+        line.len() - 1,
+    ));
     out.push(line.len() - 1);
     out
 }
 
-/// Decimate the linestring using RDP.
+/// Decimate the linestring using RDP; see [rdp_keep].
+///
+/// Open lines allocate only the returned Vec; see [rdp_reduce_into] to reuse a buffer instead.
 pub fn rdp_reduce<const D: usize>(
     line: &[Point<Precision, D>],
     epsilon: Precision,
+    kind: impl Into<CurveKind>,
 ) -> Vec<Point<Precision, D>> {
-    let kept = rdp_keep(line, epsilon);
-    kept.into_iter().map(|idx| line[idx]).collect()
+    match kind.into() {
+        CurveKind::Open => {
+            let mut out = Vec::default();
+            rdp_reduce_into(line, epsilon, &mut out);
+            out
+        }
+        CurveKind::Closed => rdp_keep(line, epsilon, CurveKind::Closed)
+            .into_iter()
+            .map(|idx| line[idx])
+            .collect(),
+    }
+}
+
+/// As [rdp_reduce], but clearing `out` and writing the simplified line into it.
+///
+/// Nothing is allocated other than to grow `out`,
+/// so reusing the same buffer for many lines (e.g. once per frame) avoids allocator churn.
+pub fn rdp_reduce_into<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    out: &mut Vec<Point<Precision, D>>,
+) {
+    out.clear();
+    if line.len() <= 2 {
+        out.extend_from_slice(line);
+        return;
+    }
+    out.push(line[0]);
+    let mut split = split_within(line, epsilon * epsilon);
+    rdp_recurse(0, line.len() - 1, &mut split, &mut |idx| {
+        out.push(line[idx])
+    });
+    out.push(line[line.len() - 1]);
+}
+
+/// As [rdp_keep], but measuring distances with the given [DistanceMetric],
+/// so that `epsilon` is in the metric's units (e.g. metres for [crate::metric::Haversine]).
+pub fn rdp_keep_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    metric: &M,
+) -> Vec<usize> {
+    rdp_keep_by(line, |start, end| {
+        let (first, last) = (&line[start], &line[end]);
+        let mut furthest = (0, Precision::NEG_INFINITY);
+        for (idx, point) in line.iter().enumerate().take(end).skip(start + 1) {
+            let d = metric.segment_distance(first, last, point);
+            if d > furthest.1 {
+                furthest = (idx, d);
+            }
+        }
+        (furthest.1 > epsilon).then_some(furthest.0)
+    })
+}
+
+/// As [rdp_reduce], but measuring distances with the given [DistanceMetric].
+pub fn rdp_reduce_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    metric: &M,
+) -> Vec<Point<Precision, D>> {
+    rdp_keep_metric(line, epsilon, metric)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+/// As [rdp_keep], but with a tolerance which varies along the line.
+///
+/// Each sub-line's tolerance is given by `epsilon_fn` called with its first and last points,
+/// e.g. to allow more deviation where a contour line is in steep terrain.
+pub fn rdp_keep_fn<const D: usize, F>(line: &[Point<Precision, D>], epsilon_fn: F) -> Vec<usize>
+where
+    F: Fn(&Point<Precision, D>, &Point<Precision, D>) -> Precision,
+{
+    rdp_keep_by(line, |start, end| {
+        let epsilon = epsilon_fn(&line[start], &line[end]);
+        split_within(line, epsilon * epsilon)(start, end)
+    })
+}
+
+/// As [rdp_reduce], but with a tolerance for each sub-line; see [rdp_keep_fn].
+pub fn rdp_reduce_fn<const D: usize, F>(
+    line: &[Point<Precision, D>],
+    epsilon_fn: F,
+) -> Vec<Point<Precision, D>>
+where
+    F: Fn(&Point<Precision, D>, &Point<Precision, D>) -> Precision,
+{
+    rdp_keep_fn(line, epsilon_fn)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+/// As [rdp_keep], but with a tolerance for each point.
+///
+/// A sub-line is simplified to a single edge if every interior point is within its own tolerance of the chord;
+/// otherwise it is split at the point which exceeds its tolerance by the most.
+/// The tolerances of the first and last points are ignored.
+///
+/// Fails if `epsilons` is not the same length as `line`.
+pub fn rdp_keep_per_point<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilons: &[Precision],
+) -> Result<Vec<usize>> {
+    if epsilons.len() != line.len() {
+        return Err(Error::invalid(
+            "epsilons",
+            "must be the same length as line",
+        ));
+    }
+    Ok(rdp_keep_by(line, |start, end| {
+        let length_sq = distance_squared(&line[start], &line[end]);
+        let mut worst = (0, 0.0);
+        for idx in (start + 1)..end {
+            let d = proj_dist2(&line[start], &line[end], &line[idx], length_sq).sqrt();
+            let excess = d - epsilons[idx];
+            if excess > worst.1 {
+                worst = (idx, excess);
+            }
+        }
+        (worst.1 > 0.0).then_some(worst.0)
+    }))
+}
+
+/// As [rdp_reduce], but with a tolerance for each point; see [rdp_keep_per_point].
+pub fn rdp_reduce_per_point<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilons: &[Precision],
+) -> Result<Vec<Point<Precision, D>>> {
+    Ok(rdp_keep_per_point(line, epsilons)?
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect())
+}
+
+/// As [rdp_keep], but always keeping the points at the `preserved` indices (as well as the ends),
+/// by simplifying each section between them separately.
+///
+/// The preserved points could be, for example, corners found by [detect_corners](crate::corners::detect_corners).
+/// Indices may be given in any order, and duplicates are ignored.
+///
+/// Panics if any index is out of bounds.
+pub fn rdp_keep_preserving<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    preserved: &[usize],
+) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let anchors = normalise_anchors(line.len(), preserved);
+
+    let mut out = vec![0];
+    for pair in anchors.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        out.extend(
+            rdp_keep(&line[start..=end], epsilon, false)
+                .into_iter()
+                .skip(1)
+                .map(|idx| idx + start),
+        );
+    }
+    out
+}
+
+/// As [rdp_reduce], but always keeping the points at the `preserved` indices; see [rdp_keep_preserving].
+pub fn rdp_reduce_preserving<const D: usize>(
+    line: &[Point<Precision, D>],
+    epsilon: Precision,
+    preserved: &[usize],
+) -> Vec<Point<Precision, D>> {
+    rdp_keep_preserving(line, epsilon, preserved)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+/// Decimate the linestring to `n_points` points using RDP ranking (see [rdp_keep_constrained]),
+/// with an explicit policy for lines which already have fewer than `n_points` points.
+pub fn rdp_reduce_to<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    too_few: TooFewPoints,
+) -> Result<Vec<Point<Precision, D>>> {
+    if let Some(out) = too_few.handle(line, n_points) {
+        return out;
+    }
+    let kept = rdp_keep_constrained(line, 0.0, n_points);
+    Ok(kept.into_iter().map(|idx| line[idx]).collect())
 }
 
 #[cfg(test)]
@@ -88,11 +681,11 @@ mod tests {
 
     use crate::test_utils::make_line;
 
-    fn assert_reduce(orig: Vec<[f64; 2]>, expected: Vec<[f64; 2]>, epsilon: f64) {
+    fn assert_reduce(orig: Vec<[Precision; 2]>, expected: Vec<[Precision; 2]>, epsilon: Precision) {
         let orig_line = make_line(orig);
         let exp_line = make_line(expected);
 
-        let out = rdp_reduce(orig_line.as_slice(), epsilon);
+        let out = rdp_reduce(orig_line.as_slice(), epsilon, false);
         assert_eq!(out, exp_line);
     }
 
@@ -113,4 +706,374 @@ mod tests {
             0.2,
         )
     }
+
+    #[test]
+    fn reduce_nested() {
+        assert_reduce(
+            vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [2.0, 5.0],
+                [3.0, 0.0],
+                [4.0, 1.0],
+                [5.0, 0.0],
+                [6.0, 1.0],
+                [7.0, 0.0],
+            ],
+            vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [2.0, 5.0],
+                [3.0, 0.0],
+                [4.0, 1.0],
+                [5.0, 0.0],
+                [6.0, 1.0],
+                [7.0, 0.0],
+            ],
+            0.1,
+        )
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn keep_par_matches() {
+        let line: Vec<_> = (0..20_000)
+            .map(|i| {
+                let x = i as Precision / 100.0;
+                [x, x.sin() + (x * 7.0).cos() * 0.1]
+            })
+            .collect();
+        let line = make_line(line);
+        assert_eq!(rdp_keep(&line, 0.05, false), rdp_keep_par(&line, 0.05));
+    }
+
+    #[test]
+    fn keep_constrained() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 5.0],
+            [3.0, 0.0],
+            [4.0, 1.0],
+            [5.0, 0.0],
+            [6.0, 1.0],
+            [7.0, 0.0],
+        ]);
+        assert_eq!(
+            rdp_keep_constrained(&line, 0.1, 100),
+            rdp_keep(&line, 0.1, false)
+        );
+        assert_eq!(rdp_keep_constrained(&line, 0.1, 3), vec![0, 2, 7]);
+        assert_eq!(rdp_keep_constrained(&line, 0.1, 0), vec![0, 7]);
+    }
+
+    #[test]
+    fn constrained_short_lines() {
+        let empty: Vec<Point<Precision, 2>> = vec![];
+        assert!(rdp_keep_constrained(&empty, 0.1, 3).is_empty());
+        assert_eq!(rdp_reduce_to(&empty, 0, TooFewPoints::Error), Ok(vec![]));
+        let single = make_line(vec![[1.0, 1.0]]);
+        assert_eq!(rdp_keep_constrained(&single, 0.1, 3), vec![0]);
+        assert_eq!(rdp_reduce_to(&single, 1, TooFewPoints::Error), Ok(single));
+    }
+
+    #[test]
+    fn steps_match_keep() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 5.0],
+            [3.0, 0.0],
+            [4.0, 1.0],
+            [5.0, 0.0],
+            [6.0, 1.0],
+            [7.0, 0.0],
+        ]);
+        let mut kept = vec![0];
+        for step in RdpSteps::new(&line, 0.8) {
+            if let RdpStep::Split { split, .. } = step {
+                kept.push(split);
+            }
+        }
+        kept.push(line.len() - 1);
+        kept.sort();
+        assert_eq!(kept, rdp_keep(&line, 0.8, false));
+    }
+
+    #[test]
+    fn reduce_loop() {
+        // first and last points coincide, so distances are measured from that point
+        assert_reduce(
+            vec![
+                [0.0, 0.0],
+                [1.0, 0.1],
+                [2.0, 0.0],
+                [2.1, 1.0],
+                [2.0, 2.0],
+                [0.0, 2.0],
+                [0.0, 0.0],
+            ],
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]],
+            0.2,
+        );
+    }
+
+    #[test]
+    fn reduce_spike() {
+        // collinear points which double back beyond the ends of the chord are kept
+        assert_reduce(
+            vec![[0.0, 0.0], [3.0, 0.0], [1.0, 0.0], [2.0, 0.0]],
+            vec![[0.0, 0.0], [3.0, 0.0], [1.0, 0.0], [2.0, 0.0]],
+            0.2,
+        );
+        assert_reduce(
+            vec![[0.0, 0.0], [1.0, 0.0], [1.5, 0.0], [2.0, 0.0]],
+            vec![[0.0, 0.0], [2.0, 0.0]],
+            0.2,
+        );
+    }
+
+    #[test]
+    fn proj_degenerate() {
+        let a: Point<Precision, 2> = [1.0, 1.0].into();
+        let p: Point<Precision, 2> = [4.0, 5.0].into();
+        assert_eq!(proj_dist2(&a, &a, &p, 0.0), 25.0);
+    }
+
+    #[test]
+    fn reduce_to_n() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0], [3.0, 0.1]]);
+        assert_eq!(
+            rdp_reduce_to(&line, 3, TooFewPoints::Error).unwrap(),
+            make_line(vec![[0.0, 0.0], [1.0, 1.0], [3.0, 0.1]])
+        );
+        assert_eq!(rdp_reduce_to(&line, 5, TooFewPoints::AsIs).unwrap(), line);
+        assert_eq!(
+            rdp_reduce_to(&line, 5, TooFewPoints::Densify)
+                .unwrap()
+                .len(),
+            5
+        );
+        assert!(rdp_reduce_to(&line, 5, TooFewPoints::Error).is_err());
+    }
+
+    #[test]
+    fn mask_matches_keep() {
+        let line = make_line(
+            (0..50)
+                .map(|i| [i as Precision, ((i * 7) % 11) as Precision * 0.3])
+                .collect(),
+        );
+        for epsilon in [0.0, 0.5, 1.0, 10.0] {
+            let mask = rdp_keep_mask(&line, epsilon);
+            let from_mask: Vec<_> = (0..line.len()).filter(|idx| mask[*idx]).collect();
+            assert_eq!(from_mask, rdp_keep(&line, epsilon, false));
+        }
+        assert!(rdp_keep_mask::<2>(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn stats() {
+        // one split at the peak, then two sub-lines with nothing to split
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 5.0],
+            [3.0, 0.0],
+            [4.0, 0.0],
+        ]);
+        let (keep, stats) = rdp_keep_with_stats(&line, 1.0);
+        assert_eq!(keep, vec![0, 2, 4]);
+        assert_eq!(stats.points_visited, 3 + 1 + 1);
+        assert_eq!(stats.cache_hits, 0);
+    }
+
+    #[test]
+    fn rank_matches_keep() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.1],
+            [2.0, -0.1],
+            [3.0, 5.0],
+            [4.0, 6.0],
+            [5.0, 7.0],
+            [6.0, 8.1],
+            [7.0, 9.0],
+            [8.0, 9.0],
+            [9.0, 9.0],
+        ]);
+        let ranks = rdp_rank(&line);
+        assert_eq!(ranks[0], Precision::INFINITY);
+        assert_eq!(ranks[9], Precision::INFINITY);
+        for epsilon in [0.0, 0.05, 0.1, 0.5, 1.0, 3.0, 10.0] {
+            let from_rank: Vec<_> = (0..line.len()).filter(|i| ranks[*i] > epsilon).collect();
+            assert_eq!(
+                from_rank,
+                rdp_keep(&line, epsilon, false),
+                "epsilon {epsilon}"
+            );
+        }
+    }
+
+    #[test]
+    fn rank_nan() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, Precision::NAN],
+            [2.0, 1.0],
+            [3.0, 0.0],
+        ]);
+        let ranks = rdp_rank(&line);
+        assert_eq!(ranks[1], 0.0);
+        for epsilon in [0.0, 0.5, 2.0] {
+            let from_rank: Vec<_> = (0..line.len()).filter(|i| ranks[*i] > epsilon).collect();
+            assert_eq!(from_rank, rdp_keep(&line, epsilon, false));
+        }
+        assert!(suggest_rdp_epsilon(&line, 0.5).is_ok());
+
+        let all_nan = make_line(vec![[Precision::NAN, 0.0]; 5]);
+        assert_eq!(
+            rdp_rank(&all_nan),
+            vec![Precision::INFINITY, 0.0, 0.0, 0.0, Precision::INFINITY]
+        );
+        assert_eq!(
+            crate::simplify::multilevel::MultiLevelLine::rdp(all_nan)
+                .at_count(5)
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn suggest_epsilon() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.1],
+            [2.0, -0.1],
+            [3.0, 5.0],
+            [4.0, 6.0],
+            [5.0, 7.0],
+            [6.0, 8.1],
+            [7.0, 9.0],
+            [8.0, 9.0],
+            [9.0, 9.0],
+        ]);
+        let ranks = rdp_rank(&line);
+        for n in 2..line.len() {
+            let epsilon = suggest_rdp_epsilon(&line, n as Precision / 10.0).unwrap();
+            let kept = rdp_keep(&line, epsilon, false).len();
+            // as close as possible without going over
+            let next = ranks
+                .iter()
+                .filter(|r| **r < epsilon)
+                .fold(0.0, |a, b| b.max(a));
+            let tied = ranks.iter().filter(|r| **r == next).count();
+            assert!(kept <= n && kept + tied > n, "{n} {kept} {tied}");
+        }
+        assert_eq!(suggest_rdp_epsilon(&line, 1.0).unwrap(), 0.0);
+        assert_eq!(
+            suggest_rdp_epsilon(&line, 0.0).unwrap(),
+            suggest_rdp_epsilon(&line, 0.2).unwrap()
+        );
+        assert!(suggest_rdp_epsilon(&line, 1.5).is_err());
+        assert!(suggest_rdp_epsilon(&line, Precision::NAN).is_err());
+        assert_eq!(suggest_rdp_epsilon(&line[..2], 0.5).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn keep_fn() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.5],
+            [2.0, 0.0],
+            [10.0, 0.0],
+            [11.0, 0.5],
+            [12.0, 0.0],
+        ]);
+        // constant tolerance is the same as normal RDP
+        assert_eq!(rdp_keep_fn(&line, |_, _| 0.3), rdp_keep(&line, 0.3, false));
+        // more tolerant further along x:
+        // the whole line is split at the first bump, but the rest can then be a single edge
+        let keep = rdp_keep_fn(&line, |a, b| if a.x + b.x > 12.0 { 1.0 } else { 0.3 });
+        assert_eq!(keep, vec![0, 1, 5]);
+    }
+
+    #[test]
+    fn keep_per_point() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.5],
+            [2.0, 0.0],
+            [3.0, 0.5],
+            [4.0, 0.0],
+        ]);
+        let uniform = vec![0.3; line.len()];
+        assert_eq!(
+            rdp_keep_per_point(&line, &uniform).unwrap(),
+            rdp_keep(&line, 0.3, false)
+        );
+        let varying = vec![0.0, 0.3, 1.0, 1.0, 0.0];
+        assert_eq!(rdp_keep_per_point(&line, &varying).unwrap(), vec![0, 1, 4]);
+        assert!(rdp_keep_per_point(&line, &uniform[1..]).is_err());
+    }
+
+    #[test]
+    fn keep_preserving() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.1],
+            [2.0, 0.0],
+            [3.0, 0.1],
+            [4.0, 0.0],
+        ]);
+        assert_eq!(rdp_keep_preserving(&line, 1.0, &[]), vec![0, 4]);
+        assert_eq!(
+            rdp_keep_preserving(&line, 1.0, &[3, 1, 3]),
+            vec![0, 1, 3, 4]
+        );
+        assert_eq!(
+            rdp_keep_preserving(&line, 0.0, &[2]),
+            rdp_keep(&line, 0.0, false)
+        );
+        assert_eq!(rdp_keep_preserving(&line, 1.0, &[0, 4]), vec![0, 4]);
+        assert_eq!(
+            rdp_reduce_preserving(&line, 1.0, &[2]),
+            vec![line[0], line[2], line[4]]
+        );
+    }
+
+    #[test]
+    fn reduce_into_reuses() {
+        let lines = [
+            make_line(vec![
+                [0.0, 0.0],
+                [0.5, 0.6],
+                [1.0, 1.0],
+                [1.6, 0.5],
+                [2.0, 0.0],
+            ]),
+            make_line(vec![[0.0, 0.0], [1.0, 0.1], [2.0, 0.0]]),
+            make_line(vec![[0.0, 0.0]]),
+        ];
+        let mut out = vec![];
+        for line in lines.iter() {
+            rdp_reduce_into(line, 0.2, &mut out);
+            let expected: Vec<_> = rdp_keep(line, 0.2, false)
+                .into_iter()
+                .map(|i| line[i])
+                .collect();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn non_finite_endpoint() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.5], [Precision::INFINITY, 0.0]]);
+        let mut out = vec![];
+        rdp_reduce_into(&line, 0.1, &mut out);
+        assert_eq!(
+            (out[0], out.len()),
+            (line[0], rdp_keep(&line, 0.1, false).len())
+        );
+    }
 }
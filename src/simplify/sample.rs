@@ -1,6 +1,14 @@
 //! Resample a linestring by placing evenly-spaced points along its length.
-use crate::{total_length, Precision};
+use super::TooFewPoints;
+use crate::cumulative_lengths;
+use crate::curve::explicitly_closed;
+use crate::error::{Error, Result};
+use crate::metric::DistanceMetric;
+use crate::stats::length;
+use crate::{check_finite, total_length, CurveKind, Lerp, Location, Precision};
 use nalgebra::Point;
+#[cfg(feature = "rand")]
+use rand::Rng;
 use std::cmp::{Ordering, PartialOrd};
 
 /// Create a new linestring by traversing the original, placing a node every `sample_distance`.
@@ -10,33 +18,226 @@ use std::cmp::{Ordering, PartialOrd};
 /// A zero-point line remains zero-point; a single-point line keeps that single point.
 /// A line shorter than `sample_distance + offset` will be reduced to a single point.
 ///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// A closed line is traversed all the way around, back to its first point,
+/// which is not repeated at the end if a sample lands exactly on it.
+///
 /// `sample_distance` must be positive and `offset` must be non-negative (panics if these are invalid).
 pub fn sample_every<const D: usize>(
     line: &[Point<Precision, D>],
     sample_distance: Precision,
     offset: Precision,
+    kind: impl Into<CurveKind>,
 ) -> (Vec<Point<Precision, D>>, Precision) {
+    let mut out = Vec::default();
+    let remainder = match kind.into() {
+        CurveKind::Open => sample_every_into(line, sample_distance, offset, &mut out),
+        CurveKind::Closed => {
+            let remainder =
+                sample_every_into(&explicitly_closed(line), sample_distance, offset, &mut out);
+            if out.len() > 1 && out.first() == out.last() {
+                out.pop();
+            }
+            remainder
+        }
+    };
+    (out, remainder)
+}
+
+/// As [sample_every], but returns an error rather than panicking on invalid parameters,
+/// or on lines with non-finite coordinates.
+pub fn try_sample_every<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    kind: impl Into<CurveKind>,
+) -> Result<(Vec<Point<Precision, D>>, Precision)> {
+    if !(sample_distance > 0.0 && sample_distance.is_finite()) {
+        return Err(Error::invalid(
+            "sample_distance",
+            "must be positive and finite",
+        ));
+    }
+    if !(offset >= 0.0 && offset.is_finite()) {
+        return Err(Error::invalid("offset", "must be non-negative and finite"));
+    }
+    check_finite(line)?;
+    Ok(sample_every(line, sample_distance, offset, kind))
+}
+
+/// Where [sample_every_with_remainder] puts the distance left over
+/// when the line's length is not a whole number of `sample_distance`s.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RemainderPolicy {
+    /// Start at the first point, leaving a short gap at the end (as [sample_every]).
+    #[default]
+    ToEnd,
+    /// Stretch the spacing so that it divides the length evenly,
+    /// with the same number of gaps as [RemainderPolicy::ToEnd] has full-length gaps.
+    /// Open lines keep both endpoints.
+    Distribute,
+    /// Keep the spacing, but split the leftover distance evenly between both ends
+    /// (for closed lines, either side of the first point).
+    Center,
+}
+
+/// Number of whole `step`s in `length`, allowing for float error which leaves `length` a hair short.
+fn whole_steps(length: Precision, step: Precision) -> usize {
+    let steps = length / step;
+    let rounded = steps.round();
+    if (steps - rounded).abs() < 1e-9 {
+        rounded as usize
+    } else {
+        steps.floor() as usize
+    }
+}
+
+/// As [sample_every] with an offset of 0.0, but with a choice of where the leftover distance goes,
+/// and optionally going all the way around a closed line.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// For closed lines, the gap across the first point is the one which absorbs the leftover distance
+/// (except with [RemainderPolicy::Distribute], where every gap is equal),
+/// and the first point is not repeated at the end.
+///
+/// Each point is placed independently by its distance along the line, so error does not accumulate.
+/// A zero-point line remains zero-point; a line with zero length is reduced to its first point.
+///
+/// `sample_distance` must be positive (panics if it is invalid).
+pub fn sample_every_with_remainder<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    remainder: RemainderPolicy,
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, D>> {
+    if sample_distance <= 0.0 {
+        panic!("`sample_distance` must be positive");
+    }
+    let closed = kind.into().is_closed();
+    let path = if closed {
+        explicitly_closed(line)
+    } else {
+        line.to_vec()
+    };
+    let cumulative = cumulative_lengths(&path);
+    let total = cumulative.last().copied().unwrap_or(0.0);
+    if total == 0.0 {
+        return line.first().copied().into_iter().collect();
+    }
+    let steps = whole_steps(total, sample_distance);
+    let (n_points, spacing, offset) = match remainder {
+        RemainderPolicy::Distribute => {
+            let n_gaps = steps.max(1);
+            let n_points = if closed { n_gaps } else { n_gaps + 1 };
+            (n_points, total / n_gaps as Precision, 0.0)
+        }
+        RemainderPolicy::ToEnd | RemainderPolicy::Center => {
+            // a closed line's closing gap may be a full step, so there is one fewer point
+            let n_points = if closed && steps as Precision * sample_distance >= total {
+                steps
+            } else {
+                steps + 1
+            };
+            let offset = match remainder {
+                RemainderPolicy::Center => {
+                    (total - steps as Precision * sample_distance).max(0.0) / 2.0
+                }
+                _ => 0.0,
+            };
+            (n_points, sample_distance, offset)
+        }
+    };
+    (0..n_points)
+        .map(|idx| {
+            if !closed && remainder == RemainderPolicy::Distribute && idx == n_points - 1 {
+                return path[path.len() - 1];
+            }
+            point_at_length(&path, &cumulative, offset + idx as Precision * spacing)
+        })
+        .collect()
+}
+
+/// Where on the original linestring a resampled point came from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Provenance {
+    /// Index of the edge, i.e. the point is between original points `edge` and `edge + 1`.
+    pub edge: usize,
+    /// Fraction of the way along the edge, from 0.0 (at point `edge`) to 1.0 (at point `edge + 1`).
+    pub t: Precision,
+}
+
+/// As [sample_every], but also returns the [Provenance] of every resampled point,
+/// so that attributes of the original points can be interpolated onto the resampled ones.
+///
+/// Points of a single-point line are given edge 0 and `t = 0.0`.
+pub fn sample_every_with_provenance<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+) -> (Vec<Point<Precision, D>>, Vec<Provenance>, Precision) {
+    let mut out = Vec::default();
+    let mut prov = Vec::default();
+    let remainder = sample_every_inner(line, sample_distance, offset, &mut out, Some(&mut prov));
+    (out, prov, remainder)
+}
+
+/// As [sample_every], but clearing `out` and writing the samples into it, returning the remainder.
+///
+/// Nothing is allocated other than to grow `out`,
+/// so reusing the same buffer for many lines avoids allocator churn.
+pub fn sample_every_into<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    out: &mut Vec<Point<Precision, D>>,
+) -> Precision {
+    out.clear();
+    sample_every_inner(line, sample_distance, offset, out, None)
+}
+
+/// Append the samples to `out` (and their provenance to `prov`, if given), returning the remainder.
+fn sample_every_inner<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    out: &mut Vec<Point<Precision, D>>,
+    mut prov: Option<&mut Vec<Provenance>>,
+) -> Precision {
     if sample_distance <= 0.0 {
         panic!("`sample_distance` must be positive");
     }
     if offset < 0.0 {
         panic!("`offset` must be non-negative");
     }
+    let mut push = |p: Point<Precision, D>, provenance: Provenance| {
+        out.push(p);
+        if let Some(prov) = prov.as_mut() {
+            prov.push(provenance);
+        }
+    };
     let mut iter = line.iter();
     if line.len() <= 1 {
-        return (iter.cloned().collect(), 0.0);
+        for p in iter {
+            push(*p, Provenance { edge: 0, t: 0.0 });
+        }
+        return 0.0;
     }
     let mut prev = *iter.next().unwrap();
-    let mut out = Vec::default();
-    let mut remaining_dist: f64;
+    let mut remaining_dist: Precision;
     if offset == 0.0 {
-        out.push(prev);
+        push(prev, Provenance { edge: 0, t: 0.0 });
         remaining_dist = sample_distance;
     } else {
         remaining_dist = offset
     }
     let mut next = *iter.next().unwrap();
 
+    // which edge we're on, how long it is, and how far along it `prev` is
+    let mut edge = 0;
+    let mut full_edge_length = (next - prev).magnitude();
+    let mut along = 0.0;
+
     loop {
         let vec = next - prev;
         let edge_length = vec.magnitude();
@@ -44,17 +245,27 @@ pub fn sample_every<const D: usize>(
         match remaining_dist.partial_cmp(&edge_length).unwrap() {
             Ordering::Less => {
                 prev += (vec / edge_length) * remaining_dist;
-                out.push(prev);
+                along += remaining_dist;
+                push(
+                    prev,
+                    Provenance {
+                        edge,
+                        t: along / full_edge_length,
+                    },
+                );
                 remaining_dist = sample_distance;
             }
             Ordering::Equal => {
                 prev = next;
-                out.push(prev);
+                push(prev, Provenance { edge, t: 1.0 });
                 let Some(next_ref) = iter.next() else {
                     remaining_dist = 0.0;
                     break;
                 };
                 next = *next_ref;
+                edge += 1;
+                full_edge_length = (next - prev).magnitude();
+                along = 0.0;
                 remaining_dist = sample_distance;
             }
             Ordering::Greater => {
@@ -63,37 +274,432 @@ pub fn sample_every<const D: usize>(
 
                 let Some(next_ref) = iter.next() else { break };
                 next = *next_ref;
+                edge += 1;
+                full_edge_length = (next - prev).magnitude();
+                along = 0.0;
             }
         };
     }
 
-    (out, sample_distance - remaining_dist)
+    sample_distance - remaining_dist
+}
+
+/// As [sample_every], but any sample which lands within `snap_tolerance` of an original vertex
+/// is replaced by that vertex, so that key coordinates are kept exactly.
+///
+/// Samples are still placed at regular distances along the line,
+/// so snapping does not accumulate: spacing changes by at most `snap_tolerance` either side.
+/// Each original vertex is emitted at most once, by the nearest sample (or the earlier, if two are equally near).
+/// `snap_tolerance` should be less than half of `sample_distance`.
+///
+/// `sample_distance` must be positive and `offset` and `snap_tolerance` must be non-negative
+/// (panics if these are invalid).
+pub fn sample_every_snapped<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    snap_tolerance: Precision,
+) -> (Vec<Point<Precision, D>>, Precision) {
+    if snap_tolerance < 0.0 {
+        panic!("`snap_tolerance` must be non-negative");
+    }
+    let (mut out, prov, remainder) = sample_every_with_provenance(line, sample_distance, offset);
+    // the vertex each sample would snap to, and how far it would move
+    let candidates: Vec<Option<(usize, Precision)>> = out
+        .iter()
+        .zip(prov.iter())
+        .map(|(p, pr)| {
+            [pr.edge, pr.edge + 1]
+                .into_iter()
+                .filter(|idx| *idx < line.len())
+                .map(|idx| (idx, (line[idx] - p).magnitude()))
+                .filter(|(_, d)| *d <= snap_tolerance)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        })
+        .collect();
+    let mut last_snapped = None;
+    for (idx, cand) in candidates.iter().enumerate() {
+        let Some((vertex, dist)) = cand else {
+            continue;
+        };
+        if last_snapped == Some(*vertex) {
+            continue;
+        }
+        // samples are in order along the line, so only neighbours can compete for a vertex
+        let beaten = |other: Option<&Option<(usize, Precision)>>| {
+            other
+                .copied()
+                .flatten()
+                .is_some_and(|(v, d)| v == *vertex && d < *dist)
+        };
+        if !beaten(idx.checked_sub(1).and_then(|i| candidates.get(i)))
+            && !beaten(candidates.get(idx + 1))
+        {
+            out[idx] = line[*vertex];
+            last_snapped = Some(*vertex);
+        }
+    }
+    (out, remainder)
 }
 
 /// Resample a linestring to ensure that it has `n_points` points,
 /// by dividing the total length evenly.
 ///
+/// Floating point error can leave this one point short, or without the original last point;
+/// see [resample_exact] for a version which avoids this.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// A closed line is resampled all the way around, starting at its first point,
+/// so that the closing edge is the same length as the others.
+///
 /// Panics if line has zero length.
 pub fn resample<const D: usize>(
     line: &[Point<Precision, D>],
     n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, D>> {
+    match kind.into() {
+        CurveKind::Open => {
+            let len = total_length(line);
+            if len == 0.0 {
+                panic!("Not enough points");
+            }
+            let dist = len / ((n_points - 1) as Precision);
+            sample_every(line, dist, 0.0, CurveKind::Open).0
+        }
+        CurveKind::Closed => {
+            // exactly n_points + 1 points, the last of which repeats the first
+            let mut out = resample_parameterised(
+                &explicitly_closed(line),
+                n_points + 1,
+                Parameterisation::ChordLength,
+            );
+            out.pop();
+            out
+        }
+    }
+}
+
+/// As [resample], but always returning exactly `n_points` points, starting and ending with the original endpoints.
+///
+/// Each point is placed independently at its fraction of the total length,
+/// rather than by stepping along the line, so error does not accumulate.
+/// This is [resample_parameterised] with [Parameterisation::ChordLength].
+///
+/// Panics if the line has zero length, or `n_points` is less than 2.
+pub fn resample_exact<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+) -> Vec<Point<Precision, D>> {
+    resample_parameterised(line, n_points, Parameterisation::ChordLength)
+}
+
+/// As [sample_every], but for any [Location], so that other attributes of the points are interpolated too.
+///
+/// Returns the resampled points and the distance from the last resampled point to the original last point
+/// (or the whole length of the line if no points were sampled).
+/// Edges of zero length are skipped over.
+///
+/// `sample_distance` must be positive and `offset` must be non-negative (panics if these are invalid).
+pub fn sample_every_with<L: Location + Clone>(
+    line: &[L],
+    sample_distance: Precision,
+    offset: Precision,
+) -> (Vec<L>, Precision) {
+    sample_every_by(line, sample_distance, offset, L::distance)
+}
+
+/// As [sample_every], but measuring distances along the line with the given [DistanceMetric].
+///
+/// New points are still linearly interpolated in coordinate space within each edge.
+///
+/// `sample_distance` must be positive and `offset` must be non-negative (panics if these are invalid).
+pub fn sample_every_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    metric: &M,
+) -> (Vec<Point<Precision, D>>, Precision) {
+    sample_every_by(line, sample_distance, offset, |a, b| metric.distance(a, b))
+}
+
+/// As [resample], but measuring distances along the line with the given [DistanceMetric].
+///
+/// The first and last points are always kept, so there are exactly `n_points` points
+/// (unless the line has fewer than 2 points, in which case it is returned unchanged).
+///
+/// Panics if `n_points` is less than 2 and the line has at least 2 points.
+pub fn resample_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    metric: &M,
 ) -> Vec<Point<Precision, D>> {
-    let len = total_length(line);
+    if line.len() < 2 {
+        return line.to_vec();
+    }
+    if n_points < 2 {
+        panic!("`n_points` must be at least 2");
+    }
+    let len = metric.length(line);
+    if len == 0.0 {
+        return vec![line[0]; n_points];
+    }
+    let mut out = sample_every_metric(line, len / (n_points - 1) as Precision, 0.0, metric).0;
+    // floating point error may or may not place a sample at the very end
+    out.truncate(n_points - 1);
+    out.push(line[line.len() - 1]);
+    out
+}
+
+/// As [resample], but returns an error rather than panicking if the line has zero length,
+/// `n_points` is less than 2, or the line has non-finite coordinates.
+pub fn try_resample<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> Result<Vec<Point<Precision, D>>> {
+    let kind = kind.into();
+    if n_points < 2 {
+        return Err(Error::invalid("n_points", "must be at least 2"));
+    }
+    check_finite(line)?;
+    if length(line, kind) == 0.0 {
+        return Err(Error::ZeroLength);
+    }
+    Ok(resample(line, n_points, kind))
+}
+
+/// As [try_resample], with an explicit policy for lines which have fewer than `n_points` points.
+///
+/// As resampling creates new points anyway, [TooFewPoints::Densify] is the same as [try_resample].
+pub fn resample_to<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+    too_few: TooFewPoints,
+) -> Result<Vec<Point<Precision, D>>> {
+    if too_few != TooFewPoints::Densify {
+        if let Some(out) = too_few.handle(line, n_points) {
+            return out;
+        }
+    }
+    try_resample(line, n_points, kind)
+}
+
+/// As [resample], but for any [Location], so that other attributes of the points are interpolated too.
+///
+/// Panics if line has zero length.
+pub fn resample_with<L: Location + Clone>(line: &[L], n_points: usize) -> Vec<L> {
+    let len: Precision = line.windows(2).map(|p| p[0].distance(&p[1])).sum();
     if len == 0.0 {
         panic!("Not enough points");
     }
-    let dist = len / ((n_points - 1) as f64);
-    sample_every(line, dist, 0.0).0
+    let dist = len / ((n_points - 1) as Precision);
+    sample_every_with(line, dist, 0.0).0
+}
+
+/// How to assign a parameter value to each point of a linestring,
+/// for distributing samples along it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Parameterisation {
+    /// Every edge covers the same parameter range, however long it is.
+    Uniform,
+    /// Parameter is proportional to distance along the line, as in [resample] and [sample_every].
+    #[default]
+    ChordLength,
+    /// Each edge's parameter range is the square root of its length,
+    /// which places relatively more samples on short edges (e.g. around tight corners).
+    Centripetal,
+}
+
+impl Parameterisation {
+    /// The parameter value of every point, starting at 0.0.
+    pub fn parameters<const D: usize>(&self, line: &[Point<Precision, D>]) -> Vec<Precision> {
+        match self {
+            Parameterisation::Uniform => (0..line.len()).map(|idx| idx as Precision).collect(),
+            Parameterisation::ChordLength => cumulative_lengths(line),
+            Parameterisation::Centripetal => {
+                let mut total = 0.0;
+                let mut out = Vec::with_capacity(line.len());
+                if !line.is_empty() {
+                    out.push(total);
+                }
+                for pair in line.windows(2) {
+                    total += (pair[1] - pair[0]).magnitude().sqrt();
+                    out.push(total);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// As [sample_every], but with `sample_distance` and `offset` measured in the given [Parameterisation],
+/// rather than distance along the line.
+/// Points are placed by linear interpolation within each edge.
+///
+/// Returns the resampled points and the parameter distance from the last resampled point to the original last point.
+/// Edges with zero parameter range are skipped over.
+///
+/// `sample_distance` must be positive and `offset` must be non-negative (panics if these are invalid).
+pub fn sample_every_parameterised<const D: usize>(
+    line: &[Point<Precision, D>],
+    sample_distance: Precision,
+    offset: Precision,
+    parameterisation: Parameterisation,
+) -> (Vec<Point<Precision, D>>, Precision) {
+    if sample_distance <= 0.0 {
+        panic!("`sample_distance` must be positive");
+    }
+    if offset < 0.0 {
+        panic!("`offset` must be non-negative");
+    }
+    if line.len() <= 1 {
+        return (line.to_vec(), 0.0);
+    }
+    let params = parameterisation.parameters(line);
+    let total = params[params.len() - 1];
+    let mut out = Vec::default();
+    let mut target = offset;
+    let mut last_placed = None;
+    while target <= total {
+        out.push(point_at_length(line, &params, target));
+        last_placed = Some(target);
+        target += sample_distance;
+    }
+    (out, total - last_placed.unwrap_or(0.0))
+}
+
+/// As [resample], but dividing the given [Parameterisation] evenly,
+/// rather than distance along the line.
+///
+/// Panics if the line has a total parameter range of zero, or `n_points` is less than 2.
+pub fn resample_parameterised<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    parameterisation: Parameterisation,
+) -> Vec<Point<Precision, D>> {
+    if n_points < 2 {
+        panic!("`n_points` must be at least 2");
+    }
+    let params = parameterisation.parameters(line);
+    let total = params.last().copied().unwrap_or(0.0);
+    if total == 0.0 {
+        panic!("Not enough points");
+    }
+    (0..n_points)
+        .map(|idx| {
+            // avoid drifting past the last point due to floating point error
+            if idx == n_points - 1 {
+                return line[line.len() - 1];
+            }
+            let target = total * idx as Precision / (n_points - 1) as Precision;
+            point_at_length(line, &params, target)
+        })
+        .collect()
+}
+
+/// Find the point `length` along the linestring, given the line's [crate::cumulative_lengths].
+///
+/// Lengths beyond either end of the line are clamped to that end.
+/// Panics if the line is empty.
+pub(crate) fn point_at_length<const D: usize>(
+    line: &[Point<Precision, D>],
+    cumulative: &[Precision],
+    length: Precision,
+) -> Point<Precision, D> {
+    // index of the first point at or beyond the given length
+    let idx = cumulative.partition_point(|l| *l < length);
+    if idx == 0 {
+        return line[0];
+    }
+    if idx >= line.len() {
+        return line[line.len() - 1];
+    }
+    let edge_length = cumulative[idx] - cumulative[idx - 1];
+    let frac = (length - cumulative[idx - 1]) / edge_length;
+    line[idx - 1] + (line[idx] - line[idx - 1]) * frac
+}
+
+/// Place `n_points` points at random along the linestring,
+/// uniformly distributed by length (rather than by vertex).
+///
+/// Points are returned in order along the line.
+/// For reproducible output, use a seeded, portable RNG like `rand_chacha::ChaCha8Rng`.
+/// A zero-point line produces no points.
+#[cfg(feature = "rand")]
+pub fn sample_random_along<const D: usize, R: Rng + ?Sized>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    rng: &mut R,
+) -> Vec<Point<Precision, D>> {
+    if line.is_empty() {
+        return Vec::default();
+    }
+    let cumulative = cumulative_lengths(line);
+    let total = *cumulative.last().unwrap();
+
+    let mut lengths: Vec<Precision> = (0..n_points).map(|_| rng.gen_range(0.0..=total)).collect();
+    lengths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    lengths
+        .into_iter()
+        .map(|l| point_at_length(line, &cumulative, l))
+        .collect()
+}
+
+/// Shared implementation of [sample_every_with] and [sample_every_metric].
+fn sample_every_by<L: Lerp + Clone>(
+    line: &[L],
+    sample_distance: Precision,
+    offset: Precision,
+    distance: impl Fn(&L, &L) -> Precision,
+) -> (Vec<L>, Precision) {
+    if sample_distance <= 0.0 {
+        panic!("`sample_distance` must be positive");
+    }
+    if offset < 0.0 {
+        panic!("`offset` must be non-negative");
+    }
+    if line.len() <= 1 {
+        return (line.to_vec(), 0.0);
+    }
+
+    let mut out = Vec::default();
+    // distance along the line of the next point to place
+    let mut target = offset;
+    // distance along the line of the start of the current edge
+    let mut edge_start = 0.0;
+    let mut last_placed = None;
+
+    for pair in line.windows(2) {
+        let edge_length = distance(&pair[0], &pair[1]);
+        let edge_end = edge_start + edge_length;
+        while target <= edge_end {
+            let t = if edge_length == 0.0 {
+                0.0
+            } else {
+                (target - edge_start) / edge_length
+            };
+            out.push(pair[0].lerp(&pair[1], t));
+            last_placed = Some(target);
+            target += sample_distance;
+        }
+        edge_start = edge_end;
+    }
+
+    (out, edge_start - last_placed.unwrap_or(0.0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{make_line, tol};
 
     #[test]
     fn half_line() {
-        let ls1: Vec<Point<f64, 1>> = vec![[0.0].into(), [1.0].into()];
-        let resampled = resample(ls1.as_slice(), 3);
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into()];
+        let resampled = resample(ls1.as_slice(), 3, false);
         println!("{:?}", resampled);
         assert_eq!(resampled.len(), 3);
         assert_eq!(resampled[0], ls1[0]);
@@ -101,10 +707,102 @@ mod tests {
         assert_eq!(resampled[2], ls1[1]);
     }
 
+    #[test]
+    fn resample_exact_count() {
+        let line = make_line(vec![[0.0, 0.0], [0.1, 0.7], [0.3, 0.3], [1.3, 0.3]]);
+        for n_points in 2..100 {
+            let out = resample_exact(&line, n_points);
+            assert_eq!(out.len(), n_points);
+            assert_eq!(out[0], line[0]);
+            assert_eq!(out[n_points - 1], line[3]);
+        }
+        let half = resample_exact(&line[2..], 3);
+        assert!((half[1] - Point::from([0.8, 0.3])).norm() <= tol(0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn resample_exact_zero_length() {
+        resample_exact(&make_line(vec![[1.0, 1.0]; 2]), 3);
+    }
+
+    #[test]
+    fn resample_closed_count() {
+        // stepping along the ring can fall one point short of the closing point,
+        // so closed lines are resampled by fraction of the length instead
+        let ring = make_line(vec![[0.0, 0.0], [0.1, 0.7], [0.3, 0.3], [1.3, 0.3]]);
+        for n_points in 3..100 {
+            let out = resample(&ring, n_points, true);
+            assert_eq!(out.len(), n_points);
+            assert_eq!(out[0], ring[0]);
+        }
+    }
+
+    #[test]
+    fn remainder_policies() {
+        let line = make_line(vec![[0.0, 0.0], [4.5, 0.0]]);
+        let xs = |policy, closed| -> Vec<Precision> {
+            sample_every_with_remainder(&line, 1.0, policy, closed)
+                .iter()
+                .map(|p| p.x)
+                .collect()
+        };
+        assert_eq!(
+            xs(RemainderPolicy::ToEnd, false),
+            vec![0.0, 1.0, 2.0, 3.0, 4.0]
+        );
+        assert_eq!(
+            sample_every_with_remainder(&line, 1.0, RemainderPolicy::ToEnd, false),
+            sample_every(&line, 1.0, 0.0, false).0
+        );
+        assert_eq!(
+            xs(RemainderPolicy::Distribute, false),
+            vec![0.0, 1.125, 2.25, 3.375, 4.5]
+        );
+        assert_eq!(
+            xs(RemainderPolicy::Center, false),
+            vec![0.25, 1.25, 2.25, 3.25, 4.25]
+        );
+
+        // there and back again, 9.0 long, so no remainder
+        let expected = vec![0.0, 1.0, 2.0, 3.0, 4.0, 4.0, 3.0, 2.0, 1.0];
+        assert_eq!(xs(RemainderPolicy::ToEnd, true), expected);
+        assert_eq!(xs(RemainderPolicy::Distribute, true), expected);
+        assert_eq!(xs(RemainderPolicy::Center, true), expected);
+    }
+
+    #[test]
+    fn remainder_closed_ring() {
+        // perimeter 10, so a spacing of 3 leaves 1 over
+        let ring = make_line(vec![[0.0, 0.0], [4.0, 0.0], [4.0, 1.0], [0.0, 1.0]]);
+        assert_eq!(
+            sample_every_with_remainder(&ring, 3.0, RemainderPolicy::ToEnd, true),
+            make_line(vec![[0.0, 0.0], [3.0, 0.0], [3.0, 1.0], [0.0, 1.0]])
+        );
+        assert_eq!(
+            sample_every_with_remainder(&ring, 3.0, RemainderPolicy::Center, true),
+            make_line(vec![[0.5, 0.0], [3.5, 0.0], [2.5, 1.0], [0.0, 0.5]])
+        );
+        let even = sample_every_with_remainder(&ring, 3.0, RemainderPolicy::Distribute, true);
+        assert_eq!(even.len(), 3);
+        assert_eq!(even[0], ring[0]);
+        assert!((even[1].x - 10.0 / 3.0).abs() < tol(1e-12));
+        assert!((even[2].x - 7.0 / 3.0).abs() < tol(1e-12));
+
+        assert!(
+            sample_every_with_remainder(&ring[..0], 1.0, RemainderPolicy::Center, true).is_empty()
+        );
+        let zero = make_line(vec![[1.0, 1.0]; 3]);
+        assert_eq!(
+            sample_every_with_remainder(&zero, 1.0, RemainderPolicy::Distribute, false),
+            zero[..1]
+        );
+    }
+
     #[test]
     fn resample_line() {
-        let ls1: Vec<Point<f64, 1>> = vec![[0.0].into(), [3.0].into()];
-        let (resampled, remainder) = sample_every(ls1.as_slice(), 1.0, 0.5);
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [3.0].into()];
+        let (resampled, remainder) = sample_every(ls1.as_slice(), 1.0, 0.5, false);
         println!("{:?}", resampled);
         assert_eq!(resampled.len(), 3);
         assert_eq!(resampled[0], [0.5].into());
@@ -112,4 +810,176 @@ mod tests {
         assert_eq!(resampled[2], [2.5].into());
         assert_eq!(remainder, 0.5);
     }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_along() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        let seeded = |seed| ChaCha8Rng::seed_from_u64(seed);
+        let ls1: Vec<Point<Precision, 1>> =
+            vec![[0.0].into(), [1.0].into(), [1.0].into(), [3.0].into()];
+        let sampled = sample_random_along(ls1.as_slice(), 100, &mut seeded(1));
+        assert_eq!(sampled.len(), 100);
+        assert!(sampled.windows(2).all(|w| w[0].x <= w[1].x));
+        assert!(sampled.iter().all(|p| p.x >= 0.0 && p.x <= 3.0));
+        // roughly two thirds of the points should be on the longer edge
+        let n_long = sampled.iter().filter(|p| p.x > 1.0).count();
+        assert!(n_long > 50 && n_long < 85);
+
+        assert_eq!(
+            sampled,
+            sample_random_along(ls1.as_slice(), 100, &mut seeded(1))
+        );
+        assert_ne!(
+            sampled,
+            sample_random_along(ls1.as_slice(), 100, &mut seeded(2))
+        );
+    }
+
+    #[test]
+    fn provenance() {
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into(), [3.0].into()];
+        let (resampled, prov, _) = sample_every_with_provenance(ls1.as_slice(), 0.5, 0.0);
+        assert_eq!(resampled.len(), 7);
+        assert_eq!(prov[0], Provenance { edge: 0, t: 0.0 });
+        assert_eq!(prov[1], Provenance { edge: 0, t: 0.5 });
+        assert_eq!(prov[2], Provenance { edge: 0, t: 1.0 });
+        assert_eq!(prov[3], Provenance { edge: 1, t: 0.25 });
+        assert_eq!(prov[6], Provenance { edge: 1, t: 1.0 });
+    }
+
+    #[test]
+    fn snapped() {
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.1].into(), [3.0].into()];
+        let (plain, _) = sample_every(ls1.as_slice(), 0.5, 0.0, false);
+        let (snapped, _) = sample_every_snapped(ls1.as_slice(), 0.5, 0.0, 0.15);
+        assert_eq!(snapped.len(), plain.len());
+        assert_eq!(snapped[2], [1.1].into());
+        assert!((snapped[1].x - 0.5).abs() < tol(1e-12));
+        assert!((snapped[3].x - 1.5).abs() < tol(1e-12));
+
+        // only the nearer of two candidate samples snaps
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.13].into(), [3.0].into()];
+        let (snapped, _) = sample_every_snapped(ls1.as_slice(), 0.2, 0.0, 0.15);
+        assert_eq!(snapped.iter().filter(|p| p.x == 1.13).count(), 1);
+        assert!(snapped.iter().any(|p| (p.x - 1.0).abs() < tol(1e-12)));
+
+        // of two equally near samples, only the earlier snaps
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into(), [2.0].into()];
+        let (snapped, _) = sample_every_snapped(ls1.as_slice(), 0.5, 0.25, 0.25);
+        assert_eq!(
+            snapped,
+            vec![[0.0].into(), [1.0].into(), [1.25].into(), [2.0].into()]
+        );
+    }
+
+    #[test]
+    fn parameterised() {
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into(), [5.0].into()];
+        let chord = resample_parameterised(ls1.as_slice(), 6, Parameterisation::ChordLength);
+        assert_eq!(chord, resample(ls1.as_slice(), 6, false));
+
+        // each edge gets half of the points
+        let uniform = resample_parameterised(ls1.as_slice(), 5, Parameterisation::Uniform);
+        let xs: Vec<_> = uniform.iter().map(|p| p.x).collect();
+        assert_eq!(xs, vec![0.0, 0.5, 1.0, 3.0, 5.0]);
+
+        // parameters of 0, 1, 3
+        let (centripetal, remainder) =
+            sample_every_parameterised(ls1.as_slice(), 1.5, 0.0, Parameterisation::Centripetal);
+        let xs: Vec<_> = centripetal.iter().map(|p| p.x).collect();
+        assert_eq!(xs, vec![0.0, 2.0, 5.0]);
+        assert_eq!(remainder, 0.0);
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Node {
+        pos: Point<Precision, 2>,
+        radius: Precision,
+    }
+
+    impl crate::Lerp for Node {
+        fn lerp(&self, other: &Self, t: Precision) -> Self {
+            Self {
+                pos: self.pos.lerp(&other.pos, t),
+                radius: self.radius.lerp(&other.radius, t),
+            }
+        }
+    }
+
+    impl Location for Node {
+        fn distance(&self, other: &Self) -> Precision {
+            self.pos.distance(&other.pos)
+        }
+    }
+
+    #[test]
+    fn resample_attributes() {
+        let line = vec![
+            Node {
+                pos: [0.0, 0.0].into(),
+                radius: 1.0,
+            },
+            Node {
+                pos: [2.0, 0.0].into(),
+                radius: 3.0,
+            },
+        ];
+        let resampled = resample_with(&line, 5);
+        assert_eq!(resampled.len(), 5);
+        assert_eq!(resampled[0], line[0]);
+        assert_eq!(resampled[4], line[1]);
+        assert_eq!(resampled[1].radius, 1.5);
+        assert_eq!(resampled[2].pos, [1.0, 0.0].into());
+    }
+
+    #[test]
+    fn sample_with_matches_points() {
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [3.0].into()];
+        assert_eq!(
+            sample_every_with(ls1.as_slice(), 1.0, 0.5),
+            sample_every(ls1.as_slice(), 1.0, 0.5, false)
+        );
+    }
+
+    #[test]
+    fn fallible() {
+        let ls1: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into()];
+        assert!(matches!(
+            try_sample_every(ls1.as_slice(), 0.0, 0.0, false),
+            Err(Error::InvalidParameter { .. })
+        ));
+        assert!(matches!(
+            try_sample_every(ls1.as_slice(), 1.0, -1.0, false),
+            Err(Error::InvalidParameter { .. })
+        ));
+        assert_eq!(try_resample(ls1.as_slice(), 3, false).unwrap().len(), 3);
+        assert_eq!(try_resample(&ls1[..1], 3, false), Err(Error::ZeroLength));
+        let ring = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]);
+        assert_eq!(
+            try_resample(&ring, 5, true).unwrap(),
+            resample(&ring, 5, true)
+        );
+        assert_eq!(try_resample(&ring[..1], 3, true), Err(Error::ZeroLength));
+
+        let nan: Vec<Point<Precision, 1>> = vec![[0.0].into(), [Precision::NAN].into()];
+        assert_eq!(
+            try_sample_every(nan.as_slice(), 1.0, 0.0, false),
+            Err(Error::NonFiniteCoordinate { index: 1 })
+        );
+    }
+
+    #[test]
+    fn sample_into_reuses() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 2.5]]);
+        let mut out = make_line(vec![[9.0, 9.0]; 10]);
+        let remainder = sample_every_into(&line, 0.7, 0.2, &mut out);
+        let (expected, prov, expected_remainder) = sample_every_with_provenance(&line, 0.7, 0.2);
+        assert_eq!((out.clone(), remainder), (expected, expected_remainder));
+        assert_eq!(prov.len(), out.len());
+        sample_every_into(&line[..1], 0.7, 0.0, &mut out);
+        assert_eq!(out, line[..1]);
+    }
 }
@@ -0,0 +1,235 @@
+//! Simplify a streamed 2D linestring using the Zhao-Saalfeld sleeve-fitting algorithm.
+//!
+//! Points are consumed one at a time with a single point of lookahead,
+//! so lines of unbounded length (e.g. live telemetry) can be simplified on-line.
+//! Every dropped point is within `epsilon` of the simplified edge which replaces it.
+//!
+//! ```
+//! use simples::simplify::sleeve::Sleeve;
+//! use simples::Point;
+//!
+//! let mut sleeve = Sleeve::new(0.1);
+//! let mut out = Vec::default();
+//! for p in [[0.0, 0.0], [1.0, 0.05], [2.0, 0.0], [2.0, 1.0], [2.0, 2.0]] {
+//!     out.extend(sleeve.push(Point::from(p)));
+//! }
+//! out.extend(sleeve.finish());
+//! assert_eq!(out.len(), 3);
+//! ```
+use crate::consts::PI;
+use crate::Precision;
+use nalgebra::{distance, Point};
+
+/// Wrap an angle into (-pi, pi].
+fn wrap(angle: Precision) -> Precision {
+    let out = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if out == -PI {
+        PI
+    } else {
+        out
+    }
+}
+
+/// Directions from the anchor which pass within `epsilon` of every point so far.
+#[derive(Copy, Clone, Debug)]
+struct Cone {
+    /// Absolute angle which the limits are relative to, to avoid wrapping issues.
+    reference: Precision,
+    lo: Precision,
+    hi: Precision,
+}
+
+/// On-line sleeve-fitting simplifier.
+///
+/// Feed it points with [Sleeve::push], which returns each kept point once it is known to be kept,
+/// then call [Sleeve::finish] to get the last point.
+#[derive(Clone, Debug)]
+pub struct Sleeve {
+    epsilon: Precision,
+    anchor: Option<Point<Precision, 2>>,
+    last: Option<Point<Precision, 2>>,
+    cone: Option<Cone>,
+    /// Distance from the anchor of the furthest point since the anchor.
+    max_dist: Precision,
+}
+
+impl Sleeve {
+    pub fn new(epsilon: Precision) -> Self {
+        Self {
+            epsilon,
+            anchor: None,
+            last: None,
+            cone: None,
+            max_dist: 0.0,
+        }
+    }
+
+    /// Start a new edge from `anchor`.
+    fn restart(&mut self, anchor: Point<Precision, 2>) {
+        self.anchor = Some(anchor);
+        self.last = None;
+        self.cone = None;
+        self.max_dist = 0.0;
+    }
+
+    /// Try to extend the current edge to `p`, returning whether that is possible.
+    fn extend(&mut self, anchor: Point<Precision, 2>, p: Point<Precision, 2>) -> bool {
+        let d = distance(&anchor, &p);
+        // points near the anchor are near any edge from it
+        if d <= self.epsilon {
+            self.max_dist = self.max_dist.max(d);
+            return true;
+        }
+        let v = p - anchor;
+        let theta = v.y.atan2(v.x);
+        let half = (self.epsilon / d).asin();
+        let cone = match self.cone {
+            None => Cone {
+                reference: theta,
+                lo: -half,
+                hi: half,
+            },
+            Some(cone) => {
+                let rel = wrap(theta - cone.reference);
+                // the edge must pass near every previous point, and not double back past any of them
+                if rel < cone.lo || rel > cone.hi || d < self.max_dist {
+                    return false;
+                }
+                Cone {
+                    reference: cone.reference,
+                    lo: cone.lo.max(rel - half),
+                    hi: cone.hi.min(rel + half),
+                }
+            }
+        };
+        self.cone = Some(cone);
+        self.max_dist = d;
+        true
+    }
+
+    /// Add the next point of the line.
+    ///
+    /// Returns a point of the simplified line when one is confirmed:
+    /// the first point immediately, and later points once the following point shows they are needed.
+    pub fn push(&mut self, p: Point<Precision, 2>) -> Option<Point<Precision, 2>> {
+        let Some(anchor) = self.anchor else {
+            self.restart(p);
+            return Some(p);
+        };
+        if self.extend(anchor, p) {
+            self.last = Some(p);
+            return None;
+        }
+        // the previous point must be kept, and becomes the anchor for the next edge
+        let kept = self
+            .last
+            .expect("the first point after the anchor always extends");
+        self.restart(kept);
+        self.extend(kept, p);
+        self.last = Some(p);
+        Some(kept)
+    }
+
+    /// Finish the line, returning the last point if it has not already been returned.
+    ///
+    /// The simplifier can then be reused for another line.
+    pub fn finish(&mut self) -> Option<Point<Precision, 2>> {
+        let last = self.last;
+        *self = Self::new(self.epsilon);
+        last
+    }
+}
+
+/// Return the indices of the points kept by the sleeve-fitting simplifier; see [Sleeve].
+pub fn sleeve_keep(line: &[Point<Precision, 2>], epsilon: Precision) -> Vec<usize> {
+    let mut sleeve = Sleeve::new(epsilon);
+    let mut out = Vec::default();
+    for (idx, p) in line.iter().enumerate() {
+        // anything returned other than the first point is the previous point
+        if sleeve.push(*p).is_some() {
+            out.push(idx.saturating_sub(1));
+        }
+    }
+    if sleeve.finish().is_some() {
+        out.push(line.len() - 1);
+    }
+    out
+}
+
+/// Simplify the line using the sleeve-fitting simplifier; see [Sleeve].
+pub fn sleeve_reduce(line: &[Point<Precision, 2>], epsilon: Precision) -> Vec<Point<Precision, 2>> {
+    let mut sleeve = Sleeve::new(epsilon);
+    let mut out: Vec<_> = line.iter().filter_map(|p| sleeve.push(*p)).collect();
+    out.extend(sleeve.finish());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::rdp::proj_dist2;
+    use crate::test_utils::make_line;
+
+    /// Every dropped point is within epsilon of the edge which replaced it.
+    fn assert_bounded(line: &[Point<Precision, 2>], keep: &[usize], epsilon: Precision) {
+        assert_eq!(keep[0], 0);
+        assert_eq!(keep[keep.len() - 1], line.len() - 1);
+        for pair in keep.windows(2) {
+            let (a, b) = (&line[pair[0]], &line[pair[1]]);
+            for p in &line[pair[0]..=pair[1]] {
+                let d2 = proj_dist2(a, b, p, (b - a).norm_squared());
+                assert!(
+                    d2.sqrt() <= epsilon + 1e-12,
+                    "{p:?} too far from {a:?}-{b:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn straight() {
+        let line = make_line(
+            (0..50)
+                .map(|i| [i as Precision, if i % 2 == 0 { 0.04 } else { -0.04 }])
+                .collect(),
+        );
+        let keep = sleeve_keep(&line, 0.1);
+        assert_eq!(keep, vec![0, line.len() - 1]);
+    }
+
+    #[test]
+    fn wiggly() {
+        let line = make_line(
+            (0..200)
+                .map(|i| {
+                    let x = i as Precision * 0.1;
+                    [x, x.sin() + (x * 5.0).sin() * 0.05]
+                })
+                .collect(),
+        );
+        for epsilon in [0.01, 0.1, 0.5] {
+            let keep = sleeve_keep(&line, epsilon);
+            assert!(keep.len() < line.len());
+            assert_bounded(&line, &keep, epsilon);
+            let reduced: Vec<_> = keep.iter().map(|i| line[*i]).collect();
+            assert_eq!(sleeve_reduce(&line, epsilon), reduced);
+        }
+    }
+
+    #[test]
+    fn doubling_back() {
+        // the last point is within the sleeve's angle, but would leave the middle point beyond the edge's end
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [1.0, 0.0]]);
+        let keep = sleeve_keep(&line, 0.1);
+        assert_eq!(keep, vec![0, 1, 2]);
+        assert_bounded(&line, &keep, 0.1);
+    }
+
+    #[test]
+    fn short() {
+        assert!(sleeve_keep(&[], 1.0).is_empty());
+        assert_eq!(sleeve_keep(&make_line(vec![[0.0, 0.0]]), 1.0), vec![0]);
+        let two = make_line(vec![[0.0, 0.0], [1.0, 0.0]]);
+        assert_eq!(sleeve_keep(&two, 1.0), vec![0, 1]);
+    }
+}
@@ -1,202 +1,446 @@
 //! Simplify a linestring using the [Visvalingam-Whyatt](https://en.wikipedia.org/wiki/Visvalingam%E2%80%93Whyatt_algorithm) algorithm.
-use crate::Precision;
-use nalgebra::{distance, Point};
+//!
+//! Points are removed in order of increasing effective area.
+//! Ties between equal areas (common in symmetric shapes) are broken by removing the point with the lowest index first,
+//! so output is deterministic and does not depend on how the priority queue happens to be arranged.
+//! Points with NaN areas (from non-finite coordinates) are treated as having infinite area, so are removed last.
+//! Closed lines are never reduced below 3 points, and open lines never lose their endpoints.
+use super::{Stats, TooFewPoints};
+use crate::error::Result;
+use crate::metric::{DistanceMetric, Euclidean};
+use crate::{CurveKind, Precision};
+use nalgebra::Point;
 use std::cmp::{Ord, Ordering, PartialOrd};
-use std::collections::{BinaryHeap, HashSet};
-
-/// Area of a triangle based on the locations of its 3 corners.
-fn tri_area<const D: usize>(
-    p1: &Point<Precision, D>,
-    p2: &Point<Precision, D>,
-    p3: &Point<Precision, D>,
-) -> Precision {
-    let s1 = distance(p1, p2);
-    let s2 = distance(p2, p3);
-    let s3 = distance(p3, p1);
-
-    let s = (s1 + s2 + s3) / 2.0;
-    (s * (s - s1) * (s - s2) * (s - s3)).sqrt()
-}
+use std::collections::BinaryHeap;
 
 /// A triangle whose corners are indices into some reference table of points.
 #[derive(Copy, Clone, Debug)]
-struct Triangle<const D: usize> {
+struct Triangle {
     pub indices: (usize, usize, usize),
     pub area: Precision,
 }
 
-impl<const D: usize> Triangle<D> {
-    fn from_indices(all_points: &[Point<Precision, D>], indices: (usize, usize, usize)) -> Self {
-        Self {
+impl Triangle {
+    /// NaN areas are replaced by infinity, so that those points are removed last.
+    fn new(indices: (usize, usize, usize), area: Precision) -> Self {
+        let area = if area.is_nan() {
+            Precision::INFINITY
+        } else {
+            area
+        };
+        Self { indices, area }
+    }
+
+    fn from_indices<M: DistanceMetric<D>, const D: usize>(
+        all_points: &[Point<Precision, D>],
+        indices: (usize, usize, usize),
+        metric: &M,
+    ) -> Self {
+        Self::new(
             indices,
-            area: tri_area(
+            metric.triangle_area(
                 &all_points[indices.0],
                 &all_points[indices.1],
                 &all_points[indices.2],
             ),
-        }
-    }
-
-    /// Check whether the triangle is invalid, i.e. its corner indices appear in the given disallowed set.
-    fn is_valid(&self, skipped: &HashSet<usize>) -> bool {
-        let is_invalid = skipped.contains(&self.indices.0)
-            || skipped.contains(&self.indices.1)
-            || skipped.contains(&self.indices.2);
-        !is_invalid
-    }
-
-    /// Find a triangle with the same midpoint and end points which do not appear in the `skipped` set.
-    ///
-    /// The left index goes leftwards, the right index goes rightwards.
-    fn get_replacement(
-        &self,
-        points: &[Point<Precision, D>],
-        skipped: &HashSet<usize>,
-        wrapping: bool,
-    ) -> Option<Self> {
-        let (new_left, new_right) = neighbours_not_in(
-            self.indices.0,
-            self.indices.2,
-            skipped,
-            points.len(),
-            wrapping,
-        )?;
-        Some(Self::from_indices(
-            points,
-            (new_left, self.indices.1, new_right),
-        ))
-    }
-
-    fn center_index(&self) -> usize {
-        self.indices.1
+        )
     }
 }
 
-impl<const D: usize> PartialEq for Triangle<D> {
+impl PartialEq for Triangle {
     fn eq(&self, other: &Self) -> bool {
-        self.area == other.area
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl<const D: usize> Eq for Triangle<D> {}
+impl Eq for Triangle {}
 
-impl<const D: usize> PartialOrd for Triangle<D> {
+impl PartialOrd for Triangle {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<const D: usize> Ord for Triangle<D> {
+impl Ord for Triangle {
+    /// Smaller areas, then lower center indices, are greater, so that [BinaryHeap] pops them first.
     fn cmp(&self, other: &Self) -> Ordering {
-        // reversed operands for min-heap
-        other
-            .area
-            .partial_cmp(&self.area)
-            .unwrap_or(Ordering::Equal)
+        let key = |t: &Self| (t.area, t.indices.1, t.indices.0, t.indices.2);
+        let (a, b) = (key(self), key(other));
+        b.0.total_cmp(&a.0)
+            .then(b.1.cmp(&a.1))
+            .then(b.2.cmp(&a.2))
+            .then(b.3.cmp(&a.3))
     }
 }
 
-/// Scan left and right from `left` and `right` for non-`skipped` indices.
-fn neighbours_not_in(
-    mut left: usize,
-    mut right: usize,
-    skipped: &HashSet<usize>,
-    len: usize,
-    wrapping: bool,
-) -> Option<(usize, usize)> {
-    if skipped.len() >= len - 3 {
-        return None;
-    }
-    while skipped.contains(&left) {
-        if left > 0 {
-            left -= 1;
-        } else if wrapping {
-            left = len - 1;
-        } else {
-            return None;
+/// The state of a VW decimation, which removes one point at a time.
+///
+/// Remaining points form a doubly-linked list, so neighbours are found in constant time,
+/// and removed points are marked in a mask.
+/// Triangles in the queue whose corners are no longer neighbours are stale, and skipped when popped.
+struct VwState<'a, const D: usize, M> {
+    line: &'a [Point<Precision, D>],
+    closed: bool,
+    metric: &'a M,
+    queue: BinaryHeap<Triangle>,
+    removed: Vec<bool>,
+    n_removed: usize,
+    prev: Vec<Option<usize>>,
+    next: Vec<Option<usize>>,
+    stats: Stats,
+}
+
+impl<'a, const D: usize, M: DistanceMetric<D>> VwState<'a, D, M> {
+    fn new(line: &'a [Point<Precision, D>], closed: bool, metric: &'a M) -> Self {
+        let len = line.len();
+        let wrap = closed && len > 2;
+        let prev = (0..len)
+            .map(|idx| match idx.checked_sub(1) {
+                Some(p) => Some(p),
+                None => wrap.then(|| len - 1),
+            })
+            .collect();
+        let next = (0..len)
+            .map(|idx| {
+                if idx + 1 < len {
+                    Some(idx + 1)
+                } else {
+                    wrap.then_some(0)
+                }
+            })
+            .collect();
+        let mut state = Self {
+            line,
+            closed,
+            metric,
+            queue: BinaryHeap::with_capacity(len),
+            removed: vec![false; len],
+            n_removed: 0,
+            prev,
+            next,
+            stats: Stats::default(),
+        };
+        for (idx, area) in metric.triangle_areas(line).into_iter().enumerate() {
+            state
+                .queue
+                .push(Triangle::new((idx, idx + 1, idx + 2), area));
+        }
+        if wrap {
+            state.queue.extend(state.triangle(0));
+            state.queue.extend(state.triangle(len - 1));
         }
+        state
     }
-    while skipped.contains(&right) {
-        if right == len - 1 {
-            right += 1;
-        } else if wrapping {
-            right = 0;
-        } else {
+
+    /// The triangle centred on a remaining point, if it has neighbours on both sides.
+    fn triangle(&self, center: usize) -> Option<Triangle> {
+        let indices = (self.prev[center]?, center, self.next[center]?);
+        Some(Triangle::from_indices(self.line, indices, self.metric))
+    }
+
+    fn is_current(&self, tri: &Triangle) -> bool {
+        let (l, c, r) = tri.indices;
+        !self.removed[c] && self.prev[c] == Some(l) && self.next[c] == Some(r)
+    }
+
+    /// Remove the next point, returning the triangle it was the center of.
+    ///
+    /// None if no more points can be removed.
+    fn remove_next(&mut self) -> Option<Triangle> {
+        let min_remaining = if self.closed { 3 } else { 2 };
+        if self.line.len() - self.n_removed <= min_remaining {
             return None;
         }
+        loop {
+            let tri = self.queue.pop()?;
+            self.stats.points_visited += 1;
+            if !self.is_current(&tri) {
+                continue;
+            }
+            self.stats.cache_hits += 1;
+            let (l, c, r) = tri.indices;
+            self.removed[c] = true;
+            self.n_removed += 1;
+            self.next[l] = Some(r);
+            self.prev[r] = Some(l);
+            for neighbour in [l, r] {
+                if let Some(t) = self.triangle(neighbour) {
+                    self.stats.triangles_recomputed += 1;
+                    self.queue.push(t);
+                }
+            }
+            return Some(tri);
+        }
     }
-    Some((left, right))
 }
 
 fn vw_drop<const D: usize>(
     line: &[Point<Precision, D>],
     n_points: usize,
     closed: bool,
-) -> HashSet<usize> {
-    if line.len() <= 2.min(n_points) {
-        return HashSet::with_capacity(0);
-    }
-    let mut queue = BinaryHeap::default();
-    let mut drop = HashSet::with_capacity(line.len() - n_points);
-    for idx in 0..(line.len() - 2) {
-        queue.push(Triangle::from_indices(line, (idx, idx + 1, idx + 2)))
-    }
-    if closed {
-        let len = line.len();
-        queue.push(Triangle::from_indices(line, (len - 2, len - 1, 0)));
-        queue.push(Triangle::from_indices(line, (len - 1, 0, 1)));
-    }
-    while line.len() - drop.len() > n_points {
-        if let Some(tri) = queue.pop() {
-            if tri.is_valid(&drop) {
-                drop.insert(tri.center_index());
-            } else if let Some(repl) = tri.get_replacement(line, &drop, closed) {
-                queue.push(repl);
-            } else {
-                break;
-            }
-        } else {
+) -> Vec<bool> {
+    vw_drop_with_stats(line, n_points, closed, &Euclidean).0
+}
+
+/// Whether each point is removed.
+fn vw_drop_with_stats<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    closed: bool,
+    metric: &M,
+) -> (Vec<bool>, Stats) {
+    let mut state = VwState::new(line, closed, metric);
+    while line.len() - state.n_removed > n_points {
+        if state.remove_next().is_none() {
             break;
         }
     }
-    drop
+    (state.removed, state.stats)
+}
+
+/// A single point removal made by VW.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VwStep {
+    /// Index of the removed point.
+    pub removed: usize,
+    /// Indices of the remaining points either side of the removed point at the time of removal.
+    pub neighbours: (usize, usize),
+    /// Area of the triangle formed by the removed point and its neighbours.
+    pub area: Precision,
+}
+
+/// Iterator over the decisions made by VW, in order, until no more points can be removed.
+///
+/// Useful for visualising or debugging the algorithm.
+/// Taking `line.len() - n_points` steps removes the same points as [vw_reduce].
+pub struct VwSteps<'a, const D: usize> {
+    state: VwState<'a, D, Euclidean>,
+}
+
+impl<'a, const D: usize> VwSteps<'a, D> {
+    /// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+    pub fn new(line: &'a [Point<Precision, D>], kind: impl Into<CurveKind>) -> Self {
+        Self {
+            state: VwState::new(line, kind.into().is_closed(), &Euclidean),
+        }
+    }
+}
+
+impl<'a, const D: usize> Iterator for VwSteps<'a, D> {
+    type Item = VwStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tri = self.state.remove_next()?;
+        Some(VwStep {
+            removed: tri.indices.1,
+            neighbours: (tri.indices.0, tri.indices.2),
+            area: tri.area,
+        })
+    }
 }
 
 /// Return the indices of points on the linestring to be kept if decimated by VW.
 ///
-/// `closed = true` where the linestring represents a polygon and there is an edge from the last point to the first.
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
 pub fn vw_keep<const D: usize>(
     line: &[Point<Precision, D>],
     n_points: usize,
-    closed: bool,
+    kind: impl Into<CurveKind>,
 ) -> Vec<usize> {
+    let closed = kind.into().is_closed();
     let drop = vw_drop(line, n_points, closed);
-    (0..line.len()).filter(|idx| !drop.contains(idx)).collect()
+    (0..line.len()).filter(|idx| !drop[*idx]).collect()
+}
+
+/// As [vw_keep], but also returning [Stats] about the work done.
+pub fn vw_keep_with_stats<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> (Vec<usize>, Stats) {
+    let closed = kind.into().is_closed();
+    let (drop, stats) = vw_drop_with_stats(line, n_points, closed, &Euclidean);
+    let keep = (0..line.len()).filter(|idx| !drop[*idx]).collect();
+    (keep, stats)
+}
+
+/// As [vw_keep], but returning whether each point is kept, rather than the indices of kept points.
+///
+/// Useful for filtering other arrays of per-point data alongside the line.
+pub fn vw_keep_mask<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+) -> Vec<bool> {
+    let closed = kind.into().is_closed();
+    let drop = vw_drop(line, n_points, closed);
+    drop.into_iter().map(|d| !d).collect()
 }
 
 /// Decimate the linestring using VW.
 ///
-/// `closed = true` where the linestring represents a polygon and there is an edge from the last point to the first.
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
 pub fn vw_reduce<const D: usize>(
     line: &[Point<Precision, D>],
     n_points: usize,
-    closed: bool,
+    kind: impl Into<CurveKind>,
 ) -> Vec<Point<Precision, D>> {
+    let closed = kind.into().is_closed();
     let drop = vw_drop(line, n_points, closed);
     line.iter()
         .enumerate()
-        .filter_map(|(idx, p)| if drop.contains(&idx) { None } else { Some(*p) })
+        .filter_map(|(idx, p)| if drop[idx] { None } else { Some(*p) })
+        .collect()
+}
+
+/// As [vw_keep], but measuring triangle areas with the given [DistanceMetric].
+pub fn vw_keep_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+    metric: &M,
+) -> Vec<usize> {
+    let closed = kind.into().is_closed();
+    let drop = vw_drop_with_stats(line, n_points, closed, metric).0;
+    (0..line.len()).filter(|idx| !drop[*idx]).collect()
+}
+
+/// As [vw_reduce], but measuring triangle areas with the given [DistanceMetric].
+pub fn vw_reduce_metric<const D: usize, M: DistanceMetric<D>>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+    metric: &M,
+) -> Vec<Point<Precision, D>> {
+    vw_keep_metric(line, n_points, kind, metric)
+        .into_iter()
+        .map(|idx| line[idx])
+        .collect()
+}
+
+/// As [vw_reduce], with an explicit policy for lines which already have fewer than `n_points` points.
+///
+/// Densification treats the line as open.
+pub fn vw_reduce_to<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    kind: impl Into<CurveKind>,
+    too_few: TooFewPoints,
+) -> Result<Vec<Point<Precision, D>>> {
+    let closed = kind.into().is_closed();
+    if let Some(out) = too_few.handle(line, n_points) {
+        return out;
+    }
+    Ok(vw_reduce(line, n_points, closed))
+}
+
+/// The effective area of each point: the area of its triangle when it is removed by VW,
+/// or of any earlier removal if that was larger, so that areas never decrease in removal order.
+///
+/// Points which are never removed (the ends of an open line, or the last 3 points of a closed one) have infinite area.
+/// Keeping the points whose effective area is above some threshold gives the same result as VW with the matching number of points
+/// (up to ties), so the ranking can be computed once and the line sliced at many levels of detail.
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::simplify::vw::{vw_keep, vw_rank};
+///
+/// let line: Vec<simples::Point<Precision, 2>> = vec![
+///     [0.0, 0.0].into(),
+///     [1.0, 0.1].into(),
+///     [2.0, 1.0].into(),
+///     [3.0, 0.0].into(),
+/// ];
+/// let ranks = vw_rank(&line, false);
+/// assert_eq!(ranks[0], Precision::INFINITY);
+/// let keep: Vec<_> = (0..line.len()).filter(|i| ranks[*i] > 0.5).collect();
+/// assert_eq!(keep, vw_keep(&line, 3, false));
+/// ```
+pub fn vw_rank<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Vec<Precision> {
+    let mut state = VwState::new(line, kind.into().is_closed(), &Euclidean);
+    let mut ranks = vec![Precision::INFINITY; line.len()];
+    let mut max_area = Precision::NEG_INFINITY;
+    while let Some(tri) = state.remove_next() {
+        max_area = max_area.max(tri.area);
+        ranks[tri.indices.1] = max_area;
+    }
+    ranks
+}
+
+/// As [vw_keep] on an open line, but simplifying one window of about `window` points at a time,
+/// so that memory use is bounded by the window size rather than the length of the line.
+///
+/// Consecutive windows share a point, which is always kept, and each window is simplified separately
+/// to its share of the `n_points` (in proportion to its size).
+/// Points near window boundaries may be kept where whole-line VW would remove them, and vice versa,
+/// so larger windows give results closer to [vw_keep].
+/// Every window keeps its ends, so at least `(line.len() - 1).div_ceil(window - 1) + 1` points are kept.
+///
+/// `window` is at least 3.
+pub fn vw_keep_windowed<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    window: usize,
+) -> Vec<usize> {
+    if line.len() <= 2 {
+        return (0..line.len()).collect();
+    }
+    let step = window.max(3) - 1;
+    let last = line.len() - 1;
+    let bounds: Vec<_> = (0..last.div_ceil(step))
+        .map(|w| (w * step, ((w + 1) * step).min(last)))
+        .collect();
+
+    // share out the interior points by the largest remainder method
+    let total_interior = line.len() - bounds.len() - 1;
+    let budget = n_points
+        .saturating_sub(bounds.len() + 1)
+        .min(total_interior);
+    let quota = |(start, end): (usize, usize)| {
+        let exact = budget as u128 * (end - start - 1) as u128;
+        let total = total_interior.max(1) as u128;
+        ((exact / total) as usize, exact % total)
+    };
+    let mut shares: Vec<_> = bounds.iter().map(|b| quota(*b).0).collect();
+    let mut by_remainder: Vec<_> = (0..bounds.len()).collect();
+    by_remainder.sort_by_key(|w| std::cmp::Reverse(quota(bounds[*w]).1));
+    let allocated: usize = shares.iter().sum();
+    for w in by_remainder.into_iter().take(budget - allocated) {
+        shares[w] += 1;
+    }
+
+    let mut out = Vec::with_capacity(n_points.max(bounds.len() + 1));
+    out.push(0);
+    for ((start, end), share) in bounds.into_iter().zip(shares) {
+        let kept = vw_keep(&line[start..=end], share + 2, false);
+        out.extend(kept.into_iter().skip(1).map(|idx| idx + start));
+    }
+    out
+}
+
+/// Decimate the linestring using VW one window at a time; see [vw_keep_windowed].
+pub fn vw_reduce_windowed<const D: usize>(
+    line: &[Point<Precision, D>],
+    n_points: usize,
+    window: usize,
+) -> Vec<Point<Precision, D>> {
+    vw_keep_windowed(line, n_points, window)
+        .into_iter()
+        .map(|idx| line[idx])
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::make_line;
+    use crate::test_utils::{make_line, tol};
 
     fn assert_reduce(
-        orig: Vec<[f64; 2]>,
-        expected: Vec<[f64; 2]>,
+        orig: Vec<[Precision; 2]>,
+        expected: Vec<[Precision; 2]>,
         n_points: Option<usize>,
         closed: bool,
     ) {
@@ -234,11 +478,12 @@ mod tests {
                 [2.1, 0.0],
                 [3.0, 0.0],
             ],
+            // once the first spike is gone, its neighbours are collinear
             vec![
                 [0.0, 0.0],
-                [0.9, 0.0],
                 [1.1, 0.0],
                 [1.9, 0.0],
+                [2.0, 1.0],
                 [2.1, 0.0],
                 [3.0, 0.0],
             ],
@@ -266,4 +511,171 @@ mod tests {
             false,
         );
     }
+
+    #[test]
+    fn steps() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.9, 0.0],
+            [1.0, 1.0],
+            [1.1, 0.0],
+            [2.0, 0.0],
+        ]);
+        let first = VwSteps::new(&line, false).next().unwrap();
+        assert_eq!(first.removed, 2);
+        assert_eq!(first.neighbours, (1, 3));
+        assert!((first.area - 0.1).abs() < tol(1e-9));
+    }
+
+    #[test]
+    fn nan_removed_last() {
+        let mut line = make_line((0..7).map(|i| [i as Precision, 0.0]).collect());
+        line[3].y = Precision::NAN;
+        // long enough for the vectorised areas too
+        assert!(Euclidean.triangle_areas(&line)[1..4]
+            .iter()
+            .all(|a| a.is_nan()));
+        // the collinear points away from the NaN go first
+        assert_eq!(vw_keep(&line, 5, false), vec![0, 2, 3, 4, 6]);
+        assert_eq!(vw_rank(&line, false)[3], Precision::INFINITY);
+    }
+
+    #[test]
+    fn mask_matches_keep() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.1],
+            [2.0, 1.0],
+            [3.0, 0.0],
+            [4.0, 0.0],
+        ]);
+        let mask = vw_keep_mask(&line, 3, false);
+        let from_mask: Vec<_> = (0..line.len()).filter(|idx| mask[*idx]).collect();
+        assert_eq!(from_mask, vw_keep(&line, 3, false));
+    }
+
+    #[test]
+    fn stats() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.9, 0.0],
+            [1.0, 1.0],
+            [1.1, 0.0],
+            [2.0, 0.0],
+        ]);
+        let (keep, stats) = vw_keep_with_stats(&line, 3, false);
+        assert_eq!(keep, vw_keep(&line, 3, false));
+        assert_eq!(stats.cache_hits, 2);
+        // both neighbours of the spike, then the remaining neighbour of the next point
+        assert_eq!(stats.triangles_recomputed, 3);
+        assert!(stats.points_visited >= stats.cache_hits);
+    }
+
+    /// A square with `per_side` evenly spaced points on each side, starting at the origin.
+    fn subdivided_square(per_side: usize) -> Vec<Point<Precision, 2>> {
+        let n = per_side as Precision;
+        let side = |i: usize| -> [Precision; 2] {
+            let t = (i % per_side) as Precision;
+            match i / per_side {
+                0 => [t, 0.0],
+                1 => [n, t],
+                2 => [n - t, n],
+                _ => [0.0, n - t],
+            }
+        };
+        (0..4 * per_side).map(|i| side(i).into()).collect()
+    }
+
+    #[test]
+    fn ties_remove_lowest_index() {
+        let square = subdivided_square(1);
+        let removed: Vec<_> = VwSteps::new(&square, true).map(|s| s.removed).collect();
+        assert_eq!(removed, vec![0]);
+
+        // midpoints all have zero area, then the corners all tie
+        let square = subdivided_square(2);
+        let removed: Vec<_> = VwSteps::new(&square, true).map(|s| s.removed).collect();
+        assert_eq!(removed, vec![1, 3, 5, 7, 0]);
+    }
+
+    #[test]
+    fn symmetric_deterministic() {
+        let square = subdivided_square(5);
+        assert_eq!(vw_keep(&square, 4, true), vec![0, 5, 10, 15]);
+        for _ in 0..10 {
+            assert_eq!(vw_keep(&square, 3, true), vec![5, 10, 15]);
+        }
+        assert_eq!(vw_keep(&square, 2, false), vec![0, 19]);
+        // the open line has no last side, so the corner before it has the smallest triangle
+        assert_eq!(vw_keep(&square, 4, false), vec![0, 5, 10, 19]);
+    }
+
+    #[test]
+    fn minimum_sizes() {
+        let square = subdivided_square(2);
+        assert_eq!(vw_keep(&square, 0, true).len(), 3);
+        assert_eq!(vw_keep(&square, 0, false), vec![0, 7]);
+        assert_eq!(VwSteps::new(&square, false).count(), 6);
+    }
+
+    #[test]
+    fn rank() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.9, 0.0],
+            [1.0, 1.0],
+            [1.1, 0.0],
+            [1.9, 0.0],
+            [2.0, 0.5],
+            [2.1, 0.0],
+            [3.0, 0.0],
+        ]);
+        let ranks = vw_rank(&line, false);
+        assert_eq!(ranks[0], Precision::INFINITY);
+        assert_eq!(ranks[7], Precision::INFINITY);
+        // the collinear neighbours of a removed spike inherit its area
+        assert!((ranks[5] - 0.05).abs() < tol(1e-9));
+        assert_eq!(ranks[4], ranks[5]);
+
+        let mut sorted: Vec<_> = ranks.iter().copied().enumerate().collect();
+        sorted.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        for w in sorted.windows(2).filter(|w| w[0].1 > w[1].1) {
+            let n = sorted.iter().filter(|(_, r)| *r >= w[0].1).count();
+            let from_rank: Vec<_> = (0..line.len()).filter(|i| ranks[*i] >= w[0].1).collect();
+            assert_eq!(from_rank, vw_keep(&line, n, false));
+        }
+
+        let square = subdivided_square(2);
+        let ranks = vw_rank(&square, true);
+        assert_eq!(ranks.iter().filter(|r| r.is_infinite()).count(), 3);
+        assert_eq!(ranks[1], 0.0);
+    }
+
+    #[test]
+    fn windowed() {
+        let line: Vec<Point<Precision, 2>> = (0..101)
+            .map(|i| {
+                [
+                    i as Precision,
+                    (i as Precision / 7.0).sin() + (i % 3) as Precision * 0.1,
+                ]
+                .into()
+            })
+            .collect();
+        // one window is whole-line VW
+        assert_eq!(vw_keep_windowed(&line, 30, 200), vw_keep(&line, 30, false));
+        for window in [3, 10, 25, 50] {
+            let keep = vw_keep_windowed(&line, 30, window);
+            let n_windows = 100_usize.div_ceil(window - 1);
+            assert_eq!(keep.len(), 30.max(n_windows + 1), "window {window}");
+            assert!(keep.windows(2).all(|w| w[0] < w[1]));
+            assert_eq!((keep[0], keep[keep.len() - 1]), (0, 100));
+            for boundary in (0..=100).step_by(window - 1) {
+                assert!(keep.contains(&boundary));
+            }
+        }
+        assert_eq!(vw_keep_windowed(&line, 200, 10).len(), line.len());
+        assert_eq!(vw_reduce_windowed(&line, 2, 1000), vec![line[0], line[100]]);
+        assert_eq!(vw_keep_windowed(&line[..2], 1, 10), vec![0, 1]);
+    }
 }
@@ -0,0 +1,203 @@
+//! Kalman filtering and Rauch-Tung-Striebel smoothing of timed trajectories.
+//!
+//! Uses a constant-velocity motion model, independently on each axis:
+//! the object is assumed to move at a constant velocity, perturbed by random accelerations.
+//!
+//! The filter always computes in double precision, even with the `f32` feature,
+//! as its covariances span too many orders of magnitude for single precision.
+use crate::error::{Error, Result};
+use crate::timed::{Time, TimedPoint};
+use crate::Precision;
+use nalgebra::{Matrix2, Vector2};
+
+/// The type the filter computes in.
+type Float = f64;
+
+/// Constant-velocity Kalman filter and smoother.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Kalman {
+    process_noise: Precision,
+    measurement_noise: Precision,
+}
+
+/// State estimates for a single axis at every timestep.
+struct Pass {
+    predicted: Vec<(Vector2<Float>, Matrix2<Float>)>,
+    filtered: Vec<(Vector2<Float>, Matrix2<Float>)>,
+}
+
+impl Kalman {
+    /// `process_noise` is the spectral density of the random acceleration (in distance² / time³):
+    /// larger values let the estimate turn and change speed more quickly.
+    /// `measurement_noise` is the variance of the position measurements (in distance²):
+    /// larger values smooth more.
+    pub fn new(process_noise: Precision, measurement_noise: Precision) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+        }
+    }
+
+    fn transition(dt: Time) -> Matrix2<Float> {
+        Matrix2::new(1.0, dt, 0.0, 1.0)
+    }
+
+    fn process_covariance(&self, dt: Time) -> Matrix2<Float> {
+        let dt2 = dt * dt;
+        Matrix2::new(dt2 * dt / 3.0, dt2 / 2.0, dt2 / 2.0, dt) * Float::from(self.process_noise)
+    }
+
+    /// Forward pass over one axis' positions.
+    fn forward(&self, times: &[Time], positions: &[Float]) -> Pass {
+        let r = Float::from(self.measurement_noise);
+        let mut predicted = Vec::with_capacity(positions.len());
+        let mut filtered = Vec::with_capacity(positions.len());
+        // start at the first measurement, with unknown velocity
+        let mut x = Vector2::new(positions[0], 0.0);
+        let mut p = Matrix2::new(r, 0.0, 0.0, 1e12);
+        for (idx, z) in positions.iter().enumerate() {
+            if idx > 0 {
+                let dt = times[idx] - times[idx - 1];
+                let f = Self::transition(dt);
+                x = f * x;
+                p = f * p * f.transpose() + self.process_covariance(dt);
+            }
+            predicted.push((x, p));
+            // update with the position measurement
+            let innovation_cov = p[(0, 0)] + r;
+            let gain = Vector2::new(p[(0, 0)], p[(1, 0)]) / innovation_cov;
+            x += gain * (z - x[0]);
+            p -= gain * Vector2::new(p[(0, 0)], p[(0, 1)]).transpose();
+            filtered.push((x, p));
+        }
+        Pass {
+            predicted,
+            filtered,
+        }
+    }
+
+    /// Backward (Rauch-Tung-Striebel) pass, returning smoothed positions.
+    fn backward(&self, times: &[Time], pass: &Pass) -> Vec<Float> {
+        let n = pass.filtered.len();
+        let mut smoothed = pass.filtered.clone();
+        for idx in (0..(n - 1)).rev() {
+            let (x_f, p_f) = pass.filtered[idx];
+            let (x_p, p_p) = pass.predicted[idx + 1];
+            let (x_s, p_s) = smoothed[idx + 1];
+            let f = Self::transition(times[idx + 1] - times[idx]);
+            let Some(p_p_inv) = p_p.try_inverse() else {
+                continue;
+            };
+            let c = p_f * f.transpose() * p_p_inv;
+            smoothed[idx] = (x_f + c * (x_s - x_p), p_f + c * (p_s - p_p) * c.transpose());
+        }
+        smoothed.into_iter().map(|(x, _)| x[0]).collect()
+    }
+
+    fn run<const D: usize>(
+        &self,
+        line: &[TimedPoint<D>],
+        smooth: bool,
+    ) -> Result<Vec<TimedPoint<D>>> {
+        if line.windows(2).any(|w| w[1].time < w[0].time) {
+            return Err(Error::invalid("line", "timestamps must not decrease"));
+        }
+        if line.len() <= 1 {
+            return Ok(line.to_vec());
+        }
+        let times: Vec<_> = line.iter().map(|p| p.time).collect();
+        let mut out = line.to_vec();
+        for dim in 0..D {
+            let positions: Vec<_> = line.iter().map(|p| Float::from(p.point[dim])).collect();
+            let pass = self.forward(&times, &positions);
+            let estimates = if smooth {
+                self.backward(&times, &pass)
+            } else {
+                pass.filtered.iter().map(|(x, _)| x[0]).collect()
+            };
+            for (p, e) in out.iter_mut().zip(estimates) {
+                p.point[dim] = e as Precision;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Estimate each position using only the measurements up to that point,
+    /// e.g. for real-time use.
+    ///
+    /// Fails if the timestamps decrease.
+    pub fn filter<const D: usize>(&self, line: &[TimedPoint<D>]) -> Result<Vec<TimedPoint<D>>> {
+        self.run(line, false)
+    }
+
+    /// Estimate each position using all of the measurements.
+    ///
+    /// Unlike most smoothers in this crate, the endpoints may move.
+    /// Fails if the timestamps decrease.
+    pub fn smooth<const D: usize>(&self, line: &[TimedPoint<D>]) -> Result<Vec<TimedPoint<D>>> {
+        self.run(line, true)
+    }
+}
+
+/// Smooth a timed trajectory; see [Kalman::smooth].
+pub fn smooth_kalman<const D: usize>(
+    line: &[TimedPoint<D>],
+    process_noise: Precision,
+    measurement_noise: Precision,
+) -> Result<Vec<TimedPoint<D>>> {
+    Kalman::new(process_noise, measurement_noise).smooth(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy_track() -> (Vec<TimedPoint<2>>, Vec<TimedPoint<2>>) {
+        let noise = [0.3, -0.2, 0.1, -0.3, 0.25, -0.1, 0.2, -0.25, 0.15, -0.2];
+        let truth: Vec<_> = (0..30)
+            .map(|i| TimedPoint::new([i as Precision * 2.0, i as Precision].into(), i as Time))
+            .collect();
+        let noisy = truth
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let mut p = *p;
+                p.point.y += noise[i % noise.len()];
+                p
+            })
+            .collect();
+        (truth, noisy)
+    }
+
+    fn error(a: &[TimedPoint<2>], b: &[TimedPoint<2>]) -> Precision {
+        a.iter()
+            .zip(b.iter())
+            .map(|(p, q)| (p.point - q.point).norm_squared())
+            .sum()
+    }
+
+    #[test]
+    fn smoothing_reduces_error() {
+        let (truth, noisy) = noisy_track();
+        let kalman = Kalman::new(0.01, 0.05);
+        let filtered = kalman.filter(&noisy).unwrap();
+        let smoothed = kalman.smooth(&noisy).unwrap();
+        assert!(error(&truth, &smoothed) < error(&truth, &noisy) / 4.0);
+        assert!(error(&truth, &smoothed) < error(&truth, &filtered));
+        // times are untouched
+        assert!(smoothed
+            .iter()
+            .zip(noisy.iter())
+            .all(|(a, b)| a.time == b.time));
+    }
+
+    #[test]
+    fn decreasing_time() {
+        let line = vec![
+            TimedPoint::new([0.0].into(), 1.0),
+            TimedPoint::new([1.0].into(), 0.0),
+        ];
+        assert!(smooth_kalman(&line, 1.0, 1.0).is_err());
+    }
+}
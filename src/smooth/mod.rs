@@ -0,0 +1,1973 @@
+//! Smooth linestrings.
+//!
+//! Linestrings are smoothed if they keep the same number of points, but move them around.
+use crate::bvh::Aabb;
+use crate::error::{Error, Result};
+use crate::metric::{DistanceMetric, Euclidean};
+use crate::util::normalise_anchors;
+use crate::{check_finite, CurveKind, Precision};
+use lru::LruCache;
+use nalgebra::{DMatrix, Point, SVector};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::ops::{Bound, RangeBounds};
+
+pub mod kalman;
+pub mod tv;
+
+fn mean<const D: usize>(
+    points: impl ExactSizeIterator<Item = Point<Precision, D>>,
+) -> Point<Precision, D> {
+    let n = points.len() as Precision;
+    Point::from(points.map(|p| p.coords).sum::<SVector<Precision, D>>() / n)
+}
+
+fn weighted_mean<const D: usize>(
+    point_weights: &[(Point<Precision, D>, Precision)],
+) -> Point<Precision, D> {
+    let (point, weight) = point_weights
+        .iter()
+        .map(|(point, weight)| (point * *weight, *weight))
+        .reduce(|prev, next| (prev.0 + next.0.coords, prev.1 + next.1))
+        .unwrap();
+    point / weight
+}
+
+/// How to find neighbours for points near the ends of a line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeMode {
+    /// Repeat the end points beyond the ends of the line.
+    Clamp,
+    /// Reflect the line through its end points,
+    /// so that the ends stay in place and straight ends stay straight.
+    Reflect,
+    /// Treat the line as closed, continuing from the other end.
+    Wrap,
+    /// Shrink the window so that it fits within the line, keeping it centred;
+    /// the end points are unchanged.
+    #[default]
+    Shrink,
+}
+
+impl EdgeMode {
+    /// The point at `idx`, which may be beyond either end of the line.
+    ///
+    /// Not for use with [EdgeMode::Shrink], which never looks beyond the ends.
+    fn extended<const D: usize>(
+        &self,
+        line: &[Point<Precision, D>],
+        idx: isize,
+    ) -> Point<Precision, D> {
+        let last = line.len() as isize - 1;
+        let clamp = |i: isize| line[i.clamp(0, last) as usize];
+        match self {
+            EdgeMode::Clamp | EdgeMode::Shrink => clamp(idx),
+            EdgeMode::Reflect if idx < 0 => reflect_point(&clamp(-idx), &line[0]),
+            EdgeMode::Reflect if idx > last => {
+                reflect_point(&clamp(2 * last - idx), &line[last as usize])
+            }
+            EdgeMode::Reflect => clamp(idx),
+            EdgeMode::Wrap => line[idx.rem_euclid(last + 1) as usize],
+        }
+    }
+}
+
+/// Smooth line using a moving average with `2*width + 1` points centred on the point of interest.
+/// At the ends of an open line, uses a smaller window (see [EdgeMode::Shrink]);
+/// a [CurveKind::Closed] line (or `true`) wraps around instead (see [EdgeMode::Wrap]).
+///
+/// Should probably only be used on a line already resampled with same-length gaps.
+pub fn smooth_moving_average<const D: usize>(
+    line: &[Point<Precision, D>],
+    width: usize,
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, D>> {
+    let edges = match kind.into() {
+        CurveKind::Open => EdgeMode::Shrink,
+        CurveKind::Closed => EdgeMode::Wrap,
+    };
+    smooth_moving_average_with_edges(line, width, edges)
+}
+
+/// As [smooth_moving_average], with the given handling of windows which extend beyond the ends of the line.
+///
+/// The window is always symmetric about the point of interest,
+/// and the output always has the same number of points as the input.
+pub fn smooth_moving_average_with_edges<const D: usize>(
+    line: &[Point<Precision, D>],
+    width: usize,
+    edges: EdgeMode,
+) -> Vec<Point<Precision, D>> {
+    let len = line.len();
+    if width == 0 || len <= 1 {
+        return line.to_vec();
+    }
+    (0..len)
+        .map(|idx| match edges {
+            EdgeMode::Shrink => {
+                let w = width.min(idx).min(len - 1 - idx);
+                mean(line[(idx - w)..=(idx + w)].iter().copied())
+            }
+            _ => {
+                let start = idx as isize - width as isize;
+                mean(
+                    (0..(2 * width + 1))
+                        .map(|offset| edges.extended(line, start + offset as isize)),
+                )
+            }
+        })
+        .collect()
+}
+
+/// How strongly smoothed points should be pulled back towards their original positions.
+///
+/// A fidelity of 0.0 keeps the smoothed position, 1.0 keeps the original position,
+/// and values in between blend the two linearly.
+#[derive(Copy, Clone, Debug)]
+pub enum Fidelity<'a> {
+    /// The same fidelity for every point.
+    Uniform(Precision),
+    /// A fidelity for each point; must be the same length as the line.
+    PerPoint(&'a [Precision]),
+}
+
+impl<'a> Fidelity<'a> {
+    fn get(&self, idx: usize) -> Precision {
+        match self {
+            Fidelity::Uniform(f) => *f,
+            Fidelity::PerPoint(fs) => fs[idx],
+        }
+    }
+}
+
+impl<'a> From<Precision> for Fidelity<'a> {
+    fn from(value: Precision) -> Self {
+        Fidelity::Uniform(value)
+    }
+}
+
+impl<'a> From<&'a [Precision]> for Fidelity<'a> {
+    fn from(value: &'a [Precision]) -> Self {
+        Fidelity::PerPoint(value)
+    }
+}
+
+/// Blend smoothed points back towards their original positions, in place.
+///
+/// Panics if the lines (or per-point fidelities) are of different lengths.
+pub fn apply_fidelity<'a, const D: usize>(
+    original: &[Point<Precision, D>],
+    smoothed: &mut [Point<Precision, D>],
+    fidelity: impl Into<Fidelity<'a>>,
+) {
+    let fidelity = fidelity.into();
+    assert_eq!(original.len(), smoothed.len(), "Lines of different length");
+    if let Fidelity::PerPoint(fs) = fidelity {
+        assert_eq!(original.len(), fs.len(), "Wrong number of fidelities");
+    }
+    for (idx, (orig, smooth)) in original.iter().zip(smoothed.iter_mut()).enumerate() {
+        let f = fidelity.get(idx);
+        *smooth += (orig - *smooth) * f;
+    }
+}
+
+/// As [smooth_moving_average], with the output blended towards the original line by `fidelity`.
+pub fn smooth_moving_average_with_fidelity<'a, const D: usize>(
+    line: &[Point<Precision, D>],
+    width: usize,
+    fidelity: impl Into<Fidelity<'a>>,
+) -> Vec<Point<Precision, D>> {
+    let mut out = smooth_moving_average(line, width, false);
+    apply_fidelity(line, &mut out, fidelity);
+    out
+}
+
+/// Smooth only the points in the given index range, leaving the rest of the line untouched.
+///
+/// `smoother` is any function which smooths a linestring, e.g. `|l| smooth_convolve(l, kernel, false)`.
+/// It is given only the points within the range,
+/// so its own end handling (e.g. reflection in [smooth_convolve]) applies at the range boundaries.
+/// The first and last points of the range are pinned in place,
+/// so the smoothed section still joins the rest of the line.
+///
+/// Panics if the range is out of bounds.
+pub fn smooth_range<const D: usize, F>(
+    line: &[Point<Precision, D>],
+    range: impl RangeBounds<usize>,
+    smoother: F,
+) -> Vec<Point<Precision, D>>
+where
+    F: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+{
+    let start = match range.start_bound() {
+        Bound::Included(s) => *s,
+        Bound::Excluded(s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(e) => e + 1,
+        Bound::Excluded(e) => *e,
+        Bound::Unbounded => line.len(),
+    };
+    let mut out = line.to_vec();
+    if end <= start + 2 {
+        return out;
+    }
+    let smoothed = smoother(&line[start..end]);
+    out[(start + 1)..(end - 1)].copy_from_slice(&smoothed[1..(smoothed.len() - 1)]);
+    out
+}
+
+/// Structs which can be use as a smoothing kernel.
+pub trait Kernel {
+    /// If a point is `dist` away from the point of interest, how much should we care about its position?
+    ///
+    /// None if too low to care about or otherwise invalid.
+    /// Weights do not need to sum up to 1.
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision>;
+
+    /// If the squared distance from the point of interest to another point is `dist2`, how much should we care about its position?
+    ///
+    /// None if too low to care about or otherwise invalid.
+    /// Weights do not need to sum up to 1.
+    fn weigh_dist2(&self, dist2: Precision) -> Option<Precision> {
+        self.weigh_dist(dist2.sqrt())
+    }
+
+    /// The weight of the point of interest.
+    fn at_center(&self) -> Precision;
+
+    /// Combine this kernel with another, multiplying their weights.
+    ///
+    /// A point is only weighted if both kernels care about it.
+    fn then<K: Kernel>(self, other: K) -> Compose<Self, K>
+    where
+        Self: Sized,
+    {
+        Compose::new(self, other)
+    }
+}
+
+/// Kernel whose weights are the product of the weights of two other kernels.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Compose<K1: Kernel, K2: Kernel> {
+    first: K1,
+    second: K2,
+}
+
+impl<K1: Kernel, K2: Kernel> Compose<K1, K2> {
+    pub fn new(first: K1, second: K2) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<K1: Kernel, K2: Kernel> Kernel for Compose<K1, K2> {
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        Some(self.first.weigh_dist(dist)? * self.second.weigh_dist(dist)?)
+    }
+
+    fn weigh_dist2(&self, dist2: Precision) -> Option<Precision> {
+        Some(self.first.weigh_dist2(dist2)? * self.second.weigh_dist2(dist2)?)
+    }
+
+    fn at_center(&self) -> Precision {
+        self.first.at_center() * self.second.at_center()
+    }
+}
+
+/// Stretch another kernel along the distance axis.
+///
+/// A `scale` of 2.0 gives a point 2.0 away the weight the inner kernel would give a point 1.0 away.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scaled<K: Kernel> {
+    kernel: K,
+    scale: Precision,
+}
+
+impl<K: Kernel> Scaled<K> {
+    pub fn new(kernel: K, scale: Precision) -> Self {
+        Self { kernel, scale }
+    }
+
+    /// As [Scaled::new], but fails unless `scale` is positive and finite.
+    pub fn try_new(kernel: K, scale: Precision) -> Result<Self> {
+        if !(scale > 0.0 && scale.is_finite()) {
+            return Err(Error::invalid("scale", "must be positive and finite"));
+        }
+        Ok(Self::new(kernel, scale))
+    }
+}
+
+impl<K: Kernel> Kernel for Scaled<K> {
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        self.kernel.weigh_dist(dist / self.scale)
+    }
+
+    fn weigh_dist2(&self, dist2: Precision) -> Option<Precision> {
+        self.kernel.weigh_dist2(dist2 / (self.scale * self.scale))
+    }
+
+    fn at_center(&self) -> Precision {
+        self.kernel.at_center()
+    }
+}
+
+/// Weight points by how far they are from the point of interest in a linear fashion.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Linear {
+    max_dist: Precision,
+}
+
+impl Linear {
+    /// Points further than `max_dist` away are ignored.
+    pub fn new(max_dist: Precision) -> Self {
+        Self { max_dist }
+    }
+
+    /// As [Linear::new], but fails unless `max_dist` is positive and finite.
+    pub fn try_new(max_dist: Precision) -> Result<Self> {
+        if !(max_dist > 0.0 && max_dist.is_finite()) {
+            return Err(Error::invalid("max_dist", "must be positive and finite"));
+        }
+        Ok(Self::new(max_dist))
+    }
+
+    pub fn max_dist(&self) -> Precision {
+        self.max_dist
+    }
+}
+
+impl Kernel for Linear {
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        if dist > self.max_dist {
+            return None;
+        }
+        Some(self.max_dist - dist)
+    }
+
+    fn at_center(&self) -> Precision {
+        self.max_dist
+    }
+}
+
+/// Another name for the [Linear] kernel, whose weights form a triangle centred on the point of interest.
+pub type Triangular = Linear;
+
+/// Weight points by `1 - (dist / max_dist)^2`, which is optimal in a mean-squared-error sense.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Epanechnikov {
+    max_dist: Precision,
+}
+
+impl Epanechnikov {
+    /// Points further than `max_dist` away are ignored.
+    pub fn new(max_dist: Precision) -> Self {
+        Self { max_dist }
+    }
+
+    /// As [Epanechnikov::new], but fails unless `max_dist` is positive and finite.
+    pub fn try_new(max_dist: Precision) -> Result<Self> {
+        if !(max_dist > 0.0 && max_dist.is_finite()) {
+            return Err(Error::invalid("max_dist", "must be positive and finite"));
+        }
+        Ok(Self::new(max_dist))
+    }
+
+    pub fn max_dist(&self) -> Precision {
+        self.max_dist
+    }
+}
+
+impl Kernel for Epanechnikov {
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        self.weigh_dist2(dist * dist)
+    }
+
+    fn weigh_dist2(&self, dist2: Precision) -> Option<Precision> {
+        let max2 = self.max_dist * self.max_dist;
+        if dist2 > max2 {
+            return None;
+        }
+        Some(1.0 - dist2 / max2)
+    }
+
+    fn at_center(&self) -> Precision {
+        1.0
+    }
+}
+
+/// Weight points by a quarter-period of a cosine, from 1 at the point of interest to 0 at `max_dist`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cosine {
+    max_dist: Precision,
+}
+
+impl Cosine {
+    /// Points further than `max_dist` away are ignored.
+    pub fn new(max_dist: Precision) -> Self {
+        Self { max_dist }
+    }
+
+    /// As [Cosine::new], but fails unless `max_dist` is positive and finite.
+    pub fn try_new(max_dist: Precision) -> Result<Self> {
+        if !(max_dist > 0.0 && max_dist.is_finite()) {
+            return Err(Error::invalid("max_dist", "must be positive and finite"));
+        }
+        Ok(Self::new(max_dist))
+    }
+
+    pub fn max_dist(&self) -> Precision {
+        self.max_dist
+    }
+}
+
+impl Kernel for Cosine {
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        if dist > self.max_dist {
+            return None;
+        }
+        Some((crate::consts::FRAC_PI_2 * dist / self.max_dist).cos())
+    }
+
+    fn at_center(&self) -> Precision {
+        1.0
+    }
+}
+
+/// Kernel for Gaussian smoothing.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gaussian {
+    double_variance: Precision,
+    cut_off_weight: Precision,
+    at_center: Precision,
+}
+
+impl Gaussian {
+    /// Points more than `width` standard deviations away are ignored.
+    pub fn new(stdev: Precision, width: Precision) -> Self {
+        let variance = stdev * stdev;
+        let cut_off_weight = gaussian_dist(variance, stdev * width);
+        Self {
+            double_variance: variance * 2.0,
+            cut_off_weight,
+            at_center: gaussian_dist2(variance, 0.0),
+        }
+    }
+
+    /// As [Gaussian::new], but fails unless `stdev` is positive and finite
+    /// and `width` is positive (it may be infinite).
+    pub fn try_new(stdev: Precision, width: Precision) -> Result<Self> {
+        if !(stdev > 0.0 && stdev.is_finite()) {
+            return Err(Error::invalid("stdev", "must be positive and finite"));
+        }
+        if width.is_nan() || width <= 0.0 {
+            return Err(Error::invalid("width", "must be positive"));
+        }
+        Ok(Self::new(stdev, width))
+    }
+
+    pub fn stdev(&self) -> Precision {
+        (self.double_variance / 2.0).sqrt()
+    }
+
+    /// Cut-off distance, in standard deviations.
+    pub fn width(&self) -> Precision {
+        (-2.0 * self.cut_off_weight.ln()).sqrt()
+    }
+
+    /// Copy of this kernel with a different standard deviation, keeping the width in standard deviations.
+    pub fn with_stdev(&self, stdev: Precision) -> Result<Self> {
+        Self::try_new(stdev, self.width())
+    }
+
+    /// Copy of this kernel with a different cut-off width, in standard deviations.
+    pub fn with_width(&self, width: Precision) -> Result<Self> {
+        Self::try_new(self.stdev(), width)
+    }
+}
+
+impl Kernel for Gaussian {
+    fn at_center(&self) -> Precision {
+        self.at_center
+    }
+
+    fn weigh_dist(&self, dist: Precision) -> Option<Precision> {
+        self.weigh_dist2(dist * dist)
+    }
+
+    fn weigh_dist2(&self, dist2: Precision) -> Option<Precision> {
+        let w = (-dist2 / self.double_variance).exp();
+        if w < self.cut_off_weight {
+            None
+        } else {
+            Some(w)
+        }
+    }
+}
+
+fn gaussian_dist2(variance: Precision, dist2: Precision) -> Precision {
+    // skip the constant term (peak height); it gets normalised out in the weighted mean
+    // use dist2 because it's faster to calculate, and it saves us having to square it again here
+    // mean = 0, val will always be positive
+    (-dist2 / (2.0 * variance)).exp()
+}
+
+fn gaussian_dist(variance: Precision, dist: Precision) -> Precision {
+    gaussian_dist2(variance, dist * dist)
+}
+
+/// How [smooth_convolve] and friends remember the kernel weights between pairs of points.
+///
+/// Every pair of nearby points is weighed twice (once from each end), so remembering weights saves recomputing them;
+/// the policy does not affect the smoothed output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CachePolicy {
+    /// Remember every weight for the whole line.
+    /// Memory grows with the number of points times the number of neighbours each has.
+    Unbounded,
+    /// Remember at most this many of the most recently used weights.
+    Lru(NonZeroUsize),
+    /// Remember each point's weights to the points after it, by offset,
+    /// only until the smoothing has moved beyond them.
+    /// Memory grows with the square of the number of neighbours each point has, regardless of the line's length.
+    ///
+    /// Where a line folds back on itself, weights between distant parts of the line are recomputed.
+    #[default]
+    Banded,
+    /// Recompute every weight when it is needed.
+    Disabled,
+}
+
+/// How far apart two points are, for a [Kernel] to weigh them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KernelDistance {
+    /// Straight-line distance between the points.
+    /// Where a line folds back on itself, the points on either side of the fold pull on each other.
+    #[default]
+    Straight,
+    /// Distance along the line between the points, from a table of cumulative lengths,
+    /// so that only points on the same stretch of line pull on each other.
+    /// This is also cheaper to calculate.
+    ArcLength,
+}
+
+/// Options for convolution smoothing; see [smooth_convolve_with_options] and [Smoother::with_options].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SmoothOptions {
+    pub cache: CachePolicy,
+    pub distance: KernelDistance,
+}
+
+impl SmoothOptions {
+    pub fn with_distance(mut self, distance: KernelDistance) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: CachePolicy) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+/// Weights between pairs of points, keyed by lesser and greater index, remembered according to a [CachePolicy].
+enum WeightStore {
+    Unbounded(HashMap<(usize, usize), Option<Precision>>),
+    Lru(LruCache<(usize, usize), Option<Precision>>),
+    /// Row `i` holds the weights of point `first + i` to the points 1, 2, ... after it.
+    /// Forgotten rows are kept in `spare` to be reused.
+    Banded {
+        first: usize,
+        rows: VecDeque<Vec<Option<Precision>>>,
+        spare: Vec<Vec<Option<Precision>>>,
+    },
+    Disabled,
+}
+
+impl WeightStore {
+    fn new(policy: CachePolicy) -> Self {
+        match policy {
+            CachePolicy::Unbounded => WeightStore::Unbounded(HashMap::default()),
+            CachePolicy::Lru(cap) => WeightStore::Lru(LruCache::new(cap)),
+            CachePolicy::Banded => WeightStore::Banded {
+                first: 0,
+                rows: VecDeque::default(),
+                spare: Vec::default(),
+            },
+            CachePolicy::Disabled => WeightStore::Disabled,
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            WeightStore::Unbounded(map) => map.clear(),
+            WeightStore::Lru(lru) => lru.clear(),
+            WeightStore::Banded { first, rows, spare } => {
+                *first = 0;
+                spare.extend(rows.drain(..).map(|mut row| {
+                    row.clear();
+                    row
+                }));
+            }
+            WeightStore::Disabled => (),
+        }
+    }
+
+    /// The remembered weight between the points at `lesser` and `greater`,
+    /// otherwise calling `weigh` (and possibly remembering the result).
+    fn get_or_insert_with<F: FnOnce() -> Option<Precision>>(
+        &mut self,
+        lesser: usize,
+        greater: usize,
+        weigh: F,
+    ) -> Option<Precision> {
+        match self {
+            WeightStore::Unbounded(map) => *map.entry((lesser, greater)).or_insert_with(weigh),
+            WeightStore::Lru(lru) => *lru.get_or_insert((lesser, greater), weigh),
+            WeightStore::Banded { first, rows, spare } => {
+                // only extend the band contiguously; anything else is a one-off
+                let Some(row_idx) = lesser.checked_sub(*first) else {
+                    return weigh();
+                };
+                if row_idx == rows.len() {
+                    rows.push_back(spare.pop().unwrap_or_default());
+                }
+                let Some(row) = rows.get_mut(row_idx) else {
+                    return weigh();
+                };
+                let offset = greater - lesser;
+                match offset.cmp(&(row.len() + 1)) {
+                    Ordering::Less => row[offset - 1],
+                    Ordering::Equal => {
+                        let w = weigh();
+                        row.push(w);
+                        w
+                    }
+                    Ordering::Greater => weigh(),
+                }
+            }
+            WeightStore::Disabled => weigh(),
+        }
+    }
+
+    /// Forget weights which points from `idx` onwards will not look up.
+    fn forget_before(&mut self, idx: usize) {
+        if let WeightStore::Banded { first, rows, spare } = self {
+            while rows.front().is_some_and(|row| *first + row.len() < idx) {
+                let mut row = rows.pop_front().unwrap();
+                row.clear();
+                spare.push(row);
+                *first += 1;
+            }
+        }
+    }
+}
+
+/// Buffers used by [smooth_convolve] which can be reused between lines.
+struct Scratch<const D: usize> {
+    cache: WeightStore,
+    cumulative: Vec<Precision>,
+    reflected_l: Vec<(Point<Precision, D>, Precision)>,
+    reflected_r: Vec<(Point<Precision, D>, Precision)>,
+    neighbours: Vec<(Point<Precision, D>, Precision)>,
+}
+
+impl<const D: usize> Scratch<D> {
+    fn new(policy: CachePolicy) -> Self {
+        Self {
+            cache: WeightStore::new(policy),
+            cumulative: Vec::default(),
+            reflected_l: Vec::default(),
+            reflected_r: Vec::default(),
+            neighbours: Vec::default(),
+        }
+    }
+}
+
+impl<const D: usize> Default for Scratch<D> {
+    fn default() -> Self {
+        Self::new(CachePolicy::default())
+    }
+}
+
+struct WeightCache<'a, K: Kernel, M, const D: usize> {
+    line: &'a [Point<Precision, D>],
+    kernel: &'a K,
+    metric: &'a M,
+    /// Distance along the line to each point, if weighing by [KernelDistance::ArcLength].
+    arc_lengths: Option<&'a [Precision]>,
+    cache: &'a mut WeightStore,
+}
+
+impl<'a, K: Kernel, M: DistanceMetric<D>, const D: usize> WeightCache<'a, K, M, D> {
+    /// Clears the given cache before use.
+    pub fn new(
+        line: &'a [Point<Precision, D>],
+        kernel: &'a K,
+        metric: &'a M,
+        arc_lengths: Option<&'a [Precision]>,
+        cache: &'a mut WeightStore,
+    ) -> Self {
+        cache.clear();
+        Self {
+            line,
+            kernel,
+            metric,
+            arc_lengths,
+            cache,
+        }
+    }
+
+    /// None if the weight of the edge is too low.
+    /// `idx1` must be less than `idx2`; does not check that indices are in range.
+    pub fn get_weight_unchecked(&mut self, idx1: usize, idx2: usize) -> Option<Precision> {
+        let Self {
+            line,
+            kernel,
+            metric,
+            arc_lengths,
+            cache,
+        } = self;
+        cache.get_or_insert_with(idx1, idx2, || match arc_lengths {
+            Some(lengths) => kernel.weigh_dist(lengths[idx2] - lengths[idx1]),
+            None => kernel.weigh_dist2(metric.distance_squared(&line[idx1], &line[idx2])),
+        })
+    }
+
+    /// None if the indices are out of range,
+    /// or if the weight of the edge is too low.
+    /// Enforces key order.
+    pub fn get_weight(&mut self, idx1: usize, idx2: usize) -> Option<Precision> {
+        let (lesser, greater) = match idx1.cmp(&idx2) {
+            Ordering::Less => (idx1, idx2),
+            Ordering::Equal => return Some(self.at_center()),
+            Ordering::Greater => (idx2, idx1),
+        };
+        if greater >= self.line.len() {
+            return None;
+        }
+        self.get_weight_unchecked(lesser, greater)
+    }
+
+    /// Forget cached weights which points from `idx` onwards will not need.
+    pub fn forget_before(&mut self, idx: usize) {
+        self.cache.forget_before(idx);
+    }
+
+    pub fn at_center(&self) -> Precision {
+        self.kernel.at_center()
+    }
+}
+
+fn reflect_point<const D: usize>(
+    reflect: &Point<Precision, D>,
+    reflect_around: &Point<Precision, D>,
+) -> Point<Precision, D> {
+    2.0 * reflect_around - reflect.coords
+}
+
+/// As [smooth_convolve], with the output blended towards the original line by `fidelity`.
+pub fn smooth_convolve_with_fidelity<'a, K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    fidelity: impl Into<Fidelity<'a>>,
+) -> Vec<Point<Precision, D>> {
+    let mut out = smooth_convolve(line, kernel, false);
+    apply_fidelity(line, &mut out, fidelity);
+    out
+}
+
+/// How much of the line contributed to a smoothed point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Support {
+    /// Number of points, other than the point of interest, given a weight by the kernel.
+    /// Includes reflected points beyond the ends of the line.
+    pub n_neighbours: usize,
+    /// Sum of all weights, including the point of interest's.
+    pub total_weight: Precision,
+}
+
+/// Fail if the kernel's weight at the centre is not positive and finite,
+/// as smoothing with it would produce NaNs or points at infinity.
+pub(crate) fn check_kernel<K: Kernel>(kernel: &K) -> Result<()> {
+    let w = kernel.at_center();
+    if !(w > 0.0 && w.is_finite()) {
+        return Err(Error::invalid(
+            "kernel",
+            "weight at centre must be positive and finite",
+        ));
+    }
+    Ok(())
+}
+
+/// Smooth line by applying an arbitrary kernel.
+///
+/// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+/// A closed line is smoothed as though it repeated endlessly, so every point moves.
+/// Kernels which reach further than the whole ring either side are truncated.
+pub fn smooth_convolve<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    kind: impl Into<CurveKind>,
+) -> Vec<Point<Precision, D>> {
+    if !kind.into().is_closed() || line.len() <= 2 {
+        return smooth_convolve_with_support(line, kernel).0;
+    }
+    let len = line.len();
+    let tiled: Vec<_> = line.iter().cycle().take(len * 3).copied().collect();
+    let mut out = smooth_convolve_with_support(&tiled, kernel).0;
+    out.truncate(len * 2);
+    out.drain(..len);
+    out
+}
+
+/// As [smooth_convolve], but clearing `out` and writing the smoothed line into it.
+///
+/// This saves allocating the output, but working buffers are still allocated for every call;
+/// use [Smoother::smooth_into] to reuse those too.
+pub fn smooth_convolve_into<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    out: &mut Vec<Point<Precision, D>>,
+) {
+    out.clear();
+    if line.is_empty() {
+        return;
+    }
+    convolve(
+        line,
+        &kernel,
+        &Euclidean,
+        &mut Scratch::default(),
+        out,
+        None,
+        None,
+        KernelDistance::Straight,
+    );
+}
+
+/// As [smooth_convolve], with the given [SmoothOptions].
+pub fn smooth_convolve_with_options<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    options: SmoothOptions,
+) -> Vec<Point<Precision, D>> {
+    let mut smoothed = Vec::with_capacity(line.len());
+    if line.is_empty() {
+        return smoothed;
+    }
+    convolve(
+        line,
+        &kernel,
+        &Euclidean,
+        &mut Scratch::new(options.cache),
+        &mut smoothed,
+        None,
+        None,
+        options.distance,
+    );
+    smoothed
+}
+
+/// As [smooth_convolve], but returns an error rather than producing nonsense
+/// if the kernel's weight at the centre is not positive and finite,
+/// or the line has non-finite coordinates.
+pub fn try_smooth_convolve<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+) -> Result<Vec<Point<Precision, D>>> {
+    check_kernel(&kernel)?;
+    check_finite(line)?;
+    Ok(smooth_convolve(line, kernel, false))
+}
+
+/// As [smooth_convolve], but pinning the points at the `preserved` indices (as well as the ends)
+/// and smoothing each section between them separately,
+/// so that smooth sections meet at crisp corners.
+///
+/// The corners could be, for example, the points kept by [rdp_keep](crate::simplify::rdp::rdp_keep) with a large tolerance.
+/// Indices may be given in any order, and duplicates are ignored.
+///
+/// Panics if any index is out of bounds.
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::simplify::rdp::rdp_keep;
+/// use simples::smooth::{smooth_preserving, Gaussian};
+///
+/// // a noisy right angle
+/// let line: Vec<simples::Point<Precision, 2>> = (0..=20)
+///     .map(|i| {
+///         let noise = if i % 2 == 0 { 0.05 } else { -0.05 };
+///         if i <= 10 { [i as Precision, noise] } else { [10.0 + noise, (i - 10) as Precision] }.into()
+///     })
+///     .collect();
+/// let corners = rdp_keep(&line, 1.0, false);
+/// let smoothed = smooth_preserving(&line, Gaussian::new(1.0, 3.0), &corners);
+/// assert_eq!(smoothed[10], line[10]);
+/// ```
+pub fn smooth_preserving<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    preserved: &[usize],
+) -> Vec<Point<Precision, D>> {
+    if line.len() <= 2 {
+        return line.to_vec();
+    }
+    let anchors = normalise_anchors(line.len(), preserved);
+
+    let mut scratch = Scratch::default();
+    let mut section = Vec::default();
+    let mut out = Vec::with_capacity(line.len());
+    out.push(line[0]);
+    for pair in anchors.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        if end - start > 1 {
+            section.clear();
+            convolve(
+                &line[start..=end],
+                &kernel,
+                &Euclidean,
+                &mut scratch,
+                &mut section,
+                None,
+                None,
+                KernelDistance::Straight,
+            );
+            out.extend_from_slice(&section[1..(section.len() - 1)]);
+        }
+        out.push(line[end]);
+    }
+    out
+}
+
+/// As [smooth_convolve], but also report the [Support] of every smoothed point.
+///
+/// Points with few neighbours or a low total weight (relative to the kernel's `at_center`)
+/// were barely smoothed, which may indicate that the line is too sparsely sampled there.
+pub fn smooth_convolve_with_support<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+) -> (Vec<Point<Precision, D>>, Vec<Support>) {
+    let mut smoothed = Vec::with_capacity(line.len());
+    let mut support = Vec::with_capacity(line.len());
+    convolve(
+        line,
+        &kernel,
+        &Euclidean,
+        &mut Scratch::default(),
+        &mut smoothed,
+        Some(&mut support),
+        None,
+        KernelDistance::Straight,
+    );
+    (smoothed, support)
+}
+
+/// As [smooth_convolve], but weighing neighbours by their distance under the given [DistanceMetric],
+/// e.g. [ScaledEuclidean](crate::metric::ScaledEuclidean) for anisotropic voxel data.
+pub fn smooth_convolve_metric<K: Kernel, M: DistanceMetric<D>, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    metric: &M,
+) -> Vec<Point<Precision, D>> {
+    let mut smoothed = Vec::with_capacity(line.len());
+    if line.is_empty() {
+        return smoothed;
+    }
+    convolve(
+        line,
+        &kernel,
+        metric,
+        &mut Scratch::default(),
+        &mut smoothed,
+        None,
+        None,
+        KernelDistance::Straight,
+    );
+    smoothed
+}
+
+/// As [smooth_convolve], but keeping every point within `bounds`,
+/// e.g. to keep elevations above zero or points inside a tile.
+///
+/// Use [Aabb::unbounded] and [Aabb::with_axis] to bound only some axes.
+/// As well as the smoothed points, the reflected points used to balance the kernel at the ends of the line are clamped,
+/// so that points near the ends are not pulled out of bounds and flattened against them.
+pub fn smooth_convolve_bounded<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+    bounds: &Aabb<D>,
+) -> Vec<Point<Precision, D>> {
+    let mut smoothed = Vec::with_capacity(line.len());
+    convolve(
+        line,
+        &kernel,
+        &Euclidean,
+        &mut Scratch::default(),
+        &mut smoothed,
+        None,
+        Some(bounds),
+        KernelDistance::Straight,
+    );
+    smoothed
+}
+
+/// As [smooth_convolve], but weighing points by their offset in index from the point of interest
+/// (e.g. 1.0 for its immediate neighbours), rather than by their distance from it.
+///
+/// This behaves better than distance-based smoothing on unevenly sampled lines,
+/// but the smoothing radius is then a number of points rather than a distance.
+/// Offsets are tried in order until the kernel returns None.
+/// As with [smooth_convolve], points beyond the ends of the line are reflected around the endpoints,
+/// and the endpoints themselves are not moved.
+pub fn smooth_convolve_by_index<K: Kernel, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: K,
+) -> Vec<Point<Precision, D>> {
+    if line.len() <= 2 {
+        return line.to_vec();
+    }
+    let weights: Vec<_> = (1..line.len())
+        .map_while(|offset| kernel.weigh_dist(offset as Precision))
+        .collect();
+    let total_weight = kernel.at_center() + 2.0 * weights.iter().sum::<Precision>();
+    let last = line.len() - 1;
+    let at = |idx: isize| {
+        if idx < 0 {
+            reflect_point(&line[idx.unsigned_abs()], &line[0])
+        } else if idx as usize > last {
+            reflect_point(&line[2 * last - idx as usize], &line[last])
+        } else {
+            line[idx as usize]
+        }
+    };
+
+    let mut out = Vec::with_capacity(line.len());
+    out.push(line[0]);
+    for (idx, p) in line.iter().enumerate().take(last).skip(1) {
+        let mut sum = p.coords * kernel.at_center();
+        for (offset, weight) in weights.iter().enumerate() {
+            let offset = offset as isize + 1;
+            let idx = idx as isize;
+            sum += (at(idx - offset).coords + at(idx + offset).coords) * *weight;
+        }
+        out.push(Point::from(sum / total_weight));
+    }
+    out.push(line[last]);
+    out
+}
+
+/// Smooth `line`, appending the smoothed points to `smoothed` and (optionally) their support to `support`.
+#[allow(clippy::too_many_arguments)]
+fn convolve<K: Kernel, M: DistanceMetric<D>, const D: usize>(
+    line: &[Point<Precision, D>],
+    kernel: &K,
+    metric: &M,
+    scratch: &mut Scratch<D>,
+    smoothed: &mut Vec<Point<Precision, D>>,
+    mut support: Option<&mut Vec<Support>>,
+    bounds: Option<&Aabb<D>>,
+    distance: KernelDistance,
+) {
+    if line.is_empty() {
+        return;
+    }
+    let Scratch {
+        cache,
+        cumulative,
+        reflected_l,
+        reflected_r,
+        neighbours,
+    } = scratch;
+    let arc_lengths = match distance {
+        KernelDistance::Straight => None,
+        KernelDistance::ArcLength => {
+            cumulative.clear();
+            let mut total = 0.0;
+            cumulative.push(total);
+            cumulative.extend(line.windows(2).map(|w| {
+                total += metric.distance(&w[0], &w[1]);
+                total
+            }));
+            Some(&cumulative[..])
+        }
+    };
+    let mut weight_cache = WeightCache::new(line, kernel, metric, arc_lengths, cache);
+
+    let first_point = line.first().unwrap();
+    let last_point = line.last().unwrap();
+    let last_idx = line.len() - 1;
+    let clamp = |p: Point<Precision, D>| match bounds {
+        Some(b) => b.clamp(&p),
+        None => p,
+    };
+
+    // We want the end points to stay where they are.
+    // If we just cut off the kernel near the ends of the line,
+    // the points would be smoothed away from the end points.
+    // So we create some fake points just before and after the line,
+    // effectively reflections of the first few and last few points,
+    // to balance out the smoothing.
+
+    reflected_l.clear();
+    reflected_l.extend(
+        (1..)
+            .map(|idx| {
+                weight_cache
+                    .get_weight(0, idx)
+                    .map(|w| (clamp(reflect_point(&line[idx], first_point)), w))
+            })
+            .take_while(|o| o.is_some())
+            .map(|o| o.unwrap()),
+    );
+
+    reflected_r.clear();
+    reflected_r.extend(
+        (1..=last_idx)
+            .map(|idx| {
+                weight_cache
+                    .get_weight(last_idx, last_idx - idx)
+                    .map(|w| (clamp(reflect_point(&line[last_idx - idx], last_point)), w))
+            })
+            .take_while(|o| o.is_some())
+            .map(|o| o.unwrap()),
+    );
+
+    for (current_idx, current_point) in line.iter().enumerate() {
+        weight_cache.forget_before(current_idx);
+        neighbours.clear();
+        neighbours.push((*current_point, weight_cache.at_center()));
+        let mut to_reflect: usize = 0;
+
+        // Go forward from the current point, possibly off the end of the line
+        for idx_diff in 1.. {
+            let next_idx = current_idx + idx_diff;
+
+            if next_idx >= line.len() {
+                // points will need to be taken from the pre-generated reflected points
+                to_reflect = next_idx - line.len();
+                break;
+            }
+
+            // can use *_unchecked because we know the index order and that they're in range
+            if let Some(weight) = weight_cache.get_weight_unchecked(current_idx, next_idx) {
+                neighbours.push((line[next_idx], weight));
+            } else {
+                break;
+            }
+        }
+
+        neighbours.extend(reflected_r.iter().take(to_reflect));
+
+        to_reflect = 0;
+
+        // Go backward from the current point, possibly off the start of the line
+        for idx_diff in 1.. {
+            if current_idx < idx_diff {
+                to_reflect = idx_diff - current_idx;
+                break;
+            }
+            let next_idx = current_idx - idx_diff;
+
+            if let Some(weight) = weight_cache.get_weight_unchecked(next_idx, current_idx) {
+                neighbours.push((line[next_idx], weight));
+            } else {
+                break;
+            }
+        }
+
+        neighbours.extend(reflected_l.iter().take(to_reflect));
+
+        smoothed.push(clamp(weighted_mean(&neighbours[..])));
+        if let Some(s) = support.as_mut() {
+            s.push(Support {
+                n_neighbours: neighbours.len() - 1,
+                total_weight: neighbours.iter().map(|(_, w)| w).sum(),
+            });
+        }
+    }
+}
+
+/// Smooths any number of lines with the same kernel (as [smooth_convolve]),
+/// reusing its internal buffers between lines to save on allocations.
+pub struct Smoother<K: Kernel, const D: usize> {
+    kernel: K,
+    options: SmoothOptions,
+    scratch: Scratch<D>,
+    bounds: Option<Aabb<D>>,
+}
+
+impl<K: Kernel, const D: usize> Smoother<K, D> {
+    pub fn new(kernel: K) -> Self {
+        Self {
+            kernel,
+            options: SmoothOptions::default(),
+            scratch: Scratch::default(),
+            bounds: None,
+        }
+    }
+
+    /// As [Smoother::new], but fails if the kernel's weight at the centre is not positive and finite.
+    pub fn try_new(kernel: K) -> Result<Self> {
+        check_kernel(&kernel)?;
+        Ok(Self::new(kernel))
+    }
+
+    /// Keep smoothed points within `bounds`; see [smooth_convolve_bounded].
+    pub fn with_bounds(mut self, bounds: Aabb<D>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Smooth with the given [SmoothOptions]; see [smooth_convolve_with_options].
+    pub fn with_options(mut self, options: SmoothOptions) -> Self {
+        self.options = options;
+        self.scratch = Scratch::new(options.cache);
+        self
+    }
+
+    pub fn kernel(&self) -> &K {
+        &self.kernel
+    }
+
+    pub fn options(&self) -> &SmoothOptions {
+        &self.options
+    }
+
+    /// Smooth a single line.
+    pub fn smooth(&mut self, line: &[Point<Precision, D>]) -> Vec<Point<Precision, D>> {
+        let mut out = Vec::with_capacity(line.len());
+        self.smooth_into(line, &mut out);
+        out
+    }
+
+    /// Smooth a single line, clearing `out` and writing the smoothed line into it.
+    ///
+    /// Once the smoother's buffers and `out` have grown to fit the longest line,
+    /// smoothing more lines allocates nothing
+    /// (except with [CachePolicy::Lru], which allocates every weight it remembers).
+    pub fn smooth_into(
+        &mut self,
+        line: &[Point<Precision, D>],
+        out: &mut Vec<Point<Precision, D>>,
+    ) {
+        out.clear();
+        if line.is_empty() {
+            return;
+        }
+        convolve(
+            line,
+            &self.kernel,
+            &Euclidean,
+            &mut self.scratch,
+            out,
+            None,
+            self.bounds.as_ref(),
+            self.options.distance,
+        );
+    }
+
+    /// Smooth every line in turn.
+    pub fn smooth_many<L: AsRef<[Point<Precision, D>]>>(
+        &mut self,
+        lines: &[L],
+    ) -> Vec<Vec<Point<Precision, D>>> {
+        lines.iter().map(|l| self.smooth(l.as_ref())).collect()
+    }
+
+    /// Smooth every line in parallel, with one set of buffers per thread.
+    #[cfg(feature = "rayon")]
+    pub fn par_smooth_many<L: AsRef<[Point<Precision, D>]> + Sync>(
+        &self,
+        lines: &[L],
+    ) -> Vec<Vec<Point<Precision, D>>>
+    where
+        K: Sync,
+    {
+        use rayon::prelude::*;
+
+        lines
+            .par_iter()
+            .map_init(
+                || Scratch::new(self.options.cache),
+                |scratch, line| {
+                    let line = line.as_ref();
+                    let mut out = Vec::with_capacity(line.len());
+                    convolve(
+                        line,
+                        &self.kernel,
+                        &Euclidean,
+                        scratch,
+                        &mut out,
+                        None,
+                        self.bounds.as_ref(),
+                        self.options.distance,
+                    );
+                    out
+                },
+            )
+            .collect()
+    }
+}
+
+/// Savitzky-Golay filter: smooths each coordinate by fitting a polynomial
+/// to the points in a window around each point.
+///
+/// Unlike kernel smoothing, this preserves the height and position of peaks (e.g. sharp turns).
+/// Points are treated as evenly spaced, so the line should usually be resampled first.
+/// The filter coefficients are calculated once on construction,
+/// so reuse the filter for many lines with the same parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavitzkyGolay {
+    half_width: usize,
+    /// Row `i` gives the weights of the window's points for the fitted value at window position `i`.
+    coefficients: DMatrix<Precision>,
+}
+
+impl SavitzkyGolay {
+    /// `window` is the (odd) number of points to fit each polynomial to;
+    /// `polyorder` is the degree of the polynomials, which must be less than `window`.
+    pub fn new(window: usize, polyorder: usize) -> Result<Self> {
+        if window.is_multiple_of(2) {
+            return Err(Error::invalid("window", "must be odd"));
+        }
+        if polyorder >= window {
+            return Err(Error::invalid("polyorder", "must be less than window"));
+        }
+        let half_width = window / 2;
+        // Vandermonde matrix of window offsets
+        let vander = DMatrix::from_fn(window, polyorder + 1, |row, col| {
+            (row as Precision - half_width as Precision).powi(col as i32)
+        });
+        let vt = vander.transpose();
+        let inverse = (&vt * &vander)
+            .try_inverse()
+            .ok_or(Error::invalid("polyorder", "too high to fit"))?;
+        Ok(Self {
+            half_width,
+            coefficients: &vander * inverse * vt,
+        })
+    }
+
+    pub fn window(&self) -> usize {
+        self.half_width * 2 + 1
+    }
+
+    /// Fitted value at `position` in the window of points starting at `start`,
+    /// where `index` maps positions in the line (which may be beyond its ends) to real indices.
+    fn fit<const D: usize, F: Fn(usize) -> usize>(
+        &self,
+        line: &[Point<Precision, D>],
+        start: usize,
+        position: usize,
+        index: F,
+    ) -> Point<Precision, D> {
+        let mut out = Point::origin();
+        for col in 0..self.window() {
+            out += line[index(start + col)].coords * self.coefficients[(position, col)];
+        }
+        out
+    }
+
+    /// Smooth the line.
+    ///
+    /// If `kind` is [CurveKind::Closed] (or `true`), the line is treated as a ring with an edge from the last point to the first,
+    /// and every point is smoothed.
+    /// Otherwise, the first and last points are kept in place,
+    /// and the other points within half a window of the ends are fitted using the first or last full window.
+    /// Lines shorter than the window are returned unchanged.
+    pub fn smooth<const D: usize>(
+        &self,
+        line: &[Point<Precision, D>],
+        kind: impl Into<CurveKind>,
+    ) -> Vec<Point<Precision, D>> {
+        let closed = kind.into().is_closed();
+        let len = line.len();
+        let w = self.window();
+        if len < w {
+            return line.to_vec();
+        }
+        let hw = self.half_width;
+        if closed {
+            return (0..len)
+                .map(|idx| self.fit(line, idx + len - hw, hw, |i| i % len))
+                .collect();
+        }
+        let mut out = Vec::with_capacity(len);
+        out.push(line[0]);
+        for idx in 1..(len - 1) {
+            let p = if idx < hw {
+                self.fit(line, 0, idx, |i| i)
+            } else if idx >= len - hw {
+                self.fit(line, len - w, idx + w - len, |i| i)
+            } else {
+                self.fit(line, idx - hw, hw, |i| i)
+            };
+            out.push(p);
+        }
+        out.push(line[len - 1]);
+        out
+    }
+}
+
+/// Smooth the line with a [SavitzkyGolay] filter.
+///
+/// To smooth many lines with the same parameters, construct the filter once and reuse it.
+pub fn smooth_savitzky_golay<const D: usize>(
+    line: &[Point<Precision, D>],
+    window: usize,
+    polyorder: usize,
+    kind: impl Into<CurveKind>,
+) -> Result<Vec<Point<Precision, D>>> {
+    Ok(SavitzkyGolay::new(window, polyorder)?.smooth(line, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+    use nalgebra::distance_squared;
+    use proptest::prelude::*;
+
+    #[test]
+    fn short_lines() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0]]);
+        let bounds = Aabb::new([0.0, 0.0].into(), [1.0, 1.0].into());
+        for len in 0..=2 {
+            let short = &line[..len];
+            for closed in [false, true] {
+                assert_eq!(smooth_convolve(short, Linear::new(2.0), closed).len(), len);
+            }
+            assert_eq!(
+                smooth_convolve_bounded(short, Linear::new(2.0), &bounds).len(),
+                len
+            );
+            assert_eq!(
+                smooth_convolve_with_support(short, Linear::new(2.0))
+                    .1
+                    .len(),
+                len
+            );
+        }
+        assert_eq!(
+            smooth_convolve(&line[..1], Linear::new(2.0), false),
+            line[..1]
+        );
+    }
+
+    #[test]
+    fn moving_average_edges() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 3.0], [2.0, 0.0], [3.0, 3.0]]);
+        let y = |mode| -> Vec<_> {
+            smooth_moving_average_with_edges(&line, 1, mode)
+                .iter()
+                .map(|p| p.y)
+                .collect()
+        };
+        assert_eq!(y(EdgeMode::Shrink), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(y(EdgeMode::Clamp), vec![1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(y(EdgeMode::Reflect), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(y(EdgeMode::Wrap), vec![2.0, 1.0, 2.0, 1.0]);
+        // straight lines stay straight and in place when reflected
+        let straight = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]);
+        assert_eq!(
+            smooth_moving_average_with_edges(&straight, 3, EdgeMode::Reflect),
+            straight
+        );
+    }
+
+    #[test]
+    fn moving_average_symmetric() {
+        // a single spike is spread evenly either side
+        let mut line = make_line((0..9).map(|i| [i as Precision, 0.0]).collect());
+        line[4].y = 5.0;
+        let out = smooth_moving_average(&line, 2, false);
+        for i in 0..4 {
+            assert_eq!(out[i].y, out[8 - i].y);
+        }
+        assert_eq!(out[4].y, 1.0);
+        assert_eq!(out[2].y, 1.0);
+        assert_eq!(out[1].y, 0.0);
+    }
+
+    fn line2() -> impl Strategy<Value = Vec<Point<Precision, 2>>> {
+        proptest::collection::vec(
+            proptest::array::uniform2((-100.0 as Precision)..100.0).prop_map(Point::from),
+            0..30,
+        )
+    }
+
+    fn edge_mode() -> impl Strategy<Value = EdgeMode> {
+        prop_oneof![
+            Just(EdgeMode::Clamp),
+            Just(EdgeMode::Reflect),
+            Just(EdgeMode::Wrap),
+            Just(EdgeMode::Shrink),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn moving_average_keeps_length(line in line2(), width in 0usize..40, mode in edge_mode()) {
+            let out = smooth_moving_average_with_edges(&line, width, mode);
+            prop_assert_eq!(out.len(), line.len());
+            prop_assert!(out.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+        }
+
+        #[test]
+        fn moving_average_shrink_pins_ends(line in line2(), width in 0usize..40) {
+            let out = smooth_moving_average(&line, width, false);
+            prop_assert_eq!(out.first(), line.first());
+            prop_assert_eq!(out.last(), line.last());
+        }
+    }
+
+    #[test]
+    fn compose_multiplies() {
+        let k = Linear::new(2.0).then(Linear::new(4.0));
+        assert_eq!(k.at_center(), 8.0);
+        assert_eq!(k.weigh_dist(1.0), Some(3.0));
+        assert_eq!(k.weigh_dist(3.0), None);
+    }
+
+    #[test]
+    fn scaled_stretches() {
+        let k = Scaled::new(Linear::new(2.0), 2.0);
+        assert_eq!(k.weigh_dist(2.0), Some(1.0));
+        assert_eq!(k.weigh_dist2(4.0), Some(1.0));
+        assert_eq!(k.weigh_dist(5.0), None);
+    }
+
+    #[test]
+    fn convolve_composed() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 0.0],
+            [3.0, 1.0],
+            [4.0, 0.0],
+        ]);
+        let smoothed = smooth_convolve(&line, Linear::new(1.5).then(Linear::new(3.0)), false);
+        assert_eq!(smoothed.len(), line.len());
+        assert!(smoothed[2].y > 0.0);
+        assert!(smoothed[1].y < 1.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn kernel_serde_roundtrip() {
+        let k = Scaled::new(Linear::new(2.0), 3.0).then(Gaussian::new(1.0, 3.0));
+        let s = serde_json::to_string(&k).unwrap();
+        let k2: Compose<Scaled<Linear>, Gaussian> = serde_json::from_str(&s).unwrap();
+        assert_eq!(k.at_center(), k2.at_center());
+        assert_eq!(k.weigh_dist(1.0), k2.weigh_dist(1.0));
+    }
+
+    #[test]
+    fn support_reflects_sparsity() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [5.0, 0.0]]);
+        let (smoothed, support) = smooth_convolve_with_support(&line, Linear::new(1.5));
+        assert_eq!(smoothed.len(), support.len());
+        assert_eq!(support[2].n_neighbours, 1);
+        assert_eq!(support[2].total_weight, 2.0);
+        assert_eq!(support[3].n_neighbours, 0);
+        assert_eq!(support[3].total_weight, 1.5);
+    }
+
+    #[test]
+    fn fidelity_blends() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 0.0],
+            [3.0, 1.0],
+            [4.0, 0.0],
+        ]);
+        let smoothed = smooth_convolve(&line, Linear::new(1.5), false);
+
+        let full = smooth_convolve_with_fidelity(&line, Linear::new(1.5), 1.0);
+        assert_eq!(full, line);
+        let none = smooth_convolve_with_fidelity(&line, Linear::new(1.5), 0.0);
+        assert_eq!(none, smoothed);
+
+        let fs = [0.0, 0.5, 1.0, 0.5, 0.0];
+        let half = smooth_convolve_with_fidelity(&line, Linear::new(1.5), &fs[..]);
+        assert_eq!(half[2], line[2]);
+        assert!((half[1].y - (line[1].y + smoothed[1].y) / 2.0).abs() < tol(1e-12));
+    }
+
+    #[test]
+    fn range_pins_boundaries() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 0.0],
+            [3.0, 1.0],
+            [4.0, 0.0],
+            [5.0, 1.0],
+        ]);
+        let out = smooth_range(&line, 1..5, |l| smooth_convolve(l, Linear::new(1.5), false));
+        assert_eq!(out.len(), line.len());
+        assert_eq!(out[..2], line[..2]);
+        assert_eq!(out[4..], line[4..]);
+        assert!(out[2].y > 0.0);
+        assert!(out[3].y < 1.0);
+    }
+
+    #[test]
+    fn smoother_matches_convolve() {
+        let lines: Vec<_> = (3..10)
+            .map(|n| {
+                make_line(
+                    (0..n)
+                        .map(|i| [i as Precision, (i % 2) as Precision])
+                        .collect(),
+                )
+            })
+            .collect();
+        let mut smoother = Smoother::new(Linear::new(2.5));
+        let expected: Vec<_> = lines
+            .iter()
+            .map(|l| smooth_convolve(l, Linear::new(2.5), false))
+            .collect();
+        assert_eq!(smoother.smooth_many(&lines), expected);
+        #[cfg(feature = "rayon")]
+        assert_eq!(smoother.par_smooth_many(&lines), expected);
+
+        let mut out = vec![];
+        for (line, e) in lines.iter().zip(expected.iter()) {
+            smoother.smooth_into(line, &mut out);
+            assert_eq!(&out, e);
+            smooth_convolve_into(line, Linear::new(2.5), &mut out);
+            assert_eq!(&out, e);
+        }
+        smoother.smooth_into(&[], &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn wide_kernel() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        let smoothed = smooth_convolve(&line, Linear::new(100.0), false);
+        assert_eq!(smoothed.len(), 3);
+    }
+
+    #[test]
+    fn cache_policies_agree() {
+        // a zigzag which folds back over itself halfway along
+        let mut coords: Vec<_> = (0..30)
+            .map(|i| [i as Precision * 0.5, (i % 3) as Precision])
+            .collect();
+        coords.extend((0..30).map(|i| [15.0 - i as Precision * 0.5, 0.3 + (i % 2) as Precision]));
+        let line = make_line(coords);
+        let kernel = Gaussian::new(1.0, 3.0);
+        let expected = smooth_convolve_with_options(
+            &line,
+            kernel,
+            SmoothOptions::default().with_cache(CachePolicy::Disabled),
+        );
+        for cache in [
+            CachePolicy::Unbounded,
+            CachePolicy::Lru(NonZeroUsize::new(4).unwrap()),
+            CachePolicy::Banded,
+        ] {
+            let options = SmoothOptions::default().with_cache(cache);
+            assert_eq!(
+                smooth_convolve_with_options(&line, kernel, options),
+                expected,
+                "{cache:?}"
+            );
+            let mut smoother = Smoother::new(kernel).with_options(options);
+            assert_eq!(smoother.smooth(&line), expected, "{cache:?}");
+        }
+        assert_eq!(smooth_convolve(&line, kernel, false), expected);
+    }
+
+    #[test]
+    fn arc_length_distance() {
+        // a hairpin, whose arms are closer to each other than the kernel's reach
+        let mut coords: Vec<_> = (0..=10).map(|i| [i as Precision, 0.0]).collect();
+        coords.extend((0..=10).rev().map(|i| [i as Precision, 0.5]));
+        let line = make_line(coords);
+        let kernel = Gaussian::new(1.0, 3.0);
+        let straight = smooth_convolve(&line, kernel, false);
+        let options = SmoothOptions::default().with_distance(KernelDistance::ArcLength);
+        let along = smooth_convolve_with_options(&line, kernel, options);
+        // the arms of the hairpin are pulled together by straight-line distance, but barely along the line
+        assert!(straight[8].y > 0.2);
+        assert!(along[8].y < 0.01);
+        assert!(along[..8].iter().all(|p| p.y == 0.0));
+        assert_eq!(
+            Smoother::new(kernel).with_options(options).smooth(&line),
+            along
+        );
+
+        // on a straight line the two agree
+        let diagonal = make_line(
+            (0..20)
+                .map(|i| [i as Precision, 2.0 * i as Precision])
+                .collect(),
+        );
+        let straight = smooth_convolve(&diagonal, kernel, false);
+        let along = smooth_convolve_with_options(&diagonal, kernel, options);
+        for (a, b) in straight.iter().zip(along.iter()) {
+            assert!(distance_squared(a, b) < tol(1e-12));
+        }
+    }
+
+    #[test]
+    fn cache_bounded() {
+        let line = make_line(
+            (0..1000)
+                .map(|i| [i as Precision, (i % 2) as Precision])
+                .collect(),
+        );
+        let kernel = Linear::new(3.5);
+        let mut scratch = Scratch::new(CachePolicy::Banded);
+        convolve(
+            &line,
+            &kernel,
+            &Euclidean,
+            &mut scratch,
+            &mut vec![],
+            None,
+            None,
+            KernelDistance::Straight,
+        );
+        let WeightStore::Banded { rows, .. } = &scratch.cache else {
+            panic!("wrong store");
+        };
+        // each point has 3 neighbours either side
+        assert!(rows.len() <= 5);
+        assert!(rows.iter().all(|r| r.len() <= 4));
+
+        let mut scratch = Scratch::new(CachePolicy::Lru(NonZeroUsize::new(10).unwrap()));
+        convolve(
+            &line,
+            &kernel,
+            &Euclidean,
+            &mut scratch,
+            &mut vec![],
+            None,
+            None,
+            KernelDistance::Straight,
+        );
+        let WeightStore::Lru(lru) = &scratch.cache else {
+            panic!("wrong store");
+        };
+        assert_eq!(lru.len(), 10);
+    }
+
+    #[test]
+    fn savitzky_golay_preserves_polynomials() {
+        let line = make_line(
+            (0..12)
+                .map(|i| [i as Precision, (i * i) as Precision * 0.5])
+                .collect(),
+        );
+        let smoothed = smooth_savitzky_golay(&line, 5, 2, false).unwrap();
+        for (a, b) in smoothed.iter().zip(line.iter()) {
+            assert!(distance_squared(a, b) < tol(1e-18));
+        }
+    }
+
+    #[test]
+    fn savitzky_golay_smooths() {
+        let line = make_line(
+            (0..20)
+                .map(|i| [i as Precision, if i % 2 == 0 { 1.0 } else { -1.0 }])
+                .collect(),
+        );
+        let sg = SavitzkyGolay::new(7, 2).unwrap();
+        for closed in [false, true] {
+            let smoothed = sg.smooth(&line, closed);
+            assert_eq!(smoothed.len(), line.len());
+            assert!(smoothed[1..19].iter().all(|p| p.y.abs() < 0.5));
+        }
+        assert_eq!(sg.smooth(&line, false)[0], line[0]);
+    }
+
+    #[test]
+    fn savitzky_golay_params() {
+        assert!(SavitzkyGolay::new(4, 2).is_err());
+        assert!(SavitzkyGolay::new(5, 5).is_err());
+        assert_eq!(SavitzkyGolay::new(5, 4).unwrap().window(), 5);
+    }
+
+    #[test]
+    fn by_index_ignores_spacing() {
+        // unevenly spaced, but evenly spaced in index
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [0.1, 1.0],
+            [5.0, 0.0],
+            [5.1, 1.0],
+            [10.0, 0.0],
+        ]);
+        let smoothed = smooth_convolve_by_index(&line, Linear::new(2.0));
+        assert_eq!(smoothed[0], line[0]);
+        assert_eq!(smoothed[4], line[4]);
+        // weights 1, 2, 1 for offsets -1, 0, 1
+        assert!((smoothed[2] - Point::from([(0.1 + 10.0 + 5.1) / 4.0, 0.5])).norm() <= tol(0.0));
+        // no neighbours: unchanged
+        assert_eq!(smooth_convolve_by_index(&line, Linear::new(0.5)), line);
+    }
+
+    #[test]
+    fn bounded() {
+        // a valley touching the ground at one end
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 0.2],
+            [3.0, 1.0],
+            [4.0, 2.0],
+        ]);
+        let bounds = Aabb::unbounded().with_axis(1, 0.0, 1.5);
+        let kernel = Linear::new(2.5);
+        let smoothed = smooth_convolve_bounded(&line, kernel, &bounds);
+        assert!(smoothed.iter().all(|p| p.y >= 0.0 && p.y <= 1.5));
+
+        // reflected points are clamped too, so this is not just a post-clip
+        let clipped: Vec<_> = smooth_convolve(&line, kernel, false)
+            .iter()
+            .map(|p| bounds.clamp(p))
+            .collect();
+        assert_ne!(smoothed, clipped);
+
+        let mut smoother = Smoother::new(kernel).with_bounds(bounds);
+        assert_eq!(smoother.smooth(&line), smoothed);
+    }
+
+    /// Weights decay monotonically from the centre, and stop at exactly `cutoff`.
+    fn assert_kernel_shape<K: Kernel>(kernel: K, cutoff: Precision) {
+        let mut prev = kernel.at_center();
+        assert!(prev > 0.0);
+        for i in 0..=100 {
+            let dist = cutoff * i as Precision / 100.0;
+            let w = kernel
+                .weigh_dist(dist)
+                .unwrap_or_else(|| panic!("no weight at {dist}"));
+            assert!(w <= prev + 1e-12, "weight increased at {dist}");
+            let w2 = kernel.weigh_dist2(dist * dist).unwrap();
+            assert!((w - w2).abs() < tol(1e-12));
+            prev = w;
+        }
+        assert!(kernel.weigh_dist(cutoff * 1.01).is_none());
+        assert!(kernel.weigh_dist2(cutoff * cutoff * 1.01).is_none());
+    }
+
+    #[test]
+    fn kernel_shapes() {
+        assert_kernel_shape(Linear::new(2.0), 2.0);
+        assert_kernel_shape(Epanechnikov::new(2.0), 2.0);
+        assert_kernel_shape(Cosine::new(2.0), 2.0);
+        assert_kernel_shape(Gaussian::new(2.0, 3.0), 6.0);
+        assert_kernel_shape(Scaled::new(Cosine::new(1.0), 3.0), 3.0);
+        assert_kernel_shape(Linear::new(5.0).then(Epanechnikov::new(2.0)), 2.0);
+    }
+
+    #[test]
+    fn gaussian_cutoff() {
+        let g = Gaussian::new(1.0, 2.0);
+        assert_eq!(g.weigh_dist(0.0), Some(1.0));
+        assert!((g.weigh_dist(1.0).unwrap() - (-0.5 as Precision).exp()).abs() < tol(1e-12));
+        assert!(g.weigh_dist(2.5).is_none());
+        // an infinite width never cuts off
+        assert!(Gaussian::new(1.0, Precision::INFINITY)
+            .weigh_dist(100.0)
+            .is_some());
+    }
+
+    #[test]
+    fn gaussian_smooths() {
+        let mut line = make_line((0..11).map(|i| [i as Precision, 0.0]).collect());
+        line[5].y = 1.0;
+        let out = smooth_convolve(&line, Gaussian::new(1.0, 3.0), false);
+        assert!(out[5].y < 0.6);
+        assert!(out[4].y > 0.0 && out[6].y > 0.0);
+    }
+
+    #[test]
+    fn preserving_corners() {
+        // an evenly-spaced L
+        let line = make_line(
+            (0..=10)
+                .map(|i| {
+                    if i <= 5 {
+                        [i as Precision, 0.0]
+                    } else {
+                        [5.0, (i - 5) as Precision]
+                    }
+                })
+                .collect(),
+        );
+        let kernel = Gaussian::new(1.0, 3.0);
+        let smoothed = smooth_preserving(&line, kernel, &[5, 5]);
+        assert_eq!(smoothed.len(), line.len());
+        assert_eq!(smoothed[5], line[5]);
+        // straight sections stay straight
+        assert!(smoothed[..=5].iter().all(|p| p.y.abs() < tol(1e-12)));
+        assert!(smoothed[5..].iter().all(|p| (p.x - 5.0).abs() < tol(1e-12)));
+        // whereas smoothing the whole line cuts the corner
+        let whole = smooth_convolve(&line, kernel, false);
+        assert!(whole[5].x < 4.9 && whole[5].y > 0.1);
+
+        // with no corners, only the ends are pinned
+        let unpinned = smooth_preserving(&line, kernel, &[]);
+        assert_eq!(unpinned[1..10], whole[1..10]);
+        assert_eq!((unpinned[0], unpinned[10]), (line[0], line[10]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn preserving_out_of_bounds() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        smooth_preserving(&line, Linear::new(1.0), &[3]);
+    }
+
+    #[test]
+    fn fallible_kernels() {
+        assert!(Linear::try_new(-1.0).is_err());
+        assert!(Linear::try_new(Precision::NAN).is_err());
+        assert_eq!(Linear::try_new(2.0).unwrap().max_dist(), 2.0);
+
+        assert!(Gaussian::try_new(0.0, 3.0).is_err());
+        assert!(Gaussian::try_new(1.0, 0.0).is_err());
+        assert!(Gaussian::try_new(1.0, Precision::INFINITY).is_ok());
+        let g = Gaussian::try_new(2.0, 3.0).unwrap();
+        assert!((g.stdev() - 2.0).abs() < tol(1e-12));
+        assert!((g.width() - 3.0).abs() < tol(1e-12));
+        let g = g.with_width(1.5).unwrap();
+        assert!((g.stdev() - 2.0).abs() < tol(1e-12));
+        assert!((g.width() - 1.5).abs() < tol(1e-12));
+        assert!(g.with_stdev(-1.0).is_err());
+
+        assert!(Epanechnikov::try_new(0.0).is_err());
+        assert!(Cosine::try_new(Precision::INFINITY).is_err());
+        assert_eq!(Cosine::try_new(2.0).unwrap().max_dist(), 2.0);
+
+        assert!(Scaled::try_new(Linear::new(1.0), 0.0).is_err());
+
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        assert!(try_smooth_convolve(&line, Linear::new(0.0)).is_err());
+        assert!(try_smooth_convolve(&line, Linear::new(Precision::INFINITY)).is_err());
+        assert!(Smoother::<_, 2>::try_new(Linear::new(-1.0)).is_err());
+        assert_eq!(
+            try_smooth_convolve(&line, Linear::new(1.5)).unwrap(),
+            smooth_convolve(&line, Linear::new(1.5), false)
+        );
+    }
+}
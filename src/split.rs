@@ -0,0 +1,143 @@
+//! Cut linestrings into pieces.
+use crate::simplify::sample::point_at_length;
+use crate::{cumulative_lengths, Precision};
+use nalgebra::{distance_squared, Point};
+
+/// Cut the line at each of the given distances along it.
+///
+/// Every piece starts with the point at which the previous piece ended,
+/// so the pieces can be joined back together.
+/// Distances may be given in any order;
+/// those not strictly between the start and end of the line are ignored, as are duplicates,
+/// so there are no zero-length pieces.
+/// Lines with fewer than 2 points are returned as a single piece.
+pub fn split_at_distances<const D: usize>(
+    line: &[Point<Precision, D>],
+    distances: &[Precision],
+) -> Vec<Vec<Point<Precision, D>>> {
+    if line.len() < 2 {
+        return vec![line.to_vec()];
+    }
+    let cumulative = cumulative_lengths(line);
+    let total = cumulative[cumulative.len() - 1];
+    let mut distances: Vec<_> = distances
+        .iter()
+        .copied()
+        .filter(|d| *d > 0.0 && *d < total)
+        .collect();
+    distances.sort_by(Precision::total_cmp);
+    distances.dedup();
+
+    let mut out = Vec::with_capacity(distances.len() + 1);
+    let mut current = vec![line[0]];
+    let mut next_vertex = 1;
+    for d in distances {
+        // first vertex at or beyond the cut
+        let idx = cumulative.partition_point(|l| *l < d);
+        current.extend_from_slice(&line[next_vertex..idx.max(next_vertex)]);
+        let cut = point_at_length(line, &cumulative, d);
+        current.push(cut);
+        out.push(std::mem::replace(&mut current, vec![cut]));
+        next_vertex = if cumulative[idx] == d { idx + 1 } else { idx };
+    }
+    current.extend_from_slice(&line[next_vertex.min(line.len())..]);
+    out.push(current);
+    out
+}
+
+/// Fraction of the way along the edge from `a` to `b` of the point on the edge nearest to `p`.
+pub(crate) fn project_onto_edge<const D: usize>(
+    a: &Point<Precision, D>,
+    b: &Point<Precision, D>,
+    p: &Point<Precision, D>,
+) -> Precision {
+    let ab = b - a;
+    let len2 = ab.norm_squared();
+    if len2 == 0.0 {
+        return 0.0;
+    }
+    ((p - a).dot(&ab) / len2).clamp(0.0, 1.0)
+}
+
+/// Cut the line in two at the point on it nearest to `point`,
+/// if that is no further than `tolerance` away.
+///
+/// Both pieces contain the cut point (the first as its last point, the second as its first),
+/// so cutting at one end of the line produces a single-point piece.
+/// Returns None if the line is empty or too far away.
+#[allow(clippy::type_complexity)]
+pub fn split_at_point<const D: usize>(
+    line: &[Point<Precision, D>],
+    point: &Point<Precision, D>,
+    tolerance: Precision,
+) -> Option<(Vec<Point<Precision, D>>, Vec<Point<Precision, D>>)> {
+    if line.len() < 2 {
+        let p = line.first()?;
+        if distance_squared(p, point) > tolerance * tolerance {
+            return None;
+        }
+        return Some((line.to_vec(), line.to_vec()));
+    }
+    let (edge, cut, dist2) = line
+        .windows(2)
+        .enumerate()
+        .map(|(edge, pair)| {
+            let t = project_onto_edge(&pair[0], &pair[1], point);
+            let cut = pair[0] + (pair[1] - pair[0]) * t;
+            (edge, cut, distance_squared(&cut, point))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))?;
+    if dist2 > tolerance * tolerance {
+        return None;
+    }
+
+    let mut first = line[..=edge].to_vec();
+    if first[edge] != cut {
+        first.push(cut);
+    }
+    let mut second = vec![cut];
+    let rest = &line[(edge + 1)..];
+    second.extend_from_slice(if rest[0] == cut { &rest[1..] } else { rest });
+    Some((first, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn at_distances() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]]);
+        let pieces = split_at_distances(&line, &[3.0, 1.0, 2.0, 5.0, 0.0]);
+        assert_eq!(
+            pieces,
+            vec![
+                make_line(vec![[0.0, 0.0], [1.0, 0.0]]),
+                make_line(vec![[1.0, 0.0], [2.0, 0.0]]),
+                make_line(vec![[2.0, 0.0], [2.0, 1.0]]),
+                make_line(vec![[2.0, 1.0], [2.0, 2.0]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_distances() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]]);
+        assert_eq!(split_at_distances(&line, &[]), vec![line]);
+    }
+
+    #[test]
+    fn at_point() {
+        let line = make_line(vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]]);
+        let (a, b) = split_at_point(&line, &[1.0, 0.1].into(), 0.2).unwrap();
+        assert_eq!(a, make_line(vec![[0.0, 0.0], [1.0, 0.0]]));
+        assert_eq!(b, make_line(vec![[1.0, 0.0], [2.0, 0.0], [2.0, 2.0]]));
+
+        let (a, b) = split_at_point(&line, &[2.0, 0.0].into(), 0.0).unwrap();
+        assert_eq!(a, make_line(vec![[0.0, 0.0], [2.0, 0.0]]));
+        assert_eq!(b, make_line(vec![[2.0, 0.0], [2.0, 2.0]]));
+
+        assert!(split_at_point(&line, &[1.0, 1.0].into(), 0.5).is_none());
+    }
+}
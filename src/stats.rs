@@ -0,0 +1,128 @@
+//! Basic summary statistics of linestrings.
+//!
+//! See [crate::online] for versions which accumulate over streamed points.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::stats::{aabb, centroid, signed_area, winding, Winding};
+//! use simples::Point;
+//!
+//! let square: Vec<Point<Precision, 2>> =
+//!     vec![[0.0, 0.0].into(), [2.0, 0.0].into(), [2.0, 2.0].into(), [0.0, 2.0].into()];
+//! assert_eq!(aabb(&square), Some(([0.0, 0.0], [2.0, 2.0])));
+//! assert_eq!(centroid(&square), Some([1.0, 1.0].into()));
+//! assert_eq!(signed_area(&square), 4.0);
+//! assert_eq!(winding(&square), Some(Winding::Anticlockwise));
+//! ```
+use crate::convert::Coord;
+use crate::metrics::shoelace2;
+use crate::{total_length, CurveKind, Precision};
+use nalgebra::{distance, Point, SVector};
+
+/// Minimum and maximum of each coordinate; None for an empty line.
+pub fn aabb<const D: usize>(
+    line: &[Point<Precision, D>],
+) -> Option<(Coord<Precision, D>, Coord<Precision, D>)> {
+    let first = line.first()?;
+    let (min, max) = line
+        .iter()
+        .fold((*first, *first), |(min, max), p| (min.inf(p), max.sup(p)));
+    Some((min.coords.into(), max.coords.into()))
+}
+
+/// Mean position of the points; None for an empty line.
+///
+/// This is not weighted by edge length, so dense parts of the line pull the centroid towards them.
+pub fn centroid<const D: usize>(line: &[Point<Precision, D>]) -> Option<Point<Precision, D>> {
+    if line.is_empty() {
+        return None;
+    }
+    let sum: SVector<Precision, D> = line.iter().map(|p| p.coords).sum();
+    Some(Point::from(sum / line.len() as Precision))
+}
+
+/// Total length of the line, including the closing edge if closed; see [crate::total_length].
+pub fn length<const D: usize>(
+    line: &[Point<Precision, D>],
+    kind: impl Into<CurveKind>,
+) -> Precision {
+    let open = total_length(line);
+    match (kind.into(), line.first(), line.last()) {
+        (CurveKind::Closed, Some(first), Some(last)) => open + distance(last, first),
+        _ => open,
+    }
+}
+
+/// Number of points, not counting any which repeat the previous point.
+pub fn point_count<const D: usize>(line: &[Point<Precision, D>]) -> usize {
+    let repeats = line.windows(2).filter(|w| w[0] == w[1]).count();
+    line.len() - repeats
+}
+
+/// Area enclosed by a 2D ring, positive if it runs anticlockwise (with the y axis pointing up).
+///
+/// The edge from the last point to the first is implicit.
+/// Parts of a self-intersecting ring which run in opposite directions cancel out.
+pub fn signed_area(ring: &[Point<Precision, 2>]) -> Precision {
+    shoelace2(ring) / 2.0
+}
+
+/// Direction in which a 2D ring runs, with the y axis pointing up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Winding {
+    Anticlockwise,
+    Clockwise,
+}
+
+/// Direction in which a 2D ring runs, by the sign of its [signed_area].
+///
+/// None if the ring encloses no area.
+pub fn winding(ring: &[Point<Precision, 2>]) -> Option<Winding> {
+    let area = shoelace2(ring);
+    if area > 0.0 {
+        Some(Winding::Anticlockwise)
+    } else if area < 0.0 {
+        Some(Winding::Clockwise)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn empty() {
+        let line: Vec<Point<Precision, 3>> = vec![];
+        assert_eq!(aabb(&line), None);
+        assert_eq!(centroid(&line), None);
+        assert_eq!(length(&line, true), 0.0);
+        assert_eq!(point_count(&line), 0);
+        assert_eq!(signed_area(&[]), 0.0);
+        assert_eq!(winding(&[]), None);
+    }
+
+    #[test]
+    fn summary() {
+        let line = make_line(vec![[1.0, 0.0], [4.0, 4.0], [4.0, 4.0], [-2.0, 4.0]]);
+        assert_eq!(aabb(&line), Some(([-2.0, 0.0], [4.0, 4.0])));
+        assert_eq!(centroid(&line), Some([1.75, 3.0].into()));
+        assert_eq!(length(&line, false), 11.0);
+        assert_eq!(point_count(&line), 3);
+    }
+
+    #[test]
+    fn orientation() {
+        let mut ring = make_line(vec![[0.0, 0.0], [3.0, 0.0], [0.0, 2.0]]);
+        assert_eq!(signed_area(&ring), 3.0);
+        assert_eq!(winding(&ring), Some(Winding::Anticlockwise));
+        ring.reverse();
+        assert_eq!(signed_area(&ring), -3.0);
+        assert_eq!(winding(&ring), Some(Winding::Clockwise));
+        let flat = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]]);
+        assert_eq!(winding(&flat), None);
+    }
+}
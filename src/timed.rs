@@ -0,0 +1,57 @@
+//! Points with timestamps, e.g. from GPS tracks.
+use crate::{Lerp, Location, Precision};
+use nalgebra::{distance, Point};
+
+/// Timestamps, in seconds (e.g. since the Unix epoch).
+///
+/// Always double precision, even with the `f32` feature,
+/// as single precision cannot resolve present-day Unix timestamps to better than a couple of minutes.
+pub type Time = f64;
+
+/// A point with the time at which it was recorded.
+///
+/// Distances are purely spatial; interpolation interpolates the time too,
+/// so resampling a timed line gives the (estimated) time at each new point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedPoint<const D: usize> {
+    pub point: Point<Precision, D>,
+    pub time: Time,
+}
+
+impl<const D: usize> TimedPoint<D> {
+    pub fn new(point: Point<Precision, D>, time: Time) -> Self {
+        Self { point, time }
+    }
+}
+
+impl<const D: usize> Lerp for TimedPoint<D> {
+    fn lerp(&self, other: &Self, t: Precision) -> Self {
+        Self {
+            point: self.point.lerp(&other.point, t),
+            time: self.time + (other.time - self.time) * Time::from(t),
+        }
+    }
+}
+
+impl<const D: usize> Location for TimedPoint<D> {
+    fn distance(&self, other: &Self) -> Precision {
+        distance(&self.point, &other.point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simplify::sample::resample_with;
+
+    #[test]
+    fn resample_interpolates_time() {
+        let line = vec![
+            TimedPoint::new([0.0, 0.0].into(), 0.0),
+            TimedPoint::new([4.0, 0.0].into(), 10.0),
+        ];
+        let out = resample_with(&line, 3);
+        assert_eq!(out[1], TimedPoint::new([2.0, 0.0].into(), 5.0));
+    }
+}
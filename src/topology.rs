@@ -0,0 +1,274 @@
+//! Lines which separate labelled faces, as in topological polygon datasets
+//! where each boundary is stored once and shared by the polygons on either side.
+//!
+//! Simplifying each shared line once (keeping its endpoints),
+//! rather than each polygon separately, keeps neighbouring polygons consistent:
+//! [face_boundary] then rebuilds every face from the same simplified lines.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::topology::{face_boundary, LabelledLine};
+//! use simples::simplify::rdp::rdp_reduce;
+//! use simples::Point;
+//!
+//! fn line(coords: &[[Precision; 2]]) -> Vec<Point<Precision, 2>> {
+//!     coords.iter().map(|c| Point::from(*c)).collect()
+//! }
+//!
+//! // two unit squares, "a" to the left of "b", with a wiggly shared edge
+//! let lines = vec![
+//!     LabelledLine::new(line(&[[1.0, 0.0], [1.01, 0.5], [1.0, 1.0]]), "a", "b"),
+//!     LabelledLine::new(line(&[[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]), "a", ""),
+//!     LabelledLine::new(line(&[[1.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0]]), "b", ""),
+//! ];
+//! let simplified: Vec<_> = lines
+//!     .iter()
+//!     .map(|l| l.simplify(|pts| rdp_reduce(pts, 0.1, false)).unwrap())
+//!     .collect();
+//! assert_eq!(face_boundary(&simplified, &"a", 0.0)[0].len(), 5);
+//! assert_eq!(face_boundary(&simplified, &"b", 0.0)[0].len(), 5);
+//! ```
+use crate::edges::{canonicalize_direction, concat_lines_where};
+use crate::error::{Error, Result};
+use crate::split::split_at_distances;
+use crate::Precision;
+use nalgebra::Point;
+
+/// The faces either side of a line, looking along its direction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Faces<F> {
+    pub left: F,
+    pub right: F,
+}
+
+impl<F> Faces<F> {
+    pub fn new(left: F, right: F) -> Self {
+        Self { left, right }
+    }
+
+    /// The faces as seen looking along the line in the other direction.
+    pub fn swapped(self) -> Self {
+        Self {
+            left: self.right,
+            right: self.left,
+        }
+    }
+}
+
+/// A linestring with the faces on its left and right.
+///
+/// Operations which reverse the line also swap its faces, so the labels always stay on the correct side.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelledLine<F, const D: usize> {
+    pub line: Vec<Point<Precision, D>>,
+    pub faces: Faces<F>,
+}
+
+impl<F: Clone, const D: usize> LabelledLine<F, D> {
+    pub fn new(line: Vec<Point<Precision, D>>, left: F, right: F) -> Self {
+        Self {
+            line,
+            faces: Faces::new(left, right),
+        }
+    }
+
+    /// Reverse the line in place, swapping its faces.
+    pub fn reverse(&mut self) {
+        self.line.reverse();
+        self.faces = self.faces.clone().swapped();
+    }
+
+    /// Orient the line as [canonicalize_direction] does, swapping its faces if it is reversed.
+    ///
+    /// Returns whether the line was reversed.
+    pub fn canonicalize(&mut self) -> bool {
+        let reversed = canonicalize_direction(&mut self.line);
+        if reversed {
+            self.faces = self.faces.clone().swapped();
+        }
+        reversed
+    }
+
+    /// Whether the face is on either side of the line.
+    pub fn borders(&self, face: &F) -> bool
+    where
+        F: PartialEq,
+    {
+        &self.faces.left == face || &self.faces.right == face
+    }
+
+    /// Cut the line as [split_at_distances] does; every piece has the same faces.
+    pub fn split_at_distances(&self, distances: &[Precision]) -> Vec<Self> {
+        split_at_distances(&self.line, distances)
+            .into_iter()
+            .map(|line| Self {
+                line,
+                faces: self.faces.clone(),
+            })
+            .collect()
+    }
+
+    /// Replace the line with the output of some simplification or smoothing function,
+    /// keeping its faces.
+    ///
+    /// Fails if the function moves either endpoint,
+    /// as the line would no longer meet its neighbours.
+    pub fn simplify<G>(&self, f: G) -> Result<Self>
+    where
+        G: FnOnce(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        let line = f(&self.line);
+        if line.first() != self.line.first() || line.last() != self.line.last() {
+            return Err(Error::invalid("f", "must keep the line's endpoints"));
+        }
+        Ok(Self {
+            line,
+            faces: self.faces.clone(),
+        })
+    }
+}
+
+/// Join lines whose endpoints coincide (within `tolerance`) as [crate::edges::concat_lines] does,
+/// but only where the joined lines have the same faces on the same sides.
+///
+/// Lines are reversed (and their faces swapped) as necessary.
+pub fn merge_labelled<F: Clone + PartialEq, const D: usize>(
+    lines: &[LabelledLine<F, D>],
+    tolerance: Precision,
+) -> Vec<LabelledLine<F, D>> {
+    let points: Vec<_> = lines.iter().map(|l| l.line.as_slice()).collect();
+    concat_lines_where(&points, tolerance, |seed, idx, reversed| {
+        let seed = &lines[seed].faces;
+        let faces = &lines[idx].faces;
+        if reversed {
+            faces.left == seed.right && faces.right == seed.left
+        } else {
+            faces == seed
+        }
+    })
+    .into_iter()
+    .map(|(line, members)| {
+        // the path keeps the seed's direction, and the seed has the lowest index of its members
+        // (lines joined at the head come before it in `members`, possibly reversed)
+        let seed = *members.iter().min().unwrap();
+        LabelledLine {
+            line,
+            faces: lines[seed].faces.clone(),
+        }
+    })
+    .collect()
+}
+
+/// Rebuild the boundary of a face from the lines which border it,
+/// joining endpoints which coincide within `tolerance`.
+///
+/// Each line is oriented so that the face is on its left,
+/// so in 2D with the y axis pointing up, exterior rings run anticlockwise and holes clockwise.
+/// Lines with the face on both sides are ignored.
+/// Rings are closed (i.e. the last point repeats the first) if the lines form a complete loop.
+pub fn face_boundary<F: Clone + PartialEq, const D: usize>(
+    lines: &[LabelledLine<F, D>],
+    face: &F,
+    tolerance: Precision,
+) -> Vec<Vec<Point<Precision, D>>> {
+    let oriented: Vec<_> = lines
+        .iter()
+        .filter_map(|l| {
+            if &l.faces.left == face && &l.faces.right != face {
+                Some(l.line.clone())
+            } else if &l.faces.right == face && &l.faces.left != face {
+                let mut line = l.line.clone();
+                line.reverse();
+                Some(line)
+            } else {
+                None
+            }
+        })
+        .collect();
+    concat_lines_where(&oriented, tolerance, |_, _, reversed| !reversed)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn reverse_swaps() {
+        let mut line = LabelledLine::new(make_line(vec![[1.0, 0.0], [0.0, 0.0]]), 1, 2);
+        assert!(line.canonicalize());
+        assert_eq!(line.faces, Faces::new(2, 1));
+        line.reverse();
+        assert_eq!(line.faces, Faces::new(1, 2));
+    }
+
+    #[test]
+    fn merge_respects_faces() {
+        let lines = vec![
+            LabelledLine::new(make_line(vec![[0.0, 0.0], [1.0, 0.0]]), 1, 2),
+            // reversed, so its faces are swapped too
+            LabelledLine::new(make_line(vec![[2.0, 0.0], [1.0, 0.0]]), 2, 1),
+            // continues the path, but separates different faces
+            LabelledLine::new(make_line(vec![[2.0, 0.0], [3.0, 0.0]]), 1, 3),
+        ];
+        let merged = merge_labelled(&lines, 0.0);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged[0],
+            LabelledLine::new(make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]), 1, 2)
+        );
+        assert_eq!(merged[1], lines[2]);
+    }
+
+    #[test]
+    fn merge_at_head() {
+        // the second line is joined (reversed) before the start of the first
+        let lines = vec![
+            LabelledLine::new(make_line(vec![[1.0, 0.0], [2.0, 0.0]]), 1, 2),
+            LabelledLine::new(make_line(vec![[1.0, 0.0], [0.0, 0.0]]), 2, 1),
+        ];
+        let merged = merge_labelled(&lines, 0.0);
+        assert_eq!(
+            merged,
+            vec![LabelledLine::new(
+                make_line(vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]),
+                1,
+                2
+            )]
+        );
+    }
+
+    #[test]
+    fn boundary_orientation() {
+        let lines = vec![
+            LabelledLine::new(make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]), 1, 0),
+            LabelledLine::new(make_line(vec![[0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]), 0, 1),
+        ];
+        let rings = face_boundary(&lines, &1, 0.0);
+        assert_eq!(
+            rings,
+            vec![make_line(vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [1.0, 1.0],
+                [0.0, 1.0],
+                [0.0, 0.0]
+            ])]
+        );
+        assert!(face_boundary(&lines, &2, 0.0).is_empty());
+    }
+
+    #[test]
+    fn simplify_keeps_endpoints() {
+        let line = LabelledLine::new(make_line(vec![[0.0, 0.0], [1.0, 0.1], [2.0, 0.0]]), 1, 2);
+        let simplified = line.simplify(|l| vec![l[0], l[2]]).unwrap();
+        assert_eq!(simplified.line.len(), 2);
+        assert_eq!(simplified.faces, line.faces);
+        assert!(line.simplify(|l| l[..2].to_vec()).is_err());
+    }
+}
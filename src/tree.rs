@@ -0,0 +1,317 @@
+//! Trees of linestrings which share branch points, such as neuron skeletons or river networks.
+//!
+//! A [PolylineTree] is stored as a list of nodes, each with an optional parent.
+//! Its "key" nodes (roots, leaves and branch points) divide it into unbranched branches,
+//! which can be simplified, smoothed or resampled like any other linestring,
+//! while the key nodes are never moved or dropped, so connectivity is kept intact.
+//!
+//! ```
+//! # use simples::Precision;
+//! use simples::tree::PolylineTree;
+//! use simples::Point;
+//!
+//! // a "Y" shape, with a kink in the stem
+//! let points: Vec<Point<Precision, 2>> = vec![
+//!     [0.0, 0.0].into(),
+//!     [0.01, 1.0].into(),
+//!     [0.0, 2.0].into(),
+//!     [-1.0, 3.0].into(),
+//!     [1.0, 3.0].into(),
+//! ];
+//! let parents = vec![None, Some(0), Some(1), Some(2), Some(2)];
+//! let tree = PolylineTree::new(points, parents).unwrap();
+//! assert_eq!(tree.branches().len(), 3);
+//!
+//! let simplified = tree.rdp(0.1);
+//! assert_eq!(simplified.len(), 4);
+//! ```
+use crate::error::{Error, Result};
+use crate::simplify::rdp::rdp_reduce;
+use crate::simplify::sample::sample_every;
+use crate::smooth::{smooth_convolve, Kernel};
+use crate::Precision;
+use nalgebra::Point;
+
+/// A forest of linestrings, where each node has at most one parent.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolylineTree<const D: usize> {
+    points: Vec<Point<Precision, D>>,
+    parents: Vec<Option<usize>>,
+}
+
+/// Where a node produced by [PolylineTree::map_branches_with_origin] came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Origin {
+    /// A key node, copied from this index of the original tree.
+    Node(usize),
+    /// An interior point of the branch at this index of [PolylineTree::branches].
+    Branch(usize),
+}
+
+impl<const D: usize> PolylineTree<D> {
+    /// `parents[i]` is the index of the parent of node `i`, or None if it is a root.
+    ///
+    /// Fails if the lists are different lengths, a parent index is out of range,
+    /// or the parents form a cycle.
+    pub fn new(points: Vec<Point<Precision, D>>, parents: Vec<Option<usize>>) -> Result<Self> {
+        if points.len() != parents.len() {
+            return Err(Error::invalid(
+                "parents",
+                "must be the same length as points",
+            ));
+        }
+        if parents.iter().flatten().any(|p| *p >= points.len()) {
+            return Err(Error::invalid("parents", "index out of range"));
+        }
+        let tree = Self { points, parents };
+        let n_reachable: usize = tree.roots().map(|r| tree.descendants(r).len()).sum();
+        if n_reachable != tree.len() {
+            return Err(Error::invalid("parents", "must not contain cycles"));
+        }
+        Ok(tree)
+    }
+
+    pub fn points(&self) -> &[Point<Precision, D>] {
+        &self.points
+    }
+
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parents
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Indices of nodes without parents.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.parents
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, p)| p.is_none().then_some(idx))
+    }
+
+    /// Indices of every node's children.
+    pub fn children(&self) -> Vec<Vec<usize>> {
+        let mut out = vec![Vec::default(); self.len()];
+        for (idx, parent) in self.parents.iter().enumerate() {
+            if let Some(p) = parent {
+                out[*p].push(idx);
+            }
+        }
+        out
+    }
+
+    /// Indices of the node and all of its descendants, depth-first.
+    fn descendants(&self, node: usize) -> Vec<usize> {
+        let children = self.children();
+        let mut out = Vec::default();
+        let mut stack = vec![node];
+        while let Some(idx) = stack.pop() {
+            out.push(idx);
+            stack.extend(children[idx].iter().rev());
+        }
+        out
+    }
+
+    /// Whether each node is a root, leaf or branch point, i.e. does not have exactly one parent and one child.
+    pub fn key_nodes(&self) -> Vec<bool> {
+        let children = self.children();
+        self.parents
+            .iter()
+            .zip(children.iter())
+            .map(|(p, c)| p.is_none() || c.len() != 1)
+            .collect()
+    }
+
+    /// Node indices of every unbranched path between key nodes,
+    /// each starting with the key node nearer the root.
+    ///
+    /// Branches are in depth-first order, so every branch's first node
+    /// is a root or the last node of an earlier branch.
+    /// Isolated roots (with no children) are not part of any branch.
+    pub fn branches(&self) -> Vec<Vec<usize>> {
+        let children = self.children();
+        let mut out = Vec::default();
+        let mut stack: Vec<(usize, usize)> = Vec::default();
+        for root in self.roots() {
+            stack.extend(children[root].iter().rev().map(|c| (root, *c)));
+            while let Some((start, mut idx)) = stack.pop() {
+                let mut branch = vec![start, idx];
+                while children[idx].len() == 1 {
+                    idx = children[idx][0];
+                    branch.push(idx);
+                }
+                stack.extend(children[idx].iter().rev().map(|c| (idx, *c)));
+                out.push(branch);
+            }
+        }
+        out
+    }
+
+    /// Replace the interior of every branch with the output of `f`, which is given the branch's points.
+    ///
+    /// The ends of each branch (key nodes) are kept in place, whatever `f` does to them;
+    /// if `f` returns fewer than 2 points, the branch becomes a single edge.
+    /// Nodes of the new tree are in the order of [PolylineTree::branches],
+    /// with every parent before its children.
+    pub fn map_branches<F>(&self, f: F) -> Self
+    where
+        F: FnMut(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        self.map_branches_with_origin(f).0
+    }
+
+    /// As [PolylineTree::map_branches], also returning where each new node came from.
+    pub(crate) fn map_branches_with_origin<F>(&self, mut f: F) -> (Self, Vec<Origin>)
+    where
+        F: FnMut(&[Point<Precision, D>]) -> Vec<Point<Precision, D>>,
+    {
+        let mut out = Self {
+            points: Vec::with_capacity(self.len()),
+            parents: Vec::with_capacity(self.len()),
+        };
+        let mut origins = Vec::with_capacity(self.len());
+        // index of each key node in the new tree
+        let mut new_idx: Vec<Option<usize>> = vec![None; self.len()];
+
+        let branches = self.branches();
+        let mut branch_iter = branches.iter().enumerate().peekable();
+        for root in self.roots() {
+            new_idx[root] = Some(out.push(self.points[root], None));
+            origins.push(Origin::Node(root));
+            // branches are depth-first, so those from this root come next
+            while let Some((branch_idx, branch)) =
+                branch_iter.next_if(|(_, b)| new_idx[b[0]].is_some())
+            {
+                let original: Vec<_> = branch.iter().map(|idx| self.points[*idx]).collect();
+                let processed = f(&original);
+                let mut parent = new_idx[branch[0]];
+                if processed.len() > 2 {
+                    for p in processed[1..(processed.len() - 1)].iter() {
+                        parent = Some(out.push(*p, parent));
+                        origins.push(Origin::Branch(branch_idx));
+                    }
+                }
+                let end = branch[branch.len() - 1];
+                new_idx[end] = Some(out.push(self.points[end], parent));
+                origins.push(Origin::Node(end));
+            }
+        }
+        (out, origins)
+    }
+
+    /// Add a node, returning its index.
+    fn push(&mut self, point: Point<Precision, D>, parent: Option<usize>) -> usize {
+        self.points.push(point);
+        self.parents.push(parent);
+        self.points.len() - 1
+    }
+
+    /// Simplify every branch using RDP; see [rdp_reduce].
+    pub fn rdp(&self, epsilon: Precision) -> Self {
+        self.map_branches(|b| rdp_reduce(b, epsilon, false))
+    }
+
+    /// Resample every branch with points roughly `sample_distance` apart; see [sample_every].
+    ///
+    /// Each branch's spacing is adjusted so that its points are evenly spaced from one key node to the next.
+    pub fn resample(&self, sample_distance: Precision) -> Self {
+        self.map_branches(|b| {
+            let length = crate::total_length(b);
+            let n_edges = (length / sample_distance).round().max(1.0);
+            let mut out = sample_every(b, length / n_edges, 0.0, false).0;
+            // floating point error may leave a sample just short of the end
+            if out.len() as Precision > n_edges {
+                out.truncate(n_edges as usize);
+            }
+            out.push(b[b.len() - 1]);
+            out
+        })
+    }
+
+    /// Smooth every branch with a kernel; see [smooth_convolve].
+    pub fn smooth<K: Kernel + Clone>(&self, kernel: K) -> Self {
+        self.map_branches(|b| smooth_convolve(b, kernel.clone(), false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smooth::Linear;
+
+    /// A "Y" whose stem and arms are sampled every 0.5.
+    fn y_tree() -> PolylineTree<2> {
+        let mut points: Vec<Point<Precision, 2>> = Vec::default();
+        let mut parents = Vec::default();
+        for i in 0..=4usize {
+            points.push([0.0, i as Precision * 0.5].into());
+            parents.push(i.checked_sub(1));
+        }
+        for dir in [-1.0, 1.0] {
+            for i in 1..=4 {
+                points.push([dir * i as Precision * 0.5, 2.0 + i as Precision * 0.5].into());
+                parents.push(Some(if i == 1 { 4 } else { points.len() - 2 }));
+            }
+        }
+        PolylineTree::new(points, parents).unwrap()
+    }
+
+    #[test]
+    fn invalid() {
+        let points: Vec<Point<Precision, 1>> = vec![[0.0].into(), [1.0].into()];
+        assert!(PolylineTree::new(points.clone(), vec![None]).is_err());
+        assert!(PolylineTree::new(points.clone(), vec![None, Some(2)]).is_err());
+        assert!(PolylineTree::new(points, vec![Some(1), Some(0)]).is_err());
+    }
+
+    #[test]
+    fn branches() {
+        let tree = y_tree();
+        assert_eq!(
+            tree.key_nodes().iter().filter(|k| **k).count(),
+            4,
+            "root, branch point and 2 leaves"
+        );
+        assert_eq!(
+            tree.branches(),
+            vec![
+                vec![0, 1, 2, 3, 4],
+                vec![4, 5, 6, 7, 8],
+                vec![4, 9, 10, 11, 12]
+            ]
+        );
+    }
+
+    #[test]
+    fn key_nodes_pinned() {
+        let tree = y_tree();
+        let keys = [[0.0, 0.0], [0.0, 2.0], [-2.0, 4.0], [2.0, 4.0]];
+        for processed in [
+            tree.rdp(0.1),
+            tree.resample(0.3),
+            tree.smooth(Linear::new(1.0)),
+            // even if the function moves the ends
+            tree.map_branches(|b| b.iter().map(|p| p * 2.0).collect()),
+        ] {
+            for key in keys {
+                let key = Point::from(key);
+                assert!(processed.points().contains(&key));
+            }
+            assert_eq!(processed.roots().count(), 1);
+            assert_eq!(processed.branches().len(), 3);
+            let rebuilt =
+                PolylineTree::new(processed.points().to_vec(), processed.parents().to_vec());
+            assert!(rebuilt.is_ok());
+        }
+        assert_eq!(tree.rdp(0.1).len(), 4);
+        // stem of length 2 and diagonal arms of length 2.83
+        assert_eq!(tree.resample(0.3).len(), 1 + 7 + 2 * 9);
+    }
+}
@@ -1,86 +1,253 @@
-use lru::LruCache;
+//! Utilities for measuring linestrings.
 use nalgebra::{distance, Point};
 
-use crate::Precision;
+use crate::simplify::sample::point_at_length;
+use crate::{cumulative_lengths, CurveKind, Precision};
 
 /// Find length of a segment between any two points of a linestring.
 ///
-/// Optionally caches pairwise distances.
-struct DistanceFinder<'a, const D: usize> {
+/// Builds the cumulative lengths of the linestring once, in O(n),
+/// so that each length query is O(1),
+/// and each point found by distance along the line (e.g. to resample it many times) is O(log n).
+///
+/// ```
+/// # use simples::Precision;
+/// use simples::util::DistanceFinder;
+/// use simples::Point;
+///
+/// let line: Vec<Point<Precision, 2>> = vec![[0.0, 0.0].into(), [3.0, 0.0].into(), [3.0, 4.0].into()];
+/// let finder = DistanceFinder::new(&line, true);
+/// assert_eq!(finder.length(0, 2), Some(7.0));
+/// assert_eq!(finder.length(2, 1), Some(8.0));
+/// assert_eq!(finder.total_length(), 12.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DistanceFinder<'a, const D: usize> {
     points: &'a [Point<Precision, D>],
     closed: bool,
-    cache: Option<LruCache<(usize, usize), Precision>>,
-    total_length: Option<Precision>,
+    cumulative: Vec<Precision>,
+    closing_length: Precision,
 }
 
 impl<'a, const D: usize> DistanceFinder<'a, D> {
-    pub fn new(points: &'a [Point<Precision, D>], closed: bool, cache_size: Option<usize>) -> Self {
+    /// `kind` is [CurveKind::Closed] (or `true`) where the linestring represents a polygon and there is an edge from the last point to the first.
+    pub fn new(points: &'a [Point<Precision, D>], kind: impl Into<CurveKind>) -> Self {
+        let closed = kind.into().is_closed();
+        let closing_length = match (points.first(), points.last()) {
+            (Some(first), Some(last)) if closed => distance(last, first),
+            _ => 0.0,
+        };
         Self {
             points,
             closed,
-            cache: cache_size.map(|s| LruCache::new(NonZeroUsize::new(s).unwrap())),
-            total_length: None,
+            cumulative: cumulative_lengths(points),
+            closing_length,
         }
     }
 
-    pub fn total_length(&mut self) -> Precision {
-        if let Some(t) = self.total_length {
-            return t;
-        }
-
-        if self.points.len() < 2 {
-            return 0.0;
-        }
-        let mut out = (0..self.points.len() - 1)
-            .map(|a| self.length_pair(a, a + 1))
-            .sum::<Precision>();
-        if self.closed {
-            out += self.length_pair(self.points.len() - 1, 0);
-        }
-        self.total_length = Some(out);
+    pub fn points(&self) -> &'a [Point<Precision, D>] {
+        self.points
+    }
 
-        out
+    pub fn is_closed(&self) -> bool {
+        self.closed
     }
 
-    /// Ascending order.
-    ///
-    /// For closed linestrings, the closing pair is (last_idx, 0).
-    /// Panics if indices do not exist.
-    fn length_pair(&mut self, start: usize, end: usize) -> Precision {
-        if let Some(c) = self.cache.as_mut() {
-            let key = (start, end);
-            let r = c.get_or_insert(key, || distance(&self.points[start], &self.points[end]));
-            *r
-        } else {
-            distance(&self.points[start], &self.points[end])
-        }
+    /// Total length of the linestring, including the closing edge if closed.
+    pub fn total_length(&self) -> Precision {
+        self.cumulative.last().copied().unwrap_or(0.0) + self.closing_length
     }
 
     /// Length in ascending order, i.e. `start` must be lower than `end` on a non-closed linestring.
+    ///
+    /// On a closed linestring, `end < start` goes through the closing edge (last_idx, 0).
     /// [None] if indices are not present, or `end < start` with a non-closed linestring.
-    pub fn length(&mut self, start: usize, end: usize) -> Option<Precision> {
+    pub fn length(&self, start: usize, end: usize) -> Option<Precision> {
         use std::cmp::Ordering::*;
         if start >= self.points.len() || end >= self.points.len() {
             return None;
         }
 
         match start.cmp(&end) {
-            Less => Some(
-                (start..end)
-                    .map(|i| self.length_pair(i, i + 1))
-                    .sum::<Precision>(),
-            ),
+            Less => Some(self.cumulative[end] - self.cumulative[start]),
             Equal => Some(0.0),
             Greater => {
                 if !self.closed {
                     return None;
                 }
-                let out = (start..(self.points.len() - 1))
-                    .chain(0..end)
-                    .map(|i| self.length_pair(i, i + 1))
-                    .sum::<Precision>();
-                Some(out + self.length_pair(self.points.len() - 1, 0))
+                Some(self.total_length() - (self.cumulative[start] - self.cumulative[end]))
+            }
+        }
+    }
+
+    /// Distance along the linestring to each point.
+    pub fn lengths(&self) -> &[Precision] {
+        &self.cumulative
+    }
+
+    /// The point `length` along the linestring (including the closing edge if closed), clamped to its ends;
+    /// [None] for an empty linestring.
+    pub fn point_at(&self, length: Precision) -> Option<Point<Precision, D>> {
+        let last = self.points.last()?;
+        let open_length = self.cumulative[self.cumulative.len() - 1];
+        if length > open_length && self.closing_length > 0.0 {
+            let frac = ((length - open_length) / self.closing_length).min(1.0);
+            return Some(last + (self.points[0] - last) * frac);
+        }
+        Some(point_at_length(self.points, &self.cumulative, length))
+    }
+
+    /// As [crate::simplify::sample::sample_every], placing a point every `sample_distance` along the linestring
+    /// (including the closing edge if closed), starting `offset` along it.
+    ///
+    /// Returns the resampled points and the distance from the last resampled point to the end
+    /// (or the whole length of the linestring if no points were sampled).
+    /// Each point is placed independently, so error does not accumulate along the line;
+    /// this means that where a sample lands within rounding error of the end,
+    /// it may be included where `sample_every` would leave it out, or vice versa.
+    ///
+    /// `sample_distance` must be positive and `offset` must be non-negative (panics if these are invalid).
+    ///
+    /// ```
+    /// # use simples::Precision;
+    /// use simples::simplify::sample::sample_every;
+    /// use simples::util::DistanceFinder;
+    /// use simples::Point;
+    ///
+    /// let line: Vec<Point<Precision, 2>> = (0..1000).map(|i| [i as Precision, (i % 2) as Precision].into()).collect();
+    /// let finder = DistanceFinder::new(&line, false);
+    /// for spacing in [10.0, 50.0, 100.0] {
+    ///     let (points, _) = finder.sample_every(spacing, 0.0);
+    ///     assert_eq!(points.len(), sample_every(&line, spacing, 0.0, false).0.len());
+    /// }
+    /// ```
+    pub fn sample_every(
+        &self,
+        sample_distance: Precision,
+        offset: Precision,
+    ) -> (Vec<Point<Precision, D>>, Precision) {
+        if sample_distance <= 0.0 {
+            panic!("`sample_distance` must be positive");
+        }
+        if offset < 0.0 {
+            panic!("`offset` must be non-negative");
+        }
+        if self.points.len() <= 1 {
+            return (self.points.to_vec(), 0.0);
+        }
+        let total = self.total_length();
+        if offset > total {
+            return (vec![], total);
+        }
+        let n_points = ((total - offset) / sample_distance).floor() as usize + 1;
+        let out = (0..n_points)
+            .filter_map(|idx| self.point_at(offset + idx as Precision * sample_distance))
+            .collect();
+        let last_placed = offset + (n_points - 1) as Precision * sample_distance;
+        (out, total - last_placed)
+    }
+}
+
+/// Sorted, deduplicated indices of the `preserved` points of a linestring with `len` points,
+/// plus its first and last points, for splitting it into sections which are processed independently.
+///
+/// Panics if any preserved index is out of bounds, or the linestring is empty.
+pub(crate) fn normalise_anchors(len: usize, preserved: &[usize]) -> Vec<usize> {
+    let last = len - 1;
+    let mut anchors = Vec::with_capacity(preserved.len() + 2);
+    anchors.push(0);
+    for idx in preserved {
+        assert!(*idx <= last, "Preserved index {idx} out of bounds");
+        anchors.push(*idx);
+    }
+    anchors.push(last);
+    anchors.sort_unstable();
+    anchors.dedup();
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{make_line, tol};
+
+    #[test]
+    fn lengths() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 2.0], [0.0, 2.0]]);
+        let open = DistanceFinder::new(&line, false);
+        assert_eq!(open.total_length(), 4.0);
+        assert_eq!(open.length(1, 3), Some(3.0));
+        assert_eq!(open.length(2, 2), Some(0.0));
+        assert_eq!(open.length(3, 1), None);
+        assert_eq!(open.length(0, 4), None);
+
+        let closed = DistanceFinder::new(&line, true);
+        assert_eq!(closed.total_length(), 6.0);
+        assert_eq!(closed.length(1, 3), Some(3.0));
+        assert_eq!(closed.length(3, 1), Some(3.0));
+        assert_eq!(closed.length(3, 0), Some(2.0));
+    }
+
+    #[test]
+    fn sample_every_matches() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [3.0, 4.0],
+            [3.0, 4.0],
+            [3.0, 6.5],
+            [0.0, 6.5],
+        ]);
+        let finder = DistanceFinder::new(&line, false);
+        assert_eq!(finder.total_length(), 10.5);
+        assert_eq!(finder.lengths(), cumulative_lengths(&line));
+        assert_eq!(finder.point_at(6.0), Some([3.0, 5.0].into()));
+        assert_eq!(finder.point_at(20.0), Some(line[4]));
+        for (spacing, offset) in [(1.0, 0.0), (0.5, 0.25), (2.5, 1.0), (4.0, 3.0), (20.0, 0.0)] {
+            let (points, remainder) = finder.sample_every(spacing, offset);
+            let (expected, expected_remainder) =
+                crate::simplify::sample::sample_every(&line, spacing, offset, false);
+            assert_eq!(
+                points.len(),
+                expected.len(),
+                "spacing {spacing}, offset {offset}"
+            );
+            for (p, e) in points.iter().zip(expected.iter()) {
+                assert!((p - e).norm() < tol(1e-12));
             }
+            assert!((remainder - expected_remainder).abs() < tol(1e-12));
         }
+        assert_eq!(finder.sample_every(1.0, 11.0), (vec![], 10.5));
+
+        let empty: Vec<Point<Precision, 2>> = vec![];
+        let finder = DistanceFinder::new(&empty, false);
+        assert_eq!(finder.point_at(1.0), None);
+        assert_eq!(finder.sample_every(1.0, 0.0), (vec![], 0.0));
+    }
+
+    #[test]
+    fn point_at_closed() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 2.0], [0.0, 2.0]]);
+        let finder = DistanceFinder::new(&line, true);
+        assert_eq!(finder.point_at(5.0), Some([0.0, 1.0].into()));
+        assert_eq!(finder.point_at(7.0), Some(line[0]));
+        let (points, remainder) = finder.sample_every(2.0, 0.0);
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[3], line[0]);
+        assert_eq!(remainder, 0.0);
+    }
+    #[test]
+    fn anchors() {
+        assert_eq!(normalise_anchors(6, &[3, 0, 3, 1]), vec![0, 1, 3, 5]);
+        assert_eq!(normalise_anchors(1, &[]), vec![0]);
+    }
+
+    #[test]
+    fn degenerate() {
+        let empty = make_line(vec![]);
+        assert_eq!(DistanceFinder::new(&empty, true).total_length(), 0.0);
+        let single = make_line(vec![[1.0, 1.0]]);
+        let finder = DistanceFinder::new(&single, true);
+        assert_eq!(finder.total_length(), 0.0);
+        assert_eq!(finder.length(0, 0), Some(0.0));
     }
 }
@@ -0,0 +1,277 @@
+//! Check linestrings for features which cause algorithms to misbehave.
+//!
+//! Also check that the outputs of simplification algorithms satisfy their invariants.
+use crate::clean::{dedup_consecutive, drop_non_finite};
+use crate::simplify::rdp::proj_dist2;
+use crate::Precision;
+use nalgebra::{distance, distance_squared, Point};
+use std::fmt;
+
+/// Problems with a linestring which would cause algorithms to misbehave.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// The point at this index has a NaN or infinite coordinate.
+    NonFinite { index: usize },
+    /// The point at this index is the same as the previous point, i.e. the edge ending here has zero length.
+    Duplicate { index: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NonFinite { index } => {
+                write!(f, "point {index} has a non-finite coordinate")
+            }
+            ValidationError::Duplicate { index } => {
+                write!(f, "point {index} duplicates the previous point")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Basic information about a valid linestring.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineStats {
+    pub n_points: usize,
+    pub length: Precision,
+    /// 0.0 if there are no edges.
+    pub min_edge_length: Precision,
+    /// 0.0 if there are no edges.
+    pub max_edge_length: Precision,
+}
+
+/// Check that a linestring has only finite coordinates and no zero-length edges.
+///
+/// Returns the first problem found, or some statistics about the line if there are none.
+/// See [clean_line] to fix these problems.
+pub fn validate_line<const D: usize>(
+    line: &[Point<Precision, D>],
+) -> Result<LineStats, ValidationError> {
+    let mut stats = LineStats {
+        n_points: line.len(),
+        length: 0.0,
+        min_edge_length: Precision::INFINITY,
+        max_edge_length: 0.0,
+    };
+    for (index, p) in line.iter().enumerate() {
+        if p.iter().any(|c| !c.is_finite()) {
+            return Err(ValidationError::NonFinite { index });
+        }
+        if index == 0 {
+            continue;
+        }
+        let edge_length = distance(&line[index - 1], p);
+        if edge_length == 0.0 {
+            return Err(ValidationError::Duplicate { index });
+        }
+        stats.length += edge_length;
+        stats.min_edge_length = stats.min_edge_length.min(edge_length);
+        stats.max_edge_length = stats.max_edge_length.max(edge_length);
+    }
+    if line.len() < 2 {
+        stats.min_edge_length = 0.0;
+    }
+    Ok(stats)
+}
+
+/// Drop points with non-finite coordinates, then consecutive duplicate points,
+/// so that the output passes [validate_line].
+pub fn clean_line<const D: usize>(line: &[Point<Precision, D>]) -> Vec<Point<Precision, D>> {
+    dedup_consecutive(&drop_non_finite(line))
+}
+
+/// Ways in which a simplified linestring fails to be a valid simplification of the original.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SimplificationError {
+    /// The first or last point of the simplified line differs from the original's.
+    EndpointMoved,
+    /// The point at this index of the simplified line is not in the original,
+    /// or is out of order.
+    NotSubsequence { index: usize },
+    /// The point at this index of the simplified line has a NaN or infinite coordinate.
+    NonFinite { index: usize },
+    /// The point at this index of the original line, which was dropped,
+    /// is further than the allowed distance from the simplified line.
+    DeviationExceeded { index: usize, deviation: Precision },
+}
+
+impl fmt::Display for SimplificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimplificationError::EndpointMoved => write!(f, "endpoints were not preserved"),
+            SimplificationError::NotSubsequence { index } => {
+                write!(
+                    f,
+                    "simplified point {index} is not in order in the original"
+                )
+            }
+            SimplificationError::NonFinite { index } => {
+                write!(f, "simplified point {index} has a non-finite coordinate")
+            }
+            SimplificationError::DeviationExceeded { index, deviation } => {
+                write!(
+                    f,
+                    "original point {index} is {deviation} from the simplified line"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimplificationError {}
+
+/// Check that `simplified` is a valid simplification of `original`:
+/// it has only finite coordinates, starts and ends at the same points,
+/// consists of points of the original in the same order,
+/// and every dropped point is within `epsilon` of the simplified edge which replaced it.
+///
+/// Use an infinite `epsilon` to skip the deviation check.
+pub fn check_simplified<const D: usize>(
+    original: &[Point<Precision, D>],
+    simplified: &[Point<Precision, D>],
+    epsilon: Precision,
+) -> Result<(), SimplificationError> {
+    if let Some(index) = simplified
+        .iter()
+        .position(|p| p.iter().any(|c| !c.is_finite()))
+    {
+        return Err(SimplificationError::NonFinite { index });
+    }
+    match (original, simplified) {
+        ([], []) => return Ok(()),
+        ([o_first, .., o_last], [s_first, .., s_last])
+            if o_first == s_first && o_last == s_last => {}
+        ([o], [s]) if o == s => return Ok(()),
+        _ => return Err(SimplificationError::EndpointMoved),
+    }
+
+    // greedily match all but the last point, which must match the original's last point
+    let mut kept = Vec::with_capacity(simplified.len());
+    let mut candidates = original[..(original.len() - 1)].iter().enumerate();
+    for (index, p) in simplified[..(simplified.len() - 1)].iter().enumerate() {
+        match candidates.find(|(_, q)| *q == p) {
+            Some((orig_idx, _)) => kept.push(orig_idx),
+            None => return Err(SimplificationError::NotSubsequence { index }),
+        }
+    }
+    kept.push(original.len() - 1);
+
+    if epsilon.is_infinite() {
+        return Ok(());
+    }
+    let epsilon_sq = epsilon * epsilon;
+    for pair in kept.windows(2) {
+        let (start, end) = (&original[pair[0]], &original[pair[1]]);
+        let length_sq = distance_squared(start, end);
+        for (index, p) in original.iter().enumerate().take(pair[1]).skip(pair[0] + 1) {
+            let d2 = proj_dist2(start, end, p, length_sq);
+            if d2 > epsilon_sq {
+                return Err(SimplificationError::DeviationExceeded {
+                    index,
+                    deviation: d2.sqrt(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Panic if `simplified` is not a valid simplification of `original`; see [check_simplified].
+pub fn assert_simplified<const D: usize>(
+    original: &[Point<Precision, D>],
+    simplified: &[Point<Precision, D>],
+    epsilon: Precision,
+) {
+    if let Err(e) = check_simplified(original, simplified, epsilon) {
+        panic!("invalid simplification: {e}");
+    }
+}
+
+/// As [assert_simplified], but only in debug builds.
+///
+/// The simplification algorithms do not call this themselves;
+/// call it on their output to catch integration bugs during development.
+pub fn debug_assert_simplified<const D: usize>(
+    original: &[Point<Precision, D>],
+    simplified: &[Point<Precision, D>],
+    epsilon: Precision,
+) {
+    if cfg!(debug_assertions) {
+        assert_simplified(original, simplified, epsilon);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn validate() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 2.0]]);
+        assert_eq!(
+            validate_line(&line),
+            Ok(LineStats {
+                n_points: 3,
+                length: 3.0,
+                min_edge_length: 1.0,
+                max_edge_length: 2.0
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_then_clean() {
+        let line = make_line(vec![
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [Precision::NAN, 0.0],
+            [1.0, 0.0],
+            [1.0, 2.0],
+        ]);
+        assert_eq!(
+            validate_line(&line),
+            Err(ValidationError::NonFinite { index: 2 })
+        );
+        let no_nan = drop_non_finite(&line);
+        assert_eq!(
+            validate_line(&no_nan),
+            Err(ValidationError::Duplicate { index: 2 })
+        );
+        let cleaned = clean_line(&line);
+        assert_eq!(cleaned, make_line(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 2.0]]));
+        assert!(validate_line(&cleaned).is_ok());
+    }
+
+    #[test]
+    fn simplified() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.1], [2.0, 0.0], [3.0, 5.0]]);
+        let simple = make_line(vec![[0.0, 0.0], [2.0, 0.0], [3.0, 5.0]]);
+        assert_eq!(check_simplified(&line, &simple, 0.1), Ok(()));
+        assert_eq!(
+            check_simplified(&line, &simple, 0.05),
+            Err(SimplificationError::DeviationExceeded {
+                index: 1,
+                deviation: 0.1
+            })
+        );
+        assert_eq!(
+            check_simplified(&line, &line[..3], 1.0),
+            Err(SimplificationError::EndpointMoved)
+        );
+        let reordered = make_line(vec![[0.0, 0.0], [2.0, 0.0], [1.0, 0.1], [3.0, 5.0]]);
+        assert_eq!(
+            check_simplified(&line, &reordered, Precision::INFINITY),
+            Err(SimplificationError::NotSubsequence { index: 2 })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn assert_invalid() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]]);
+        assert_simplified(&line, &[line[0], line[1]], 1.0);
+    }
+}
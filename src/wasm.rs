@@ -0,0 +1,151 @@
+//! Entry points for WebAssembly, taking and returning flat coordinate arrays.
+//!
+//! Lines are passed as `[x0, y0, x1, y1, ...]` (or `[x0, y0, z0, ...]` with `dim = 3`),
+//! which `wasm-bindgen` maps to and from a JavaScript `Float64Array` (`Float32Array` with the `f32` feature).
+//! Invalid input is reported as an error message, which is thrown as an exception in JavaScript.
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::simplify::{rdp, sample, vw};
+use crate::smooth::{smooth_convolve, Gaussian};
+use crate::{Error, Point, Precision};
+
+type FlatResult = std::result::Result<Vec<Precision>, String>;
+
+fn to_points<const D: usize>(coords: &[Precision]) -> Vec<Point<Precision, D>> {
+    coords
+        .chunks_exact(D)
+        .map(|c| Point::from(<[Precision; D]>::try_from(c).unwrap()))
+        .collect()
+}
+
+fn to_flat<const D: usize>(points: Vec<Point<Precision, D>>) -> Vec<Precision> {
+    let mut out = Vec::with_capacity(points.len() * D);
+    for p in points {
+        out.extend(p.coords.iter());
+    }
+    out
+}
+
+/// Apply `f` to the flat coordinates as points of dimensionality `dim`.
+fn with_dim(
+    coords: &[Precision],
+    dim: usize,
+    f2: impl FnOnce(&[Point<Precision, 2>]) -> Vec<Point<Precision, 2>>,
+    f3: impl FnOnce(&[Point<Precision, 3>]) -> Vec<Point<Precision, 3>>,
+) -> FlatResult {
+    if !(dim == 2 || dim == 3) {
+        return Err(Error::invalid("dim", "must be 2 or 3").to_string());
+    }
+    if !coords.len().is_multiple_of(dim) {
+        return Err(Error::invalid("coords", "length must be a multiple of dim").to_string());
+    }
+    Ok(match dim {
+        2 => to_flat(f2(&to_points(coords))),
+        _ => to_flat(f3(&to_points(coords))),
+    })
+}
+
+/// Simplify a line using the Ramer-Douglas-Peucker algorithm; see [rdp::rdp_reduce].
+#[wasm_bindgen(js_name = simplifyRdpFlat)]
+pub fn simplify_rdp_flat(
+    coords: &[Precision],
+    dim: usize,
+    epsilon: Precision,
+    closed: bool,
+) -> FlatResult {
+    with_dim(
+        coords,
+        dim,
+        |l| rdp::rdp_reduce(l, epsilon, closed),
+        |l| rdp::rdp_reduce(l, epsilon, closed),
+    )
+}
+
+/// Decimate a line to `n_points` using the Visvalingam-Whyatt algorithm; see [vw::vw_reduce].
+#[wasm_bindgen(js_name = simplifyVwFlat)]
+pub fn simplify_vw_flat(
+    coords: &[Precision],
+    dim: usize,
+    n_points: usize,
+    closed: bool,
+) -> FlatResult {
+    with_dim(
+        coords,
+        dim,
+        |l| vw::vw_reduce(l, n_points, closed),
+        |l| vw::vw_reduce(l, n_points, closed),
+    )
+}
+
+/// Resample a line with a point every `sample_distance`; see [sample::sample_every].
+///
+/// The remainder is discarded.
+#[wasm_bindgen(js_name = sampleEveryFlat)]
+pub fn sample_every_flat(
+    coords: &[Precision],
+    dim: usize,
+    sample_distance: Precision,
+    offset: Precision,
+    closed: bool,
+) -> FlatResult {
+    if sample_distance.is_nan() || sample_distance <= 0.0 {
+        return Err(Error::invalid("sample_distance", "must be positive").to_string());
+    }
+    if offset.is_nan() || offset < 0.0 {
+        return Err(Error::invalid("offset", "must be non-negative").to_string());
+    }
+    with_dim(
+        coords,
+        dim,
+        |l| sample::sample_every(l, sample_distance, offset, closed).0,
+        |l| sample::sample_every(l, sample_distance, offset, closed).0,
+    )
+}
+
+/// Smooth a line with a Gaussian kernel; see [Gaussian::try_new].
+#[wasm_bindgen(js_name = smoothGaussianFlat)]
+pub fn smooth_gaussian_flat(
+    coords: &[Precision],
+    dim: usize,
+    stdev: Precision,
+    width: Precision,
+    closed: bool,
+) -> FlatResult {
+    let kernel = Gaussian::try_new(stdev, width).map_err(|e| e.to_string())?;
+    with_dim(
+        coords,
+        dim,
+        |l| smooth_convolve(l, kernel, closed),
+        |l| smooth_convolve(l, kernel, closed),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    #[test]
+    fn rdp_flat_matches_points() {
+        let line = make_line(vec![[0.0, 0.0], [1.0, 0.1], [2.0, 0.0], [3.0, 2.0]]);
+        let flat: Vec<_> = line.iter().flat_map(|p| [p.x, p.y]).collect();
+        let expected = to_flat(rdp::rdp_reduce(&line, 0.5, false));
+        assert_eq!(simplify_rdp_flat(&flat, 2, 0.5, false).unwrap(), expected);
+        assert_eq!(expected, vec![0.0, 0.0, 2.0, 0.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn three_dimensional() {
+        let flat = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0];
+        assert_eq!(
+            simplify_rdp_flat(&flat, 3, 0.1, false).unwrap(),
+            vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn bad_shape() {
+        assert!(simplify_rdp_flat(&[0.0, 0.0, 1.0], 2, 0.1, false).is_err());
+        assert!(simplify_rdp_flat(&[0.0; 4], 4, 0.1, false).is_err());
+    }
+}
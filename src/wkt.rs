@@ -0,0 +1,672 @@
+//! Read and write `LINESTRING` and `MULTILINESTRING` geometries as
+//! [well-known text and well-known binary](https://en.wikipedia.org/wiki/Well-known_text_representation_of_geometry).
+//!
+//! Coordinates map on to the const dimensionality `D` of the points:
+//! XY geometries are `D = 2`, XYZ and XYM are `D = 3` and XYZM is `D = 4`.
+//! Reading a geometry with a different number of coordinates than `D` is an error.
+//!
+//! PostGIS' extended formats (EWKT with an `SRID=...;` prefix, and EWKB) can also be read;
+//! the SRID is discarded.
+//!
+//! ```
+//! use simples::wkt::{read_wkt, write_wkt, Dims, Geometry};
+//! use simples::simplify::rdp::rdp_reduce;
+//!
+//! let geom = read_wkt::<2>("LINESTRING (0 0, 1 0.01, 2 0)").unwrap();
+//! let Geometry::LineString(line) = geom else { unreachable!() };
+//! let simplified = Geometry::LineString(rdp_reduce(&line, 0.1, false));
+//! assert_eq!(write_wkt(&simplified, Dims::Xy).unwrap(), "LINESTRING (0 0, 2 0)");
+//! ```
+use crate::error::{Error, Result};
+use crate::multi::MultiLine;
+use crate::{check_finite, Point, Precision};
+
+const LINESTRING: u32 = 2;
+const MULTILINESTRING: u32 = 5;
+
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// Which coordinates each point of a serialised geometry has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dims {
+    Xy,
+    Xyz,
+    Xym,
+    Xyzm,
+}
+
+impl Dims {
+    /// The number of coordinates in each point.
+    pub fn n_coords(self) -> usize {
+        match self {
+            Dims::Xy => 2,
+            Dims::Xyz | Dims::Xym => 3,
+            Dims::Xyzm => 4,
+        }
+    }
+
+    /// The usual interpretation of `n_coords` coordinates, preferring Z over M.
+    pub fn from_n_coords(n_coords: usize) -> Option<Self> {
+        match n_coords {
+            2 => Some(Dims::Xy),
+            3 => Some(Dims::Xyz),
+            4 => Some(Dims::Xyzm),
+            _ => None,
+        }
+    }
+
+    fn from_flags(z: bool, m: bool) -> Self {
+        match (z, m) {
+            (false, false) => Dims::Xy,
+            (true, false) => Dims::Xyz,
+            (false, true) => Dims::Xym,
+            (true, true) => Dims::Xyzm,
+        }
+    }
+
+    fn wkt_suffix(self) -> &'static str {
+        match self {
+            Dims::Xy => "",
+            Dims::Xyz => " Z",
+            Dims::Xym => " M",
+            Dims::Xyzm => " ZM",
+        }
+    }
+
+    /// Offset added to the ISO WKB geometry type code.
+    fn wkb_offset(self) -> u32 {
+        match self {
+            Dims::Xy => 0,
+            Dims::Xyz => 1000,
+            Dims::Xym => 2000,
+            Dims::Xyzm => 3000,
+        }
+    }
+
+    fn check<const D: usize>(self) -> Result<()> {
+        if self.n_coords() == D {
+            Ok(())
+        } else {
+            Err(Error::invalid("dims", "must have D coordinates"))
+        }
+    }
+}
+
+/// A linestring geometry, with one or many parts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Geometry<const D: usize> {
+    LineString(Vec<Point<Precision, D>>),
+    MultiLineString(MultiLine<D>),
+}
+
+impl<const D: usize> Geometry<D> {
+    /// Treat either kind of geometry as a [MultiLine].
+    pub fn into_multi(self) -> MultiLine<D> {
+        match self {
+            Geometry::LineString(line) => MultiLine::new(vec![line]),
+            Geometry::MultiLineString(multi) => multi,
+        }
+    }
+
+    fn check_finite(&self) -> Result<()> {
+        match self {
+            Geometry::LineString(line) => check_finite(line),
+            Geometry::MultiLineString(multi) => {
+                multi.parts().iter().try_for_each(|p| check_finite(p))
+            }
+        }
+    }
+}
+
+impl<const D: usize> From<Vec<Point<Precision, D>>> for Geometry<D> {
+    fn from(line: Vec<Point<Precision, D>>) -> Self {
+        Geometry::LineString(line)
+    }
+}
+
+impl<const D: usize> From<MultiLine<D>> for Geometry<D> {
+    fn from(multi: MultiLine<D>) -> Self {
+        Geometry::MultiLineString(multi)
+    }
+}
+
+struct WktReader<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WktReader<'a> {
+    fn err(&self, reason: &'static str) -> Error {
+        Error::Parse {
+            offset: self.pos,
+            reason,
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self
+            .s
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        self.s.get(self.pos).copied()
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &'a str {
+        self.peek();
+        let start = self.pos;
+        while self.s.get(self.pos).is_some_and(|b| f(*b)) {
+            self.pos += 1;
+        }
+        // only ASCII bytes are taken
+        std::str::from_utf8(&self.s[start..self.pos]).unwrap()
+    }
+
+    fn word(&mut self) -> String {
+        self.take_while(|b| b.is_ascii_alphabetic())
+            .to_ascii_uppercase()
+    }
+
+    fn expect(&mut self, c: u8, reason: &'static str) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.err(reason))
+        }
+    }
+
+    /// Skip an EWKT `SRID=...;` prefix.
+    fn srid(&mut self) -> Result<()> {
+        let start = self.pos;
+        if self.word() != "SRID" {
+            self.pos = start;
+            return Ok(());
+        }
+        self.expect(b'=', "expected `=` after SRID")?;
+        if self.take_while(|b| b.is_ascii_digit()).is_empty() {
+            return Err(self.err("expected an SRID"));
+        }
+        self.expect(b';', "expected `;` after SRID")
+    }
+
+    /// Check the dimensionality keyword against `D`, if present.
+    fn dims<const D: usize>(&mut self) -> Result<()> {
+        let start = self.pos;
+        let dims = match self.word().as_str() {
+            "Z" => Dims::Xyz,
+            "M" => Dims::Xym,
+            "ZM" => Dims::Xyzm,
+            "" => return Ok(()),
+            "EMPTY" => {
+                self.pos = start;
+                return Ok(());
+            }
+            _ => {
+                self.pos = start;
+                return Err(self.err("expected Z, M, ZM or a coordinate list"));
+            }
+        };
+        if dims.n_coords() != D {
+            self.pos = start;
+            return Err(self.err("geometry dimensions do not match D"));
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<Option<Precision>> {
+        let start = self.pos;
+        let token = self
+            .take_while(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'));
+        if token.is_empty() {
+            return Ok(None);
+        }
+        token.parse().map(Some).map_err(|_| Error::Parse {
+            offset: start,
+            reason: "invalid number",
+        })
+    }
+
+    fn point<const D: usize>(&mut self) -> Result<Point<Precision, D>> {
+        let mut coords = [0.0; D];
+        let start = self.pos;
+        for c in coords.iter_mut() {
+            *c = self
+                .number()?
+                .ok_or_else(|| self.err("point has fewer coordinates than D"))?;
+        }
+        if self.number()?.is_some() {
+            self.pos = start;
+            return Err(self.err("point has more coordinates than D"));
+        }
+        Ok(coords.into())
+    }
+
+    /// Read `EMPTY` or a parenthesised list of items.
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let start = self.pos;
+        if self.word() == "EMPTY" {
+            return Ok(Vec::new());
+        }
+        self.pos = start;
+        self.expect(b'(', "expected `(` or EMPTY")?;
+        let mut out = vec![item(self)?];
+        while self.peek() == Some(b',') {
+            self.pos += 1;
+            out.push(item(self)?);
+        }
+        self.expect(b')', "expected `,` or `)`")?;
+        Ok(out)
+    }
+
+    fn line<const D: usize>(&mut self) -> Result<Vec<Point<Precision, D>>> {
+        self.list(|r| r.point())
+    }
+}
+
+/// Parse a `LINESTRING` or `MULTILINESTRING` from well-known text.
+///
+/// Keywords are case-insensitive.
+/// Without a `Z`, `M` or `ZM` keyword, each point must have `D` coordinates.
+pub fn read_wkt<const D: usize>(wkt: &str) -> Result<Geometry<D>> {
+    let mut r = WktReader {
+        s: wkt.as_bytes(),
+        pos: 0,
+    };
+    r.srid()?;
+    let start = r.pos;
+    let geom = match r.word().as_str() {
+        "LINESTRING" => {
+            r.dims::<D>()?;
+            Geometry::LineString(r.line()?)
+        }
+        "MULTILINESTRING" => {
+            r.dims::<D>()?;
+            Geometry::MultiLineString(MultiLine::new(r.list(|r| r.line())?))
+        }
+        _ => {
+            r.pos = start;
+            return Err(r.err("expected LINESTRING or MULTILINESTRING"));
+        }
+    };
+    if r.peek().is_some() {
+        return Err(r.err("unexpected trailing input"));
+    }
+    Ok(geom)
+}
+
+fn write_wkt_line<const D: usize>(line: &[Point<Precision, D>], out: &mut String) {
+    if line.is_empty() {
+        out.push_str("EMPTY");
+        return;
+    }
+    out.push('(');
+    for (i, p) in line.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        for (j, c) in p.iter().enumerate() {
+            if j > 0 {
+                out.push(' ');
+            }
+            out.push_str(&c.to_string());
+        }
+    }
+    out.push(')');
+}
+
+/// Serialise a geometry as well-known text.
+///
+/// `dims` must have `D` coordinates; it distinguishes between XYZ and XYM for `D = 3`.
+pub fn write_wkt<const D: usize>(geom: &Geometry<D>, dims: Dims) -> Result<String> {
+    dims.check::<D>()?;
+    geom.check_finite()?;
+    let mut out = String::new();
+    match geom {
+        Geometry::LineString(line) => {
+            out.push_str("LINESTRING");
+            out.push_str(dims.wkt_suffix());
+            out.push(' ');
+            write_wkt_line(line, &mut out);
+        }
+        Geometry::MultiLineString(multi) => {
+            out.push_str("MULTILINESTRING");
+            out.push_str(dims.wkt_suffix());
+            out.push(' ');
+            if multi.n_parts() == 0 {
+                out.push_str("EMPTY");
+            } else {
+                out.push('(');
+                for (i, part) in multi.parts().iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write_wkt_line(part, &mut out);
+                }
+                out.push(')');
+            }
+        }
+    }
+    Ok(out)
+}
+
+struct WkbReader<'a> {
+    b: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+impl WkbReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self
+            .b
+            .get(self.pos..self.pos + N)
+            .ok_or(Error::Parse {
+                offset: self.pos,
+                reason: "unexpected end of input",
+            })?
+            .try_into()
+            .unwrap();
+        self.pos += N;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take()?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn f64(&mut self) -> Result<Precision> {
+        let bytes = self.take()?;
+        Ok(if self.little_endian {
+            f64::from_le_bytes(bytes) as Precision
+        } else {
+            f64::from_be_bytes(bytes) as Precision
+        })
+    }
+
+    /// Read the byte order and geometry type, returning the base type code.
+    fn header<const D: usize>(&mut self) -> Result<u32> {
+        let offset = self.pos;
+        self.little_endian = match self.take::<1>()? {
+            [0] => false,
+            [1] => true,
+            _ => {
+                return Err(Error::Parse {
+                    offset,
+                    reason: "invalid byte order",
+                })
+            }
+        };
+        let offset = self.pos;
+        let code = self.u32()?;
+        let (base, dims) = if code & (EWKB_Z | EWKB_M | EWKB_SRID) != 0 {
+            if code & EWKB_SRID != 0 {
+                self.u32()?;
+            }
+            (
+                code & 0xffff,
+                Dims::from_flags(code & EWKB_Z != 0, code & EWKB_M != 0),
+            )
+        } else {
+            let dims = match code / 1000 {
+                0 => Dims::Xy,
+                1 => Dims::Xyz,
+                2 => Dims::Xym,
+                3 => Dims::Xyzm,
+                _ => {
+                    return Err(Error::Parse {
+                        offset,
+                        reason: "unknown geometry type",
+                    })
+                }
+            };
+            (code % 1000, dims)
+        };
+        if dims.n_coords() != D {
+            return Err(Error::Parse {
+                offset,
+                reason: "geometry dimensions do not match D",
+            });
+        }
+        Ok(base)
+    }
+
+    fn count(&mut self, item_size: usize) -> Result<usize> {
+        let offset = self.pos;
+        let n = self.u32()? as usize;
+        // guard against allocating for a corrupt count
+        if n.saturating_mul(item_size) > self.b.len() - self.pos {
+            return Err(Error::Parse {
+                offset,
+                reason: "count is larger than the remaining input",
+            });
+        }
+        Ok(n)
+    }
+
+    fn line<const D: usize>(&mut self) -> Result<Vec<Point<Precision, D>>> {
+        let n = self.count(8 * D)?;
+        let mut out = Vec::with_capacity(n);
+        for _ in 0..n {
+            let mut coords = [0.0; D];
+            for c in coords.iter_mut() {
+                *c = self.f64()?;
+            }
+            out.push(coords.into());
+        }
+        Ok(out)
+    }
+}
+
+/// Parse a `LINESTRING` or `MULTILINESTRING` from well-known binary (ISO or PostGIS EWKB) in either byte order.
+pub fn read_wkb<const D: usize>(wkb: &[u8]) -> Result<Geometry<D>> {
+    let mut r = WkbReader {
+        b: wkb,
+        pos: 0,
+        little_endian: true,
+    };
+    let offset = r.pos;
+    let geom = match r.header::<D>()? {
+        LINESTRING => Geometry::LineString(r.line()?),
+        MULTILINESTRING => {
+            // each part is at least a header and a count
+            let n = r.count(9)?;
+            let mut parts = Vec::with_capacity(n);
+            for _ in 0..n {
+                let offset = r.pos;
+                if r.header::<D>()? != LINESTRING {
+                    return Err(Error::Parse {
+                        offset,
+                        reason: "MULTILINESTRING part is not a LINESTRING",
+                    });
+                }
+                parts.push(r.line()?);
+            }
+            Geometry::MultiLineString(MultiLine::new(parts))
+        }
+        _ => {
+            return Err(Error::Parse {
+                offset,
+                reason: "expected LINESTRING or MULTILINESTRING",
+            })
+        }
+    };
+    if r.pos != wkb.len() {
+        return Err(Error::Parse {
+            offset: r.pos,
+            reason: "unexpected trailing input",
+        });
+    }
+    Ok(geom)
+}
+
+fn write_wkb_line<const D: usize>(line: &[Point<Precision, D>], dims: Dims, out: &mut Vec<u8>) {
+    out.push(1);
+    out.extend((LINESTRING + dims.wkb_offset()).to_le_bytes());
+    out.extend((line.len() as u32).to_le_bytes());
+    for p in line {
+        for c in p.iter() {
+            out.extend(f64::from(*c).to_le_bytes());
+        }
+    }
+}
+
+/// Serialise a geometry as little-endian ISO well-known binary.
+///
+/// `dims` must have `D` coordinates; it distinguishes between XYZ and XYM for `D = 3`.
+pub fn write_wkb<const D: usize>(geom: &Geometry<D>, dims: Dims) -> Result<Vec<u8>> {
+    dims.check::<D>()?;
+    let mut out = Vec::new();
+    match geom {
+        Geometry::LineString(line) => write_wkb_line(line, dims, &mut out),
+        Geometry::MultiLineString(multi) => {
+            out.push(1);
+            out.extend((MULTILINESTRING + dims.wkb_offset()).to_le_bytes());
+            out.extend((multi.n_parts() as u32).to_le_bytes());
+            for part in multi.parts() {
+                write_wkb_line(part, dims, &mut out);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::make_line;
+
+    fn multi() -> Geometry<2> {
+        Geometry::MultiLineString(MultiLine::new(vec![
+            make_line(vec![[0.0, 0.0], [1.5, -2.0]]),
+            vec![],
+            make_line(vec![[1e-7, 3.0], [4.0, 5.0], [6.0, 7.0]]),
+        ]))
+    }
+
+    #[test]
+    fn wkt_roundtrip() {
+        let geom = multi();
+        let wkt = write_wkt(&geom, Dims::Xy).unwrap();
+        assert_eq!(
+            wkt,
+            "MULTILINESTRING ((0 0, 1.5 -2), EMPTY, (0.0000001 3, 4 5, 6 7))"
+        );
+        assert_eq!(read_wkt::<2>(&wkt).unwrap(), geom);
+    }
+
+    #[test]
+    fn wkt_variants() {
+        let line: Geometry<3> = vec![[1.0, 2.0, 3.0].into(), [4.0, 5.0, 6.0].into()].into();
+        for wkt in [
+            "LINESTRING Z (1 2 3, 4 5 6)",
+            "linestring m(1 2 3,4 5 6)",
+            "LINESTRING (1 2 3, 4 5 6)",
+            "SRID=4326;LINESTRING Z (1 2 3, 4 5 6)",
+            "  LINESTRING Z\n(1 2 3e0, 4 5 +6)  ",
+        ] {
+            assert_eq!(read_wkt::<3>(wkt).unwrap(), line, "{wkt}");
+        }
+        assert_eq!(
+            write_wkt(&line, Dims::Xym).unwrap(),
+            "LINESTRING M (1 2 3, 4 5 6)"
+        );
+        assert_eq!(
+            read_wkt::<4>("LINESTRING ZM (1 2 3 4)").unwrap(),
+            Geometry::LineString(vec![[1.0, 2.0, 3.0, 4.0].into()])
+        );
+        assert_eq!(
+            read_wkt::<3>("LINESTRING Z EMPTY").unwrap(),
+            Geometry::LineString(vec![])
+        );
+        assert_eq!(
+            read_wkt::<2>("MULTILINESTRING EMPTY").unwrap(),
+            Geometry::MultiLineString(MultiLine::default())
+        );
+    }
+
+    #[test]
+    fn wkt_errors() {
+        for wkt in [
+            "POINT (1 2)",
+            "LINESTRING Z (1 2, 3 4)",
+            "LINESTRING (1 2 3, 4 5 6)",
+            "LINESTRING (1 2, 3)",
+            "LINESTRING (1 2, 3 4",
+            "LINESTRING (1 2) x",
+            "LINESTRING (1 -)",
+        ] {
+            assert!(
+                matches!(read_wkt::<2>(wkt), Err(Error::Parse { .. })),
+                "{wkt}"
+            );
+        }
+        assert_eq!(
+            read_wkt::<2>("LINESTRING (1 2, 3)"),
+            Err(Error::Parse {
+                offset: 18,
+                reason: "point has fewer coordinates than D"
+            })
+        );
+        assert!(write_wkt(&multi(), Dims::Xyz).is_err());
+    }
+
+    #[test]
+    fn wkb_layout() {
+        let line: Geometry<2> = make_line(vec![[0.0, 0.0], [1.0, 1.0]]).into();
+        let wkb = write_wkb(&line, Dims::Xy).unwrap();
+        assert_eq!(wkb.len(), 1 + 4 + 4 + 2 * 16);
+        assert_eq!(&wkb[..9], &[1, 2, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(read_wkb::<2>(&wkb).unwrap(), line);
+    }
+
+    #[test]
+    fn wkb_roundtrip() {
+        let geom = multi();
+        assert_eq!(
+            read_wkb::<2>(&write_wkb(&geom, Dims::Xy).unwrap()).unwrap(),
+            geom
+        );
+        let line: Geometry<3> = vec![[1.0, 2.0, 3.0].into()].into();
+        let wkb = write_wkb(&line, Dims::Xym).unwrap();
+        assert_eq!(&wkb[1..5], &2002_u32.to_le_bytes());
+        assert_eq!(read_wkb::<3>(&wkb).unwrap(), line);
+        assert!(read_wkb::<2>(&wkb).is_err());
+    }
+
+    #[test]
+    fn ewkb_big_endian() {
+        let mut wkb = vec![0];
+        wkb.extend((LINESTRING | EWKB_Z | EWKB_SRID).to_be_bytes());
+        wkb.extend(4326_u32.to_be_bytes());
+        wkb.extend(1_u32.to_be_bytes());
+        for c in [1.0_f64, 2.0, 3.0] {
+            wkb.extend(c.to_be_bytes());
+        }
+        assert_eq!(
+            read_wkb::<3>(&wkb).unwrap(),
+            Geometry::LineString(vec![[1.0, 2.0, 3.0].into()])
+        );
+    }
+
+    #[test]
+    fn wkb_errors() {
+        let wkb = write_wkb(&multi(), Dims::Xy).unwrap();
+        assert!(read_wkb::<2>(&wkb[..wkb.len() - 1]).is_err());
+        let mut extra = wkb.clone();
+        extra.push(0);
+        assert!(read_wkb::<2>(&extra).is_err());
+        let mut huge = wkb;
+        huge[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_wkb::<2>(&huge).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Regression tests comparing the main algorithms' output on bundled lines against stored golden outputs,
+//! so that changes to the algorithms' internals can be shown not to change their results.
+//!
+//! The input lines in `tests/data` are synthetic, generated to resemble the shapes and scales of real-world data:
+//! a noisy GPS trace (2D, metres), an island coastline (2D ring, metres) and a neurite tracing (3D, nanometres).
+//! They pin down current behaviour, rather than checking it against an independent reference.
+//!
+//! Run with `cargo test --features golden`.
+//! The golden outputs are in double precision, so are not checked with the `f32` feature.
+//! To regenerate the golden outputs after an intentional change,
+//! set `SIMPLES_BLESS=1` and check the diff of `tests/data/golden` carefully.
+#![cfg(all(feature = "golden", not(feature = "f32")))]
+use simples::simplify::{
+    pd::pd_reduce, radial::radial_reduce, rdp::rdp_reduce, sample::resample, sample::sample_every,
+    vw::vw_reduce,
+};
+use simples::smooth::kalman::smooth_kalman;
+use simples::smooth::tv::smooth_tv;
+use simples::smooth::{
+    smooth_convolve, smooth_moving_average, smooth_savitzky_golay, Gaussian, Linear,
+};
+use simples::{CurveKind, Point, Precision, Time, TimedPoint};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Absolute tolerance for each coordinate, scaled up for large coordinates.
+const TOLERANCE: Precision = 1e-9;
+
+type Outputs = BTreeMap<String, Vec<Vec<Precision>>>;
+
+fn data_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+}
+
+fn load<const D: usize>(name: &str) -> Vec<Point<Precision, D>> {
+    let path = data_dir().join(format!("{name}.json"));
+    let coords: Vec<Vec<Precision>> =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    coords
+        .into_iter()
+        .map(|c| Point::from(<[Precision; D]>::try_from(c).unwrap()))
+        .collect()
+}
+
+fn to_coords<const D: usize>(line: Vec<Point<Precision, D>>) -> Vec<Vec<Precision>> {
+    line.iter().map(|p| p.iter().copied().collect()).collect()
+}
+
+/// Run every algorithm on the line, with parameters appropriate to its scale.
+fn run_all<const D: usize>(
+    line: &[Point<Precision, D>],
+    scale: Precision,
+    kind: CurveKind,
+) -> Outputs {
+    let mut out = Outputs::default();
+    let mut add = |name: &str, result: Vec<Point<Precision, D>>| {
+        out.insert(name.to_owned(), to_coords(result));
+    };
+    let n = line.len();
+    add("rdp", rdp_reduce(line, scale, false));
+    add("rdp_curve", rdp_reduce(line, scale, kind));
+    add("vw", vw_reduce(line, n / 4, kind));
+    add("radial", radial_reduce(line, scale * 3.0));
+    add("pd", pd_reduce(line, scale / 2.0, 3));
+    add("resample", resample(line, n / 3, false));
+    add("resample_curve", resample(line, n / 3, kind));
+    add(
+        "sample_every",
+        sample_every(line, scale * 5.0, scale, false).0,
+    );
+    add(
+        "smooth_linear",
+        smooth_convolve(line, Linear::new(scale * 10.0), false),
+    );
+    add(
+        "smooth_linear_curve",
+        smooth_convolve(line, Linear::new(scale * 10.0), kind),
+    );
+    add(
+        "savitzky_golay",
+        smooth_savitzky_golay(line, 7, 2, kind).unwrap(),
+    );
+    add("tv", smooth_tv(line, scale));
+    add("moving_average", smooth_moving_average(line, 3, kind));
+    add(
+        "smooth_gaussian",
+        smooth_convolve(line, Gaussian::new(scale * 3.0, 3.0), kind),
+    );
+    // one point per second
+    let timed: Vec<_> = line
+        .iter()
+        .enumerate()
+        .map(|(idx, p)| TimedPoint::new(*p, idx as Time))
+        .collect();
+    add(
+        "kalman",
+        smooth_kalman(&timed, scale, scale * scale)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.point)
+            .collect(),
+    );
+    out
+}
+
+fn compare(dataset: &str, actual: Outputs) {
+    let path = data_dir().join("golden").join(format!("{dataset}.json"));
+    if std::env::var_os("SIMPLES_BLESS").is_some() {
+        std::fs::write(&path, serde_json::to_string_pretty(&actual).unwrap()).unwrap();
+        return;
+    }
+    let expected: Outputs = serde_json::from_str(
+        &std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("no golden output at {path:?}; run with SIMPLES_BLESS=1")),
+    )
+    .unwrap();
+    assert_eq!(
+        expected.keys().collect::<Vec<_>>(),
+        actual.keys().collect::<Vec<_>>(),
+        "{dataset}: different algorithms"
+    );
+    for (name, exp_line) in expected.iter() {
+        let act_line = &actual[name];
+        assert_eq!(
+            exp_line.len(),
+            act_line.len(),
+            "{dataset}/{name}: different number of points"
+        );
+        for (idx, (e, a)) in exp_line.iter().zip(act_line.iter()).enumerate() {
+            for (ec, ac) in e.iter().zip(a.iter()) {
+                let tol = TOLERANCE * ec.abs().max(1.0);
+                assert!(
+                    (ec - ac).abs() <= tol,
+                    "{dataset}/{name}: point {idx} differs: expected {e:?}, got {a:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn gps_trace() {
+    let line = load::<2>("gps_trace");
+    compare("gps_trace", run_all(&line, 3.0, CurveKind::Open));
+}
+
+#[test]
+fn coastline_ring() {
+    let line = load::<2>("coastline_ring");
+    compare("coastline_ring", run_all(&line, 10.0, CurveKind::Closed));
+}
+
+#[test]
+fn neurite_3d() {
+    let line = load::<3>("neurite_3d");
+    compare("neurite_3d", run_all(&line, 10.0, CurveKind::Open));
+}
@@ -0,0 +1,21 @@
+//! Randomised functions must give identical output for identical seeds, on every platform.
+//! The expected values are in double precision, so are not checked with the `f32` feature.
+#![cfg(all(feature = "rand", not(feature = "f32")))]
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use simples::simplify::sample::sample_random_along;
+use simples::Point;
+
+#[test]
+fn sample_random_along_is_portable() {
+    let line: Vec<Point<f64, 2>> = vec![[0.0, 0.0].into(), [3.0, 0.0].into(), [3.0, 4.0].into()];
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let sampled = sample_random_along(&line, 4, &mut rng);
+    let expected: Vec<Point<f64, 2>> = vec![
+        [2.9926148199956386, 0.0].into(),
+        [3.0, 1.3915236483813835].into(),
+        [3.0, 1.7732733461467003].into(),
+        [3.0, 3.65192785370739].into(),
+    ];
+    assert_eq!(sampled, expected);
+}